@@ -0,0 +1,247 @@
+//! Create, list, rename and delete branches
+//!
+//! Local branches are the references under `refs/heads/` and remote-tracking branches the ones
+//! under `refs/remotes/`. The configuration of a branch lives in the `branch.<name>` section of
+//! the config and its reflog in `.git/logs/refs/heads/<name>`, both follow the branch when it is
+//! renamed or deleted.
+
+use crate::config::{ConfigError, ConfigFile};
+use crate::head::Head;
+use crate::object::ObjectReadError;
+use crate::oid::ObjectId;
+use crate::references::{Ref, ReferenceError};
+use crate::refname::is_valid_branch_name;
+use crate::revision::RevisionError;
+use crate::Repository;
+use std::{io, path::PathBuf};
+
+#[derive(thiserror::Error, Debug)]
+pub enum BranchError {
+    #[error("a branch named '{0}' already exists")]
+    AlreadyExists(String),
+
+    #[error("branch '{0}' not found")]
+    NotFound(String),
+
+    #[error("'{0}' is not a valid branch name")]
+    InvalidName(String),
+
+    #[error("cannot force update the branch '{name}' checked out at '{}'", .path.display())]
+    UpdateCurrent { name: String, path: PathBuf },
+
+    #[error("Cannot delete branch '{name}' checked out at '{}'", .path.display())]
+    DeleteCurrent { name: String, path: PathBuf },
+
+    #[error("The branch '{0}' is not fully merged")]
+    NotFullyMerged(String),
+
+    #[error(transparent)]
+    ReferenceError(#[from] ReferenceError),
+
+    #[error(transparent)]
+    ObjectReadError(#[from] ObjectReadError),
+
+    #[error(transparent)]
+    RevisionError(#[from] RevisionError),
+
+    #[error(transparent)]
+    ConfigError(#[from] ConfigError),
+
+    #[error("IO Error")]
+    IOError(#[from] io::Error),
+}
+
+/// Whether a branch lives in this repository or tracks the branch of a remote
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BranchKind {
+    Local,
+    Remote,
+}
+
+impl BranchKind {
+    /// The prefix of the references of the branches
+    pub fn prefix(self) -> &'static str {
+        match self {
+            BranchKind::Local => "refs/heads/",
+            BranchKind::Remote => "refs/remotes/",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Branch {
+    /// The name without its prefix, such as `main` or `origin/main`
+    pub name: String,
+    pub kind: BranchKind,
+
+    /// What the branch points to. Usually a commit, but `refs/remotes/<remote>/HEAD` is a
+    /// symbolic reference to another branch of the remote
+    pub target: Ref,
+}
+
+impl Branch {
+    /// The fully-qualified name of the reference of the branch
+    pub fn reference_name(&self) -> String {
+        format!("{}{}", self.kind.prefix(), self.name)
+    }
+}
+
+fn branch_reference(name: &str) -> String {
+    format!("refs/heads/{name}")
+}
+
+impl Repository {
+    /// The branches of a kind, sorted by name
+    pub fn branches(&self, kind: BranchKind) -> Result<Vec<Branch>, ReferenceError> {
+        Ok(self
+            .references(kind.prefix())?
+            .map(|reference| Branch {
+                name: reference.name[kind.prefix().len()..].to_string(),
+                kind,
+                target: reference.target,
+            })
+            .collect())
+    }
+
+    /// The name of the branch HEAD refers to, which may not exist yet. None if HEAD is detached
+    pub fn current_branch(&self) -> Result<Option<String>, ReferenceError> {
+        Ok(self
+            .head()?
+            .referent_name()
+            .and_then(|name| name.strip_prefix("refs/heads/"))
+            .map(str::to_string))
+    }
+
+    fn branch_exists(&self, name: &str) -> Result<bool, ReferenceError> {
+        Ok(self.try_find_reference(&branch_reference(name))?.is_some())
+    }
+
+    /// Create a branch pointing to a commit, recording `message` in its reflog. With `force`, an
+    /// existing branch is reset to the commit, unless it is the current branch
+    pub fn create_branch(
+        &self,
+        name: &str,
+        target: ObjectId,
+        force: bool,
+        message: &str,
+    ) -> Result<(), BranchError> {
+        if !is_valid_branch_name(name) {
+            return Err(BranchError::InvalidName(name.to_string()));
+        }
+        if self.branch_exists(name)? {
+            if !force {
+                return Err(BranchError::AlreadyExists(name.to_string()));
+            }
+            if self.current_branch()?.as_deref() == Some(name) {
+                return Err(BranchError::UpdateCurrent {
+                    name: name.to_string(),
+                    path: self.repository_dir.clone(),
+                });
+            }
+        }
+        self.update_reference(&branch_reference(name), target, message)?;
+        Ok(())
+    }
+
+    /// Rename a branch, with its reflog and its configuration. HEAD follows the current branch,
+    /// which can be renamed before its first commit. With `force`, an existing branch named
+    /// `new_name` is overwritten
+    pub fn rename_branch(
+        &self,
+        old_name: &str,
+        new_name: &str,
+        force: bool,
+    ) -> Result<(), BranchError> {
+        let is_current = self.current_branch()?.as_deref() == Some(old_name);
+        let target = self.try_find_reference(&branch_reference(old_name))?;
+        let unborn = is_current && self.head()?.is_unborn();
+        if target.is_none() && !unborn {
+            return Err(BranchError::NotFound(old_name.to_string()));
+        }
+        if !is_valid_branch_name(new_name) {
+            return Err(BranchError::InvalidName(new_name.to_string()));
+        }
+        if old_name != new_name && self.branch_exists(new_name)? && !force {
+            return Err(BranchError::AlreadyExists(new_name.to_string()));
+        }
+
+        let old_reference = branch_reference(old_name);
+        let new_reference = branch_reference(new_name);
+        let message = format!("Branch: renamed {old_reference} to {new_reference}");
+        let oid = match target {
+            Some(target) => {
+                // delete first, so that `a` can be renamed to `a/b`
+                self.remove_reference(&old_reference)?;
+                if old_name != new_name {
+                    self.delete_reference(&new_reference)?;
+                }
+                Some(self.resolve_reference(&target)?)
+            }
+            None => None,
+        };
+
+        if old_name != new_name {
+            self.rename_reflog(&old_reference, &new_reference)?;
+
+            let mut config = ConfigFile::open(&self.config_path())?;
+            let new_section = format!("branch.{new_name}");
+            let removed = config.remove_section(&new_section)?;
+            let renamed = config.rename_section(&format!("branch.{old_name}"), &new_section)?;
+            if removed + renamed > 0 {
+                config.save()?;
+            }
+        }
+
+        if is_current {
+            self.update_symbolic_reference("HEAD", &new_reference, &message)?;
+        }
+        if let Some(oid) = oid {
+            self.write_reference(&new_reference, oid, oid, &message)?;
+        }
+        Ok(())
+    }
+
+    /// Delete a branch with its reflog and its configuration, and return the commit it pointed
+    /// to. Unless `force` is set, the branch must be merged into its upstream, or into HEAD when
+    /// it has no upstream
+    pub fn delete_branch(&self, name: &str, force: bool) -> Result<ObjectId, BranchError> {
+        let reference = branch_reference(name);
+        let target = self
+            .try_find_reference(&reference)?
+            .ok_or_else(|| BranchError::NotFound(name.to_string()))?;
+        let oid = self.resolve_reference(&target)?;
+
+        let head = self.head()?;
+        if head.referent_name() == Some(reference.as_str()) {
+            return Err(BranchError::DeleteCurrent {
+                name: name.to_string(),
+                path: self.repository_dir.clone(),
+            });
+        }
+
+        if !force {
+            let merged_into = match self.resolve_revision(&format!("{name}@{{upstream}}")) {
+                Ok(upstream) => Some(upstream),
+                Err(RevisionError::NoUpstream(_)) => match head {
+                    Head::Unborn(_) => None,
+                    _ => Some(self.head_id()?),
+                },
+                Err(e) => return Err(e.into()),
+            };
+            let merged = match merged_into {
+                Some(merged_into) => self.is_ancestor(oid, merged_into)?,
+                None => false,
+            };
+            if !merged {
+                return Err(BranchError::NotFullyMerged(name.to_string()));
+            }
+        }
+
+        self.delete_reference(&reference)?;
+        let mut config = ConfigFile::open(&self.config_path())?;
+        if config.remove_section(&format!("branch.{name}"))? > 0 {
+            config.save()?;
+        }
+        Ok(oid)
+    }
+}
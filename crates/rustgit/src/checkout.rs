@@ -0,0 +1,295 @@
+//! Switch HEAD to another commit, bringing the index and the working tree along
+//!
+//! Like git, the files that are the same in the current and the target commits keep their local
+//! changes, staged or not. The files that differ are replaced, which is refused when it would
+//! lose a local change or an untracked file.
+
+use crate::config::ConfigError;
+use crate::diff::DiffError;
+use crate::index::{EntryMetadata, Index, IndexReadError};
+use crate::lockfile::{Lockfile, LockfileError};
+use crate::merge::MergeConflict;
+use crate::object::{ObjectReadError, TreeEntry, GITLINK_MODE};
+use crate::oid::ObjectId;
+use crate::references::ReferenceError;
+use crate::worktree::worktree_metadata;
+use crate::Repository;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+pub(crate) fn path_list(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| format!("\n\t{}", path.display()))
+        .collect()
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CheckoutError {
+    #[error(transparent)]
+    ObjectReadError(#[from] ObjectReadError),
+
+    #[error(transparent)]
+    IndexReadError(#[from] IndexReadError),
+
+    #[error(transparent)]
+    ReferenceError(#[from] ReferenceError),
+
+    #[error(transparent)]
+    DiffError(#[from] DiffError),
+
+    #[error(transparent)]
+    LockfileError(#[from] LockfileError),
+
+    #[error(transparent)]
+    ConfigError(#[from] ConfigError),
+
+    #[error("IO Error")]
+    IOError(#[from] io::Error),
+
+    #[error(
+        "Your local changes to the following files would be overwritten by checkout:{}",
+        path_list(.0)
+    )]
+    LocalChanges(Vec<PathBuf>),
+
+    #[error(
+        "The following untracked working tree files would be overwritten by checkout:{}",
+        path_list(.0)
+    )]
+    UntrackedFiles(Vec<PathBuf>),
+
+    #[error(
+        "Updating the following directories would lose untracked files in them:{}",
+        path_list(.0)
+    )]
+    UntrackedDirectories(Vec<PathBuf>),
+
+    #[error("you need to resolve your current index first")]
+    UnmergedFiles,
+}
+
+/// Where HEAD points after a checkout
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckoutTarget {
+    /// A branch, given by its fully-qualified name
+    Branch(String),
+
+    /// A detached HEAD
+    Detached,
+}
+
+// Same content and mode
+fn same_entry(a: Option<&TreeEntry>, b: Option<&TreeEntry>) -> bool {
+    a.map(|entry| (entry.oid, entry.mode)) == b.map(|entry| (entry.oid, entry.mode))
+}
+
+// The files and symbolic links inside a directory of the working tree, relative to `base`
+fn files_inside(base: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(base.join(dir))? {
+        let entry = entry?;
+        let path = dir.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            files_inside(base, &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+impl Repository {
+    /// The files of a tree by path. A `None` tree is treated as empty
+    pub(crate) fn tree_entries(
+        &self,
+        tree: Option<ObjectId>,
+    ) -> Result<BTreeMap<PathBuf, TreeEntry>, ObjectReadError> {
+        match tree {
+            Some(tree) => self.read_tree_recursive(tree),
+            None => Ok(BTreeMap::new()),
+        }
+    }
+
+    /// Make the file of the working tree and the index entry of a path match a version, with
+    /// fresh stat data in the index. `symlinks` tells how to check out symbolic links, see
+    /// `has_symlinks`. Gitlinks are only recorded in the index
+    pub(crate) fn checkout_path(
+        &self,
+        index: &mut Index,
+        path: &Path,
+        entry: Option<&TreeEntry>,
+        symlinks: bool,
+    ) -> Result<(), CheckoutError> {
+        index.remove(path);
+        match entry {
+            Some(entry) if entry.mode == GITLINK_MODE => {
+                self.write_worktree_file(path, entry.mode, &[], symlinks)?;
+                let metadata = EntryMetadata {
+                    mode: entry.mode,
+                    ..EntryMetadata::default()
+                };
+                index.add(path.to_path_buf(), entry.oid, metadata);
+            }
+            Some(entry) => {
+                let content = self.read_blob(entry.oid)?;
+                self.write_worktree_file(path, entry.mode, &content, symlinks)?;
+                let full_path = self.repository_dir.join(path);
+                let metadata = worktree_metadata(&full_path, entry.mode, symlinks)?;
+                index.add(path.to_path_buf(), entry.oid, metadata);
+            }
+            None => self.remove_worktree_file(path)?,
+        }
+        Ok(())
+    }
+
+    /// Bring the working tree and the index from the `current` files to the `target` files and
+    /// the `conflicts`, after making sure that no local change is lost. Paths whose index entry
+    /// already matches the target are left as they are
+    pub(crate) fn update_worktree(
+        &self,
+        index: &mut Index,
+        current: &BTreeMap<PathBuf, TreeEntry>,
+        target: &BTreeMap<PathBuf, TreeEntry>,
+        conflicts: &[MergeConflict],
+    ) -> Result<(), CheckoutError> {
+        let conflicted: BTreeSet<&PathBuf> =
+            conflicts.iter().map(|conflict| &conflict.path).collect();
+        let staged = |path: &Path| {
+            index
+                .get(path, 0)
+                .map(|entry| (entry.oid, entry.metadata.mode))
+        };
+        let changed: BTreeSet<&PathBuf> = current
+            .keys()
+            .chain(target.keys())
+            .filter(|&path| !same_entry(current.get(path), target.get(path)))
+            .filter(|&path| staged(path) != target.get(path).map(|entry| (entry.oid, entry.mode)))
+            .chain(conflicted.iter().copied())
+            .collect();
+
+        // staged changes, then changes of the working tree
+        let mut modified: BTreeSet<PathBuf> = changed
+            .iter()
+            .filter(|&&path| staged(path) != current.get(path).map(|entry| (entry.oid, entry.mode)))
+            .map(|&path| path.clone())
+            .collect();
+        modified.extend(
+            self.diff_index_to_worktree()?
+                .into_iter()
+                .map(|change| change.path)
+                .filter(|path| changed.contains(path)),
+        );
+        if !modified.is_empty() {
+            return Err(CheckoutError::LocalChanges(modified.into_iter().collect()));
+        }
+
+        // like git, ignored files can be overwritten, but not the files inside a directory that
+        // becomes a file. The files in the way of a new file, or of its directories, are only
+        // allowed when tracked, as they are removed before the new files are written
+        let tracked = |path: &Path| current.contains_key(path) || staged(path).is_some();
+        let mut ignore = self.ignore()?;
+        let mut untracked = BTreeSet::new();
+        let mut untracked_dirs = vec![];
+        for &path in &changed {
+            if target.get(path).is_none() && !conflicted.contains(path) {
+                continue;
+            }
+            for parent in path.ancestors().skip(1) {
+                if parent.as_os_str().is_empty() {
+                    break;
+                }
+                let is_file = self
+                    .repository_dir
+                    .join(parent)
+                    .symlink_metadata()
+                    .is_ok_and(|metadata| !metadata.is_dir());
+                if is_file && !tracked(parent) && !ignore.is_ignored(parent, false)? {
+                    untracked.insert(parent.to_path_buf());
+                }
+            }
+
+            let Ok(metadata) = self.repository_dir.join(path).symlink_metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                let is_gitlink = target
+                    .get(path)
+                    .is_some_and(|entry| entry.mode == GITLINK_MODE);
+                let mut files = vec![];
+                files_inside(&self.repository_dir, path, &mut files)?;
+                if !is_gitlink && files.iter().any(|file| !tracked(file)) {
+                    untracked_dirs.push(path.clone());
+                }
+            } else if !tracked(path) && !ignore.is_ignored(path, false)? {
+                untracked.insert(path.clone());
+            }
+        }
+        if !untracked.is_empty() {
+            return Err(CheckoutError::UntrackedFiles(
+                untracked.into_iter().collect(),
+            ));
+        }
+        if !untracked_dirs.is_empty() {
+            return Err(CheckoutError::UntrackedDirectories(untracked_dirs));
+        }
+
+        // removals first, so that the directories that become files are gone
+        let symlinks = self.has_symlinks()?;
+        for &path in &changed {
+            if target.get(path).is_none() && !conflicted.contains(path) {
+                self.checkout_path(index, path, None, symlinks)?;
+            }
+        }
+        for conflict in conflicts {
+            let (mode, content) = &conflict.worktree;
+            self.write_worktree_file(&conflict.path, *mode, content, symlinks)?;
+            index.remove(&conflict.path);
+            for (stage, entry) in (1..=3).zip(&conflict.stages) {
+                if let Some(entry) = entry {
+                    index.add_conflict(conflict.path.clone(), stage, entry.oid, entry.mode);
+                }
+            }
+        }
+        for path in changed {
+            if let (Some(entry), false) = (target.get(path), conflicted.contains(path)) {
+                self.checkout_path(index, path, Some(entry), symlinks)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check out a commit: update the index and the working tree to its tree, then point HEAD
+    /// to `target`, or to the commit itself when detached, recording `message` in the reflog of
+    /// HEAD. Local changes to the files that are the same in both commits are kept
+    pub fn checkout(
+        &self,
+        commit: ObjectId,
+        target: &CheckoutTarget,
+        message: &str,
+    ) -> Result<(), CheckoutError> {
+        let index_path = self.index_path();
+        let mut index_lock = Lockfile::new(&index_path)?;
+        let mut index = Index::open(&index_path)?;
+        if index.conflicts().next().is_some() {
+            return Err(CheckoutError::UnmergedFiles);
+        }
+
+        let current = self.tree_entries(self.head_tree()?)?;
+        let target_tree = self.read_commit(commit)?.tree();
+        let target_entries = self.tree_entries(Some(target_tree))?;
+        self.update_worktree(&mut index, &current, &target_entries, &[])?;
+        index.write_to(&mut index_lock)?;
+        index_lock.commit()?;
+
+        match target {
+            CheckoutTarget::Branch(name) => {
+                self.update_symbolic_reference("HEAD", name, message)?
+            }
+            CheckoutTarget::Detached => self.update_reference("HEAD", commit, message)?,
+        }
+        Ok(())
+    }
+}
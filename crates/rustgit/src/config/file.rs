@@ -0,0 +1,756 @@
+//! Parse and edit a single config file, keeping its layout and comments
+
+use super::{ConfigError, ConfigName};
+use crate::lockfile::Lockfile;
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+/// A variable as written in a config file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEntry {
+    pub name: ConfigName,
+
+    /// None for a key without `=`, which means true
+    pub value: Option<String>,
+
+    // Where the entry is in the file, including the indentation and the final newline
+    span: Range<usize>,
+
+    // Whether the entry starts its line, rather than following a section header
+    own_line: bool,
+}
+
+// An occurrence of a section header
+#[derive(Debug, Clone)]
+struct SectionHeader {
+    section: String,
+    subsection: Option<String>,
+
+    // The header line, including its newline unless a variable follows the header
+    span: Range<usize>,
+
+    // Where new variables of the section are inserted: after its last variable
+    insert_at: usize,
+}
+
+// What the file is made of, blank lines aside
+#[derive(Debug, Clone, Copy)]
+enum Item {
+    Comment,
+    Section(usize),
+    Entry(usize),
+}
+
+/// A config file that can be edited. Changes are only written by `ConfigFile::save`
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    path: PathBuf,
+    content: String,
+    entries: Vec<FileEntry>,
+    sections: Vec<SectionHeader>,
+    items: Vec<Item>,
+}
+
+struct Parser<'a> {
+    path: &'a Path,
+    content: &'a str,
+    position: usize,
+    line: usize,
+}
+
+fn is_space(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\r')
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.content[self.position..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: &'static str) -> ConfigError {
+        ConfigError::SyntaxError {
+            path: self.path.to_path_buf(),
+            line: self.line,
+            message,
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(is_space) {
+            self.next();
+        }
+    }
+
+    // Skip the rest of the line, including the newline
+    fn skip_line(&mut self) {
+        while let Some(c) = self.next() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    fn line_start(&self) -> usize {
+        self.content[..self.position]
+            .rfind('\n')
+            .map_or(0, |newline| newline + 1)
+    }
+
+    // Whether only whitespace precedes the current position on its line
+    fn at_line_start(&self) -> bool {
+        self.content[self.line_start()..self.position]
+            .chars()
+            .all(is_space)
+    }
+
+    // Parse `[section]`, `[section "subsection"]` or the deprecated `[section.subsection]`
+    fn parse_section_header(&mut self) -> Result<(String, Option<String>), ConfigError> {
+        let invalid = |parser: &Self| parser.error("invalid section header");
+        self.next(); // [
+
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                name.push(c);
+                self.next();
+            } else {
+                break;
+            }
+        }
+        if name.is_empty() {
+            return Err(invalid(self));
+        }
+
+        match self.next() {
+            Some(']') => match name.split_once('.') {
+                Some((section, subsection)) => {
+                    Ok((section.to_lowercase(), Some(subsection.to_lowercase())))
+                }
+                None => Ok((name.to_lowercase(), None)),
+            },
+            Some(c) if is_space(c) && !name.contains('.') => {
+                self.skip_spaces();
+                if self.next() != Some('"') {
+                    return Err(invalid(self));
+                }
+                let mut subsection = String::new();
+                loop {
+                    match self.next() {
+                        None | Some('\n') => return Err(invalid(self)),
+                        Some('"') => break,
+                        // any character can be escaped
+                        Some('\\') => match self.next() {
+                            None | Some('\n') => return Err(invalid(self)),
+                            Some(c) => subsection.push(c),
+                        },
+                        Some(c) => subsection.push(c),
+                    }
+                }
+                if self.next() != Some(']') {
+                    return Err(invalid(self));
+                }
+                Ok((name.to_lowercase(), Some(subsection)))
+            }
+            _ => Err(invalid(self)),
+        }
+    }
+
+    // Parse a value up to the end of its line, which is consumed
+    fn parse_value(&mut self) -> Result<String, ConfigError> {
+        let mut value = String::new();
+        let mut in_quotes = false;
+        // whitespace is only kept when followed by something else
+        let mut pending_spaces = 0;
+        loop {
+            let c = match self.next() {
+                None | Some('\n') if in_quotes => return Err(self.error("unclosed quote")),
+                None | Some('\n') => break,
+                Some(c) => c,
+            };
+            if !in_quotes && (c == '#' || c == ';') {
+                self.skip_line();
+                break;
+            }
+            if !in_quotes && is_space(c) {
+                if !value.is_empty() {
+                    pending_spaces += 1;
+                }
+                continue;
+            }
+
+            for _ in 0..pending_spaces {
+                value.push(' ');
+            }
+            pending_spaces = 0;
+            match c {
+                '"' => in_quotes = !in_quotes,
+                '\\' => match self.next() {
+                    // the value continues on the next line
+                    Some('\n') => {}
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('b') => value.push('\u{8}'),
+                    Some(c @ ('"' | '\\')) => value.push(c),
+                    _ => return Err(self.error("invalid escape sequence")),
+                },
+                c => value.push(c),
+            }
+        }
+        Ok(value)
+    }
+}
+
+// Quote a value when git would not read it back as is
+fn quote_value(value: &str) -> String {
+    let needs_quotes =
+        value.starts_with(is_space) || value.ends_with(is_space) || value.contains(['#', ';']);
+
+    let mut quoted = String::new();
+    if needs_quotes {
+        quoted.push('"');
+    }
+    for c in value.chars() {
+        match c {
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\u{8}' => quoted.push_str("\\b"),
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c => quoted.push(c),
+        }
+    }
+    if needs_quotes {
+        quoted.push('"');
+    }
+    quoted
+}
+
+fn format_section_header(section: &str, subsection: Option<&str>) -> String {
+    match subsection {
+        None => format!("[{}]\n", section),
+        Some(subsection) => format!(
+            "[{} \"{}\"]\n",
+            section,
+            subsection.replace('\\', "\\\\").replace('"', "\\\"")
+        ),
+    }
+}
+
+impl ConfigFile {
+    /// Read a config file. A missing file is the same as an empty one
+    pub fn open(path: &Path) -> Result<Self, ConfigError> {
+        let content = match fs::read_to_string(path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            content => content?,
+        };
+        Self::parse(path, content)
+    }
+
+    /// Parse the content of a config file, `path` is where it is saved
+    pub fn parse(path: &Path, content: String) -> Result<Self, ConfigError> {
+        let mut entries = vec![];
+        let mut sections: Vec<SectionHeader> = vec![];
+        let mut items = vec![];
+
+        let mut parser = Parser {
+            path,
+            content: &content,
+            position: 0,
+            line: 1,
+        };
+        loop {
+            parser.skip_spaces();
+            let Some(c) = parser.peek() else {
+                break;
+            };
+            match c {
+                '\n' => {
+                    parser.next();
+                }
+                '#' | ';' => {
+                    items.push(Item::Comment);
+                    parser.skip_line();
+                }
+                '[' => {
+                    let start = parser.line_start();
+                    let (section, subsection) = parser.parse_section_header()?;
+                    // new variables go on the line after the header, unless a variable follows
+                    // the header on the same line
+                    let mut lookahead = Parser {
+                        path,
+                        content: &content,
+                        position: parser.position,
+                        line: parser.line,
+                    };
+                    lookahead.skip_spaces();
+                    let insert_at = match lookahead.peek() {
+                        None | Some('\n' | '#' | ';') => {
+                            lookahead.skip_line();
+                            lookahead.position
+                        }
+                        _ => parser.position,
+                    };
+                    items.push(Item::Section(sections.len()));
+                    sections.push(SectionHeader {
+                        section,
+                        subsection,
+                        span: start..insert_at,
+                        insert_at,
+                    });
+                }
+                c if c.is_ascii_alphabetic() => {
+                    let own_line = parser.at_line_start();
+                    let start = if own_line {
+                        parser.line_start()
+                    } else {
+                        parser.position
+                    };
+                    let Some(section) = sections.last_mut() else {
+                        return Err(parser.error("key outside of a section"));
+                    };
+
+                    let mut key = String::new();
+                    while let Some(c) = parser.peek() {
+                        if c.is_ascii_alphanumeric() || c == '-' {
+                            key.push(c);
+                            parser.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    parser.skip_spaces();
+                    let value = match parser.peek() {
+                        Some('=') => {
+                            parser.next();
+                            Some(parser.parse_value()?)
+                        }
+                        None | Some('\n' | '#' | ';') => {
+                            parser.skip_line();
+                            None
+                        }
+                        _ => return Err(parser.error("invalid key")),
+                    };
+
+                    section.insert_at = parser.position;
+                    items.push(Item::Entry(entries.len()));
+                    entries.push(FileEntry {
+                        name: ConfigName {
+                            section: section.section.clone(),
+                            subsection: section.subsection.clone(),
+                            key,
+                        },
+                        value,
+                        span: start..parser.position,
+                        own_line,
+                    });
+                }
+                _ => return Err(parser.error("invalid line")),
+            }
+        }
+
+        Ok(ConfigFile {
+            path: path.to_path_buf(),
+            content,
+            entries,
+            sections,
+            items,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The variables of the file, in order
+    pub fn entries(&self) -> &[FileEntry] {
+        &self.entries
+    }
+
+    fn matching_entries<'a>(
+        &'a self,
+        name: &'a ConfigName,
+    ) -> impl DoubleEndedIterator<Item = &'a FileEntry> + 'a {
+        self.entries.iter().filter(move |entry| entry.name == *name)
+    }
+
+    // Replace a part of the content, then parse it again
+    fn edit(&mut self, edits: Vec<(Range<usize>, String)>) -> Result<(), ConfigError> {
+        let mut content = self.content.clone();
+        // edit from the end so that the ranges stay valid
+        for (range, replacement) in edits.into_iter().rev() {
+            content.replace_range(range, &replacement);
+        }
+        *self = Self::parse(&self.path, content)?;
+        Ok(())
+    }
+
+    fn entry_line(name: &ConfigName, value: &str) -> String {
+        format!("\t{} = {}\n", name.key, quote_value(value))
+    }
+
+    // Add a variable after the last variable of its section, or in a new section at the end
+    fn insert(&mut self, name: &ConfigName, value: &str) -> Result<(), ConfigError> {
+        let section =
+            self.sections.iter().rev().find(|header| {
+                header.section == name.section && header.subsection == name.subsection
+            });
+        let (position, mut text) = match section {
+            Some(section) => (section.insert_at, Self::entry_line(name, value)),
+            None => (
+                self.content.len(),
+                format_section_header(&name.section, name.subsection.as_deref())
+                    + &Self::entry_line(name, value),
+            ),
+        };
+        if position == self.content.len()
+            && !self.content.is_empty()
+            && !self.content.ends_with('\n')
+        {
+            text.insert(0, '\n');
+        }
+        self.edit(vec![(position..position, text)])
+    }
+
+    /// Set a variable, replacing its value if it exists
+    ///
+    /// Fails if the variable has multiple values
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), ConfigError> {
+        let name = ConfigName::parse(name)?;
+        let matching: Vec<&FileEntry> = self.matching_entries(&name).collect();
+        match matching.as_slice() {
+            [] => self.insert(&name, value),
+            [entry] => {
+                let mut line = Self::entry_line(&name, value);
+                if !entry.own_line {
+                    line.insert(0, '\n');
+                }
+                self.edit(vec![(entry.span.clone(), line)])
+            }
+            _ => Err(ConfigError::MultipleValues(name.to_string())),
+        }
+    }
+
+    /// Add a value to a variable, keeping its existing values
+    pub fn add(&mut self, name: &str, value: &str) -> Result<(), ConfigError> {
+        let name = ConfigName::parse(name)?;
+        self.insert(&name, value)
+    }
+
+    /// Remove a variable. Fails if it doesn't exist or has multiple values
+    pub fn unset(&mut self, name: &str) -> Result<(), ConfigError> {
+        let config_name = ConfigName::parse(name)?;
+        match self.matching_entries(&config_name).count() {
+            0 => Err(ConfigError::NotFound(config_name.to_string())),
+            1 => self.unset_all(name).map(|_| ()),
+            _ => Err(ConfigError::MultipleValues(config_name.to_string())),
+        }
+    }
+
+    // Like git, when removing the last variables of a section, also remove its header unless
+    // there are comments around that could be about the section. `matching` are the items of
+    // the removed variables, and `next` the first one that is not handled yet. Returns the part
+    // of the file to remove and advances `next` past the variables it covers
+    fn section_removal(
+        &self,
+        name: &ConfigName,
+        matching: &[usize],
+        next: &mut usize,
+    ) -> Option<Range<usize>> {
+        let is_keys_section = |section: usize| {
+            let header = &self.sections[section];
+            header.section == name.section && header.subsection == name.subsection
+        };
+
+        // the variable must be the first of its section, without comments before the section
+        let first = matching[*next];
+        let mut begin = 0;
+        let mut section_seen = false;
+        for item in self.items[..first].iter().rev() {
+            match *item {
+                Item::Comment => return None,
+                Item::Entry(_) if !section_seen => return None,
+                Item::Entry(entry) => {
+                    begin = self.entries[entry].span.end;
+                    break;
+                }
+                Item::Section(section) if is_keys_section(section) => section_seen = true,
+                Item::Section(section) => {
+                    begin = self.sections[section].span.end;
+                    break;
+                }
+            }
+        }
+
+        // and the following variables of the section must all be removed too
+        let mut last = *next;
+        let mut end = self.content.len();
+        for (index, item) in self.items.iter().enumerate().skip(first + 1) {
+            match *item {
+                Item::Comment => return None,
+                Item::Entry(_) if matching.get(last + 1) == Some(&index) => last += 1,
+                Item::Entry(_) => return None,
+                Item::Section(section) if is_keys_section(section) => {}
+                Item::Section(section) => {
+                    end = self.sections[section].span.start;
+                    break;
+                }
+            }
+        }
+
+        *next = last;
+        Some(begin..end)
+    }
+
+    /// Remove all the values of a variable, and return how many there were
+    pub fn unset_all(&mut self, name: &str) -> Result<usize, ConfigError> {
+        let name = ConfigName::parse(name)?;
+        let matching: Vec<usize> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| match *item {
+                Item::Entry(entry) if self.entries[entry].name == name => Some(index),
+                _ => None,
+            })
+            .collect();
+
+        let mut edits = vec![];
+        let mut next = 0;
+        while next < matching.len() {
+            let edit = match self.section_removal(&name, &matching, &mut next) {
+                Some(range) => (range, String::new()),
+                None => {
+                    let Item::Entry(entry) = self.items[matching[next]] else {
+                        unreachable!("only variables are removed");
+                    };
+                    let entry = &self.entries[entry];
+                    // keep the newline of a section header followed by the variable
+                    let replacement = if entry.own_line { "" } else { "\n" };
+                    (entry.span.clone(), replacement.to_string())
+                }
+            };
+            edits.push(edit);
+            next += 1;
+        }
+        self.edit(edits)?;
+        Ok(matching.len())
+    }
+
+    // The headers of a section given as `section` or `section.subsection`
+    fn matching_sections(&self, name: &str) -> Vec<usize> {
+        let (section, subsection) = match name.split_once('.') {
+            Some((section, subsection)) => (section, Some(subsection)),
+            None => (name, None),
+        };
+        let section = section.to_lowercase();
+        (0..self.sections.len())
+            .filter(|&index| {
+                let header = &self.sections[index];
+                header.section == section && header.subsection.as_deref() == subsection
+            })
+            .collect()
+    }
+
+    /// Rename a section given as `section` or `section.subsection`, such as `branch.old` to
+    /// `branch.new`. Returns how many headers were renamed
+    pub fn rename_section(&mut self, name: &str, new_name: &str) -> Result<usize, ConfigError> {
+        let (section, subsection) = match new_name.split_once('.') {
+            Some((section, subsection)) => (section, Some(subsection)),
+            None => (new_name, None),
+        };
+        if section.is_empty()
+            || !section
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(ConfigError::InvalidName(new_name.to_string()));
+        }
+
+        let matching = self.matching_sections(name);
+        let edits = matching
+            .iter()
+            .map(|&index| {
+                let span = self.sections[index].span.clone();
+                let mut header = format_section_header(section, subsection);
+                // a variable following the header stays on its line
+                if !self.content[span.clone()].ends_with('\n') {
+                    header.pop();
+                }
+                (span, header)
+            })
+            .collect();
+        self.edit(edits)?;
+        Ok(matching.len())
+    }
+
+    /// Remove a section given as `section` or `section.subsection`, with all its variables.
+    /// Returns how many headers were removed
+    pub fn remove_section(&mut self, name: &str) -> Result<usize, ConfigError> {
+        let matching = self.matching_sections(name);
+        let edits = matching
+            .iter()
+            .map(|&index| {
+                let end = self
+                    .sections
+                    .get(index + 1)
+                    .map_or(self.content.len(), |next| next.span.start);
+                (self.sections[index].span.start..end, String::new())
+            })
+            .collect();
+        self.edit(edits)?;
+        Ok(matching.len())
+    }
+
+    /// Atomically write the file
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let mut lock = Lockfile::new(&self.path)?;
+        lock.write_all(self.content.as_bytes())?;
+        lock.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> ConfigFile {
+        ConfigFile::parse(Path::new("config"), content.to_string()).unwrap()
+    }
+
+    #[test]
+    fn values() {
+        let file = parse(concat!(
+            "[core] bare = false\n",
+            "\tfilemode\n",
+            "[section \"sub \\\"quoted\\\"\"]\n",
+            "\tkey =  a   \"b  c\" d ; comment\n",
+            "\tescaped = \"\\t\\\\\\\"\"\n",
+            "\tcontinued = first \\\n",
+            "second\n",
+            "[Old.Style]\n",
+            "\tKey\n",
+        ));
+        let values: Vec<(String, Option<&str>)> = file
+            .entries()
+            .iter()
+            .map(|entry| (entry.name.to_string(), entry.value.as_deref()))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("core.bare".to_string(), Some("false")),
+                ("core.filemode".to_string(), None),
+                ("section.sub \"quoted\".key".to_string(), Some("a   b  c d")),
+                ("section.sub \"quoted\".escaped".to_string(), Some("\t\\\"")),
+                (
+                    "section.sub \"quoted\".continued".to_string(),
+                    Some("first second")
+                ),
+                ("old.style.key".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn set() {
+        let mut file = parse("[core]\n\tbare = false\n\tfilemode\n# comment\n[foo]\n\ty = 2\n");
+        file.set("core.filemode", "false").unwrap();
+        file.set("foo.NewKey", "v a").unwrap();
+        file.set("foo.bar.z", " lead").unwrap();
+        file.set("sec.k", "a#b\"c\\d\te").unwrap();
+        assert_eq!(
+            file.content,
+            concat!(
+                "[core]\n\tbare = false\n\tfilemode = false\n# comment\n",
+                "[foo]\n\ty = 2\n\tNewKey = v a\n",
+                "[foo \"bar\"]\n\tz = \" lead\"\n",
+                "[sec]\n\tk = \"a#b\\\"c\\\\d\\te\"\n",
+            )
+        );
+
+        // the file can be read back
+        let file = ConfigFile::parse(file.path(), file.content.clone()).unwrap();
+        assert_eq!(file.entries()[4].value.as_deref(), Some(" lead"));
+        assert_eq!(file.entries()[5].value.as_deref(), Some("a#b\"c\\d\te"));
+    }
+
+    #[test]
+    fn unset() {
+        let mut file = parse("[a] m = 1\n\tm = 2\n\tn = 3\n");
+        assert!(matches!(
+            file.set("a.m", "4"),
+            Err(ConfigError::MultipleValues(_))
+        ));
+        assert!(matches!(
+            file.unset("a.m"),
+            Err(ConfigError::MultipleValues(_))
+        ));
+        assert!(matches!(file.unset("a.x"), Err(ConfigError::NotFound(_))));
+
+        assert_eq!(file.unset_all("a.m").unwrap(), 2);
+        assert_eq!(file.content, "[a] \n\tn = 3\n");
+        file.unset("a.n").unwrap();
+        assert_eq!(file.content, "");
+    }
+
+    #[test]
+    fn remove_empty_sections() {
+        let unset_all = |content: &str| {
+            let mut file = parse(content);
+            file.unset_all("a.m").unwrap();
+            file.content
+        };
+        assert_eq!(unset_all("[b]\n[a]\n\tm = 1\n"), "[b]\n");
+        assert_eq!(
+            unset_all("[b]\n\tx = 1\n\n[a]\n\tm = 1\n\n[c]\n"),
+            "[b]\n\tx = 1\n[c]\n"
+        );
+        assert_eq!(
+            unset_all("[a]\n\tm = 1\n\tn = 2\n[a]\n\tm = 3\n[c]\n"),
+            "[a]\n\tn = 2\n[c]\n"
+        );
+
+        // comments may be about the section
+        assert_eq!(
+            unset_all("[a]\n# comment\n\tm = 1\n[c]\n"),
+            "[a]\n# comment\n[c]\n"
+        );
+        assert_eq!(unset_all("# comment\n[a]\n\tm = 1\n"), "# comment\n[a]\n");
+    }
+
+    #[test]
+    fn sections() {
+        let mut file =
+            parse("[core]\n\tbare = false\n[branch \"old\"] remote = origin\n\tmerge = x\n");
+        assert_eq!(file.rename_section("branch.old", "branch.new").unwrap(), 1);
+        assert_eq!(
+            file.content,
+            "[core]\n\tbare = false\n[branch \"new\"] remote = origin\n\tmerge = x\n"
+        );
+        assert_eq!(file.entries()[1].name.subsection.as_deref(), Some("new"));
+        assert_eq!(
+            file.rename_section("branch.old", "branch.other").unwrap(),
+            0
+        );
+
+        let mut file = parse("[a]\n\tx = 1\n[b \"c\"]\n\ty = 2\n# comment\n[a]\n\tz = 3\n[d]\n");
+        assert_eq!(file.remove_section("b.c").unwrap(), 1);
+        assert_eq!(file.content, "[a]\n\tx = 1\n[a]\n\tz = 3\n[d]\n");
+        assert_eq!(file.remove_section("A").unwrap(), 2);
+        assert_eq!(file.content, "[d]\n");
+    }
+}
@@ -0,0 +1,606 @@
+//! Read and write git config files
+//!
+//! Like git, the config of a repository is made of several files read in order: the system
+//! config, the global config of the user, the repository's `.git/config` and the variables given
+//! through `GIT_CONFIG_COUNT`. A variable set in several places takes its last value.
+
+mod file;
+
+pub use file::{ConfigFile, FileEntry};
+
+use crate::lockfile::LockfileError;
+use crate::wildmatch::wildmatch;
+use crate::Repository;
+use std::{
+    env,
+    fmt::{Display, Formatter},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+// Stop following `include.path` past this depth, which is most likely an include cycle
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+/// The name of a variable, `section.key` or `section.subsection.key`
+///
+/// Section and key names are case-insensitive, the section is stored in lowercase and the key
+/// as it was written
+#[derive(Debug, Clone)]
+pub struct ConfigName {
+    pub section: String,
+    pub subsection: Option<String>,
+    pub key: String,
+}
+
+impl PartialEq for ConfigName {
+    fn eq(&self, other: &Self) -> bool {
+        self.section == other.section
+            && self.subsection == other.subsection
+            && self.key.eq_ignore_ascii_case(&other.key)
+    }
+}
+
+impl Eq for ConfigName {}
+
+impl ConfigName {
+    /// Parse a name given as `section.key` or `section.subsection.key`. The subsection may
+    /// contain dots
+    pub fn parse(name: &str) -> Result<ConfigName, ConfigError> {
+        let invalid = || ConfigError::InvalidName(name.to_string());
+
+        let (section, rest) = name.split_once('.').ok_or_else(invalid)?;
+        let (subsection, key) = match rest.rsplit_once('.') {
+            Some((subsection, key)) => (Some(subsection), key),
+            None => (None, rest),
+        };
+
+        let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '-';
+        if section.is_empty()
+            || !section.chars().all(is_name_char)
+            || !key.starts_with(|c: char| c.is_ascii_alphabetic())
+            || !key.chars().all(is_name_char)
+        {
+            return Err(invalid());
+        }
+
+        Ok(ConfigName {
+            section: section.to_lowercase(),
+            subsection: subsection.map(str::to_string),
+            key: key.to_string(),
+        })
+    }
+}
+
+impl Display for ConfigName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.", self.section)?;
+        if let Some(subsection) = &self.subsection {
+            write!(f, "{}.", subsection)?;
+        }
+        write!(f, "{}", self.key.to_lowercase())
+    }
+}
+
+/// A variable and its value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigEntry {
+    pub name: ConfigName,
+
+    /// None for a key without `=`, which means true
+    pub value: Option<String>,
+}
+
+/// The variables of one or several config files
+#[derive(Debug, Default)]
+pub struct Config {
+    entries: Vec<ConfigEntry>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("IO Error")]
+    IOError(#[from] io::Error),
+
+    #[error(transparent)]
+    LockfileError(#[from] LockfileError),
+
+    #[error("bad config line {line} in file {}: {message}", path.display())]
+    SyntaxError {
+        path: PathBuf,
+        line: usize,
+        message: &'static str,
+    },
+
+    #[error("invalid key: {0}")]
+    InvalidName(String),
+
+    #[error("bad {expected} config value '{value}' for '{name}'")]
+    InvalidValue {
+        name: String,
+        value: String,
+        expected: &'static str,
+    },
+
+    #[error("{0} has multiple values")]
+    MultipleValues(String),
+
+    #[error("{0} is not set")]
+    NotFound(String),
+
+    #[error("exceeded maximum include depth ({}) while including {}", MAX_INCLUDE_DEPTH, .0.display())]
+    IncludeDepth(PathBuf),
+
+    #[error("invalid config environment: {0}")]
+    InvalidEnvironment(String),
+
+    #[error("author identity unknown, please set user.name and user.email")]
+    IdentityUnknown,
+
+    #[error("invalid date format: {0}")]
+    InvalidDate(String),
+}
+
+/// Interpret a value as a boolean like git. A variable without a value is true
+pub fn parse_bool(value: Option<&str>) -> Option<bool> {
+    let Some(value) = value else {
+        return Some(true);
+    };
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" => Some(true),
+        "false" | "no" | "off" | "" => Some(false),
+        _ => parse_int(value).map(|n| n != 0),
+    }
+}
+
+/// Interpret a value as an integer like git, with an optional `k`, `m` or `g` unit
+pub fn parse_int(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (number, factor) = match value.chars().last()?.to_ascii_lowercase() {
+        'k' => (&value[..value.len() - 1], 1 << 10),
+        'm' => (&value[..value.len() - 1], 1 << 20),
+        'g' => (&value[..value.len() - 1], 1 << 30),
+        _ => (value, 1),
+    };
+    number.parse::<i64>().ok()?.checked_mul(factor)
+}
+
+/// Expand a leading `~/` to the home directory
+pub fn expand_path(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME").map(PathBuf::from)
+}
+
+// What the conditions of `includeIf` are evaluated against
+#[derive(Debug, Default)]
+struct IncludeContext {
+    git_dir: Option<PathBuf>,
+    branch: Option<String>,
+}
+
+impl IncludeContext {
+    fn new(git_dir: &Path) -> Self {
+        let branch = fs::read_to_string(git_dir.join("HEAD"))
+            .ok()
+            .and_then(|head| {
+                head.trim()
+                    .strip_prefix("ref: refs/heads/")
+                    .map(str::to_string)
+            });
+        IncludeContext {
+            git_dir: Some(fs::canonicalize(git_dir).unwrap_or_else(|_| git_dir.to_path_buf())),
+            branch,
+        }
+    }
+
+    // Evaluate the condition of `[includeIf "<condition>"]` in the file at `path`
+    fn matches(&self, condition: &str, path: &Path) -> bool {
+        if let Some(pattern) = condition.strip_prefix("gitdir:") {
+            self.matches_git_dir(pattern, path, false)
+        } else if let Some(pattern) = condition.strip_prefix("gitdir/i:") {
+            self.matches_git_dir(pattern, path, true)
+        } else if let Some(pattern) = condition.strip_prefix("onbranch:") {
+            let mut pattern = pattern.to_string();
+            if pattern.ends_with('/') {
+                pattern.push_str("**");
+            }
+            self.branch
+                .as_ref()
+                .is_some_and(|branch| wildmatch(pattern.as_bytes(), branch.as_bytes()))
+        } else {
+            false
+        }
+    }
+
+    fn matches_git_dir(&self, pattern: &str, path: &Path, ignore_case: bool) -> bool {
+        let Some(git_dir) = &self.git_dir else {
+            return false;
+        };
+
+        let mut pattern = match pattern.strip_prefix("./") {
+            Some(relative) => path
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(relative)
+                .to_string_lossy()
+                .into_owned(),
+            None => expand_path(pattern).to_string_lossy().into_owned(),
+        };
+        if !pattern.starts_with('/') {
+            pattern.insert_str(0, "**/");
+        }
+        if pattern.ends_with('/') {
+            pattern.push_str("**");
+        }
+
+        let mut git_dir = git_dir.to_string_lossy().into_owned();
+        if ignore_case {
+            pattern = pattern.to_lowercase();
+            git_dir = git_dir.to_lowercase();
+        }
+        wildmatch(pattern.as_bytes(), git_dir.as_bytes())
+    }
+}
+
+impl Config {
+    /// Parse the content of a config file, without following includes
+    pub fn parse(content: &str) -> Result<Self, ConfigError> {
+        ConfigFile::parse(Path::new(""), content.to_string()).map(Config::from)
+    }
+
+    /// Read a config file and the files it includes. A missing file is the same as an empty one
+    ///
+    /// The conditions of `includeIf` are evaluated against the repository at `git_dir` if any
+    pub fn open(path: &Path, git_dir: Option<&Path>) -> Result<Self, ConfigError> {
+        let context = git_dir.map(IncludeContext::new).unwrap_or_default();
+        let mut config = Config::default();
+        config.read_file(path, &context, 0)?;
+        Ok(config)
+    }
+
+    /// Read all the config files, from the lowest to the highest priority: the system config,
+    /// the global config, the config of the repository if there is one, then the variables of
+    /// the environment
+    pub fn open_all(git_dir: Option<&Path>) -> Result<Self, ConfigError> {
+        let context = git_dir.map(IncludeContext::new).unwrap_or_default();
+
+        let mut config = Config::default();
+        if let Some(path) = Self::system_path() {
+            config.read_file(&path, &context, 0)?;
+        }
+        for path in Self::global_paths() {
+            config.read_file(&path, &context, 0)?;
+        }
+        if let Some(git_dir) = git_dir {
+            config.read_file(&git_dir.join("config"), &context, 0)?;
+        }
+        config.read_environment()?;
+        Ok(config)
+    }
+
+    /// The system config file, unless `GIT_CONFIG_NOSYSTEM` is set
+    pub fn system_path() -> Option<PathBuf> {
+        if env::var("GIT_CONFIG_NOSYSTEM").is_ok_and(|value| parse_bool(Some(&value)) == Some(true))
+        {
+            return None;
+        }
+        Some(
+            env::var_os("GIT_CONFIG_SYSTEM")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from("/etc/gitconfig")),
+        )
+    }
+
+    /// The global config files, in the order they are read: `$XDG_CONFIG_HOME/git/config` then
+    /// `~/.gitconfig`, or only `GIT_CONFIG_GLOBAL` if it is set
+    pub fn global_paths() -> Vec<PathBuf> {
+        if let Some(path) = env::var_os("GIT_CONFIG_GLOBAL") {
+            return vec![PathBuf::from(path)];
+        }
+        let xdg_config = env::var_os("XDG_CONFIG_HOME")
+            .filter(|xdg_config_home| !xdg_config_home.is_empty())
+            .map(|xdg_config_home| PathBuf::from(xdg_config_home).join("git/config"))
+            .or_else(|| home_dir().map(|home| home.join(".config/git/config")));
+        let home_config = home_dir().map(|home| home.join(".gitconfig"));
+        xdg_config.into_iter().chain(home_config).collect()
+    }
+
+    /// The global config file that is written: `~/.gitconfig`, unless only the XDG config
+    /// exists
+    pub fn global_path() -> Option<PathBuf> {
+        let paths = Self::global_paths();
+        match paths.as_slice() {
+            [xdg_config, home_config] if !home_config.exists() && xdg_config.exists() => {
+                Some(xdg_config.clone())
+            }
+            _ => paths.last().cloned(),
+        }
+    }
+
+    fn read_file(
+        &mut self,
+        path: &Path,
+        context: &IncludeContext,
+        depth: usize,
+    ) -> Result<(), ConfigError> {
+        let file = ConfigFile::open(path)?;
+        for entry in file.entries() {
+            self.entries.push(ConfigEntry {
+                name: entry.name.clone(),
+                value: entry.value.clone(),
+            });
+
+            // the variables of an included file come right after the include
+            if let Some(included) = Self::included_path(entry, path, context) {
+                if depth >= MAX_INCLUDE_DEPTH {
+                    return Err(ConfigError::IncludeDepth(included));
+                }
+                self.read_file(&included, context, depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    // The file included by `include.path` or by `includeIf.<condition>.path` if the condition
+    // holds. Relative paths are relative to the directory of the including file
+    fn included_path(entry: &FileEntry, path: &Path, context: &IncludeContext) -> Option<PathBuf> {
+        if !entry.name.key.eq_ignore_ascii_case("path") {
+            return None;
+        }
+        let included = match (entry.name.section.as_str(), &entry.name.subsection) {
+            ("include", None) => true,
+            ("includeif", Some(condition)) => context.matches(condition, path),
+            _ => false,
+        };
+        if !included {
+            return None;
+        }
+
+        let included = expand_path(entry.value.as_deref()?);
+        if included.is_relative() {
+            Some(path.parent().unwrap_or(Path::new("")).join(included))
+        } else {
+            Some(included)
+        }
+    }
+
+    // Variables given as GIT_CONFIG_KEY_<n> and GIT_CONFIG_VALUE_<n>
+    fn read_environment(&mut self) -> Result<(), ConfigError> {
+        let Ok(count) = env::var("GIT_CONFIG_COUNT") else {
+            return Ok(());
+        };
+        let count: usize = count.parse().map_err(|_| {
+            ConfigError::InvalidEnvironment("bogus count in GIT_CONFIG_COUNT".into())
+        })?;
+        for i in 0..count {
+            let variable = |prefix| {
+                let variable = format!("{}{}", prefix, i);
+                env::var(&variable)
+                    .map_err(|_| ConfigError::InvalidEnvironment(format!("missing {}", variable)))
+            };
+            let name = variable("GIT_CONFIG_KEY_")?;
+            let value = variable("GIT_CONFIG_VALUE_")?;
+            self.entries.push(ConfigEntry {
+                name: ConfigName::parse(&name)?,
+                value: Some(value),
+            });
+        }
+        Ok(())
+    }
+
+    /// All the variables, in the order they were read
+    pub fn entries(&self) -> &[ConfigEntry] {
+        &self.entries
+    }
+
+    /// The entries of a variable, in the order they were read
+    pub fn get_entries(&self, name: &str) -> impl Iterator<Item = &ConfigEntry> + '_ {
+        let name = ConfigName::parse(name).ok();
+        self.entries
+            .iter()
+            .filter(move |entry| name.as_ref() == Some(&entry.name))
+    }
+
+    /// Get the value of a variable given as `section.key` or `section.subsection.key`
+    ///
+    /// The last value wins when a variable is set multiple times. A variable without a value
+    /// (e.g. `[core] bare`) is returned as `"true"`
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.get_all(name).last()
+    }
+
+    /// Get all the values of a multi-valued variable, in the order they were read
+    pub fn get_all(&self, name: &str) -> impl Iterator<Item = &str> + '_ {
+        self.get_entries(name)
+            .map(|entry| entry.value.as_deref().unwrap_or("true"))
+    }
+
+    /// Get a boolean variable: true, yes, on or a non-zero number, and false, no, off, 0 or
+    /// empty
+    pub fn get_bool(&self, name: &str) -> Result<Option<bool>, ConfigError> {
+        let Some(entry) = self.get_entries(name).last() else {
+            return Ok(None);
+        };
+        parse_bool(entry.value.as_deref())
+            .map(Some)
+            .ok_or_else(|| ConfigError::InvalidValue {
+                name: name.to_string(),
+                value: entry.value.clone().unwrap_or_default(),
+                expected: "boolean",
+            })
+    }
+
+    /// Get an integer variable, which can have a `k`, `m` or `g` unit
+    pub fn get_int(&self, name: &str) -> Result<Option<i64>, ConfigError> {
+        let Some(value) = self.get(name) else {
+            return Ok(None);
+        };
+        parse_int(value)
+            .map(Some)
+            .ok_or_else(|| ConfigError::InvalidValue {
+                name: name.to_string(),
+                value: value.to_string(),
+                expected: "numeric",
+            })
+    }
+
+    /// Get a path variable, where a leading `~/` is the home directory
+    pub fn get_path(&self, name: &str) -> Option<PathBuf> {
+        self.get(name).map(expand_path)
+    }
+}
+
+impl From<ConfigFile> for Config {
+    /// The variables of a single file, without following includes
+    fn from(file: ConfigFile) -> Self {
+        Config {
+            entries: file
+                .entries()
+                .iter()
+                .map(|entry| ConfigEntry {
+                    name: entry.name.clone(),
+                    value: entry.value.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Repository {
+    /// Read the config that applies to the repository, see `Config::open_all`
+    pub fn config(&self) -> Result<Config, ConfigError> {
+        Config::open_all(Some(&self.git_dir))
+    }
+
+    /// The config file of the repository, `.git/config`
+    pub fn config_path(&self) -> PathBuf {
+        self.git_dir.join("config")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let config = Config::parse(
+            r#"[core]
+	bare = false
+	filemode
+[remote "origin"]
+	url = https://example.com/repo.git ; comment
+	fetch = +refs/heads/*:refs/remotes/origin/*
+[branch "feature.x"]
+	remote = origin
+	Merge = "refs/heads/feature x"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.get("core.bare"), Some("false"));
+        assert_eq!(config.get("Core.FileMode"), Some("true"));
+        assert_eq!(
+            config.get("remote.origin.url"),
+            Some("https://example.com/repo.git")
+        );
+        assert_eq!(
+            config.get("branch.feature.x.merge"),
+            Some("refs/heads/feature x")
+        );
+        assert_eq!(config.get("branch.Feature.x.merge"), None);
+        assert_eq!(config.get("core.missing"), None);
+    }
+
+    #[test]
+    fn syntax_error() {
+        assert!(Config::parse("key = value\n").is_err());
+        assert!(Config::parse("[core]\n\tname = \"unclosed\n").is_err());
+    }
+
+    #[test]
+    fn typed_values() {
+        let config = Config::parse(
+            "[a]\n\tflag\n\tyes = on\n\tno = 0\n\tsize = 10k\n\tbig = 2G\n\tbad = maybe\n",
+        )
+        .unwrap();
+        assert_eq!(config.get_bool("a.flag").unwrap(), Some(true));
+        assert_eq!(config.get_bool("a.yes").unwrap(), Some(true));
+        assert_eq!(config.get_bool("a.no").unwrap(), Some(false));
+        assert_eq!(config.get_bool("a.size").unwrap(), Some(true));
+        assert_eq!(config.get_bool("a.missing").unwrap(), None);
+        assert!(config.get_bool("a.bad").is_err());
+        assert_eq!(config.get_int("a.size").unwrap(), Some(10240));
+        assert_eq!(config.get_int("a.big").unwrap(), Some(2 << 30));
+        assert!(config.get_int("a.bad").is_err());
+    }
+
+    #[test]
+    fn names() {
+        let name = ConfigName::parse("Branch.Feature.x.Merge").unwrap();
+        assert_eq!(name.section, "branch");
+        assert_eq!(name.subsection.as_deref(), Some("Feature.x"));
+        assert_eq!(name.key, "Merge");
+        assert_eq!(name.to_string(), "branch.Feature.x.merge");
+
+        assert!(ConfigName::parse("novalue").is_err());
+        assert!(ConfigName::parse("a.1key").is_err());
+        assert!(ConfigName::parse("a_b.key").is_err());
+    }
+
+    #[test]
+    fn wildmatch_patterns() {
+        assert!(wildmatch(b"**/work/**", b"/home/me/work/repo/.git"));
+        assert!(wildmatch(b"/home/*/.git", b"/home/me/.git"));
+        assert!(!wildmatch(b"/home/*/.git", b"/home/me/repo/.git"));
+        assert!(wildmatch(b"feature/**", b"feature/a/b"));
+        assert!(wildmatch(b"ma?n", b"main"));
+    }
+
+    #[test]
+    fn includes() {
+        let dir = test_utils::test_path!();
+        fs::create_dir_all(dir.join("repo/.git")).unwrap();
+        fs::write(dir.join("repo/.git/HEAD"), "ref: refs/heads/topic\n").unwrap();
+        fs::write(
+            dir.join("repo/.git/config"),
+            concat!(
+                "[a]\n\tx = 1\n",
+                "[include]\n\tpath = ../../included\n",
+                "[a]\n\ty = 1\n",
+                "[includeIf \"onbranch:topic\"]\n\tpath = ../../branch\n",
+                "[includeIf \"onbranch:main\"]\n\tpath = ../../other\n",
+                "[includeIf \"gitdir:**/repo/.git\"]\n\tpath = ../../gitdir\n",
+                "[include]\n\tpath = ../../missing\n",
+            ),
+        )
+        .unwrap();
+        fs::write(dir.join("included"), "[a]\n\tx = 2\n\ty = 2\n").unwrap();
+        fs::write(dir.join("branch"), "[b]\n\tbranch = yes\n").unwrap();
+        fs::write(dir.join("other"), "[b]\n\tother = yes\n").unwrap();
+        fs::write(dir.join("gitdir"), "[b]\n\tgitdir = yes\n").unwrap();
+
+        let mut config = Config::default();
+        let git_dir = dir.join("repo/.git");
+        config
+            .read_file(&git_dir.join("config"), &IncludeContext::new(&git_dir), 0)
+            .unwrap();
+        assert_eq!(config.get("a.x"), Some("2"));
+        assert_eq!(config.get("a.y"), Some("1"));
+        assert_eq!(config.get("b.branch"), Some("yes"));
+        assert_eq!(config.get("b.other"), None);
+        assert_eq!(config.get("b.gitdir"), Some("yes"));
+
+        // a file including itself
+        fs::write(dir.join("cycle"), "[include]\n\tpath = cycle\n").unwrap();
+        assert!(matches!(
+            Config::open(&dir.join("cycle"), None),
+            Err(ConfigError::IncludeDepth(_))
+        ));
+    }
+}
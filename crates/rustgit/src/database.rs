@@ -1,21 +1,31 @@
 // Object Database at .git/objects
 // Also including an in-memory cache
+//
+// Objects are first looked up as loose objects, then in the packs under .git/objects/pack
 
 use crate::{
-    object::{Object, ObjectBuffer},
+    object::{read_header, Object, ObjectBuffer, ObjectHeader, ObjectType},
     object_reader::ObjectReader,
     oid::ObjectId,
+    pack::Pack,
     Repository,
 };
+use sha1::Digest;
 use std::{
+    cell::OnceCell,
     fs,
     fs::File,
+    io,
+    io::{BufWriter, ErrorKind, Read, Write},
     path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
 };
 use thiserror::Error;
 
 pub(crate) struct Database {
     objects_dir: PathBuf,
+    // lazily loaded on the first lookup of an object that is not loose
+    packs: OnceCell<Vec<Pack>>,
 }
 
 #[derive(Error, Debug)]
@@ -31,15 +41,96 @@ impl DatabaseWriteError {
     }
 }
 
+/// Minimum number of hex digits of an abbreviated object id
+pub const MIN_ABBREV_LENGTH: usize = 4;
+
+/// Number of hex digits git uses by default when displaying an abbreviated object id, in
+/// repositories with few objects
+pub const DEFAULT_ABBREV_LENGTH: usize = 7;
+
+/// An error raised when resolving an abbreviated object id
+#[derive(Error, Debug)]
+pub enum ObjectPrefixError {
+    #[error("'{0}' is not an abbreviated object id of 4 to 40 hex digits")]
+    InvalidPrefix(String),
+
+    #[error("no object matches the short object ID {0}")]
+    NotFound(String),
+
+    #[error("short object ID {prefix} is ambiguous, candidates are: {}", join_oids(.candidates))]
+    Ambiguous {
+        prefix: String,
+        candidates: Vec<ObjectId>,
+    },
+
+    #[error("IO Error")]
+    IOError(#[from] io::Error),
+}
+
+// Size of the chunks in which object contents are streamed
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Hash an object of `size` bytes read from `reader`, also copying the object with its header
+/// to `output` if any
+///
+/// Fails if `reader` doesn't give exactly `size` bytes, e.g. when a file changes while it is read
+pub(crate) fn stream_object(
+    typ: ObjectType,
+    size: u64,
+    reader: &mut impl Read,
+    mut output: Option<&mut dyn Write>,
+) -> io::Result<ObjectId> {
+    let mut hasher = sha1::Sha1::new();
+    let mut consume = |data: &[u8]| -> io::Result<()> {
+        hasher.update(data);
+        match output.as_mut() {
+            Some(output) => output.write_all(data),
+            None => Ok(()),
+        }
+    };
+
+    consume(format!("{} {}\0", typ, size).as_bytes())?;
+
+    let mut buffer = vec![0; STREAM_CHUNK_SIZE];
+    let mut remaining = size;
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        remaining = remaining.checked_sub(read as u64).ok_or_else(|| {
+            io::Error::new(ErrorKind::InvalidData, "content larger than expected")
+        })?;
+        consume(&buffer[..read])?;
+    }
+    if remaining != 0 {
+        return Err(io::Error::new(
+            ErrorKind::UnexpectedEof,
+            "content smaller than expected",
+        ));
+    }
+
+    Ok(ObjectId(hasher.finalize().into()))
+}
+
+fn join_oids(oids: &[ObjectId]) -> String {
+    oids.iter()
+        .map(ObjectId::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 impl Database {
     pub(crate) fn open(git_dir: &Path) -> Self {
         Self {
             objects_dir: git_dir.join("objects"),
+            packs: OnceCell::new(),
         }
     }
 
     pub(crate) fn object_path_from_oid(&self, oid: ObjectId) -> PathBuf {
-        // TODO: support shortest unique object hashes
         let hash_hex_string = oid.to_hex_string().0;
         let (s1, s2) = hash_hex_string.split_at(2);
 
@@ -50,14 +141,150 @@ impl Database {
         path
     }
 
+    fn packs(&self) -> io::Result<&[Pack]> {
+        if let Some(packs) = self.packs.get() {
+            return Ok(packs);
+        }
+        let packs = Pack::open_all(&self.objects_dir.join("pack"))?;
+        Ok(self.packs.get_or_init(|| packs))
+    }
+
+    fn is_packed(&self, oid: ObjectId) -> bool {
+        self.packs()
+            .is_ok_and(|packs| packs.iter().any(|pack| pack.contains(oid)))
+    }
+
     /// Given an object id, give back an object reader for the object on disk
     pub(crate) fn object_reader(&self, oid: ObjectId) -> std::io::Result<ObjectReader> {
         let file = fs::OpenOptions::new()
             .read(true)
             .create(false)
-            .open(self.object_path_from_oid(oid))?;
+            .open(self.object_path_from_oid(oid));
 
-        Ok(ObjectReader::from_file(file))
+        match file {
+            Ok(file) => Ok(ObjectReader::from_file(file)),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                let (typ, content) = self.read_packed_object(oid)?.ok_or(e)?;
+                let buffer = ObjectBuffer::new(typ, &content);
+                Ok(ObjectReader::from_bytes(buffer.into_data()))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Find all the objects, loose or packed, whose hex representation starts with `prefix`
+    ///
+    /// `prefix` should be made of at least 2 lowercase hex digits. The result is sorted
+    fn find_objects_with_prefix(&self, prefix: &str) -> io::Result<Vec<ObjectId>> {
+        let mut oids = vec![];
+
+        let (fan_out, rest) = prefix.split_at(2);
+        match fs::read_dir(self.objects_dir.join(fan_out)) {
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+            Ok(entries) => {
+                for entry in entries {
+                    let file_name = entry?.file_name();
+                    let Some(file_name) = file_name.to_str() else {
+                        continue;
+                    };
+                    if file_name.starts_with(rest) {
+                        let hex = format!("{}{}", fan_out, file_name);
+                        if let Ok(oid) = ObjectId::from_unvalidated_sh1_hex_string(&hex) {
+                            oids.push(oid);
+                        }
+                    }
+                }
+            }
+        }
+
+        for pack in self.packs()? {
+            oids.extend(pack.oids_with_prefix(prefix));
+        }
+
+        oids.sort();
+        oids.dedup();
+        Ok(oids)
+    }
+
+    // The number of objects in the packs. Like git, loose objects are not counted
+    fn approximate_object_count(&self) -> io::Result<usize> {
+        Ok(self.packs()?.iter().map(Pack::object_count).sum())
+    }
+
+    // Look up an object in all the packs, resolving its delta chain
+    fn read_packed_object(&self, oid: ObjectId) -> io::Result<Option<(ObjectType, Vec<u8>)>> {
+        for pack in self.packs()? {
+            if let Some(object) = pack.read_object(oid, |base| self.read_object_content(base))? {
+                return Ok(Some(object));
+            }
+        }
+        Ok(None)
+    }
+
+    // Read the type and content of an object wherever it is stored
+    fn read_object_content(&self, oid: ObjectId) -> io::Result<(ObjectType, Vec<u8>)> {
+        let mut reader = self.object_reader(oid)?;
+        let ObjectHeader { typ, size } =
+            read_header(&mut reader).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+        let mut content = Vec::with_capacity(size);
+        reader.read_to_end(&mut content)?;
+        Ok((typ, content))
+    }
+
+    // A path in the object directory to write an object to before its id is known
+    fn temporary_object_path(&self) -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let counter = COUNTER.fetch_add(1, Ordering::Relaxed);
+        self.objects_dir
+            .join(format!("tmp_obj_{}_{}", std::process::id(), counter))
+    }
+
+    /// Write an object whose content of `size` bytes is read from `reader`, compressing it on
+    /// the fly so that it is never fully in memory
+    pub(crate) fn write_object_stream(
+        &self,
+        typ: ObjectType,
+        size: u64,
+        reader: &mut impl Read,
+    ) -> Result<ObjectId, DatabaseWriteError> {
+        use flate2::write::ZlibEncoder;
+
+        let temporary_path = self.temporary_object_path();
+        let to_database_write_error = |path: &Path| {
+            let path = path.to_path_buf();
+            |source| DatabaseWriteError { path, source }
+        };
+
+        let mut write_temporary = || -> io::Result<ObjectId> {
+            fs::create_dir_all(&self.objects_dir)?;
+            let file = BufWriter::new(File::create(&temporary_path)?);
+            let mut encoder = ZlibEncoder::new(file, Default::default());
+            let oid = stream_object(typ, size, reader, Some(&mut encoder))?;
+            encoder.finish()?.flush()?;
+            Ok(oid)
+        };
+        let oid = match write_temporary() {
+            Ok(oid) => oid,
+            Err(e) => {
+                let _ = fs::remove_file(&temporary_path);
+                return Err(to_database_write_error(&temporary_path)(e));
+            }
+        };
+
+        let object_path = self.object_path_from_oid(oid);
+        if object_path.exists() || self.is_packed(oid) {
+            // already exist
+            let _ = fs::remove_file(&temporary_path);
+            return Ok(oid);
+        }
+
+        let parent_path = object_path
+            .parent()
+            .expect("object path should have parent");
+        fs::create_dir_all(parent_path).map_err(to_database_write_error(parent_path))?;
+        fs::rename(&temporary_path, &object_path).map_err(to_database_write_error(&object_path))?;
+        Ok(oid)
     }
 
     // Write an already in-memory object
@@ -72,7 +299,7 @@ impl Database {
         // TODO: write to a temporary object first
 
         let object_path = self.object_path_from_oid(oid);
-        if object_path.exists() {
+        if object_path.exists() || self.is_packed(oid) {
             // already exist. Quit
             return Ok(());
         }
@@ -108,6 +335,48 @@ impl Repository {
         self.database.object_reader(oid)
     }
 
+    /// Resolve an abbreviated object id of 4 to 40 hex digits to the only object it matches
+    ///
+    /// Both loose objects and the objects of the packs are considered
+    pub fn resolve_object_prefix(&self, prefix: &str) -> Result<ObjectId, ObjectPrefixError> {
+        let is_hex = prefix.bytes().all(|c| c.is_ascii_hexdigit());
+        if !(MIN_ABBREV_LENGTH..=40).contains(&prefix.len()) || !is_hex {
+            return Err(ObjectPrefixError::InvalidPrefix(prefix.to_string()));
+        }
+
+        let prefix = prefix.to_ascii_lowercase();
+        let mut candidates = self.database.find_objects_with_prefix(&prefix)?;
+        match candidates.len() {
+            0 => Err(ObjectPrefixError::NotFound(prefix)),
+            1 => Ok(candidates.remove(0)),
+            _ => Err(ObjectPrefixError::Ambiguous { prefix, candidates }),
+        }
+    }
+
+    /// The minimum length of abbreviated object ids, scaled from the number of objects like
+    /// git's `core.abbrev=auto`: never less than `DEFAULT_ABBREV_LENGTH`, one more hex digit
+    /// each time the number of objects is multiplied by 4
+    pub fn default_abbrev_length(&self) -> io::Result<usize> {
+        let count = self.database.approximate_object_count()?;
+        // the same formula as git: half the number of bits of the count, rounded up
+        let bits = (usize::BITS - count.leading_zeros()) as usize;
+        Ok(bits.div_ceil(2).max(DEFAULT_ABBREV_LENGTH))
+    }
+
+    /// The shortest prefix of at least `min_length` hex digits that only matches `oid`
+    pub fn abbreviate(&self, oid: ObjectId, min_length: usize) -> io::Result<String> {
+        let hex = oid.to_string();
+        let mut length = min_length.clamp(MIN_ABBREV_LENGTH, 40);
+        while length < 40 {
+            let candidates = self.database.find_objects_with_prefix(&hex[..length])?;
+            if candidates.iter().all(|&candidate| candidate == oid) {
+                break;
+            }
+            length += 1;
+        }
+        Ok(hex[..length].to_string())
+    }
+
     /// Calculate the oid of an object, write the object to the database, and return the oid
     pub fn write_object(&self, object: &impl Object) -> Result<ObjectId, DatabaseWriteError> {
         let buffer = object.to_buffer();
@@ -0,0 +1,250 @@
+// Changes of files between two trees, a tree and the index, or the index and the working tree
+
+use crate::config::ConfigError;
+use crate::index::IndexReadError;
+use crate::object::{hash_blob_file, ObjectReadError, GITLINK_MODE};
+use crate::oid::ObjectId;
+use crate::path_order::GitPath;
+use crate::references::ReferenceError;
+use crate::status::ChangeKind;
+use crate::worktree::worktree_metadata;
+use crate::Repository;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// Where the content of a side of a change can be read from
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FileLocation {
+    /// A blob of the object database
+    Database,
+
+    /// A file of the working tree, which is not necessarily in the database
+    WorkTree,
+}
+
+/// One side of a file change
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DiffFile {
+    pub oid: ObjectId,
+    pub mode: u32,
+    pub location: FileLocation,
+}
+
+/// A file that differs between two sides of a comparison
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    /// Path relative to the root of the repository
+    pub path: PathBuf,
+
+    /// None if the file is added
+    pub old: Option<DiffFile>,
+
+    /// None if the file is deleted
+    pub new: Option<DiffFile>,
+}
+
+impl FileChange {
+    pub fn kind(&self) -> ChangeKind {
+        match (&self.old, &self.new) {
+            (None, _) => ChangeKind::Added,
+            (_, None) => ChangeKind::Deleted,
+            _ => ChangeKind::Modified,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DiffError {
+    #[error(transparent)]
+    IndexReadError(#[from] IndexReadError),
+
+    #[error(transparent)]
+    ReferenceError(#[from] ReferenceError),
+
+    #[error(transparent)]
+    ObjectReadError(#[from] ObjectReadError),
+
+    #[error(transparent)]
+    ConfigError(#[from] ConfigError),
+
+    #[error("IO Error")]
+    IOError(#[from] io::Error),
+}
+
+fn database_file(oid: ObjectId, mode: u32) -> DiffFile {
+    DiffFile {
+        oid,
+        mode,
+        location: FileLocation::Database,
+    }
+}
+
+// Compare two sets of files keyed by path. The changes are in git's order, by the bytes of the
+// paths
+fn diff_files(
+    mut old: BTreeMap<PathBuf, DiffFile>,
+    new: BTreeMap<PathBuf, DiffFile>,
+) -> Vec<FileChange> {
+    let mut changes = BTreeMap::new();
+    for (path, new_file) in new {
+        match old.remove(&path) {
+            Some(old_file) if old_file.oid == new_file.oid && old_file.mode == new_file.mode => {}
+            old_file => {
+                let change = FileChange {
+                    path: path.clone(),
+                    old: old_file,
+                    new: Some(new_file),
+                };
+                changes.insert(GitPath(path), change);
+            }
+        }
+    }
+    for (path, old_file) in old {
+        let change = FileChange {
+            path: path.clone(),
+            old: Some(old_file),
+            new: None,
+        };
+        changes.insert(GitPath(path), change);
+    }
+    changes.into_values().collect()
+}
+
+impl Repository {
+    // The flattened files of a tree, or nothing for an absent tree
+    fn tree_files(&self, tree: Option<ObjectId>) -> Result<BTreeMap<PathBuf, DiffFile>, DiffError> {
+        let Some(tree) = tree else {
+            return Ok(BTreeMap::new());
+        };
+        Ok(self
+            .read_tree_recursive(tree)?
+            .into_iter()
+            .map(|(path, entry)| (path, database_file(entry.oid, entry.mode)))
+            .collect())
+    }
+
+    /// Compare two trees. A `None` tree is treated as empty
+    pub fn diff_trees(
+        &self,
+        old: Option<ObjectId>,
+        new: Option<ObjectId>,
+    ) -> Result<Vec<FileChange>, DiffError> {
+        Ok(diff_files(self.tree_files(old)?, self.tree_files(new)?))
+    }
+
+    /// Compare a tree, usually the one of HEAD, with the index. A `None` tree is treated as empty.
+    /// Paths with merge conflicts are left out
+    pub fn diff_tree_to_index(&self, tree: Option<ObjectId>) -> Result<Vec<FileChange>, DiffError> {
+        let index = self.index()?;
+        // paths added with intent-to-add are not staged yet
+        let index_files = index
+            .iter()
+            .filter(|entry| entry.stage() == 0 && !entry.flags.intent_to_add)
+            .map(|entry| {
+                let file = database_file(entry.oid, entry.metadata.mode);
+                (entry.path.to_path_buf(), file)
+            })
+            .collect();
+        let mut tree_files = self.tree_files(tree)?;
+        for conflict in index.conflicts() {
+            tree_files.remove(conflict.path);
+        }
+        Ok(diff_files(tree_files, index_files))
+    }
+
+    /// Compare the index with the working tree. Files that are not in the index and paths with
+    /// merge conflicts are ignored. Like git, a file added with intent-to-add is a new file
+    pub fn diff_index_to_worktree(&self) -> Result<Vec<FileChange>, DiffError> {
+        // A file modified in the same second as the index was written can't be trusted by its
+        // stat data ("racy git"), so we always rehash those
+        let index_mtime = match fs::metadata(self.index_path()) {
+            Ok(metadata) => metadata
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs()),
+            Err(e) if e.kind() == ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+
+        let symlinks = self.has_symlinks()?;
+        let mut changes = vec![];
+        // files outside of a sparse checkout are not expected in the working tree
+        for entry in self
+            .index()?
+            .iter()
+            .filter(|entry| entry.stage() == 0 && !entry.flags.skip_worktree)
+        {
+            let old = Some(database_file(entry.oid, entry.metadata.mode));
+
+            let path = self.repository_dir.join(entry.path);
+            // the commit checked out in a submodule is not compared, only its directory is
+            if entry.metadata.mode == GITLINK_MODE {
+                if !path.is_dir() {
+                    changes.push(FileChange {
+                        path: entry.path.to_path_buf(),
+                        old,
+                        new: None,
+                    });
+                }
+                continue;
+            }
+            // a symbolic link is a file of its own, even when its target is missing
+            let is_file = path
+                .symlink_metadata()
+                .is_ok_and(|metadata| !metadata.is_dir());
+            if !is_file {
+                changes.push(FileChange {
+                    path: entry.path.to_path_buf(),
+                    old,
+                    new: None,
+                });
+                continue;
+            }
+            let metadata = worktree_metadata(&path, entry.metadata.mode, symlinks)?;
+            if entry.flags.intent_to_add {
+                changes.push(FileChange {
+                    path: entry.path.to_path_buf(),
+                    old: None,
+                    new: Some(DiffFile {
+                        oid: hash_blob_file(&path)?,
+                        mode: metadata.mode,
+                        location: FileLocation::WorkTree,
+                    }),
+                });
+                continue;
+            }
+
+            let is_racy = u64::from(metadata.mtime_seconds) >= index_mtime;
+            if metadata.matches_stat(&entry.metadata) && !is_racy {
+                continue;
+            }
+
+            let oid = hash_blob_file(&path)?;
+            if metadata.mode != entry.metadata.mode || oid != entry.oid {
+                changes.push(FileChange {
+                    path: entry.path.to_path_buf(),
+                    old,
+                    new: Some(DiffFile {
+                        oid,
+                        mode: metadata.mode,
+                        location: FileLocation::WorkTree,
+                    }),
+                });
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Read the content of a side of a change
+    pub fn read_diff_file(&self, path: &Path, file: &DiffFile) -> Result<Vec<u8>, DiffError> {
+        match file.location {
+            FileLocation::Database => Ok(self.read_blob(file.oid)?),
+            FileLocation::WorkTree => Ok(fs::read(self.repository_dir.join(path))?),
+        }
+    }
+}
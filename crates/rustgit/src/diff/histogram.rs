@@ -0,0 +1,101 @@
+// Histogram diff
+//
+// Like patience diff, but instead of requiring unique lines, the common region containing the
+// least frequent line of `old` is used to split the problem. Lines that appear too often are not
+// considered, and Myers is used when no region can be found.
+
+use super::{diff_range, myers, DiffAlgorithm, Edit};
+use std::collections::HashMap;
+
+// Lines occurring more often than this in `old` are not used to find a common region
+const MAX_CHAIN_LENGTH: usize = 64;
+
+struct Region {
+    old_start: usize,
+    new_start: usize,
+    len: usize,
+    // the lowest number of occurrences in `old` of the lines of the region
+    occurrences: usize,
+}
+
+fn find_region(old: &[usize], new: &[usize]) -> Option<Region> {
+    let mut positions: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (i, line) in old.iter().enumerate() {
+        positions.entry(*line).or_default().push(i);
+    }
+
+    let mut best: Option<Region> = None;
+    let mut j = 0;
+    while j < new.len() {
+        let mut next_j = j + 1;
+        let candidates = match positions.get(&new[j]) {
+            Some(candidates) if candidates.len() <= MAX_CHAIN_LENGTH => candidates,
+            _ => {
+                j = next_j;
+                continue;
+            }
+        };
+
+        for &i in candidates {
+            let (mut old_start, mut new_start) = (i, j);
+            while old_start > 0 && new_start > 0 && old[old_start - 1] == new[new_start - 1] {
+                old_start -= 1;
+                new_start -= 1;
+            }
+            let (mut old_end, mut new_end) = (i + 1, j + 1);
+            while old_end < old.len() && new_end < new.len() && old[old_end] == new[new_end] {
+                old_end += 1;
+                new_end += 1;
+            }
+
+            let occurrences = old[old_start..old_end]
+                .iter()
+                .map(|line| positions[line].len())
+                .min()
+                .expect("a region is never empty");
+            let len = old_end - old_start;
+
+            let is_better = match &best {
+                None => true,
+                Some(best) => {
+                    occurrences < best.occurrences
+                        || (occurrences == best.occurrences && len > best.len)
+                }
+            };
+            if is_better {
+                best = Some(Region {
+                    old_start,
+                    new_start,
+                    len,
+                    occurrences,
+                });
+            }
+            // the lines of this region have been considered already
+            next_j = next_j.max(new_end);
+        }
+        j = next_j;
+    }
+    best
+}
+
+pub(super) fn diff(old: &[usize], new: &[usize], edits: &mut Vec<Edit>) {
+    let Some(region) = find_region(old, new) else {
+        myers::diff(old, new, edits);
+        return;
+    };
+
+    let (old_end, new_end) = (region.old_start + region.len, region.new_start + region.len);
+    diff_range(
+        &old[..region.old_start],
+        &new[..region.new_start],
+        DiffAlgorithm::Histogram,
+        edits,
+    );
+    edits.resize(edits.len() + region.len, Edit::Equal);
+    diff_range(
+        &old[old_end..],
+        &new[new_end..],
+        DiffAlgorithm::Histogram,
+        edits,
+    );
+}
@@ -0,0 +1,298 @@
+//! Line-based content diff, and comparisons between trees, the index and the working tree
+
+mod changes;
+mod histogram;
+mod myers;
+mod patience;
+mod unified;
+
+pub use changes::{DiffError, DiffFile, FileChange, FileLocation};
+pub use unified::{unified_hunks, DiffLine, Hunk};
+
+use std::collections::HashMap;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum DiffAlgorithm {
+    /// The basic greedy algorithm of Eugene W. Myers
+    #[default]
+    Myers,
+
+    /// Anchor the diff on the lines that appear exactly once on both sides
+    Patience,
+
+    /// Extension of patience that anchors on the least frequent lines
+    Histogram,
+}
+
+/// A step of an edit script that transforms the old lines into the new lines
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Edit {
+    /// The next old line and the next new line are the same
+    Equal,
+
+    /// The next old line is deleted
+    Delete,
+
+    /// The next new line is inserted
+    Insert,
+}
+
+/// Split a content into lines, each line keeps its trailing `\n` if any
+pub fn split_lines(content: &[u8]) -> Vec<&[u8]> {
+    content.split_inclusive(|&byte| byte == b'\n').collect()
+}
+
+/// Whether a content should be treated as binary data, using the same heuristic as git: a NUL
+/// byte among the first 8000 bytes
+pub fn is_binary(content: &[u8]) -> bool {
+    content.iter().take(8000).any(|&byte| byte == 0)
+}
+
+/// Compute an edit script from `old` lines to `new` lines
+pub fn diff_lines<'a>(old: &[&'a [u8]], new: &[&'a [u8]], algorithm: DiffAlgorithm) -> Vec<Edit> {
+    // compare small integers instead of whole lines
+    let mut ids: HashMap<&[u8], usize> = HashMap::new();
+    let mut intern = |line: &'a [u8]| -> usize {
+        let next_id = ids.len();
+        *ids.entry(line).or_insert(next_id)
+    };
+    let old: Vec<usize> = old.iter().map(|&line| intern(line)).collect();
+    let new: Vec<usize> = new.iter().map(|&line| intern(line)).collect();
+
+    let mut edits = Vec::with_capacity(old.len().max(new.len()));
+    diff_range(&old, &new, algorithm, &mut edits);
+    compact(&old, &new, &edits)
+}
+
+// Slide every group of changed lines down as far as possible, like git does, so that an
+// ambiguous change is always shown at the same place whatever the algorithm
+fn compact(old: &[usize], new: &[usize], edits: &[Edit]) -> Vec<Edit> {
+    let mut old_changed = Vec::with_capacity(old.len());
+    let mut new_changed = Vec::with_capacity(new.len());
+    for edit in edits {
+        match edit {
+            Edit::Equal => {
+                old_changed.push(false);
+                new_changed.push(false);
+            }
+            Edit::Delete => old_changed.push(true),
+            Edit::Insert => new_changed.push(true),
+        }
+    }
+    slide_down(old, &mut old_changed);
+    slide_down(new, &mut new_changed);
+
+    let mut compacted = Vec::with_capacity(edits.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && old_changed[i] {
+            compacted.push(Edit::Delete);
+            i += 1;
+        } else if j < new.len() && new_changed[j] {
+            compacted.push(Edit::Insert);
+            j += 1;
+        } else {
+            compacted.push(Edit::Equal);
+            i += 1;
+            j += 1;
+        }
+    }
+    compacted
+}
+
+// A group can move one line down when the line after it is the same as its first line
+fn slide_down(lines: &[usize], changed: &mut [bool]) {
+    let mut start = 0;
+    while start < lines.len() {
+        if !changed[start] {
+            start += 1;
+            continue;
+        }
+        let mut end = start;
+        while end < lines.len() && changed[end] {
+            end += 1;
+        }
+        while end < lines.len() && lines[start] == lines[end] {
+            changed[start] = false;
+            changed[end] = true;
+            start += 1;
+            end += 1;
+            // the group absorbs the group that follows it
+            while end < lines.len() && changed[end] {
+                end += 1;
+            }
+        }
+        start = end;
+    }
+}
+
+// Diff two ranges of interned lines, appending the result to `edits`
+fn diff_range(old: &[usize], new: &[usize], algorithm: DiffAlgorithm, edits: &mut Vec<Edit>) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+
+    edits.resize(edits.len() + prefix, Edit::Equal);
+    if old.is_empty() || new.is_empty() {
+        edits.resize(edits.len() + old.len(), Edit::Delete);
+        edits.resize(edits.len() + new.len(), Edit::Insert);
+    } else {
+        match algorithm {
+            DiffAlgorithm::Myers => myers::diff(old, new, edits),
+            DiffAlgorithm::Patience => patience::diff(old, new, edits),
+            DiffAlgorithm::Histogram => histogram::diff(old, new, edits),
+        }
+    }
+    edits.resize(edits.len() + suffix, Edit::Equal);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALGORITHMS: [DiffAlgorithm; 3] = [
+        DiffAlgorithm::Myers,
+        DiffAlgorithm::Patience,
+        DiffAlgorithm::Histogram,
+    ];
+
+    // Replay an edit script on the old lines
+    fn apply<'a>(old: &[&'a [u8]], new: &[&'a [u8]], edits: &[Edit]) -> Vec<&'a [u8]> {
+        let (mut i, mut j) = (0, 0);
+        let mut result = vec![];
+        for edit in edits {
+            match edit {
+                Edit::Equal => {
+                    assert_eq!(old[i], new[j]);
+                    result.push(old[i]);
+                    i += 1;
+                    j += 1;
+                }
+                Edit::Delete => i += 1,
+                Edit::Insert => {
+                    result.push(new[j]);
+                    j += 1;
+                }
+            }
+        }
+        assert_eq!(i, old.len());
+        result
+    }
+
+    fn count(edits: &[Edit], kind: Edit) -> usize {
+        edits.iter().filter(|&&edit| edit == kind).count()
+    }
+
+    #[test]
+    fn edit_scripts_reproduce_the_new_content() {
+        let cases: [(&[u8], &[u8]); 5] = [
+            (b"", b"a\nb\n"),
+            (b"a\nb\nc\n", b""),
+            (b"a\nb\nc\na\nb\nb\na\n", b"c\nb\na\nb\na\nc\n"),
+            (b"x\ny\nz\n", b"1\n2\n"),
+            (b"}\nfoo\n}\nbar\n}\n", b"}\nbar\n}\nfoo\n}\n"),
+        ];
+        for (old, new) in cases {
+            let (old, new) = (split_lines(old), split_lines(new));
+            for algorithm in ALGORITHMS {
+                let edits = diff_lines(&old, &new, algorithm);
+                assert_eq!(apply(&old, &new, &edits), new, "{:?}", algorithm);
+            }
+        }
+    }
+
+    #[test]
+    fn myers_is_minimal() {
+        // the classic example of the Myers paper has an edit distance of 5
+        let old = split_lines(b"a\nb\nc\na\nb\nb\na\n");
+        let new = split_lines(b"c\nb\na\nb\na\nc\n");
+        let edits = diff_lines(&old, &new, DiffAlgorithm::Myers);
+        assert_eq!(count(&edits, Edit::Delete) + count(&edits, Edit::Insert), 5);
+    }
+
+    #[test]
+    fn myers_is_minimal_on_larger_inputs() {
+        // pseudo-random lines from a small alphabet, so that there are many common lines
+        let mut seed = 42u32;
+        let mut lines = |count: usize| -> Vec<Vec<u8>> {
+            (0..count)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                    vec![b'a' + (seed >> 16) as u8 % 4, b'\n']
+                })
+                .collect()
+        };
+        for (old_count, new_count) in [(50, 60), (200, 150), (301, 300)] {
+            let (old, new) = (lines(old_count), lines(new_count));
+            let old: Vec<&[u8]> = old.iter().map(Vec::as_slice).collect();
+            let new: Vec<&[u8]> = new.iter().map(Vec::as_slice).collect();
+
+            // the length of the longest common subsequence, by dynamic programming
+            let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+            for i in (0..old.len()).rev() {
+                for j in (0..new.len()).rev() {
+                    lcs[i][j] = match old[i] == new[j] {
+                        true => lcs[i + 1][j + 1] + 1,
+                        false => lcs[i + 1][j].max(lcs[i][j + 1]),
+                    };
+                }
+            }
+
+            let edits = diff_lines(&old, &new, DiffAlgorithm::Myers);
+            assert_eq!(apply(&old, &new, &edits), new);
+            assert_eq!(count(&edits, Edit::Equal), lcs[0][0]);
+        }
+    }
+
+    #[test]
+    fn patience_anchors_on_unique_lines() {
+        let old = split_lines(b"a\n}\nunique\n}\n");
+        let new = split_lines(b"}\nunique\n}\nb\n");
+        let edits = diff_lines(&old, &new, DiffAlgorithm::Patience);
+        assert_eq!(
+            edits,
+            [
+                Edit::Delete,
+                Edit::Equal,
+                Edit::Equal,
+                Edit::Equal,
+                Edit::Insert
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_newline_at_end_of_file_is_a_change() {
+        let old = split_lines(b"a\nb");
+        let new = split_lines(b"a\nb\n");
+        for algorithm in ALGORITHMS {
+            let edits = diff_lines(&old, &new, algorithm);
+            assert_eq!(edits, [Edit::Equal, Edit::Delete, Edit::Insert]);
+        }
+    }
+
+    #[test]
+    fn changes_are_slid_down() {
+        let old = split_lines(b"a\n}\n");
+        let new = split_lines(b"a\n}\nb\n}\n");
+        for algorithm in ALGORITHMS {
+            let edits = diff_lines(&old, &new, algorithm);
+            assert_eq!(
+                edits,
+                [Edit::Equal, Edit::Equal, Edit::Insert, Edit::Insert]
+            );
+        }
+    }
+
+    #[test]
+    fn binary() {
+        assert!(is_binary(b"abc\0def"));
+        assert!(!is_binary(b"abc\ndef"));
+    }
+}
@@ -0,0 +1,102 @@
+// The O(ND) difference algorithm of Eugene W. Myers, in its linear space variant
+//
+// A forward search from the start and a reverse search from the end record the furthest reaching
+// x for each diagonal k = x - y, one round of d edits at a time. When the two searches overlap,
+// the overlapping point is on a shortest edit script: the ranges before and after it are diffed
+// recursively. Only the two searches are kept, so memory stays O(N + M).
+
+use super::{diff_range, DiffAlgorithm, Edit};
+
+pub(super) fn diff(old: &[usize], new: &[usize], edits: &mut Vec<Edit>) {
+    match middle_point(old, new) {
+        Some((x, y)) => {
+            diff_range(&old[..x], &new[..y], DiffAlgorithm::Myers, edits);
+            diff_range(&old[x..], &new[y..], DiffAlgorithm::Myers, edits);
+        }
+        None => {
+            edits.resize(edits.len() + old.len(), Edit::Delete);
+            edits.resize(edits.len() + new.len(), Edit::Insert);
+        }
+    }
+}
+
+// A point of a shortest edit script strictly between the start and the end, found where the
+// forward and the reverse searches meet. The ranges must be non-empty, and differ on their first
+// and on their last lines so that each side of the point needs at least one edit
+fn middle_point(old: &[usize], new: &[usize]) -> Option<(usize, usize)> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max_d = (n + m + 1) / 2;
+    let offset = max_d;
+    let index = |k: isize| (k + offset) as usize;
+    // -1 marks the diagonals not reached yet
+    let mut forward = vec![-1isize; 2 * max_d as usize + 2];
+    let mut reverse = forward.clone();
+    forward[index(1)] = 0;
+    reverse[index(1)] = 0;
+
+    // the diagonals of the reverse search are counted from the end: k' = (n - x) - (m - y)
+    let delta = n - m;
+    // with an odd delta the searches can only meet during a forward round, else during a
+    // reverse one
+    let odd = delta % 2 != 0;
+    // diagonals that left the grid are not searched anymore
+    let (mut forward_start, mut forward_end) = (0, 0);
+    let (mut reverse_start, mut reverse_end) = (0, 0);
+
+    for d in 0..max_d {
+        for k in (-d + forward_start..=d - forward_end).step_by(2) {
+            let mut x = if k == -d || (k != d && forward[index(k - 1)] < forward[index(k + 1)]) {
+                forward[index(k + 1)]
+            } else {
+                forward[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[index(k)] = x;
+            if x > n {
+                forward_end += 2;
+            } else if y > m {
+                forward_start += 2;
+            } else if odd {
+                let reverse_k = delta - k;
+                if reverse_k.abs() < max_d && reverse[index(reverse_k)] != -1 {
+                    // the reverse search reached that x on the same diagonal
+                    if x >= n - reverse[index(reverse_k)] {
+                        return Some((x as usize, y as usize));
+                    }
+                }
+            }
+        }
+
+        for k in (-d + reverse_start..=d - reverse_end).step_by(2) {
+            let mut x = if k == -d || (k != d && reverse[index(k - 1)] < reverse[index(k + 1)]) {
+                reverse[index(k + 1)]
+            } else {
+                reverse[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[(n - x - 1) as usize] == new[(m - y - 1) as usize] {
+                x += 1;
+                y += 1;
+            }
+            reverse[index(k)] = x;
+            if x > n {
+                reverse_end += 2;
+            } else if y > m {
+                reverse_start += 2;
+            } else if !odd {
+                let forward_k = delta - k;
+                if forward_k.abs() < max_d && forward[index(forward_k)] != -1 {
+                    let forward_x = forward[index(forward_k)];
+                    if forward_x >= n - x {
+                        return Some((forward_x as usize, (forward_x - forward_k) as usize));
+                    }
+                }
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,81 @@
+// Patience diff
+//
+// Lines that appear exactly once in both sides are matched, and the longest increasing
+// subsequence of those matches is used as anchors. The gaps between anchors are diffed
+// recursively, falling back to Myers when there is no unique line left.
+
+use super::{diff_range, myers, DiffAlgorithm, Edit};
+use std::collections::HashMap;
+
+// Positions of the lines that are unique in both sides, ordered by their position in `old`
+fn unique_matches(old: &[usize], new: &[usize]) -> Vec<(usize, usize)> {
+    // line -> (count in old, position in old, count in new, position in new)
+    let mut occurrences: HashMap<usize, (usize, usize, usize, usize)> = HashMap::new();
+    for (i, line) in old.iter().enumerate() {
+        let entry = occurrences.entry(*line).or_default();
+        entry.0 += 1;
+        entry.1 = i;
+    }
+    for (j, line) in new.iter().enumerate() {
+        if let Some(entry) = occurrences.get_mut(line) {
+            entry.2 += 1;
+            entry.3 = j;
+        }
+    }
+
+    let mut matches: Vec<(usize, usize)> = occurrences
+        .into_values()
+        .filter(|&(old_count, _, new_count, _)| old_count == 1 && new_count == 1)
+        .map(|(_, i, _, j)| (i, j))
+        .collect();
+    matches.sort_unstable();
+    matches
+}
+
+// Longest subsequence of matches that is also increasing in `new`, found by patience sorting
+fn longest_increasing_subsequence(matches: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    // the index of the top match of each pile
+    let mut piles: Vec<usize> = vec![];
+    // for each match, the top of the previous pile when it was placed
+    let mut predecessors: Vec<Option<usize>> = vec![None; matches.len()];
+
+    for (index, &(_, j)) in matches.iter().enumerate() {
+        let pile = piles.partition_point(|&top| matches[top].1 < j);
+        predecessors[index] = pile.checked_sub(1).map(|previous| piles[previous]);
+        if pile == piles.len() {
+            piles.push(index);
+        } else {
+            piles[pile] = index;
+        }
+    }
+
+    let mut result = vec![];
+    let mut current = piles.last().copied();
+    while let Some(index) = current {
+        result.push(matches[index]);
+        current = predecessors[index];
+    }
+    result.reverse();
+    result
+}
+
+pub(super) fn diff(old: &[usize], new: &[usize], edits: &mut Vec<Edit>) {
+    let anchors = longest_increasing_subsequence(&unique_matches(old, new));
+    if anchors.is_empty() {
+        myers::diff(old, new, edits);
+        return;
+    }
+
+    let (mut i, mut j) = (0, 0);
+    for (anchor_i, anchor_j) in anchors {
+        diff_range(
+            &old[i..anchor_i],
+            &new[j..anchor_j],
+            DiffAlgorithm::Patience,
+            edits,
+        );
+        edits.push(Edit::Equal);
+        (i, j) = (anchor_i + 1, anchor_j + 1);
+    }
+    diff_range(&old[i..], &new[j..], DiffAlgorithm::Patience, edits);
+}
@@ -0,0 +1,198 @@
+// Group an edit script into the hunks of a unified diff
+
+use super::Edit;
+use std::fmt::{Display, Formatter};
+
+/// A line of a hunk, including its trailing `\n` if any
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    Context(&'a [u8]),
+    Deletion(&'a [u8]),
+    Addition(&'a [u8]),
+}
+
+/// A group of changes surrounded by context lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk<'a> {
+    /// 1-based number of the first old line, or of the line before it when `old_count` is 0
+    pub old_start: usize,
+    pub old_count: usize,
+
+    /// 1-based number of the first new line, or of the line before it when `new_count` is 0
+    pub new_start: usize,
+    pub new_count: usize,
+
+    /// The closest line before the hunk that looks like the start of a function, shown after
+    /// the hunk header like git does by default
+    pub function_context: Option<&'a [u8]>,
+
+    pub lines: Vec<DiffLine<'a>>,
+}
+
+fn write_range(f: &mut Formatter<'_>, start: usize, count: usize) -> std::fmt::Result {
+    if count == 1 {
+        write!(f, "{}", start)
+    } else {
+        write!(f, "{},{}", start, count)
+    }
+}
+
+/// Display the `@@ -l,s +l,s @@` header of the hunk, without the function context
+impl Display for Hunk<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "@@ -")?;
+        write_range(f, self.old_start, self.old_count)?;
+        write!(f, " +")?;
+        write_range(f, self.new_start, self.new_count)?;
+        write!(f, " @@")
+    }
+}
+
+// git's default funcname pattern: a line starting with a letter, `_` or `$`
+fn find_function_context<'a>(old: &[&'a [u8]], before: usize) -> Option<&'a [u8]> {
+    const MAX_LENGTH: usize = 80;
+
+    let line = old[..before]
+        .iter()
+        .rev()
+        .find(|line| matches!(line.first(), Some(c) if c.is_ascii_alphabetic() || *c == b'_' || *c == b'$'))?;
+    let line = &line[..line.len().min(MAX_LENGTH)];
+    let trimmed_len = line.len()
+        - line
+            .iter()
+            .rev()
+            .take_while(|c| c.is_ascii_whitespace())
+            .count();
+    Some(&line[..trimmed_len])
+}
+
+/// Group the edit script from `old` to `new` into hunks with `context` lines of context around
+/// the changes. Changes separated by at most `2 * context` unchanged lines share a hunk
+pub fn unified_hunks<'a>(
+    old: &[&'a [u8]],
+    new: &[&'a [u8]],
+    edits: &[Edit],
+    context: usize,
+) -> Vec<Hunk<'a>> {
+    // position in old and new before each edit
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut i, mut j) = (0, 0);
+    for edit in edits {
+        positions.push((i, j));
+        match edit {
+            Edit::Equal => {
+                i += 1;
+                j += 1;
+            }
+            Edit::Delete => i += 1,
+            Edit::Insert => j += 1,
+        }
+    }
+    positions.push((i, j));
+
+    // ranges of edits covered by each hunk
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    let mut last_change_end = None;
+    for (index, edit) in edits.iter().enumerate() {
+        if *edit == Edit::Equal {
+            continue;
+        }
+        match (last_change_end, ranges.last_mut()) {
+            (Some(end), Some(range)) if index - end <= 2 * context => range.1 = index + 1,
+            _ => ranges.push((index, index + 1)),
+        }
+        last_change_end = Some(index + 1);
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            let start = start.saturating_sub(context);
+            let end = (end + context).min(edits.len());
+            let (old_begin, new_begin) = positions[start];
+            let (old_end, new_end) = positions[end];
+
+            // deletions are shown before the additions they are replaced by
+            let mut lines = vec![];
+            let mut additions = vec![];
+            for (edit, &(i, j)) in edits[start..end].iter().zip(&positions[start..end]) {
+                match edit {
+                    Edit::Equal => {
+                        lines.append(&mut additions);
+                        lines.push(DiffLine::Context(old[i]));
+                    }
+                    Edit::Delete => lines.push(DiffLine::Deletion(old[i])),
+                    Edit::Insert => additions.push(DiffLine::Addition(new[j])),
+                }
+            }
+            lines.append(&mut additions);
+
+            let old_count = old_end - old_begin;
+            let new_count = new_end - new_begin;
+            Hunk {
+                old_start: if old_count == 0 {
+                    old_begin
+                } else {
+                    old_begin + 1
+                },
+                old_count,
+                new_start: if new_count == 0 {
+                    new_begin
+                } else {
+                    new_begin + 1
+                },
+                new_count,
+                function_context: find_function_context(old, old_begin),
+                lines,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::{diff_lines, split_lines, DiffAlgorithm};
+
+    fn hunks<'a>(old: &'a [u8], new: &'a [u8], context: usize) -> Vec<Hunk<'a>> {
+        let (old, new) = (split_lines(old), split_lines(new));
+        let edits = diff_lines(&old, &new, DiffAlgorithm::Myers);
+        unified_hunks(&old, &new, &edits, context)
+    }
+
+    #[test]
+    fn header() {
+        let old = b"1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        let new = b"1\n2\n3\n4\nfive\n6\n7\n8\n9\n10\n";
+        let hunks = hunks(old, new, 3);
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].to_string(), "@@ -2,7 +2,7 @@");
+        assert_eq!(
+            hunks[0].lines[3..5],
+            [DiffLine::Deletion(b"5\n"), DiffLine::Addition(b"five\n")]
+        );
+    }
+
+    #[test]
+    fn empty_side() {
+        let hunks = hunks(b"", b"a\n", 3);
+        assert_eq!(hunks[0].to_string(), "@@ -0,0 +1 @@");
+    }
+
+    #[test]
+    fn close_changes_are_merged() {
+        let old = b"1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        let new = b"one\n2\n3\n4\n5\n6\n7\n8\n9\nten\n";
+        assert_eq!(hunks(old, new, 3).len(), 2);
+        assert_eq!(hunks(old, new, 4).len(), 1);
+        assert_eq!(hunks(old, new, 0)[1].to_string(), "@@ -10 +10 @@");
+    }
+
+    #[test]
+    fn function_context() {
+        let old = b"fn main() {\n    1\n    2\n    3\n    4\n}\n";
+        let new = b"fn main() {\n    1\n    2\n    3\n    four\n}\n";
+        let hunks = hunks(old, new, 1);
+        assert_eq!(hunks[0].function_context, Some(&b"fn main() {"[..]));
+    }
+}
@@ -23,8 +23,8 @@ impl Head {
     /// Returns None if it is a detached head
     pub fn referent_name(&self) -> Option<&str> {
         match self {
-            Head::Symbolic { name, .. } => Some(&name),
-            Head::Unborn(name) => Some(&name),
+            Head::Symbolic { name, .. } => Some(name),
+            Head::Unborn(name) => Some(name),
             Head::Detached(_) => None,
         }
     }
@@ -62,4 +62,21 @@ impl Repository {
         let head = self.try_find_reference("HEAD")?.expect("HEAD should exist");
         self.peel_reference(&head)
     }
+
+    /// Point HEAD, or the branch it refers to, to a commit, recording `message` in the reflogs
+    pub fn update_head(&self, oid: ObjectId, message: &str) -> Result<(), ReferenceError> {
+        match self.head()? {
+            Head::Detached(_) => self.update_reference("HEAD", oid, message),
+            head => self.update_reference(head.referent_name().unwrap(), oid, message),
+        }
+    }
+
+    /// The tree of the commit HEAD points to, or None on an unborn branch
+    pub fn head_tree(&self) -> Result<Option<ObjectId>, ReferenceError> {
+        if self.head()?.is_unborn() {
+            return Ok(None);
+        }
+        let commit = self.read_commit(self.head_id()?)?;
+        Ok(Some(commit.tree()))
+    }
 }
@@ -0,0 +1,274 @@
+//! Decide which untracked files are ignored
+//!
+//! The patterns come from the `.gitignore` files of the working tree, `.git/info/exclude` and the
+//! file of `core.excludesFile`. Like git, a `.gitignore` file takes precedence over the ones of
+//! its parent directories, which take precedence over `.git/info/exclude`, itself over
+//! `core.excludesFile`. Inside a file, the last matching pattern wins.
+
+use crate::config::ConfigError;
+use crate::wildmatch::wildmatch;
+use crate::Repository;
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// A line of an ignore file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnorePattern {
+    /// The pattern as written in the file
+    pub pattern: String,
+
+    /// The file the pattern comes from, relative to the repository when it is inside it
+    pub source: PathBuf,
+
+    /// The line of the pattern in its file, starting at 1
+    pub line: usize,
+
+    /// A pattern starting with `!` re-includes the paths that other patterns exclude
+    pub negated: bool,
+
+    // The pattern without `!`, leading slash and trailing slash
+    glob: String,
+
+    // A trailing slash only matches directories
+    dir_only: bool,
+
+    // A pattern with a slash matches the path relative to `base`, otherwise the file name
+    anchored: bool,
+
+    // The directory of the `.gitignore` file
+    base: PathBuf,
+}
+
+impl IgnorePattern {
+    fn parse(line: &str, source: &Path, line_number: usize, base: &Path) -> Option<Self> {
+        // trailing spaces are ignored unless escaped with a backslash
+        let mut pattern = line.strip_suffix('\r').unwrap_or(line);
+        while pattern.ends_with(' ') && !pattern.ends_with("\\ ") {
+            pattern = &pattern[..pattern.len() - 1];
+        }
+        if pattern.is_empty() || pattern.starts_with('#') {
+            return None;
+        }
+
+        let (negated, glob) = match pattern.strip_prefix('!') {
+            Some(glob) => (true, glob),
+            None => (false, pattern),
+        };
+        let (dir_only, glob) = match glob.strip_suffix('/') {
+            Some(glob) => (true, glob),
+            None => (false, glob),
+        };
+        let anchored = glob.contains('/');
+        let glob = glob.strip_prefix('/').unwrap_or(glob);
+        if glob.is_empty() {
+            return None;
+        }
+
+        Some(IgnorePattern {
+            pattern: pattern.to_string(),
+            source: source.to_path_buf(),
+            line: line_number,
+            negated,
+            glob: glob.to_string(),
+            dir_only,
+            anchored,
+            base: base.to_path_buf(),
+        })
+    }
+
+    /// Whether the pattern matches a path relative to the repository
+    pub fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let Ok(path) = path.strip_prefix(&self.base) else {
+            return false;
+        };
+        let text = if self.anchored {
+            path.as_os_str()
+        } else {
+            match path.file_name() {
+                Some(name) => name,
+                None => return false,
+            }
+        };
+        wildmatch(self.glob.as_bytes(), text.to_string_lossy().as_bytes())
+    }
+}
+
+// The patterns of an ignore file, none if it doesn't exist
+fn read_patterns(path: &Path, source: &Path, base: &Path) -> io::Result<Vec<IgnorePattern>> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    Ok(String::from_utf8_lossy(&content)
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| IgnorePattern::parse(line, source, i + 1, base))
+        .collect())
+}
+
+// The last pattern of a list that matches
+fn last_match<'a>(
+    patterns: &'a [IgnorePattern],
+    path: &Path,
+    is_dir: bool,
+) -> Option<&'a IgnorePattern> {
+    patterns
+        .iter()
+        .rev()
+        .find(|pattern| pattern.matches(path, is_dir))
+}
+
+/// The ignore rules of a repository. The `.gitignore` files are read as their directories are
+/// visited
+#[derive(Debug)]
+pub struct Ignore {
+    repository_dir: PathBuf,
+
+    // `.git/info/exclude` then `core.excludesFile`
+    exclude_files: Vec<Vec<IgnorePattern>>,
+
+    // The patterns of the `.gitignore` file of each directory read so far
+    directories: HashMap<PathBuf, Vec<IgnorePattern>>,
+}
+
+impl Ignore {
+    fn read_directory(&mut self, dir: &Path) -> io::Result<()> {
+        if !self.directories.contains_key(dir) {
+            let source = dir.join(".gitignore");
+            let patterns = read_patterns(&self.repository_dir.join(&source), &source, dir)?;
+            self.directories.insert(dir.to_path_buf(), patterns);
+        }
+        Ok(())
+    }
+
+    // The last matching pattern of the most specific file, without looking at the parent
+    // directories
+    fn match_path(&mut self, path: &Path, is_dir: bool) -> io::Result<Option<&IgnorePattern>> {
+        let dirs: Vec<&Path> = path.ancestors().skip(1).collect();
+        for dir in &dirs {
+            self.read_directory(dir)?;
+        }
+        let directories = dirs.iter().map(|dir| &self.directories[*dir]);
+        Ok(directories
+            .chain(&self.exclude_files)
+            .find_map(|patterns| last_match(patterns, path, is_dir)))
+    }
+
+    /// The pattern deciding whether a path relative to the repository is ignored, which can be a
+    /// negated pattern. Like git, the paths inside an ignored directory are ignored by the
+    /// pattern of the directory
+    pub fn matching_pattern(
+        &mut self,
+        path: &Path,
+        is_dir: bool,
+    ) -> io::Result<Option<IgnorePattern>> {
+        let mut parents: Vec<&Path> = path.ancestors().skip(1).collect();
+        parents.pop();
+        for parent in parents.into_iter().rev() {
+            if let Some(pattern) = self.match_path(parent, true)? {
+                if !pattern.negated {
+                    return Ok(Some(pattern.clone()));
+                }
+            }
+        }
+        Ok(self.match_path(path, is_dir)?.cloned())
+    }
+
+    /// Whether a path relative to the repository is ignored
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> io::Result<bool> {
+        Ok(self
+            .matching_pattern(path, is_dir)?
+            .is_some_and(|pattern| !pattern.negated))
+    }
+}
+
+// `$XDG_CONFIG_HOME/git/ignore`, or `~/.config/git/ignore`
+fn default_excludes_file() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|xdg_config_home| !xdg_config_home.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|config_home| config_home.join("git/ignore"))
+}
+
+impl Repository {
+    /// The ignore rules of the working tree
+    pub fn ignore(&self) -> Result<Ignore, ConfigError> {
+        let mut exclude_files = vec![];
+        let info_exclude = Path::new(".git/info/exclude");
+        exclude_files.push(read_patterns(
+            &self.git_dir.join("info/exclude"),
+            info_exclude,
+            Path::new(""),
+        )?);
+
+        let excludes_file = self
+            .config()?
+            .get_path("core.excludesFile")
+            .or_else(default_excludes_file);
+        if let Some(excludes_file) = excludes_file {
+            exclude_files.push(read_patterns(
+                &excludes_file,
+                &excludes_file,
+                Path::new(""),
+            )?);
+        }
+
+        Ok(Ignore {
+            repository_dir: self.repository_dir.clone(),
+            exclude_files,
+            directories: HashMap::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(line: &str, base: &str) -> IgnorePattern {
+        IgnorePattern::parse(line, Path::new(".gitignore"), 1, Path::new(base)).unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        for line in ["", "# comment", "   ", "!", "/"] {
+            assert_eq!(
+                IgnorePattern::parse(line, Path::new(""), 1, Path::new("")),
+                None
+            );
+        }
+        let negated = pattern("!/build/  ", "");
+        assert_eq!(negated.pattern, "!/build/");
+        assert!(negated.negated && negated.dir_only && negated.anchored);
+        assert_eq!(pattern("a\\ ", "").glob, "a\\ ");
+    }
+
+    #[test]
+    fn matches() {
+        let matches = |line: &str, base: &str, path: &str, is_dir: bool| {
+            pattern(line, base).matches(Path::new(path), is_dir)
+        };
+        assert!(matches("*.log", "", "a/b/c.log", false));
+        assert!(matches("build/", "", "a/build", true));
+        assert!(!matches("build/", "", "a/build", false));
+        assert!(matches("/root.txt", "", "root.txt", false));
+        assert!(!matches("/root.txt", "", "sub/root.txt", false));
+        assert!(matches("doc/*.txt", "", "doc/a.txt", false));
+        assert!(!matches("doc/*.txt", "", "doc/sub/a.txt", false));
+        assert!(matches("**/logs", "", "a/b/logs", true));
+        assert!(matches("a/**/b", "", "a/x/y/b", false));
+        assert!(matches("x.txt", "sub", "sub/deep/x.txt", false));
+        assert!(!matches("x.txt", "sub", "x.txt", false));
+        assert!(matches("/x.txt", "sub", "sub/x.txt", false));
+        assert!(matches("\\#hash", "", "#hash", false));
+    }
+}
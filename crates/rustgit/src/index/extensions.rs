@@ -0,0 +1,276 @@
+//! The extensions of the index that rustgit understands: the cache tree and resolve-undo
+
+use super::IndexReadError;
+use crate::oid::ObjectId;
+use std::{
+    cmp::Ordering,
+    io::Write,
+    path::{Component, Path, PathBuf},
+};
+
+/// The `TREE` extension: the tree ids of the directories of the index, so that writing a tree only
+/// hashes the directories that changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheTree {
+    // The id of the tree and the number of index entries under it. None when a path under the
+    // directory changed since the tree was written
+    tree: Option<(ObjectId, usize)>,
+
+    // Sorted like git, by length then by name
+    children: Vec<(String, CacheTree)>,
+}
+
+// The order git keeps the subtrees of a cache tree in
+fn subtree_order(a: &str, b: &str) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+// Split `bytes` at the first `delimiter`, which is dropped
+fn split_at_byte<'a>(
+    bytes: &mut &'a [u8],
+    delimiter: u8,
+    what: &'static str,
+) -> Result<&'a [u8], IndexReadError> {
+    let position = bytes
+        .iter()
+        .position(|&c| c == delimiter)
+        .ok_or(IndexReadError::Corrupt(what))?;
+    let (before, after) = bytes.split_at(position);
+    *bytes = &after[1..];
+    Ok(before)
+}
+
+fn read_oid(bytes: &mut &[u8], what: &'static str) -> Result<ObjectId, IndexReadError> {
+    if bytes.len() < 20 {
+        return Err(IndexReadError::Corrupt(what));
+    }
+    let (oid, rest) = bytes.split_at(20);
+    *bytes = rest;
+    Ok(ObjectId(oid.try_into().unwrap()))
+}
+
+fn parse_decimal<T: std::str::FromStr>(
+    bytes: &[u8],
+    what: &'static str,
+) -> Result<T, IndexReadError> {
+    std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|text| text.parse().ok())
+        .ok_or(IndexReadError::Corrupt(what))
+}
+
+fn parse_octal(bytes: &[u8], what: &'static str) -> Result<u32, IndexReadError> {
+    std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|text| u32::from_str_radix(text, 8).ok())
+        .ok_or(IndexReadError::Corrupt(what))
+}
+
+impl CacheTree {
+    pub(crate) fn new(
+        oid: ObjectId,
+        entry_count: usize,
+        children: Vec<(String, CacheTree)>,
+    ) -> Self {
+        let mut children = children;
+        children.sort_by(|(a, _), (b, _)| subtree_order(a, b));
+        CacheTree {
+            tree: Some((oid, entry_count)),
+            children,
+        }
+    }
+
+    /// The id of the tree, None if it is not up to date
+    pub fn oid(&self) -> Option<ObjectId> {
+        self.tree.map(|(oid, _)| oid)
+    }
+
+    /// The number of index entries under the tree, None if it is not up to date
+    pub fn entry_count(&self) -> Option<usize> {
+        self.tree.map(|(_, entry_count)| entry_count)
+    }
+
+    /// The cache of a direct subdirectory
+    pub fn child(&self, name: &str) -> Option<&CacheTree> {
+        self.children
+            .iter()
+            .find(|(child_name, _)| child_name == name)
+            .map(|(_, child)| child)
+    }
+
+    /// Mark the trees containing `path` as out of date, like git also forgetting the cache of
+    /// `path` itself if it is a directory
+    pub(crate) fn invalidate(&mut self, path: &Path) {
+        self.tree = None;
+
+        let mut components = path.components();
+        let Some(Component::Normal(name)) = components.next() else {
+            return;
+        };
+        let name = name.to_string_lossy();
+        let rest = components.as_path();
+
+        let position = self
+            .children
+            .iter()
+            .position(|(child_name, _)| *child_name == name);
+        match position {
+            Some(position) if rest.as_os_str().is_empty() => {
+                self.children.remove(position);
+            }
+            Some(position) => self.children[position].1.invalidate(rest),
+            None => {}
+        }
+    }
+
+    // Parse a tree and its subtrees, returning the name of the tree
+    pub(crate) fn parse(data: &mut &[u8]) -> Result<(String, CacheTree), IndexReadError> {
+        const WHAT: &str = "invalid TREE extension";
+
+        let name = split_at_byte(data, 0, WHAT)?;
+        let name = String::from_utf8(name.to_vec()).map_err(|_| IndexReadError::Corrupt(WHAT))?;
+        let entry_count: i64 = parse_decimal(split_at_byte(data, b' ', WHAT)?, WHAT)?;
+        let subtree_count: usize = parse_decimal(split_at_byte(data, b'\n', WHAT)?, WHAT)?;
+
+        // a negative count marks an invalidated tree, which has no id
+        let tree = match usize::try_from(entry_count) {
+            Ok(entry_count) => Some((read_oid(data, WHAT)?, entry_count)),
+            Err(_) => None,
+        };
+
+        let mut children = Vec::with_capacity(subtree_count);
+        for _ in 0..subtree_count {
+            children.push(Self::parse(data)?);
+        }
+        Ok((name, CacheTree { tree, children }))
+    }
+
+    pub(crate) fn write(&self, name: &str, output: &mut Vec<u8>) {
+        let entry_count = match self.tree {
+            Some((_, entry_count)) => entry_count as i64,
+            None => -1,
+        };
+        writeln!(output, "{}\0{} {}", name, entry_count, self.children.len()).unwrap();
+        if let Some((oid, _)) = self.tree {
+            output.extend_from_slice(&oid.0);
+        }
+        for (child_name, child) in &self.children {
+            child.write(child_name, output);
+        }
+    }
+}
+
+/// An entry of the `REUC` extension: the versions of a file before its merge conflict was
+/// resolved, so that the conflict can be recreated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolveUndo {
+    pub path: PathBuf,
+
+    /// The mode and id of the base, ours and theirs versions. None for a missing version
+    pub stages: [Option<(u32, ObjectId)>; 3],
+}
+
+impl ResolveUndo {
+    pub(crate) fn parse_all(mut data: &[u8]) -> Result<Vec<ResolveUndo>, IndexReadError> {
+        const WHAT: &str = "invalid REUC extension";
+
+        let mut entries = vec![];
+        while !data.is_empty() {
+            let path = split_at_byte(&mut data, 0, WHAT)?;
+            let path = std::str::from_utf8(path).map_err(|_| IndexReadError::Corrupt(WHAT))?;
+
+            let mut modes = [0u32; 3];
+            for mode in &mut modes {
+                *mode = parse_octal(split_at_byte(&mut data, 0, WHAT)?, WHAT)?;
+            }
+            let mut stages = [None; 3];
+            for (stage, mode) in stages.iter_mut().zip(modes) {
+                if mode != 0 {
+                    *stage = Some((mode, read_oid(&mut data, WHAT)?));
+                }
+            }
+
+            entries.push(ResolveUndo {
+                path: PathBuf::from(path),
+                stages,
+            });
+        }
+        Ok(entries)
+    }
+
+    pub(crate) fn write(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(self.path.to_string_lossy().as_bytes());
+        output.push(0);
+        for stage in &self.stages {
+            let mode = stage.map_or(0, |(mode, _)| mode);
+            write!(output, "{:o}\0", mode).unwrap();
+        }
+        for (_, oid) in self.stages.iter().flatten() {
+            output.extend_from_slice(&oid.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oid(byte: u8) -> ObjectId {
+        ObjectId([byte; 20])
+    }
+
+    #[test]
+    fn cache_tree() {
+        let tree = CacheTree::new(
+            oid(1),
+            3,
+            vec![
+                ("sub".to_string(), CacheTree::new(oid(2), 1, vec![])),
+                ("a".to_string(), CacheTree::new(oid(3), 1, vec![])),
+            ],
+        );
+        let mut data = vec![];
+        tree.write("", &mut data);
+
+        let (name, parsed) = CacheTree::parse(&mut &data[..]).unwrap();
+        assert_eq!(name, "");
+        assert_eq!(parsed, tree);
+        // shorter names first
+        assert_eq!(parsed.children[0].0, "a");
+
+        let mut invalidated = parsed.clone();
+        invalidated.invalidate(Path::new("sub/file"));
+        assert_eq!(invalidated.oid(), None);
+        assert_eq!(invalidated.child("sub").unwrap().oid(), None);
+        assert_eq!(invalidated.child("a").unwrap().oid(), Some(oid(3)));
+
+        let mut data = vec![];
+        invalidated.write("", &mut data);
+        let (_, parsed) = CacheTree::parse(&mut &data[..]).unwrap();
+        assert_eq!(parsed, invalidated);
+
+        // the cache of a replaced directory is dropped
+        invalidated.invalidate(Path::new("a"));
+        assert_eq!(invalidated.child("a"), None);
+    }
+
+    #[test]
+    fn resolve_undo() {
+        let entries = vec![
+            ResolveUndo {
+                path: PathBuf::from("dir/file"),
+                stages: [Some((0o100644, oid(1))), None, Some((0o100755, oid(2)))],
+            },
+            ResolveUndo {
+                path: PathBuf::from("other"),
+                stages: [None, Some((0o100644, oid(3))), None],
+            },
+        ];
+        let mut data = vec![];
+        for entry in &entries {
+            entry.write(&mut data);
+        }
+        assert!(data.starts_with(b"dir/file\x00100644\x000\x00100755\x00"));
+        assert_eq!(ResolveUndo::parse_all(&data).unwrap(), entries);
+    }
+}
@@ -0,0 +1,1113 @@
+//! Access to `.git/index` files
+
+mod extensions;
+
+pub use extensions::{CacheTree, ResolveUndo};
+
+use crate::object::SYMLINK_MODE;
+use crate::oid::ObjectId;
+use crate::path_order::GitPath;
+use crate::read_ext::ReadExt;
+use crate::Repository;
+use sha1::Digest;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    io::{BufRead, ErrorKind, Read, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+const METADATA_SIZE: usize = 40;
+const SHA_SIZE: usize = 20;
+const PATH_LEN_SIZE: usize = 2;
+
+const MIN_ENTRY_SIZE: usize = METADATA_SIZE + SHA_SIZE + PATH_LEN_SIZE;
+
+// The flags of an entry keep the length of its path in their lower 12 bits, or this value when
+// the path is longer
+const PATH_LENGTH_MASK: u16 = 0xfff;
+
+// Other bits of the flags of an entry
+const ASSUME_VALID_FLAG: u16 = 0x8000;
+const EXTENDED_FLAG: u16 = 0x4000;
+const STAGE_MASK: u16 = 0x3000;
+const STAGE_SHIFT: u16 = 12;
+
+// Bits of the extended flags, which entries can only have from version 3
+const SKIP_WORKTREE_FLAG: u16 = 0x4000;
+const INTENT_TO_ADD_FLAG: u16 = 0x2000;
+
+// Version 2 is the oldest one git still writes, 3 adds the extended flags and 4 compresses paths
+const SUPPORTED_VERSIONS: RangeInclusive<u32> = 2..=4;
+const DEFAULT_VERSION: u32 = 2;
+
+/// The flags git records for an entry besides the length of its path
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct EntryFlags {
+    /// 0 for a merged path. A path with a merge conflict has instead up to three entries: 1 for
+    /// the common ancestor, 2 for our version and 3 for theirs
+    pub stage: u8,
+
+    /// Set by `git update-index --assume-unchanged`: the file is taken as unchanged without
+    /// looking at the working tree
+    pub assume_valid: bool,
+
+    /// The file is outside of a sparse checkout or was marked with
+    /// `git update-index --skip-worktree`: its copy in the working tree is ignored
+    pub skip_worktree: bool,
+
+    /// The path was added with `git add -N`: it is tracked but its content is not staged yet
+    pub intent_to_add: bool,
+}
+
+impl EntryFlags {
+    // Whether the entry needs the extended flags of version 3
+    fn is_extended(&self) -> bool {
+        self.skip_worktree || self.intent_to_add
+    }
+}
+
+// None-path part of an entry
+#[derive(Debug, Copy, Clone)]
+struct EntryData {
+    metadata: EntryMetadata,
+    oid: ObjectId,
+    flags: EntryFlags,
+}
+
+// An extension that rustgit doesn't know, kept as is
+#[derive(Debug, Clone)]
+struct UnknownExtension {
+    signature: [u8; 4],
+    data: Vec<u8>,
+}
+
+/// Memory representation of an index file.
+pub struct Index {
+    // the version of the file format, kept when the index is written back
+    version: u32,
+
+    // note: paths here should already stripe repository path prefix. A path can have an entry
+    // for each stage of a merge conflict. Like in the file, the paths are sorted by their bytes
+    entries: BTreeMap<(GitPath, u8), EntryData>,
+
+    cache_tree: Option<CacheTree>,
+    resolve_undo: Vec<ResolveUndo>,
+
+    // optional extensions we don't understand, written back untouched
+    unknown_extensions: Vec<UnknownExtension>,
+}
+
+/// A reference to an entry
+#[derive(Debug, Copy, Clone)]
+pub struct EntryRef<'index> {
+    pub metadata: EntryMetadata,
+    pub oid: ObjectId,
+    pub flags: EntryFlags,
+    pub path: &'index Path,
+}
+
+impl EntryRef<'_> {
+    /// The stage of the entry, 0 unless its path has a merge conflict
+    pub fn stage(&self) -> u8 {
+        self.flags.stage
+    }
+}
+
+/// The entries of a path with a merge conflict
+#[derive(Debug, Copy, Clone)]
+pub struct Conflict<'index> {
+    pub path: &'index Path,
+
+    /// The version of the common ancestor, stage 1
+    pub base: Option<EntryRef<'index>>,
+
+    /// Our version, stage 2
+    pub ours: Option<EntryRef<'index>>,
+
+    /// Their version, stage 3
+    pub theirs: Option<EntryRef<'index>>,
+}
+
+/// An error raised from reading or parsing the index file
+#[derive(thiserror::Error, Debug)]
+pub enum IndexReadError {
+    #[error("IO Error")]
+    IOError(#[from] io::Error),
+
+    #[error("index header signature is not DIRC")]
+    HeaderSignatureError,
+
+    #[error("rustgit only support index versions 2 to 4, get {0}")]
+    VersionError(u32),
+
+    #[error("utf8 error")]
+    UTF8Error(#[from] std::str::Utf8Error),
+
+    #[error("index file corrupt: {0}")]
+    Corrupt(&'static str),
+
+    #[error("index file smaller than expected")]
+    Truncated,
+
+    #[error("index file corrupt: bad checksum {actual}, expected {expected}")]
+    ChecksumMismatch {
+        expected: ObjectId,
+        actual: ObjectId,
+    },
+
+    #[error("index uses the '{0}' extension, which rustgit does not support")]
+    UnsupportedExtension(String),
+}
+
+// Extensions that describe the layout of the index file itself, which is not kept when rustgit
+// writes the index
+const LAYOUT_EXTENSIONS: [&[u8; 4]; 2] = [b"EOIE", b"IEOT"];
+
+// Writes through to another writer while hashing what is written, for the checksum that ends
+// the index file
+struct HashingWriter<'a, W: io::Write> {
+    inner: &'a mut W,
+    hasher: sha1::Sha1,
+}
+
+impl<W: io::Write> io::Write for HashingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Read header of index and return its version and the number of entries
+fn read_header(reader: &mut impl Read) -> Result<(u32, usize), IndexReadError> {
+    let header_signature = reader.read_exact_4()?;
+    if !matches!(&header_signature, b"DIRC") {
+        return Err(IndexReadError::HeaderSignatureError);
+    }
+
+    let version_number = u32::from_be_bytes(reader.read_exact_4()?);
+    if !SUPPORTED_VERSIONS.contains(&version_number) {
+        return Err(IndexReadError::VersionError(version_number));
+    }
+
+    let entry_count = u32::from_be_bytes(reader.read_exact_4()?);
+    Ok((version_number, entry_count as usize))
+}
+
+// Read the flags of an entry, followed by its extended flags if it has some
+fn read_flags(reader: &mut impl Read, version: u32) -> Result<(u16, EntryFlags), IndexReadError> {
+    let flags = u16::from_be_bytes(reader.read_exact_n::<2>()?);
+
+    let mut extended_flags = 0;
+    if flags & EXTENDED_FLAG != 0 {
+        if version < 3 {
+            return Err(IndexReadError::Corrupt(
+                "extended flags in a version 2 index",
+            ));
+        }
+        extended_flags = u16::from_be_bytes(reader.read_exact_n::<2>()?);
+        if extended_flags & !(SKIP_WORKTREE_FLAG | INTENT_TO_ADD_FLAG) != 0 {
+            return Err(IndexReadError::Corrupt("unknown extended flags"));
+        }
+    }
+
+    let entry_flags = EntryFlags {
+        stage: ((flags & STAGE_MASK) >> STAGE_SHIFT) as u8,
+        assume_valid: flags & ASSUME_VALID_FLAG != 0,
+        skip_worktree: extended_flags & SKIP_WORKTREE_FLAG != 0,
+        intent_to_add: extended_flags & INTENT_TO_ADD_FLAG != 0,
+    };
+    Ok((flags, entry_flags))
+}
+
+// Read a number in the variable-length encoding of git: 7 bits per byte, most significant first,
+// with the high bit set on all bytes but the last. Each continuation also adds one, so that every
+// number has a single encoding
+fn read_varint(reader: &mut impl Read) -> Result<usize, IndexReadError> {
+    let [mut byte] = reader.read_exact_n::<1>()?;
+    let mut value = usize::from(byte & 0x7f);
+    while byte & 0x80 != 0 {
+        [byte] = reader.read_exact_n::<1>()?;
+        value = value
+            .checked_add(1)
+            .and_then(|value| value.checked_mul(0x80))
+            .ok_or(IndexReadError::Corrupt("number too large"))?
+            | usize::from(byte & 0x7f);
+    }
+    Ok(value)
+}
+
+fn write_varint(writer: &mut impl io::Write, mut value: usize) -> io::Result<()> {
+    let mut bytes = vec![(value & 0x7f) as u8];
+    value >>= 7;
+    while value != 0 {
+        value -= 1;
+        bytes.push(0x80 | (value & 0x7f) as u8);
+        value >>= 7;
+    }
+    bytes.reverse();
+    writer.write_all(&bytes)
+}
+
+// Read a NUL-terminated path
+fn read_path(reader: &mut impl BufRead, path: &mut Vec<u8>) -> Result<(), IndexReadError> {
+    reader.read_until(0, path)?;
+    // Exclude null byte in path
+    if path.pop() != Some(0) {
+        return Err(IndexReadError::Truncated);
+    }
+    Ok(())
+}
+
+// Version 4 stores a path as the number of bytes to drop from the end of the previous path,
+// followed by what to append to the rest
+fn read_prefix_compressed_path(
+    reader: &mut impl BufRead,
+    previous_path: &[u8],
+) -> Result<Vec<u8>, IndexReadError> {
+    let strip_length = read_varint(reader)?;
+    let prefix_length =
+        previous_path
+            .len()
+            .checked_sub(strip_length)
+            .ok_or(IndexReadError::Corrupt(
+                "path prefix longer than the previous path",
+            ))?;
+
+    let mut path = previous_path[..prefix_length].to_vec();
+    read_path(reader, &mut path)?;
+    Ok(path)
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct EntryMetadata {
+    pub ctime_seconds: u32,
+    pub ctime_nanoseconds: u32,
+    pub mtime_seconds: u32,
+    pub mtime_nanoseconds: u32,
+    pub dev: u32,
+    pub ino: u32,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+
+    /// The lower 32 bits of the size of the file, which is all the index can record
+    ///
+    /// Like git, the size of a file of 4 GiB or more wraps around rather than saturating
+    pub file_size: u32,
+}
+
+// Keep the lower 32 bits of a file size
+fn truncate_file_size(size: u64) -> u32 {
+    (size & u64::from(u32::MAX)) as u32
+}
+
+impl EntryMetadata {
+    /// Stat a file in the working tree, or a symbolic link itself rather than its target
+    #[cfg(unix)]
+    pub fn from_path(path: &Path) -> io::Result<EntryMetadata> {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata = fs::symlink_metadata(path)?;
+
+        // git only records whether a file is executable
+        let mode = if metadata.file_type().is_symlink() {
+            SYMLINK_MODE
+        } else if metadata.mode() & 0o111 != 0 {
+            0o100755
+        } else {
+            0o100644
+        };
+
+        Ok(EntryMetadata {
+            ctime_seconds: metadata.ctime() as u32,
+            ctime_nanoseconds: metadata.ctime_nsec() as u32,
+            mtime_seconds: metadata.mtime() as u32,
+            mtime_nanoseconds: metadata.mtime_nsec() as u32,
+            dev: metadata.dev() as u32,
+            ino: metadata.ino() as u32,
+            mode,
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            file_size: truncate_file_size(metadata.size()),
+        })
+    }
+
+    /// Stat a file in the working tree
+    #[cfg(windows)]
+    pub fn from_path(path: &Path) -> io::Result<EntryMetadata> {
+        let metadata = fs::metadata(path)?;
+
+        Ok(EntryMetadata {
+            ctime_seconds: 0,     // TODO
+            ctime_nanoseconds: 0, // TODO
+            mtime_seconds: 0,     // TODO
+            mtime_nanoseconds: 0, // TODO
+            dev: 0,
+            ino: 0,
+            mode: 0o100644, // TODO
+            uid: 0,
+            gid: 0,
+            file_size: truncate_file_size(metadata.len()),
+        })
+    }
+
+    /// Whether the stat data of a file is unchanged, so that we can skip rehashing it
+    pub fn matches_stat(&self, other: &EntryMetadata) -> bool {
+        self.mtime_seconds == other.mtime_seconds
+            && self.mtime_nanoseconds == other.mtime_nanoseconds
+            && self.ctime_seconds == other.ctime_seconds
+            && self.ctime_nanoseconds == other.ctime_nanoseconds
+            && self.ino == other.ino
+            && self.mode == other.mode
+            && self.uid == other.uid
+            && self.gid == other.gid
+            && self.file_size == other.file_size
+    }
+}
+
+fn read_metadata(reader: &mut impl Read) -> io::Result<EntryMetadata> {
+    let ctime_seconds = u32::from_be_bytes(reader.read_exact_4()?);
+    let ctime_nanoseconds = u32::from_be_bytes(reader.read_exact_4()?);
+    let mtime_seconds = u32::from_be_bytes(reader.read_exact_4()?);
+    let mtime_nanoseconds = u32::from_be_bytes(reader.read_exact_4()?);
+
+    let dev = u32::from_be_bytes(reader.read_exact_4()?);
+    let ino = u32::from_be_bytes(reader.read_exact_4()?);
+    let mode = u32::from_be_bytes(reader.read_exact_4()?);
+
+    let uid = u32::from_be_bytes(reader.read_exact_4()?);
+    let gid = u32::from_be_bytes(reader.read_exact_4()?);
+    let file_size = u32::from_be_bytes(reader.read_exact_4()?);
+
+    Ok(EntryMetadata {
+        ctime_seconds,
+        ctime_nanoseconds,
+        mtime_seconds,
+        mtime_nanoseconds,
+        dev,
+        ino,
+        mode,
+        uid,
+        gid,
+        file_size,
+    })
+}
+
+fn write_metadata(writer: &mut impl io::Write, metadata: &EntryMetadata) -> io::Result<()> {
+    writer.write_all(&u32::to_be_bytes(metadata.ctime_seconds))?;
+    writer.write_all(&u32::to_be_bytes(metadata.ctime_nanoseconds))?;
+
+    writer.write_all(&u32::to_be_bytes(metadata.mtime_seconds))?;
+    writer.write_all(&u32::to_be_bytes(metadata.mtime_nanoseconds))?;
+
+    writer.write_all(&u32::to_be_bytes(metadata.dev))?;
+    writer.write_all(&u32::to_be_bytes(metadata.ino))?;
+    writer.write_all(&u32::to_be_bytes(metadata.mode))?;
+    writer.write_all(&u32::to_be_bytes(metadata.uid))?;
+    writer.write_all(&u32::to_be_bytes(metadata.gid))?;
+    writer.write_all(&u32::to_be_bytes(metadata.file_size))?;
+    Ok(())
+}
+
+fn write_oid(writer: &mut impl io::Write, oid: ObjectId) -> io::Result<()> {
+    writer.write_all(&oid.0)?;
+    Ok(())
+}
+
+// Write the flags of an entry, followed by its extended flags if it needs them
+fn write_flags(writer: &mut impl io::Write, path_len: usize, flags: EntryFlags) -> io::Result<()> {
+    // path size, saturating for long paths
+    let mut bits =
+        u16::try_from(path_len).map_or(PATH_LENGTH_MASK, |length| length.min(PATH_LENGTH_MASK));
+    bits |= (u16::from(flags.stage) << STAGE_SHIFT) & STAGE_MASK;
+    if flags.assume_valid {
+        bits |= ASSUME_VALID_FLAG;
+    }
+    if flags.is_extended() {
+        bits |= EXTENDED_FLAG;
+    }
+    writer.write_all(&u16::to_be_bytes(bits))?;
+
+    if flags.is_extended() {
+        let mut extended_bits = 0;
+        if flags.skip_worktree {
+            extended_bits |= SKIP_WORKTREE_FLAG;
+        }
+        if flags.intent_to_add {
+            extended_bits |= INTENT_TO_ADD_FLAG;
+        }
+        writer.write_all(&u16::to_be_bytes(extended_bits))?;
+    }
+    Ok(())
+}
+
+fn write_prefix_compressed_path(
+    writer: &mut impl io::Write,
+    path: &[u8],
+    previous_path: &[u8],
+) -> io::Result<()> {
+    let common_length = path
+        .iter()
+        .zip(previous_path)
+        .take_while(|(a, b)| a == b)
+        .count();
+    write_varint(writer, previous_path.len() - common_length)?;
+    writer.write_all(&path[common_length..])?;
+    writer.write_all(&[0])
+}
+
+// Pad an entry of `total_size` bytes, without its path terminator, to a multiple of 8 bytes
+fn write_paddings(writer: &mut impl io::Write, total_size: usize) -> io::Result<()> {
+    let padded_size = (total_size / 8 + 1) * 8;
+    for _ in 0..(padded_size - total_size) {
+        writer.write_all(&[0])?;
+    }
+    Ok(())
+}
+
+impl Index {
+    /// Open an on-memory version of a git index from .git/index file
+    ///
+    /// If .git/index file doesn't exist, create an empty index
+    ///
+    /// TODO: very error prone API, make it private and have something else
+    pub fn open(index_path: &Path) -> Result<Self, IndexReadError> {
+        let content = match fs::read(index_path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Index::default()),
+            content => content?,
+        };
+
+        // The file ends with a checksum of everything before it
+        let checksum_start = content
+            .len()
+            .checked_sub(SHA_SIZE)
+            .ok_or(IndexReadError::Truncated)?;
+        let (body, checksum) = content.split_at(checksum_start);
+        // git skips the checksum when index.skipHash is set, leaving it zeroed
+        if checksum != [0; SHA_SIZE] {
+            let actual = ObjectId::from_data(body);
+            if actual.0 != checksum {
+                return Err(IndexReadError::ChecksumMismatch {
+                    expected: ObjectId(checksum.try_into().unwrap()),
+                    actual,
+                });
+            }
+        }
+
+        let mut index = Index::default();
+        index.parse(body).map_err(|err| match err {
+            // reading past the end of the data
+            IndexReadError::IOError(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                IndexReadError::Truncated
+            }
+            err => err,
+        })?;
+        Ok(index)
+    }
+
+    // Parse the entries and the extensions that follow them
+    fn parse(&mut self, mut reader: &[u8]) -> Result<(), IndexReadError> {
+        let (version, entry_count) = read_header(&mut reader)?;
+        self.version = version;
+
+        let mut previous_path = vec![];
+        for _ in 0..entry_count {
+            let metadata = read_metadata(&mut reader)?;
+
+            let oid = ObjectId(reader.read_exact_n::<SHA_SIZE>()?);
+
+            let (flags, entry_flags) = read_flags(&mut reader, version)?;
+
+            let path = if version >= 4 {
+                read_prefix_compressed_path(&mut reader, &previous_path)?
+            } else {
+                let mut path = vec![];
+                read_path(&mut reader, &mut path)?;
+                path
+            };
+            let path_length = flags & PATH_LENGTH_MASK;
+            if path_length != PATH_LENGTH_MASK && usize::from(path_length) != path.len() {
+                return Err(IndexReadError::Corrupt(
+                    "path length does not match the path",
+                ));
+            }
+
+            // consume padding bits, which version 4 doesn't have
+            if version < 4 {
+                let extended_size = if flags & EXTENDED_FLAG != 0 { 2 } else { 0 };
+                let padding = 8 - (MIN_ENTRY_SIZE + extended_size + path.len()) % 8 - 1;
+                reader.read_exact(&mut [0; 8][..padding])?;
+            }
+
+            self.entries.insert(
+                (
+                    GitPath(PathBuf::from(std::str::from_utf8(&path)?)),
+                    entry_flags.stage,
+                ),
+                EntryData {
+                    metadata,
+                    oid,
+                    flags: entry_flags,
+                },
+            );
+            previous_path = path;
+        }
+
+        self.read_extensions(reader)
+    }
+
+    fn read_extensions(&mut self, mut extensions: &[u8]) -> Result<(), IndexReadError> {
+        while !extensions.is_empty() {
+            let signature = extensions.read_exact_4()?;
+            let size = u32::from_be_bytes(extensions.read_exact_4()?) as usize;
+            if extensions.len() < size {
+                return Err(IndexReadError::Corrupt("extension larger than the index"));
+            }
+            let (data, rest) = extensions.split_at(size);
+            extensions = rest;
+
+            match &signature {
+                b"TREE" => {
+                    let mut data = data;
+                    if !data.is_empty() {
+                        self.cache_tree = Some(CacheTree::parse(&mut data)?.1);
+                    }
+                }
+                b"REUC" => self.resolve_undo = ResolveUndo::parse_all(data)?,
+                signature if LAYOUT_EXTENSIONS.contains(&signature) => {}
+                // extensions starting with an uppercase letter are optional
+                [b'A'..=b'Z', ..] => self.unknown_extensions.push(UnknownExtension {
+                    signature,
+                    data: data.to_vec(),
+                }),
+                _ => {
+                    return Err(IndexReadError::UnsupportedExtension(
+                        String::from_utf8_lossy(&signature).into_owned(),
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Create an iterator that will return every entry contained in the index at the time of creation.
+    /// Entries are returned in order, sorted by path then by stage.
+    pub fn iter(&self) -> impl Iterator<Item = EntryRef<'_>> {
+        self.entries.iter().map(|((path, _), data)| EntryRef {
+            metadata: data.metadata,
+            oid: data.oid,
+            flags: data.flags,
+            path: &path.0,
+        })
+    }
+
+    /// The entry of a path at the given stage
+    pub fn get(&self, path: &Path, stage: u8) -> Option<EntryRef<'_>> {
+        self.entries
+            .get_key_value(&(GitPath::from(path), stage))
+            .map(|((path, _), data)| EntryRef {
+                metadata: data.metadata,
+                oid: data.oid,
+                flags: data.flags,
+                path: &path.0,
+            })
+    }
+
+    /// The paths with merge conflicts, sorted by path
+    pub fn conflicts(&self) -> impl Iterator<Item = Conflict<'_>> {
+        let mut conflicts: Vec<Conflict> = vec![];
+        for entry in self.iter().filter(|entry| entry.stage() != 0) {
+            if conflicts.last().map(|conflict| conflict.path) != Some(entry.path) {
+                conflicts.push(Conflict {
+                    path: entry.path,
+                    base: None,
+                    ours: None,
+                    theirs: None,
+                });
+            }
+            let conflict = conflicts.last_mut().unwrap();
+            match entry.stage() {
+                1 => conflict.base = Some(entry),
+                2 => conflict.ours = Some(entry),
+                _ => conflict.theirs = Some(entry),
+            }
+        }
+        conflicts.into_iter()
+    }
+
+    /// The version of the file format of the index
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Add a merged entry. Like git, this resolves the merge conflict of the path if it has one,
+    /// remembering its stages in the resolve-undo extension
+    pub fn add(&mut self, path: PathBuf, oid: ObjectId, metadata: EntryMetadata) {
+        if let Some(cache_tree) = &mut self.cache_tree {
+            cache_tree.invalidate(&path);
+        }
+
+        let mut stages = [None; 3];
+        for (stage, resolved) in (1..=3).zip(&mut stages) {
+            if let Some(data) = self.entries.remove(&(GitPath(path.clone()), stage)) {
+                *resolved = Some((data.metadata.mode, data.oid));
+            }
+        }
+        if stages.iter().any(Option::is_some) {
+            self.record_resolve_undo(&path, stages);
+        }
+
+        self.entries.insert(
+            (GitPath(path), 0),
+            EntryData {
+                oid,
+                metadata,
+                flags: EntryFlags::default(),
+            },
+        );
+    }
+
+    /// Record a stage of a merge conflict, replacing the merged entry of the path if any. Like
+    /// git, the entry has no stat data
+    pub fn add_conflict(&mut self, path: PathBuf, stage: u8, oid: ObjectId, mode: u32) {
+        assert!((1..=3).contains(&stage), "conflict stages are 1 to 3");
+        if let Some(cache_tree) = &mut self.cache_tree {
+            cache_tree.invalidate(&path);
+        }
+        self.entries.remove(&(GitPath(path.clone()), 0));
+        self.entries.insert(
+            (GitPath(path), stage),
+            EntryData {
+                oid,
+                metadata: EntryMetadata {
+                    mode,
+                    ..EntryMetadata::default()
+                },
+                flags: EntryFlags {
+                    stage,
+                    ..EntryFlags::default()
+                },
+            },
+        );
+    }
+
+    /// Remove all the entries of a path
+    pub fn remove(&mut self, path: &Path) {
+        if let Some(cache_tree) = &mut self.cache_tree {
+            cache_tree.invalidate(path);
+        }
+        for stage in 0..=3 {
+            self.entries.remove(&(GitPath::from(path), stage));
+        }
+    }
+
+    /// The cached ids of the trees of the index, if any
+    pub fn cache_tree(&self) -> Option<&CacheTree> {
+        self.cache_tree.as_ref()
+    }
+
+    pub(crate) fn set_cache_tree(&mut self, cache_tree: CacheTree) {
+        self.cache_tree = Some(cache_tree);
+    }
+
+    // Like git, merge the stages with the ones already recorded for the path
+    fn record_resolve_undo(&mut self, path: &Path, stages: [Option<(u32, ObjectId)>; 3]) {
+        let position = match self
+            .resolve_undo
+            .binary_search_by(|entry| entry.path.as_os_str().cmp(path.as_os_str()))
+        {
+            Ok(position) => position,
+            Err(position) => {
+                self.resolve_undo.insert(
+                    position,
+                    ResolveUndo {
+                        path: path.to_path_buf(),
+                        stages: [None; 3],
+                    },
+                );
+                position
+            }
+        };
+        let recorded = &mut self.resolve_undo[position].stages;
+        for (recorded, stage) in recorded.iter_mut().zip(stages) {
+            if stage.is_some() {
+                *recorded = stage;
+            }
+        }
+    }
+
+    /// The conflicts that were resolved, recorded by git so that they can be recreated
+    pub fn resolve_undo(&self) -> &[ResolveUndo] {
+        &self.resolve_undo
+    }
+
+    fn write_extension(
+        file: &mut impl io::Write,
+        signature: &[u8; 4],
+        data: &[u8],
+    ) -> io::Result<()> {
+        file.write_all(signature)?;
+        file.write_all(&u32::to_be_bytes(u32::try_from(data.len()).unwrap()))?;
+        file.write_all(data)
+    }
+
+    /// Write the index, including its extensions and the checksum that ends the file
+    pub fn write_to(&self, file: &mut impl io::Write) -> io::Result<()> {
+        let file = &mut HashingWriter {
+            inner: file,
+            hasher: sha1::Sha1::new(),
+        };
+        let entry_size = u32::try_from(self.entries.len()).unwrap();
+
+        // like git, only use version 3 when an entry needs the extended flags
+        let extended = self.entries.values().any(|data| data.flags.is_extended());
+        let version = match self.version {
+            2 | 3 if extended => 3,
+            2 | 3 => 2,
+            version => version,
+        };
+
+        file.write_all(b"DIRC")?;
+        file.write_all(&u32::to_be_bytes(version))?;
+        file.write_all(&u32::to_be_bytes(entry_size))?;
+
+        let mut previous_path: &[u8] = &[];
+        for ((entry_path, _), entry_data) in &self.entries {
+            let path = entry_path.0.to_str().unwrap().as_bytes();
+
+            write_metadata(file, &entry_data.metadata)?;
+            write_oid(file, entry_data.oid)?;
+            write_flags(file, path.len(), entry_data.flags)?;
+            if version >= 4 {
+                write_prefix_compressed_path(file, path, previous_path)?;
+            } else {
+                file.write_all(path)?;
+                let extended_size = if entry_data.flags.is_extended() { 2 } else { 0 };
+                write_paddings(file, MIN_ENTRY_SIZE + extended_size + path.len())?;
+            }
+            previous_path = path;
+        }
+
+        if let Some(cache_tree) = &self.cache_tree {
+            let mut data = vec![];
+            cache_tree.write("", &mut data);
+            Self::write_extension(file, b"TREE", &data)?;
+        }
+        if !self.resolve_undo.is_empty() {
+            let mut data = vec![];
+            for entry in &self.resolve_undo {
+                entry.write(&mut data);
+            }
+            Self::write_extension(file, b"REUC", &data)?;
+        }
+        for extension in &self.unknown_extensions {
+            Self::write_extension(file, &extension.signature, &extension.data)?;
+        }
+
+        let checksum = file.hasher.clone().finalize();
+        file.inner.write_all(&checksum)
+    }
+}
+
+impl Default for Index {
+    fn default() -> Self {
+        Index {
+            version: DEFAULT_VERSION,
+            entries: BTreeMap::new(),
+            cache_tree: None,
+            resolve_undo: vec![],
+            unknown_extensions: vec![],
+        }
+    }
+}
+
+impl Repository {
+    /// Read `.git/index`. Returns an empty index if the file doesn't exist
+    pub fn index(&self) -> Result<Index, IndexReadError> {
+        Index::open(&self.index_path())
+    }
+
+    pub fn index_path(&self) -> PathBuf {
+        self.git_dir.join("index")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::test_path;
+
+    #[test]
+    fn large_file_size() {
+        let path = test_path!().join("large");
+        // a sparse file does not take the space on disk
+        let file = fs::File::create(&path).unwrap();
+        file.set_len((5 << 32) + 42).unwrap();
+
+        let metadata = EntryMetadata::from_path(&path).unwrap();
+        assert_eq!(metadata.file_size, 42);
+    }
+    // An index with one entry followed by an extension
+    fn index_with_extension(test_path: &Path, signature: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let file = test_path.join("file");
+        fs::write(&file, "content").unwrap();
+        let mut index = Index::default();
+        index.add(
+            PathBuf::from("file"),
+            ObjectId([1; 20]),
+            EntryMetadata::from_path(&file).unwrap(),
+        );
+
+        let mut content = vec![];
+        index.write_to(&mut content).unwrap();
+        content.truncate(content.len() - SHA_SIZE);
+        Index::write_extension(&mut content, signature, data).unwrap();
+        let checksum = ObjectId::from_data(&content);
+        content.extend_from_slice(&checksum.0);
+        content
+    }
+
+    #[test]
+    fn extensions() {
+        let test_path = test_path!();
+        let index_path = test_path.join("index");
+
+        // optional extensions are kept
+        let content = index_with_extension(&test_path, b"ABCD", b"some data");
+        fs::write(&index_path, &content).unwrap();
+        let index = Index::open(&index_path).unwrap();
+        assert_eq!(index.iter().count(), 1);
+        let mut written = vec![];
+        index.write_to(&mut written).unwrap();
+        assert_eq!(written, content);
+
+        // mandatory ones can't be ignored
+        let content = index_with_extension(&test_path, b"link", b"some data");
+        fs::write(&index_path, &content).unwrap();
+        assert!(matches!(
+            Index::open(&index_path),
+            Err(IndexReadError::UnsupportedExtension(_))
+        ));
+
+        let mut content = index_with_extension(&test_path, b"ABCD", b"some data");
+        *content.last_mut().unwrap() ^= 1;
+        fs::write(&index_path, &content).unwrap();
+        assert!(matches!(
+            Index::open(&index_path),
+            Err(IndexReadError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn integrity() {
+        let test_path = test_path!();
+        let index_path = test_path.join("index");
+        let mut content = index_with_extension(&test_path, b"ABCD", b"some data");
+
+        // a changed byte
+        content[20] ^= 1;
+        fs::write(&index_path, &content).unwrap();
+        assert!(matches!(
+            Index::open(&index_path),
+            Err(IndexReadError::ChecksumMismatch { .. })
+        ));
+
+        // a file cut in the middle of an entry, with a valid checksum
+        let mut truncated = content[..40].to_vec();
+        truncated.extend_from_slice(&ObjectId::from_data(&truncated).0);
+        fs::write(&index_path, &truncated).unwrap();
+        assert!(matches!(
+            Index::open(&index_path),
+            Err(IndexReadError::Truncated)
+        ));
+
+        fs::write(&index_path, &content[..10]).unwrap();
+        assert!(matches!(
+            Index::open(&index_path),
+            Err(IndexReadError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn long_paths() {
+        let test_path = test_path!();
+        let file = test_path.join("file");
+        fs::write(&file, "content").unwrap();
+        let metadata = EntryMetadata::from_path(&file).unwrap();
+
+        let long_path = PathBuf::from("d/".repeat(3000) + "file");
+        let mut index = Index::default();
+        index.add(long_path.clone(), ObjectId([1; 20]), metadata);
+        index.add(PathBuf::from("short"), ObjectId([2; 20]), metadata);
+
+        let index_path = test_path.join("index");
+        let mut content = vec![];
+        index.write_to(&mut content).unwrap();
+        fs::write(&index_path, &content).unwrap();
+
+        let index = Index::open(&index_path).unwrap();
+        let paths: Vec<_> = index.iter().map(|entry| entry.path.to_path_buf()).collect();
+        assert_eq!(paths, vec![long_path, PathBuf::from("short")]);
+    }
+
+    #[test]
+    fn entries_are_sorted_by_bytes() {
+        let metadata = EntryMetadata::default();
+        let mut index = Index::default();
+        for path in ["a/c", "a.b", "a-z"] {
+            index.add(PathBuf::from(path), ObjectId([1; 20]), metadata);
+        }
+
+        let paths: Vec<&Path> = index.iter().map(|entry| entry.path).collect();
+        assert_eq!(
+            paths,
+            [Path::new("a-z"), Path::new("a.b"), Path::new("a/c")]
+        );
+
+        // the entries are written in the same order
+        let mut content = vec![];
+        index.write_to(&mut content).unwrap();
+        let position = |path: &[u8]| {
+            content
+                .windows(path.len())
+                .position(|window| window == path)
+                .unwrap()
+        };
+        assert!(position(b"a-z") < position(b"a.b"));
+        assert!(position(b"a.b") < position(b"a/c"));
+    }
+
+    #[test]
+    fn varint() {
+        for (value, encoded) in [
+            (0, &[0x00][..]),
+            (127, &[0x7f]),
+            (128, &[0x80, 0x00]),
+            (16511, &[0xff, 0x7f]),
+            (16512, &[0x80, 0x80, 0x00]),
+        ] {
+            let mut written = vec![];
+            write_varint(&mut written, value).unwrap();
+            assert_eq!(written, encoded);
+            assert_eq!(read_varint(&mut &encoded[..]).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn versions() {
+        let test_path = test_path!();
+        let file = test_path.join("file");
+        fs::write(&file, "content").unwrap();
+        let metadata = EntryMetadata::from_path(&file).unwrap();
+
+        let mut index = Index::default();
+        for (i, path) in ["dir/a", "dir/ab", "dir/sub/b", "file"].iter().enumerate() {
+            index.add(PathBuf::from(path), ObjectId([i as u8; 20]), metadata);
+        }
+        let index_path = test_path.join("index");
+        let reopen = |index: &Index| {
+            let mut content = vec![];
+            index.write_to(&mut content).unwrap();
+            fs::write(&index_path, &content).unwrap();
+            Index::open(&index_path).unwrap()
+        };
+
+        // the extended flags need version 3
+        let flags = EntryFlags {
+            stage: 0,
+            assume_valid: false,
+            skip_worktree: true,
+            intent_to_add: true,
+        };
+        index
+            .entries
+            .get_mut(&(GitPath(PathBuf::from("dir/ab")), 0))
+            .unwrap()
+            .flags = flags;
+        let reopened = reopen(&index);
+        assert_eq!(reopened.version(), 3);
+        assert_eq!(reopened.get(Path::new("dir/ab"), 0).unwrap().flags, flags);
+
+        for version in [2, 4] {
+            index.version = version;
+            let reopened = reopen(&index);
+            assert_eq!(reopened.version(), version.max(3));
+            let entries: Vec<_> = reopened
+                .iter()
+                .map(|entry| (entry.path.to_path_buf(), entry.oid, entry.flags))
+                .collect();
+            let expected: Vec<_> = index
+                .iter()
+                .map(|entry| (entry.path.to_path_buf(), entry.oid, entry.flags))
+                .collect();
+            assert_eq!(entries, expected);
+        }
+
+        // and version 3 goes back to 2 when they are gone
+        index.version = 3;
+        index.add(PathBuf::from("dir/ab"), ObjectId([1; 20]), metadata);
+        assert_eq!(reopen(&index).version(), 2);
+    }
+
+    #[test]
+    fn stages() {
+        let test_path = test_path!();
+        let file = test_path.join("file");
+        fs::write(&file, "content").unwrap();
+        let metadata = EntryMetadata::from_path(&file).unwrap();
+
+        let mut index = Index::default();
+        index.add(PathBuf::from("a"), ObjectId([0; 20]), metadata);
+        for stage in [1, 3] {
+            let flags = EntryFlags {
+                stage,
+                ..EntryFlags::default()
+            };
+            let data = EntryData {
+                metadata,
+                oid: ObjectId([stage; 20]),
+                flags,
+            };
+            index
+                .entries
+                .insert((GitPath(PathBuf::from("b")), stage), data);
+        }
+
+        let index_path = test_path.join("index");
+        let mut content = vec![];
+        index.write_to(&mut content).unwrap();
+        fs::write(&index_path, &content).unwrap();
+        let mut index = Index::open(&index_path).unwrap();
+
+        let stages: Vec<_> = index.iter().map(|entry| entry.stage()).collect();
+        assert_eq!(stages, [0, 1, 3]);
+        let conflicts: Vec<_> = index.conflicts().collect();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].path, Path::new("b"));
+        assert_eq!(conflicts[0].base.unwrap().oid, ObjectId([1; 20]));
+        assert!(conflicts[0].ours.is_none());
+        assert_eq!(conflicts[0].theirs.unwrap().oid, ObjectId([3; 20]));
+
+        // resolving the conflict remembers its stages
+        index.add(PathBuf::from("b"), ObjectId([4; 20]), metadata);
+        assert_eq!(index.conflicts().count(), 0);
+        assert_eq!(
+            index.resolve_undo(),
+            [ResolveUndo {
+                path: PathBuf::from("b"),
+                stages: [
+                    Some((metadata.mode, ObjectId([1; 20]))),
+                    None,
+                    Some((metadata.mode, ObjectId([3; 20])))
+                ],
+            }]
+        );
+    }
+}
@@ -7,16 +7,36 @@ pub mod utils;
 
 mod repository;
 
+pub use crate::database::{ObjectPrefixError, DEFAULT_ABBREV_LENGTH, MIN_ABBREV_LENGTH};
 pub use crate::repository::Repository;
 
+pub mod branch;
+pub mod checkout;
 pub mod head;
 
+pub mod diff;
+
+pub mod config;
+pub mod revision;
+pub mod revwalk;
+
 // TODO: should not be public
 mod database;
+pub mod ignore;
 pub mod index;
 mod is_executable;
 pub mod lockfile;
+pub mod merge;
 mod object_reader;
+mod pack;
+mod packed_refs;
 mod parse_utils;
+mod path_order;
 mod read_ext;
+pub mod reflog;
+pub mod refname;
+pub mod reset;
+pub mod status;
+mod wildmatch;
+mod worktree;
 pub mod write_utils;
@@ -13,10 +13,19 @@ impl Lockfile {
     ///
     /// * `path` - A path without the .lock extension
     pub fn new(path: &Path) -> Result<Lockfile, LockfileError> {
-        let lock_path = path.with_extension("lock");
+        // append rather than replace the extension, so that refs like v1.0 keep their full name
+        let mut lock_path = path.as_os_str().to_os_string();
+        lock_path.push(".lock");
+        let lock_path = PathBuf::from(lock_path);
 
         // makes sure that the parent folder exist
-        fs::create_dir_all(lock_path.parent().unwrap()).unwrap();
+        if let Err(source) = fs::create_dir_all(lock_path.parent().unwrap()) {
+            return Err(LockfileError {
+                path: lock_path,
+                kind: LockfileErrorKind::Io,
+                source,
+            });
+        }
 
         let maybe_file = File::options()
             .read(true)
@@ -155,8 +164,7 @@ mod tests {
         let test_path = test_path!();
         let head_path = test_path.join("HEAD");
 
-        std::fs::write(&head_path.with_extension("lock"), "")
-            .expect("Failed to create a lock file");
+        std::fs::write(head_path.with_extension("lock"), "").expect("Failed to create a lock file");
 
         // can't grab another lock when there is an existing lock
         assert!(matches!(
@@ -0,0 +1,87 @@
+// Find the merge bases of two commits
+//
+// A merge base is a common ancestor of the two commits that is not an ancestor of another common
+// ancestor. There is usually a single one, but a criss-cross history has several.
+
+use crate::object::ObjectReadError;
+use crate::oid::ObjectId;
+use crate::Repository;
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+};
+
+// Reads each commit once, keeping what the search needs
+struct CommitCache<'r> {
+    repository: &'r Repository,
+    parents: HashMap<ObjectId, Vec<ObjectId>>,
+}
+
+impl CommitCache<'_> {
+    fn parents(&mut self, oid: ObjectId) -> Result<&[ObjectId], ObjectReadError> {
+        if !self.parents.contains_key(&oid) {
+            let commit = self.repository.read_commit(oid)?;
+            self.parents.insert(oid, commit.parents().to_vec());
+        }
+        Ok(&self.parents[&oid])
+    }
+
+    // All the commits reachable from `starts`, including themselves
+    fn reachable(
+        &mut self,
+        starts: impl IntoIterator<Item = ObjectId>,
+    ) -> Result<HashSet<ObjectId>, ObjectReadError> {
+        let mut reached = HashSet::new();
+        let mut stack: Vec<ObjectId> = starts.into_iter().collect();
+        while let Some(oid) = stack.pop() {
+            if reached.insert(oid) {
+                stack.extend_from_slice(self.parents(oid)?);
+            }
+        }
+        Ok(reached)
+    }
+}
+
+impl Repository {
+    /// The best common ancestors of two commits, the most recent first. Empty when the commits
+    /// have unrelated histories
+    pub fn merge_bases(
+        &self,
+        one: ObjectId,
+        two: ObjectId,
+    ) -> Result<Vec<ObjectId>, ObjectReadError> {
+        let mut cache = CommitCache {
+            repository: self,
+            parents: HashMap::new(),
+        };
+        let ancestors_one = cache.reachable([one])?;
+        let ancestors_two = cache.reachable([two])?;
+        let common: HashSet<ObjectId> = ancestors_one
+            .intersection(&ancestors_two)
+            .copied()
+            .collect();
+
+        // the ancestors of common ancestors are common ancestors themselves
+        let mut parents = vec![];
+        for &oid in &common {
+            parents.extend_from_slice(cache.parents(oid)?);
+        }
+        let redundant = cache.reachable(parents)?;
+
+        let mut bases = vec![];
+        for oid in common.difference(&redundant) {
+            let time = self.read_commit(*oid)?.committer().time;
+            bases.push((Reverse(time), *oid));
+        }
+        bases.sort();
+        Ok(bases.into_iter().map(|(_, oid)| oid).collect())
+    }
+    /// Whether `ancestor` can be reached from `descendant`
+    pub fn is_ancestor(
+        &self,
+        ancestor: ObjectId,
+        descendant: ObjectId,
+    ) -> Result<bool, ObjectReadError> {
+        Ok(ancestor == descendant || self.merge_bases(ancestor, descendant)?.contains(&ancestor))
+    }
+}
@@ -0,0 +1,282 @@
+// Three-way merge of the lines of a file
+//
+// Both sides are diffed against the base. A change of one side that doesn't touch a change of the
+// other side is applied as is. Changes that overlap or, like in git, that are merely adjacent form
+// a group, which is clean when both sides made the same change and a conflict otherwise.
+
+use crate::diff::{diff_lines, split_lines, DiffAlgorithm, Edit};
+
+const MARKER_SIZE: usize = 7;
+
+/// The result of merging the contents of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentMerge {
+    /// The merged content, with conflict markers around the conflicting lines
+    pub content: Vec<u8>,
+
+    /// The number of conflicts
+    pub conflicts: usize,
+}
+
+impl ContentMerge {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts == 0
+    }
+}
+
+// A range of base lines that a side replaced with a range of its own lines
+#[derive(Debug, Copy, Clone)]
+struct Change {
+    base_start: usize,
+    base_end: usize,
+    side_start: usize,
+    side_end: usize,
+}
+
+fn changes(edits: &[Edit]) -> Vec<Change> {
+    let mut changes: Vec<Change> = vec![];
+    let (mut i, mut j) = (0, 0);
+    let mut in_change = false;
+    for edit in edits {
+        let (base_start, side_start) = (i, j);
+        match edit {
+            Edit::Equal => {
+                i += 1;
+                j += 1;
+                in_change = false;
+                continue;
+            }
+            Edit::Delete => i += 1,
+            Edit::Insert => j += 1,
+        }
+        if in_change {
+            let change = changes.last_mut().unwrap();
+            change.base_end = i;
+            change.side_end = j;
+        } else {
+            changes.push(Change {
+                base_start,
+                base_end: i,
+                side_start,
+                side_end: j,
+            });
+            in_change = true;
+        }
+    }
+    changes
+}
+
+// The lines of a side that replace the base lines `start..end`, `changes` being the changes of
+// the side in that range
+fn side_lines<'a>(
+    lines: &[&'a [u8]],
+    changes: &[Change],
+    start: usize,
+    end: usize,
+) -> Vec<&'a [u8]> {
+    let (first, last) = (changes[0], changes[changes.len() - 1]);
+    let side_start = first.side_start - (first.base_start - start);
+    let side_end = last.side_end + (end - last.base_end);
+    lines[side_start..side_end].to_vec()
+}
+
+fn push_lines(content: &mut Vec<u8>, lines: &[&[u8]]) {
+    for line in lines {
+        content.extend_from_slice(line);
+    }
+}
+
+fn push_marker(content: &mut Vec<u8>, marker: u8, label: Option<&str>) {
+    content.resize(content.len() + MARKER_SIZE, marker);
+    if let Some(label) = label {
+        content.push(b' ');
+        content.extend_from_slice(label.as_bytes());
+    }
+    content.push(b'\n');
+}
+
+// Write a side of a conflict, ending it with a newline so that the next marker starts a line
+fn push_conflict_side(content: &mut Vec<u8>, lines: &[&[u8]]) {
+    push_lines(content, lines);
+    if !lines.is_empty() && !content.ends_with(b"\n") {
+        content.push(b'\n');
+    }
+}
+
+fn push_conflict(
+    content: &mut Vec<u8>,
+    ours: &[&[u8]],
+    theirs: &[&[u8]],
+    ours_label: &str,
+    theirs_label: &str,
+) {
+    // the lines both sides agree on are left out of the conflict
+    let prefix = ours.iter().zip(theirs).take_while(|(a, b)| a == b).count();
+    push_lines(content, &ours[..prefix]);
+    let (ours, theirs) = (&ours[prefix..], &theirs[prefix..]);
+    let suffix = ours
+        .iter()
+        .rev()
+        .zip(theirs.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let common_end = &ours[ours.len() - suffix..];
+    let (ours, theirs) = (
+        &ours[..ours.len() - suffix],
+        &theirs[..theirs.len() - suffix],
+    );
+
+    push_marker(content, b'<', Some(ours_label));
+    push_conflict_side(content, ours);
+    push_marker(content, b'=', None);
+    push_conflict_side(content, theirs);
+    push_marker(content, b'>', Some(theirs_label));
+    push_lines(content, common_end);
+}
+
+/// Merge the changes made to the lines of `base` by `ours` and `theirs`. The labels are written
+/// after the conflict markers, to tell which side the lines come from
+pub fn merge_content(
+    base: &[u8],
+    ours: &[u8],
+    theirs: &[u8],
+    ours_label: &str,
+    theirs_label: &str,
+) -> ContentMerge {
+    let (base, ours, theirs) = (split_lines(base), split_lines(ours), split_lines(theirs));
+    let our_changes = changes(&diff_lines(&base, &ours, DiffAlgorithm::Myers));
+    let their_changes = changes(&diff_lines(&base, &theirs, DiffAlgorithm::Myers));
+
+    let mut merge = ContentMerge {
+        content: vec![],
+        conflicts: 0,
+    };
+    let (mut i, mut j) = (0, 0);
+    let mut base_position = 0;
+    while i < our_changes.len() || j < their_changes.len() {
+        // start a group with the change that comes first
+        let start = match (our_changes.get(i), their_changes.get(j)) {
+            (Some(a), Some(b)) => a.base_start.min(b.base_start),
+            (Some(a), None) => a.base_start,
+            (None, Some(b)) => b.base_start,
+            (None, None) => unreachable!(),
+        };
+        let (group_ours, group_theirs) = (i, j);
+        let mut end = start;
+        loop {
+            if let Some(change) = our_changes.get(i).filter(|c| c.base_start <= end) {
+                end = end.max(change.base_end);
+                i += 1;
+            } else if let Some(change) = their_changes.get(j).filter(|c| c.base_start <= end) {
+                end = end.max(change.base_end);
+                j += 1;
+            } else {
+                break;
+            }
+        }
+
+        push_lines(&mut merge.content, &base[base_position..start]);
+        base_position = end;
+
+        let our_group = &our_changes[group_ours..i];
+        let their_group = &their_changes[group_theirs..j];
+        if their_group.is_empty() {
+            push_lines(
+                &mut merge.content,
+                &side_lines(&ours, our_group, start, end),
+            );
+        } else if our_group.is_empty() {
+            push_lines(
+                &mut merge.content,
+                &side_lines(&theirs, their_group, start, end),
+            );
+        } else {
+            let our_lines = side_lines(&ours, our_group, start, end);
+            let their_lines = side_lines(&theirs, their_group, start, end);
+            if our_lines == their_lines {
+                push_lines(&mut merge.content, &our_lines);
+            } else {
+                merge.conflicts += 1;
+                push_conflict(
+                    &mut merge.content,
+                    &our_lines,
+                    &their_lines,
+                    ours_label,
+                    theirs_label,
+                );
+            }
+        }
+    }
+    push_lines(&mut merge.content, &base[base_position..]);
+    merge
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merge(base: &str, ours: &str, theirs: &str) -> (String, usize) {
+        let merge = merge_content(
+            base.as_bytes(),
+            ours.as_bytes(),
+            theirs.as_bytes(),
+            "ours",
+            "theirs",
+        );
+        (String::from_utf8(merge.content).unwrap(), merge.conflicts)
+    }
+
+    #[test]
+    fn clean() {
+        let base = "a\nb\nc\nd\ne\n";
+        assert_eq!(
+            merge(base, "A\nb\nc\nd\ne\n", "a\nb\nc\nd\nE\n"),
+            ("A\nb\nc\nd\nE\n".to_string(), 0)
+        );
+        // the same change on both sides
+        assert_eq!(
+            merge(base, "a\nb\nX\nd\ne\n", "a\nb\nX\nd\ne\n"),
+            ("a\nb\nX\nd\ne\n".to_string(), 0)
+        );
+        assert_eq!(
+            merge(base, "a\nb\nd\ne\n", "a\nb\nc\nd\ne\nf\n"),
+            ("a\nb\nd\ne\nf\n".to_string(), 0)
+        );
+    }
+
+    #[test]
+    fn conflicts() {
+        let base = "a\nb\nc\n";
+        assert_eq!(
+            merge(base, "a\nB\nc\n", "a\nb2\nc\n"),
+            (
+                "a\n<<<<<<< ours\nB\n=======\nb2\n>>>>>>> theirs\nc\n".to_string(),
+                1
+            )
+        );
+        // adjacent changes conflict
+        assert_eq!(
+            merge(base, "A\nb\nc\n", "a\nB\nc\n"),
+            (
+                "<<<<<<< ours\nA\nb\n=======\na\nB\n>>>>>>> theirs\nc\n".to_string(),
+                1
+            )
+        );
+        // both sides add different files
+        assert_eq!(
+            merge("", "x\ncommon\n", "y\ncommon\n"),
+            (
+                "<<<<<<< ours\nx\n=======\ny\n>>>>>>> theirs\ncommon\n".to_string(),
+                1
+            )
+        );
+        // markers start a new line
+        assert_eq!(
+            merge(base, "a\nb\nc\nours", "a\nb\nc\ntheirs"),
+            (
+                "a\nb\nc\n<<<<<<< ours\nours\n=======\ntheirs\n>>>>>>> theirs\n".to_string(),
+                1
+            )
+        );
+    }
+}
@@ -0,0 +1,332 @@
+//! Merge histories: merge bases, three-way merges of trees and file contents, and the merge of
+//! a commit into HEAD
+//!
+//! While a merge with conflicts is waiting to be concluded, `.git/MERGE_HEAD` holds the commit
+//! being merged and `.git/MERGE_MSG` the message of the merge commit
+
+mod base;
+mod content;
+mod tree;
+
+pub use content::{merge_content, ContentMerge};
+pub use tree::{ConflictKind, MergeConflict, TreeMerge};
+
+use crate::checkout::{path_list, CheckoutError};
+use crate::config::ConfigError;
+use crate::database::DatabaseWriteError;
+use crate::diff::DiffError;
+use crate::head::Head;
+use crate::index::{Index, IndexReadError};
+use crate::lockfile::{Lockfile, LockfileError};
+use crate::object::{get_author, get_committer, Commit, ObjectReadError, WriteTreeError};
+use crate::oid::ObjectId;
+use crate::references::ReferenceError;
+use crate::Repository;
+use std::{collections::BTreeSet, fs, io, io::ErrorKind, path::PathBuf};
+
+#[derive(thiserror::Error, Debug)]
+pub enum MergeError {
+    #[error(transparent)]
+    ObjectReadError(#[from] ObjectReadError),
+
+    #[error(transparent)]
+    DatabaseWriteError(#[from] DatabaseWriteError),
+
+    #[error(transparent)]
+    IndexReadError(#[from] IndexReadError),
+
+    #[error(transparent)]
+    ReferenceError(#[from] ReferenceError),
+
+    #[error(transparent)]
+    DiffError(#[from] DiffError),
+
+    #[error(transparent)]
+    WriteTreeError(#[from] WriteTreeError),
+
+    #[error(transparent)]
+    LockfileError(#[from] LockfileError),
+
+    #[error(transparent)]
+    ConfigError(#[from] ConfigError),
+
+    #[error(transparent)]
+    CheckoutError(CheckoutError),
+
+    #[error("IO Error")]
+    IOError(#[from] io::Error),
+
+    #[error(
+        "Your local changes to the following files would be overwritten by merge:{}",
+        path_list(.0)
+    )]
+    LocalChanges(Vec<PathBuf>),
+
+    #[error(
+        "The following untracked working tree files would be overwritten by merge:{}",
+        path_list(.0)
+    )]
+    UntrackedFiles(Vec<PathBuf>),
+
+    #[error(
+        "Updating the following directories would lose untracked files in them:{}",
+        path_list(.0)
+    )]
+    UntrackedDirectories(Vec<PathBuf>),
+
+    #[error("You have not concluded your merge (MERGE_HEAD exists).")]
+    MergeInProgress,
+
+    #[error("Merging is not possible because you have unmerged files.")]
+    UnmergedFiles,
+
+    #[error("Not possible to fast-forward, aborting.")]
+    NotFastForward,
+
+    #[error("refusing to merge unrelated histories")]
+    UnrelatedHistories,
+
+    #[error("There is no merge to abort (MERGE_HEAD missing).")]
+    NoMergeToAbort,
+}
+
+// The files that the working tree update would overwrite are reported with merge's wording
+impl From<CheckoutError> for MergeError {
+    fn from(e: CheckoutError) -> Self {
+        match e {
+            CheckoutError::LocalChanges(paths) => MergeError::LocalChanges(paths),
+            CheckoutError::UntrackedFiles(paths) => MergeError::UntrackedFiles(paths),
+            CheckoutError::UntrackedDirectories(paths) => MergeError::UntrackedDirectories(paths),
+            e => MergeError::CheckoutError(e),
+        }
+    }
+}
+
+/// Whether a merge can just move HEAD forward when HEAD is an ancestor of the merged commit
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum FastForward {
+    /// Fast-forward when possible, create a merge commit otherwise
+    #[default]
+    Allow,
+
+    /// Always create a merge commit
+    Never,
+
+    /// Refuse to merge unless it is a fast-forward
+    Only,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    pub fast_forward: FastForward,
+
+    /// The message of the merge commit
+    pub message: String,
+
+    /// The name of the merged commit, written after the conflict markers and in the reflog
+    pub label: String,
+}
+
+/// What `Repository::merge` did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeOutcome {
+    /// The merged commit is already an ancestor of HEAD
+    UpToDate,
+
+    /// HEAD was an ancestor of the merged commit, and now points to it
+    FastForward {
+        from: Option<ObjectId>,
+        to: ObjectId,
+    },
+
+    /// A merge commit was created
+    Merged { commit: ObjectId, merge: TreeMerge },
+
+    /// The conflicts are left in the index and the working tree, to be resolved before
+    /// committing
+    Conflicts(TreeMerge),
+}
+
+impl Repository {
+    fn merge_head_path(&self) -> PathBuf {
+        self.git_dir.join("MERGE_HEAD")
+    }
+
+    /// The commit being merged, when a merge with conflicts is waiting to be concluded
+    pub fn merge_head(&self) -> Result<Option<ObjectId>, ReferenceError> {
+        match fs::read_to_string(self.merge_head_path()) {
+            Ok(content) => Ok(Some(ObjectId::from_unvalidated_sh1_hex_string(
+                content.lines().next().unwrap_or_default(),
+            )?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Forget the merge in progress, once it is committed or aborted
+    pub fn clear_merge_state(&self) -> io::Result<()> {
+        for name in ["MERGE_HEAD", "MERGE_MSG", "MERGE_MODE"] {
+            match fs::remove_file(self.git_dir.join(name)) {
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Merge a commit into HEAD, updating the index and the working tree. Like git's `resolve`
+    /// strategy, a criss-cross history is merged with one of its merge bases
+    pub fn merge(
+        &self,
+        theirs: ObjectId,
+        options: &MergeOptions,
+    ) -> Result<MergeOutcome, MergeError> {
+        if self.merge_head()?.is_some() {
+            return Err(MergeError::MergeInProgress);
+        }
+        let index_path = self.index_path();
+        let mut index_lock = Lockfile::new(&index_path)?;
+        let mut index = Index::open(&index_path)?;
+        if index.conflicts().next().is_some() {
+            return Err(MergeError::UnmergedFiles);
+        }
+
+        let ours = match self.head()? {
+            Head::Unborn(_) => None,
+            _ => Some(self.head_id()?),
+        };
+        let our_tree = self.head_tree()?;
+
+        // the merge must not lose what is staged
+        let staged: Vec<PathBuf> = self
+            .diff_tree_to_index(our_tree)?
+            .into_iter()
+            .map(|change| change.path)
+            .collect();
+        if !staged.is_empty() {
+            return Err(MergeError::LocalChanges(staged));
+        }
+
+        let bases = match ours {
+            Some(ours) => self.merge_bases(ours, theirs)?,
+            None => vec![],
+        };
+        if bases.contains(&theirs) {
+            return Ok(MergeOutcome::UpToDate);
+        }
+
+        let fast_forward = match ours {
+            None => true,
+            Some(ours) => bases.contains(&ours) && options.fast_forward != FastForward::Never,
+        };
+        let their_tree = self.read_commit(theirs)?.tree();
+        let current = self.tree_entries(our_tree)?;
+        if fast_forward {
+            let target = self.tree_entries(Some(their_tree))?;
+            self.update_worktree(&mut index, &current, &target, &[])?;
+            index.write_to(&mut index_lock)?;
+            index_lock.commit()?;
+            self.update_head(theirs, &format!("merge {}: Fast-forward", options.label))?;
+            return Ok(MergeOutcome::FastForward {
+                from: ours,
+                to: theirs,
+            });
+        }
+        if options.fast_forward == FastForward::Only {
+            return Err(MergeError::NotFastForward);
+        }
+        let (Some(ours), Some(our_tree)) = (ours, our_tree) else {
+            unreachable!("merging into an unborn branch is a fast-forward");
+        };
+        let Some(&base) = bases.first() else {
+            return Err(MergeError::UnrelatedHistories);
+        };
+
+        let base_tree = self.read_commit(base)?.tree();
+        let merge = self.merge_trees(
+            Some(base_tree),
+            our_tree,
+            their_tree,
+            "HEAD",
+            &options.label,
+        )?;
+        self.update_worktree(&mut index, &current, &merge.entries, &merge.conflicts)?;
+        index.write_to(&mut index_lock)?;
+        index_lock.commit()?;
+        self.update_reference("ORIG_HEAD", ours, "")?;
+
+        if !merge.is_clean() {
+            let mut message = format!("{}\n\n# Conflicts:\n", options.message);
+            for conflict in &merge.conflicts {
+                message.push_str(&format!("#\t{}\n", conflict.path.display()));
+            }
+            fs::write(self.git_dir.join("MERGE_MSG"), message)?;
+            let mode = match options.fast_forward {
+                FastForward::Never => "no-ff",
+                _ => "",
+            };
+            fs::write(self.git_dir.join("MERGE_MODE"), mode)?;
+            fs::write(self.merge_head_path(), format!("{}\n", theirs))?;
+            return Ok(MergeOutcome::Conflicts(merge));
+        }
+
+        let tree = self.write_tree()?;
+        let config = self.config()?;
+        let commit = Commit::new(
+            tree,
+            vec![ours, theirs],
+            get_author(&config)?,
+            get_committer(&config)?,
+            options.message.clone(),
+        );
+        let commit = self.write_object(&commit)?;
+        let message = format!(
+            "merge {}: Merge made by the 'resolve' strategy.",
+            options.label
+        );
+        self.update_head(commit, &message)?;
+        Ok(MergeOutcome::Merged { commit, merge })
+    }
+
+    /// Abort the merge in progress, bringing the index and the files it changed back to HEAD
+    pub fn merge_abort(&self) -> Result<(), MergeError> {
+        if self.merge_head()?.is_none() {
+            return Err(MergeError::NoMergeToAbort);
+        }
+        let index_path = self.index_path();
+        let mut index_lock = Lockfile::new(&index_path)?;
+        let mut index = Index::open(&index_path)?;
+
+        let head = self.tree_entries(self.head_tree()?)?;
+        let mut paths: BTreeSet<PathBuf> = head.keys().cloned().collect();
+        paths.extend(index.iter().map(|entry| entry.path.to_path_buf()));
+        let conflicts: BTreeSet<PathBuf> = index
+            .conflicts()
+            .map(|conflict| conflict.path.to_path_buf())
+            .collect();
+        let changed: Vec<PathBuf> = paths
+            .into_iter()
+            .filter(|path| {
+                let merged = index
+                    .get(path, 0)
+                    .map(|entry| (entry.oid, entry.metadata.mode));
+                let target = head.get(path).map(|entry| (entry.oid, entry.mode));
+                conflicts.contains(path) || merged != target
+            })
+            .collect();
+
+        // removals first, so that the directories that become files again are gone
+        let symlinks = self.has_symlinks()?;
+        let (kept, removed): (Vec<PathBuf>, Vec<PathBuf>) = changed
+            .into_iter()
+            .partition(|path| head.contains_key(path));
+        for path in removed.iter().chain(&kept) {
+            self.checkout_path(&mut index, path, head.get(path), symlinks)?;
+        }
+
+        index.write_to(&mut index_lock)?;
+        index_lock.commit()?;
+        self.clear_merge_state()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,272 @@
+// Three-way merge of trees, path by path
+//
+// A path that only one side changed takes the version of that side. When both sides changed it
+// differently, the contents of regular files are merged line by line, anything else is a
+// conflict. A file of one side where the other side has a directory is moved out of the way, to
+// `<path>~<label>`, as a conflict. Renames are not detected.
+
+use super::content::merge_content;
+use super::MergeError;
+use crate::diff::is_binary;
+use crate::object::{Blob, TreeEntry};
+use crate::oid::ObjectId;
+use crate::Repository;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+/// Why a path can't be merged automatically
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// Both sides changed the lines of the file in the same place
+    Content,
+
+    /// Both sides added the file, with different contents
+    AddAdd,
+
+    /// Both sides changed a binary file, which can't be merged line by line. The working tree
+    /// keeps our version
+    Binary,
+
+    /// One side deleted the file while the other side modified it. The working tree keeps the
+    /// modified version
+    ModifyDelete { deleted_by_us: bool },
+
+    /// A file of one side is where the other side has a directory. The file is moved to the path
+    /// of the conflict, and `original` is where it was
+    FileDirectory { original: PathBuf, ours: bool },
+}
+
+/// A path that can't be merged automatically
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    pub path: PathBuf,
+    pub kind: ConflictKind,
+
+    /// The versions of the base, ours and theirs, which become the stages 1 to 3 of the index
+    pub stages: [Option<TreeEntry>; 3],
+
+    // The mode and content the working tree gets, with conflict markers for a content conflict
+    pub(crate) worktree: (u32, Vec<u8>),
+}
+
+/// The result of merging two trees
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeMerge {
+    /// The merged files by path, without the conflicting ones
+    pub entries: BTreeMap<PathBuf, TreeEntry>,
+
+    pub conflicts: Vec<MergeConflict>,
+
+    /// The files changed by both sides, whose contents were merged line by line
+    pub auto_merged: Vec<PathBuf>,
+}
+
+impl TreeMerge {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+fn is_regular_file(entry: &TreeEntry) -> bool {
+    entry.mode & 0o170000 == 0o100000
+}
+
+fn same_version(a: Option<&TreeEntry>, b: Option<&TreeEntry>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.oid == b.oid && a.mode == b.mode,
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+// The path a file is moved to when a directory is in its way, with the label of its side. Like
+// git, the slashes of the label are replaced so that the file stays next to the directory
+fn moved_path(path: &Path, label: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap().to_os_string();
+    name.push(format!("~{}", label.replace('/', "_")));
+    path.with_file_name(name)
+}
+
+// The version of a side as an entry for `path`
+fn entry_at(path: &Path, mode: u32, oid: ObjectId) -> TreeEntry {
+    TreeEntry {
+        name: path.file_name().unwrap().to_string_lossy().into_owned(),
+        oid,
+        mode,
+    }
+}
+
+impl Repository {
+    /// Merge the changes made to the tree `base` by `ours` and by `theirs`. A `None` base is
+    /// treated as empty. The merged contents are written to the database, and the labels are
+    /// written after the markers of the content conflicts
+    pub fn merge_trees(
+        &self,
+        base: Option<ObjectId>,
+        ours: ObjectId,
+        theirs: ObjectId,
+        ours_label: &str,
+        theirs_label: &str,
+    ) -> Result<TreeMerge, MergeError> {
+        let base = self.tree_entries(base)?;
+        let ours = self.tree_entries(Some(ours))?;
+        let theirs = self.tree_entries(Some(theirs))?;
+        let paths: BTreeSet<&PathBuf> = base
+            .keys()
+            .chain(ours.keys())
+            .chain(theirs.keys())
+            .collect();
+
+        let mut merge = TreeMerge::default();
+        for path in paths {
+            let (b, o, t) = (base.get(path), ours.get(path), theirs.get(path));
+            let merged = if same_version(o, t) || same_version(b, t) {
+                o
+            } else if same_version(b, o) {
+                t
+            } else {
+                self.merge_path(path, b, o, t, ours_label, theirs_label, &mut merge)?;
+                continue;
+            };
+            if let Some(entry) = merged {
+                merge.entries.insert(path.clone(), entry.clone());
+            }
+        }
+
+        // the files where the merged tree has a directory are moved out of the way
+        let directories: BTreeSet<PathBuf> = merge
+            .entries
+            .keys()
+            .chain(merge.conflicts.iter().map(|conflict| &conflict.path))
+            .flat_map(|path| path.ancestors().skip(1))
+            .map(Path::to_path_buf)
+            .collect();
+        for conflict in &mut merge.conflicts {
+            if directories.contains(&conflict.path) {
+                let ours = conflict.stages[1].is_some();
+                let label = if ours { ours_label } else { theirs_label };
+                conflict.path = moved_path(&conflict.path, label);
+            }
+        }
+        let in_the_way: Vec<PathBuf> = merge
+            .entries
+            .keys()
+            .filter(|&path| directories.contains(path))
+            .cloned()
+            .collect();
+        for path in in_the_way {
+            let entry = merge.entries.remove(&path).unwrap();
+            let ours = same_version(ours.get(&path), Some(&entry));
+            let label = if ours { ours_label } else { theirs_label };
+            let moved = moved_path(&path, label);
+            let moved_entry = entry_at(&moved, entry.mode, entry.oid);
+            let mut stages = [None, None, None];
+            stages[if ours { 1 } else { 2 }] = Some(moved_entry);
+            merge.conflicts.push(MergeConflict {
+                path: moved,
+                kind: ConflictKind::FileDirectory {
+                    original: path,
+                    ours,
+                },
+                stages,
+                worktree: (entry.mode, self.read_blob(entry.oid)?),
+            });
+        }
+        Ok(merge)
+    }
+
+    // Merge a path that both sides changed differently
+    #[allow(clippy::too_many_arguments)]
+    fn merge_path(
+        &self,
+        path: &Path,
+        base: Option<&TreeEntry>,
+        ours: Option<&TreeEntry>,
+        theirs: Option<&TreeEntry>,
+        ours_label: &str,
+        theirs_label: &str,
+        merge: &mut TreeMerge,
+    ) -> Result<(), MergeError> {
+        let stages = [base.cloned(), ours.cloned(), theirs.cloned()];
+        let (ours, theirs) = match (ours, theirs) {
+            (Some(ours), Some(theirs)) => (ours, theirs),
+            (modified, _) => {
+                // one side deleted the file, the working tree keeps the other one
+                let deleted_by_us = modified.is_none();
+                let kept = ours.or(theirs).unwrap();
+                merge.conflicts.push(MergeConflict {
+                    path: path.to_path_buf(),
+                    kind: ConflictKind::ModifyDelete { deleted_by_us },
+                    stages,
+                    worktree: (kept.mode, self.read_blob(kept.oid)?),
+                });
+                return Ok(());
+            }
+        };
+
+        let conflict = |merge: &mut TreeMerge, kind, worktree| {
+            merge.conflicts.push(MergeConflict {
+                path: path.to_path_buf(),
+                kind,
+                stages: stages.clone(),
+                worktree,
+            });
+        };
+        if !is_regular_file(ours) || !is_regular_file(theirs) {
+            let worktree = (ours.mode, self.read_blob(ours.oid)?);
+            conflict(merge, ConflictKind::Content, worktree);
+            return Ok(());
+        }
+
+        // a mode changed by a single side is kept
+        let mode = match base {
+            Some(base) if base.mode == ours.mode => theirs.mode,
+            _ => ours.mode,
+        };
+
+        let base_oid = base.map(|base| base.oid);
+        let oid = if ours.oid == theirs.oid || base_oid == Some(ours.oid) {
+            theirs.oid
+        } else if base_oid == Some(theirs.oid) {
+            ours.oid
+        } else {
+            let base_content = match base_oid {
+                Some(oid) => self.read_blob(oid)?,
+                None => vec![],
+            };
+            let our_content = self.read_blob(ours.oid)?;
+            let their_content = self.read_blob(theirs.oid)?;
+            if [&base_content, &our_content, &their_content]
+                .iter()
+                .any(|content| is_binary(content))
+            {
+                conflict(merge, ConflictKind::Binary, (ours.mode, our_content));
+                return Ok(());
+            }
+
+            merge.auto_merged.push(path.to_path_buf());
+            let content = merge_content(
+                &base_content,
+                &our_content,
+                &their_content,
+                ours_label,
+                theirs_label,
+            );
+            if !content.is_clean() {
+                let kind = match base {
+                    Some(_) => ConflictKind::Content,
+                    None => ConflictKind::AddAdd,
+                };
+                conflict(merge, kind, (mode, content.content));
+                return Ok(());
+            }
+            self.write_object(&Blob::new(content.content.into_boxed_slice()))?
+        };
+        merge
+            .entries
+            .insert(path.to_path_buf(), entry_at(path, mode, oid));
+        Ok(())
+    }
+}
@@ -1,4 +1,11 @@
-use crate::object::{Object, ObjectBuffer, ObjectType};
+use crate::database::{stream_object, DatabaseWriteError};
+use crate::object::{read_header, Object, ObjectBuffer, ObjectHeader, ObjectReadError, ObjectType};
+use crate::oid::ObjectId;
+use crate::Repository;
+use std::fs::{self, File};
+use std::io;
+use std::io::{BufReader, Read};
+use std::path::Path;
 
 /// In memory data representation of a git blob object
 pub struct Blob {
@@ -16,3 +23,72 @@ impl Object for Blob {
         ObjectBuffer::new(ObjectType::Blob, &self.content)
     }
 }
+
+/// The target of a symbolic link, as stored in its blob. `None` if the path is not a link
+fn read_symlink(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    if !path.symlink_metadata()?.file_type().is_symlink() {
+        return Ok(None);
+    }
+    let target = fs::read_link(path)?;
+    #[cfg(unix)]
+    let target = std::os::unix::ffi::OsStrExt::as_bytes(target.as_os_str()).to_vec();
+    #[cfg(not(unix))]
+    let target = target.to_string_lossy().replace('\\', "/").into_bytes();
+    Ok(Some(target))
+}
+
+/// Hash a file as a blob without writing it to the database. Like git, a symbolic link is
+/// hashed as its target
+///
+/// The file is read in chunks, so it can be larger than the memory
+pub fn hash_blob_file(path: &Path) -> io::Result<ObjectId> {
+    if let Some(target) = read_symlink(path)? {
+        return stream_object(
+            ObjectType::Blob,
+            target.len() as u64,
+            &mut &target[..],
+            None,
+        );
+    }
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+    stream_object(ObjectType::Blob, size, &mut BufReader::new(file), None)
+}
+
+impl Repository {
+    /// Write a file to the database as a blob and return its oid. A symbolic link is written as
+    /// its target
+    ///
+    /// The file is read and compressed in chunks, so it can be larger than the memory
+    pub fn write_blob_file(&self, path: &Path) -> Result<ObjectId, DatabaseWriteError> {
+        let to_database_write_error = |source| DatabaseWriteError::new(path.to_path_buf(), source);
+        if let Some(target) = read_symlink(path).map_err(to_database_write_error)? {
+            return self.database.write_object_stream(
+                ObjectType::Blob,
+                target.len() as u64,
+                &mut &target[..],
+            );
+        }
+        let file = File::open(path).map_err(to_database_write_error)?;
+        let size = file.metadata().map_err(to_database_write_error)?.len();
+        self.database
+            .write_object_stream(ObjectType::Blob, size, &mut BufReader::new(file))
+    }
+
+    /// Read the content of a blob object from the database
+    pub fn read_blob(&self, oid: ObjectId) -> Result<Vec<u8>, ObjectReadError> {
+        let mut reader = self
+            .object_reader(oid)
+            .map_err(|err| ObjectReadError::OpenError(oid, err))?;
+        let ObjectHeader { typ, size } = read_header(&mut reader)?;
+        if typ != ObjectType::Blob {
+            return Err(ObjectReadError::MismatchObjectType(ObjectType::Blob, typ));
+        }
+
+        let mut content = Vec::with_capacity(size);
+        reader
+            .read_to_end(&mut content)
+            .map_err(ObjectReadError::ContentReadError)?;
+        Ok(content)
+    }
+}
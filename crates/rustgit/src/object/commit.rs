@@ -1,15 +1,23 @@
 use crate::{
-    object::{Object, ObjectBuffer, ObjectType},
+    object::{
+        date::parse_timezone,
+        kvlm::{decode, parse_kvlm, write_header, Kvlm},
+        read_header, summary, Object, ObjectBuffer, ObjectHeader, ObjectReadError, ObjectType,
+    },
+    object_reader::ObjectReader,
     oid::ObjectId,
+    Repository,
 };
 use chrono::prelude::*;
+use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
+use std::io::Read;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Author {
     pub name: String,
     pub email: String,
-    pub time: DateTime<Local>,
+    pub time: DateTime<FixedOffset>,
 }
 
 impl Display for Author {
@@ -24,47 +32,311 @@ impl Display for Author {
     }
 }
 
-/// In memory data representation of a git blob object
+impl Author {
+    /// Parse an identity line such as `A U Thor <author@example.com> 1112911993 -0700`
+    pub fn parse(line: &str) -> Option<Author> {
+        let email_start = line.find('<')?;
+        let email_end = email_start + line[email_start..].find('>')?;
+
+        let name = &line[..email_start];
+        let name = name.strip_suffix(' ').unwrap_or(name);
+        let email = &line[email_start + 1..email_end];
+
+        let (timestamp, timezone) = line[email_end + 1..].trim_start().split_once(' ')?;
+        let timestamp: i64 = timestamp.parse().ok()?;
+        let offset = parse_timezone(timezone)?;
+        let time = DateTime::from_timestamp(timestamp, 0)?.with_timezone(&offset);
+
+        Some(Author {
+            name: name.to_string(),
+            email: email.to_string(),
+            time,
+        })
+    }
+}
+
+/// In memory data representation of a git commit object
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Commit {
     tree: ObjectId,
-    parent_commit: Option<ObjectId>,
+    parents: Vec<ObjectId>,
     author: Author,
-    message: String,
+    committer: Author,
+    // The author and committer lines as they are in the object, so that writing a parsed
+    // commit gives back the same bytes (e.g. a `-0000` timezone or unusual spacing)
+    raw_author: Vec<u8>,
+    raw_committer: Vec<u8>,
+    encoding: Option<String>,
+    // Headers we don't interpret, such as gpgsig or mergetag, in their original order
+    extra_headers: Vec<(String, Vec<u8>)>,
+    message: Vec<u8>,
 }
 
 impl Commit {
+    /// Create a new commit. Like git, a trailing newline is added to the message
     pub fn new(
         tree: ObjectId,
-        parent_commit: Option<ObjectId>,
+        parents: Vec<ObjectId>,
         author: Author,
+        committer: Author,
         message: String,
     ) -> Self {
         Commit {
             tree,
-            parent_commit,
+            parents,
+            raw_author: author.to_string().into_bytes(),
+            raw_committer: committer.to_string().into_bytes(),
             author,
-            message,
+            committer,
+            encoding: None,
+            extra_headers: vec![],
+            message: format!("{message}\n").into_bytes(),
+        }
+    }
+
+    pub fn tree(&self) -> ObjectId {
+        self.tree
+    }
+
+    pub fn parents(&self) -> &[ObjectId] {
+        &self.parents
+    }
+
+    pub fn author(&self) -> &Author {
+        &self.author
+    }
+
+    pub fn committer(&self) -> &Author {
+        &self.committer
+    }
+
+    /// The value of the `encoding` header, if the message is not in UTF-8
+    pub fn encoding(&self) -> Option<&str> {
+        self.encoding.as_deref()
+    }
+
+    /// Headers other than tree, parent, author, committer and encoding, with their raw values
+    pub fn extra_headers(&self) -> &[(String, Vec<u8>)] {
+        &self.extra_headers
+    }
+
+    /// The full commit message, decoded from its encoding
+    pub fn message(&self) -> Cow<'_, str> {
+        decode(&self.message, self.encoding())
+    }
+
+    /// The full commit message, as bytes in the encoding of the commit
+    pub fn raw_message(&self) -> &[u8] {
+        &self.message
+    }
+
+    /// The first paragraph of the message, joined into a single line
+    pub fn summary(&self) -> String {
+        summary(&self.message())
+    }
+
+    /// The message after the first paragraph
+    pub fn body(&self) -> String {
+        let message = self.message();
+        let message = message.trim_start_matches('\n');
+        match message.find("\n\n") {
+            Some(end) => message[end..].trim_start_matches('\n').to_string(),
+            None => String::new(),
+        }
+    }
+
+    /// Parse the content of a commit object (without the object header)
+    pub fn parse(content: &[u8]) -> Result<Commit, ObjectReadError> {
+        let invalid = |reason| ObjectReadError::InvalidObject(ObjectType::Commit, reason);
+        let parse_oid = |value: &[u8]| {
+            std::str::from_utf8(value)
+                .ok()
+                .and_then(|value| ObjectId::from_unvalidated_sh1_hex_string(value).ok())
+                .ok_or(invalid("invalid object id"))
+        };
+
+        let Kvlm { headers, message } = parse_kvlm(content, ObjectType::Commit)?;
+
+        // the identities are in the encoding of the commit, which may come after them
+        let encoding = headers
+            .iter()
+            .find(|(key, _)| key == "encoding")
+            .map(|(_, value)| String::from_utf8_lossy(value).into_owned());
+        let parse_identity = |value: &[u8], reason| {
+            Author::parse(&decode(value, encoding.as_deref())).ok_or(invalid(reason))
+        };
+
+        let mut tree = None;
+        let mut parents = vec![];
+        let mut author = None;
+        let mut committer = None;
+        let mut seen_encoding = false;
+        let mut extra_headers = vec![];
+        for (key, value) in headers {
+            match key.as_str() {
+                "tree" if tree.is_none() => tree = Some(parse_oid(&value)?),
+                "parent" => parents.push(parse_oid(&value)?),
+                "author" if author.is_none() => {
+                    author = Some((parse_identity(&value, "malformed author")?, value))
+                }
+                "committer" if committer.is_none() => {
+                    committer = Some((parse_identity(&value, "malformed committer")?, value))
+                }
+                "encoding" if !seen_encoding => seen_encoding = true,
+                _ => extra_headers.push((key, value)),
+            }
         }
+        let (author, raw_author) = author.ok_or(invalid("missing author"))?;
+        let (committer, raw_committer) = committer.ok_or(invalid("missing committer"))?;
+
+        Ok(Commit {
+            tree: tree.ok_or(invalid("missing tree"))?,
+            parents,
+            author,
+            committer,
+            raw_author,
+            raw_committer,
+            encoding,
+            extra_headers,
+            message,
+        })
     }
 }
 
 impl Object for Commit {
     fn to_buffer(&self) -> ObjectBuffer {
-        let mut content = String::new();
-        content.push_str(&format!("tree {}\n", self.tree));
-        if let Some(parent_commit_sha) = &self.parent_commit {
-            content.push_str(&format!("parent {parent_commit_sha}\n"));
+        let mut content = vec![];
+        write_header(&mut content, "tree", self.tree.to_string().as_bytes());
+        for parent in &self.parents {
+            write_header(&mut content, "parent", parent.to_string().as_bytes());
+        }
+        write_header(&mut content, "author", &self.raw_author);
+        write_header(&mut content, "committer", &self.raw_committer);
+        if let Some(encoding) = &self.encoding {
+            write_header(&mut content, "encoding", encoding.as_bytes());
+        }
+        for (key, value) in &self.extra_headers {
+            write_header(&mut content, key, value);
         }
+        content.push(b'\n');
+        content.extend_from_slice(&self.message);
 
-        content.push_str(&format!(
-            "author {}
-committer {}
+        ObjectBuffer::new(ObjectType::Commit, &content)
+    }
+}
 
-{}
-",
-            &self.author, &self.author, &self.message
-        ));
+/// Read a commit object
+pub fn read_commit_object(reader: &mut ObjectReader) -> Result<Commit, ObjectReadError> {
+    let ObjectHeader { typ, size } = read_header(reader)?;
+    if typ != ObjectType::Commit {
+        return Err(ObjectReadError::MismatchObjectType(ObjectType::Commit, typ));
+    }
 
-        ObjectBuffer::new(ObjectType::Commit, &content.as_bytes())
+    let mut content = Vec::with_capacity(size);
+    reader
+        .read_to_end(&mut content)
+        .map_err(ObjectReadError::ContentReadError)?;
+    Commit::parse(&content)
+}
+
+impl Repository {
+    /// Read a commit object from the database
+    pub fn read_commit(&self, oid: ObjectId) -> Result<Commit, ObjectReadError> {
+        let mut reader = self
+            .object_reader(oid)
+            .map_err(|err| ObjectReadError::OpenError(oid, err))?;
+        read_commit_object(&mut reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNED_MERGE: &str = concat!(
+        "tree 9bedf67800b2923982bdf60c89c57ce6ce2d9a1c\n",
+        "parent 0f46983e0baf73ba9bf82a7317223d2eebc728d8\n",
+        "parent 3b18e512dba79e4c8300dd08aeb37f8e728b8dad\n",
+        "author A U Thor <author@example.com> 1112911993 -0700\n",
+        "committer C O Mitter <committer@example.com> 1112912053 +0530\n",
+        "encoding ISO-8859-1\n",
+        "gpgsig -----BEGIN PGP SIGNATURE-----\n",
+        " \n",
+        " iQEzBAABCAAdFiEE\n",
+        " -----END PGP SIGNATURE-----\n",
+        "\n",
+        "Merge branch 'topic'\n",
+        "\n",
+        "Some details\n",
+    );
+
+    #[test]
+    fn parse() {
+        let commit = Commit::parse(SIGNED_MERGE.as_bytes()).unwrap();
+
+        assert_eq!(
+            commit.tree().to_string(),
+            "9bedf67800b2923982bdf60c89c57ce6ce2d9a1c"
+        );
+        assert_eq!(commit.parents().len(), 2);
+        assert_eq!(commit.author().name, "A U Thor");
+        assert_eq!(commit.author().email, "author@example.com");
+        assert_eq!(commit.author().time.timestamp(), 1112911993);
+        assert_eq!(commit.committer().name, "C O Mitter");
+        assert_eq!(commit.encoding(), Some("ISO-8859-1"));
+        assert_eq!(commit.extra_headers().len(), 1);
+        assert_eq!(commit.extra_headers()[0].0, "gpgsig");
+        assert_eq!(
+            commit.extra_headers()[0].1,
+            b"-----BEGIN PGP SIGNATURE-----\n\niQEzBAABCAAdFiEE\n-----END PGP SIGNATURE-----"
+        );
+        assert_eq!(commit.message(), "Merge branch 'topic'\n\nSome details\n");
+    }
+
+    #[test]
+    fn round_trip() {
+        let commit = Commit::parse(SIGNED_MERGE.as_bytes()).unwrap();
+        let expected = ObjectBuffer::new(ObjectType::Commit, SIGNED_MERGE.as_bytes());
+        assert_eq!(commit.to_buffer().data(), expected.data());
+    }
+
+    #[test]
+    fn latin1() {
+        let content = b"tree 9bedf67800b2923982bdf60c89c57ce6ce2d9a1c\n\
+                        author J\xe9r\xf4me  <jerome@example.com> 1112911993 -0000\n\
+                        committer J\xe9r\xf4me <jerome@example.com> 1112911993 +0200\n\
+                        encoding ISO-8859-1\n\n\
+                        Caf\xe9\n";
+        let commit = Commit::parse(content).unwrap();
+        assert_eq!(commit.author().name, "J\u{e9}r\u{f4}me ");
+        assert_eq!(commit.committer().name, "J\u{e9}r\u{f4}me");
+        assert_eq!(commit.message(), "Caf\u{e9}\n");
+        assert_eq!(commit.raw_message(), b"Caf\xe9\n");
+
+        let expected = ObjectBuffer::new(ObjectType::Commit, content);
+        assert_eq!(commit.to_buffer().data(), expected.data());
+    }
+
+    #[test]
+    fn summary_and_body() {
+        let commit = Commit::parse(SIGNED_MERGE.as_bytes()).unwrap();
+        assert_eq!(commit.summary(), "Merge branch 'topic'");
+        assert_eq!(commit.body(), "Some details\n");
+
+        let content = "tree 9bedf67800b2923982bdf60c89c57ce6ce2d9a1c\n\
+                       author A <a@b.c> 0 +0000\ncommitter A <a@b.c> 0 +0000\n\n\
+                       a subject\non two lines\n";
+        let commit = Commit::parse(content.as_bytes()).unwrap();
+        assert_eq!(commit.summary(), "a subject on two lines");
+        assert_eq!(commit.body(), "");
+    }
+
+    #[test]
+    fn missing_tree() {
+        let content = "author A <a@b.c> 0 +0000\ncommitter A <a@b.c> 0 +0000\n\nmessage\n";
+        assert!(matches!(
+            Commit::parse(content.as_bytes()),
+            Err(ObjectReadError::InvalidObject(ObjectType::Commit, _))
+        ));
     }
 }
@@ -0,0 +1,302 @@
+// Parse dates such as the ones of `GIT_AUTHOR_DATE` and `GIT_COMMITTER_DATE`
+//
+// Only the common formats of git are supported, not everything its date parser guesses its way
+// through:
+// - git's internal format `1112911993 -0700`, optionally with a leading `@`
+// - RFC 2822, such as `Thu, 07 Apr 2005 15:13:13 -0700`
+// - the format of `git log`, such as `Thu Apr 7 15:13:13 2005 -0700`
+// - ISO 8601 and its variants, such as `2005-04-07T15:13:13-07:00`, `2005-04-07 15:13` or
+//   `2005.04.07 15:13:13`
+// - a day in git's dotted order or with the name of its month, followed by a time, such as
+//   `07.04.2005 15:13` or `April 7 2005 15:13:13`
+//
+// Like git, a day without a time is rejected, only approxidate accepts it.
+// Like git, a date without a timezone is in the local timezone.
+
+use chrono::{prelude::*, Duration, Months};
+
+/// Parse a timezone offset of the form +hhmm or -hhmm
+pub(super) fn parse_timezone(timezone: &str) -> Option<FixedOffset> {
+    // checked to be ASCII before slicing it by bytes
+    if !timezone.is_ascii()
+        || timezone.len() != 5
+        || !timezone[1..].bytes().all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let hours: i32 = timezone[1..3].parse().ok()?;
+    let minutes: i32 = timezone[3..5].parse().ok()?;
+    let seconds = (hours * 60 + minutes) * 60;
+    match &timezone[..1] {
+        "+" => FixedOffset::east_opt(seconds),
+        "-" => FixedOffset::west_opt(seconds),
+        _ => None,
+    }
+}
+
+// The timezones of ISO 8601 dates: Z, +hh, +hhmm or +hh:mm
+fn parse_iso_timezone(timezone: &str) -> Option<FixedOffset> {
+    match timezone {
+        _ if !timezone.is_ascii() => None,
+        "Z" | "UTC" | "GMT" => FixedOffset::east_opt(0),
+        _ if timezone.len() == 3 => parse_timezone(&format!("{timezone}00")),
+        _ if timezone.len() == 6 && &timezone[3..4] == ":" => {
+            parse_timezone(&timezone.replace(':', ""))
+        }
+        _ => parse_timezone(timezone),
+    }
+}
+
+// A timestamp, either with a leading `@`, with a timezone, or too large to be anything else
+fn parse_raw(date: &str) -> Option<DateTime<FixedOffset>> {
+    let (at, date) = match date.strip_prefix('@') {
+        Some(date) => (true, date),
+        None => (false, date),
+    };
+    let (timestamp, timezone) = match date.split_once(' ') {
+        Some((timestamp, timezone)) => (timestamp, Some(timezone.trim())),
+        None => (date, None),
+    };
+    if timestamp.is_empty() || !timestamp.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let timestamp: i64 = timestamp.parse().ok()?;
+    if !at && timezone.is_none() && timestamp < 100_000_000 {
+        return None;
+    }
+
+    let offset = match timezone {
+        Some(timezone) => parse_timezone(timezone)?,
+        None => FixedOffset::east_opt(0)?,
+    };
+    Some(DateTime::from_timestamp(timestamp, 0)?.with_timezone(&offset))
+}
+
+// A date and time followed by an optional timezone, local if missing
+fn with_timezone(
+    time: NaiveDateTime,
+    timezone: &str,
+    parse_timezone: fn(&str) -> Option<FixedOffset>,
+) -> Option<DateTime<FixedOffset>> {
+    match timezone.trim() {
+        "" => Local.from_local_datetime(&time).earliest().map(Into::into),
+        timezone => time.and_local_timezone(parse_timezone(timezone)?).single(),
+    }
+}
+
+// Tried in order, so a format comes before the formats that match a prefix of it. A month name
+// can be abbreviated
+const DATE_TIME_FORMATS: [&str; 12] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y.%m.%d %H:%M:%S%.f",
+    "%d.%m.%Y %H:%M:%S%.f",
+    "%B %e %Y %H:%M:%S%.f",
+    "%B %e, %Y %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+    "%Y.%m.%d %H:%M",
+    "%d.%m.%Y %H:%M",
+    "%B %e %Y %H:%M",
+    "%B %e, %Y %H:%M",
+];
+
+// A day alone, followed by the rest of the date. `07.04.2005` would be read as the year 7 by
+// `%Y.%m.%d`, so the dotted order of git comes first
+fn parse_day(date: &str) -> Option<(NaiveDate, &str)> {
+    ["%Y-%m-%d", "%d.%m.%Y", "%Y.%m.%d", "%B %e %Y", "%B %e, %Y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_and_remainder(date, format).ok())
+}
+
+fn parse_date_time(date: &str) -> Option<DateTime<FixedOffset>> {
+    let (time, timezone) = DATE_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_and_remainder(date, format).ok())?;
+    with_timezone(time, timezone, parse_iso_timezone)
+}
+
+// The format of `git log`, the one of C's `asctime` with an optional timezone
+fn parse_asctime(date: &str) -> Option<DateTime<FixedOffset>> {
+    let (time, timezone) = NaiveDateTime::parse_and_remainder(date, "%a %b %e %H:%M:%S %Y").ok()?;
+    with_timezone(time, timezone, parse_timezone)
+}
+
+/// Parse a date in one of the formats accepted by git
+pub(crate) fn parse_date(date: &str) -> Option<DateTime<FixedOffset>> {
+    let date = date.trim();
+    parse_raw(date)
+        .or_else(|| DateTime::parse_from_rfc2822(date).ok())
+        .or_else(|| parse_asctime(date))
+        .or_else(|| parse_date_time(date))
+}
+
+/// Parse a date like git's approxidate: the formats of `parse_date`, `now`, `yesterday` and
+/// relative dates such as `2 weeks ago` or `2.weeks.ago`. Unlike `parse_date`, a day alone is
+/// accepted, at the time of `now`
+pub fn parse_approxidate(date: &str, now: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+    if let Some((day, timezone)) = parse_day(date.trim()) {
+        if let Some(date) = with_timezone(day.and_time(now.time()), timezone, parse_iso_timezone) {
+            return Some(date);
+        }
+    }
+    if let Some(date) = parse_date(date) {
+        return Some(date);
+    }
+
+    let date = date.to_lowercase();
+    let words: Vec<&str> = date
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    match words.as_slice() {
+        ["now"] => Some(now),
+        ["yesterday"] => Some(now - Duration::days(1)),
+        [count, unit, "ago"] => {
+            let count: u32 = count.parse().ok()?;
+            let unit = unit.strip_suffix('s').unwrap_or(unit);
+            let seconds = match unit {
+                "second" => 1,
+                "minute" => 60,
+                "hour" => 60 * 60,
+                "day" => 24 * 60 * 60,
+                "week" => 7 * 24 * 60 * 60,
+                "month" => return now.checked_sub_months(Months::new(count)),
+                "year" => return now.checked_sub_months(Months::new(count.checked_mul(12)?)),
+                _ => return None,
+            };
+            now.checked_sub_signed(Duration::seconds(i64::from(count) * seconds))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(date: &str) -> Option<String> {
+        parse_date(date).map(|date| date.format("%s %z").to_string())
+    }
+
+    #[test]
+    fn formats() {
+        let expected = Some("1112911993 -0700".to_string());
+        for date in [
+            "1112911993 -0700",
+            "@1112911993 -0700",
+            "Thu, 07 Apr 2005 15:13:13 -0700",
+            "Thu, 7 Apr 2005 15:13:13 -0700",
+            "2005-04-07T15:13:13-0700",
+            "2005-04-07 15:13:13 -07:00",
+            "2005-04-07T15:13:13.250-07",
+        ] {
+            assert_eq!(parse(date), expected, "{}", date);
+        }
+        assert_eq!(parse("@1112911993"), Some("1112911993 +0000".to_string()));
+        assert_eq!(
+            parse("2005-04-07T22:13:13Z"),
+            Some("1112911993 +0000".to_string())
+        );
+        assert_eq!(
+            parse("2005-04-07T22:13+0100"),
+            Some("1112908380 +0100".to_string())
+        );
+        assert_eq!(
+            parse("Thu Apr 7 15:13:13 2005 -0700"),
+            Some("1112911993 -0700".to_string())
+        );
+        assert_eq!(
+            parse("Sun Apr 17 15:13:13 2005 +0000"),
+            Some("1113750793 +0000".to_string())
+        );
+        assert_eq!(
+            parse("2005.04.07 15:13:13 -0700"),
+            Some("1112911993 -0700".to_string())
+        );
+        assert_eq!(
+            parse("2005-04-07 15:13 -0700"),
+            Some("1112911980 -0700".to_string())
+        );
+        assert_eq!(
+            parse("07.04.2005 15:13:13 -0700"),
+            Some("1112911993 -0700".to_string())
+        );
+        assert_eq!(
+            parse("April 7 2005 15:13:13 -0700"),
+            Some("1112911993 -0700".to_string())
+        );
+        assert_eq!(
+            parse("Apr 7, 2005 15:13 -0700"),
+            Some("1112911980 -0700".to_string())
+        );
+    }
+
+    #[test]
+    fn local_formats() {
+        // without a timezone, the dates are in the local timezone
+        let local = |date: &str| {
+            let time = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap();
+            let time = Local.from_local_datetime(&time).earliest().unwrap();
+            Some(time.format("%s %z").to_string())
+        };
+        assert_eq!(parse("2005-04-07 15:13"), local("2005-04-07 15:13:00"));
+        assert_eq!(parse("2005.04.07 15:13:13"), local("2005-04-07 15:13:13"));
+        assert_eq!(parse("07.04.2005 10:00"), local("2005-04-07 10:00:00"));
+        assert_eq!(parse("April 7 2005 10:00"), local("2005-04-07 10:00:00"));
+        assert_eq!(
+            parse("april 17 2005 10:00:05"),
+            local("2005-04-17 10:00:05")
+        );
+        assert_eq!(
+            parse("Thu Apr 7 15:13:13 2005"),
+            local("2005-04-07 15:13:13")
+        );
+    }
+
+    #[test]
+    fn approxidate() {
+        let now = parse_date("1112911993 -0700").unwrap();
+        let approxidate =
+            |date: &str| parse_approxidate(date, now).map(|date| date.format("%s").to_string());
+        assert_eq!(approxidate("now"), Some("1112911993".to_string()));
+        assert_eq!(approxidate("yesterday"), Some("1112825593".to_string()));
+        assert_eq!(approxidate("2.hours.ago"), Some("1112904793".to_string()));
+        assert_eq!(approxidate("1 week ago"), Some("1112307193".to_string()));
+        assert_eq!(approxidate("3 Months Ago"), Some("1105135993".to_string()));
+        assert_eq!(approxidate("@1112911000"), Some("1112911000".to_string()));
+        for date in ["2005-04-01 -0700", "01.04.2005 -0700", "April 1 2005 -0700"] {
+            assert_eq!(
+                approxidate(date),
+                Some("1112393593".to_string()),
+                "{}",
+                date
+            );
+        }
+        assert_eq!(approxidate("2 fortnights ago"), None);
+    }
+
+    #[test]
+    fn invalid() {
+        for date in [
+            "",
+            "yesterday",
+            "2005",
+            "1112911993 PDT",
+            "2005-04-07T15:13:13 +7",
+            "1112911993 \u{e9}070",
+            "2005-04-07T15:13:13+0\u{e9}00",
+            "Fri Apr 7 15:13:13 2005 -0700",
+            "Thu Apr 7 15:13:13 2005 -07:00",
+            "2005-04-07 junk",
+            // a day needs a time
+            "2005-04-07",
+            "2005.04.07",
+            "07.04.2005",
+            "April 7 2005",
+            "07.04.2005 -0700",
+        ] {
+            assert_eq!(parse(date), None, "{}", date);
+        }
+    }
+}
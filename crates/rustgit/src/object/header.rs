@@ -14,7 +14,7 @@ pub fn read_header(reader: &mut ObjectReader) -> Result<ObjectHeader, ObjectRead
     let mut output = vec![];
     reader
         .read_until(0, &mut output)
-        .map_err(|err| ObjectReadError::HeaderReadError(err))?;
+        .map_err(ObjectReadError::HeaderReadError)?;
 
     parse_header(&output)
 }
@@ -23,7 +23,7 @@ fn parse_header(buffer: &[u8]) -> Result<ObjectHeader, ObjectReadError> {
     let separate_point = buffer.iter().position(|&c| c == b' ');
     let separate_point = separate_point.ok_or(ObjectReadError::MissingSpaceSeparator)?;
 
-    let (typ, mut size) = remove_last(&buffer).split_at(separate_point);
+    let (typ, mut size) = remove_last(buffer).split_at(separate_point);
     let typ = ObjectType::parse(typ).ok_or(ObjectReadError::UnknownObjectType)?;
     size = &size[1..];
     let size: usize = parse_usize(size)?;
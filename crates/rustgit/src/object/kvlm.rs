@@ -0,0 +1,78 @@
+// Key-Value List with Message, the format shared by commit and tag objects
+//
+// A list of `key value` header lines, followed by an empty line and a free-form message.
+// A header value can span several lines, in which case each continuation line starts with a
+// space (e.g. `gpgsig` or `mergetag`).
+//
+// Values and messages are kept as bytes: they are in the encoding named by the `encoding`
+// header, which is not always UTF-8, and are only decoded to be displayed.
+
+use crate::object::{ObjectReadError, ObjectType};
+use std::borrow::Cow;
+
+pub(crate) struct Kvlm {
+    pub headers: Vec<(String, Vec<u8>)>,
+    pub message: Vec<u8>,
+}
+
+pub(crate) fn parse_kvlm(content: &[u8], typ: ObjectType) -> Result<Kvlm, ObjectReadError> {
+    let invalid = |reason| ObjectReadError::InvalidObject(typ, reason);
+    let (header_section, message) = match content.windows(2).position(|pair| pair == b"\n\n") {
+        Some(position) => (&content[..position + 1], &content[position + 2..]),
+        None => (content, &[][..]),
+    };
+
+    let mut headers: Vec<(String, Vec<u8>)> = vec![];
+    let mut lines: Vec<&[u8]> = header_section.split(|&c| c == b'\n').collect();
+    if lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    for line in lines {
+        if let Some(continuation) = line.strip_prefix(b" ") {
+            let (_, value) = headers
+                .last_mut()
+                .ok_or(invalid("continuation line without a header"))?;
+            value.push(b'\n');
+            value.extend_from_slice(continuation);
+        } else {
+            let separator = line
+                .iter()
+                .position(|&c| c == b' ')
+                .ok_or(invalid("header line without a value"))?;
+            let key = std::str::from_utf8(&line[..separator])?;
+            headers.push((key.to_string(), line[separator + 1..].to_vec()));
+        }
+    }
+
+    Ok(Kvlm {
+        headers,
+        message: message.to_vec(),
+    })
+}
+
+/// Write a header line, indenting continuation lines of multi-line values
+pub(crate) fn write_header(output: &mut Vec<u8>, key: &str, value: &[u8]) {
+    output.extend_from_slice(key.as_bytes());
+    output.push(b' ');
+    for &c in value {
+        output.push(c);
+        if c == b'\n' {
+            output.push(b' ');
+        }
+    }
+    output.push(b'\n');
+}
+
+/// Decode text in the given encoding, UTF-8 if none. Latin-1 is decoded as such, other
+/// encodings and invalid sequences fall back to a lossy UTF-8 decoding
+pub(crate) fn decode<'a>(text: &'a [u8], encoding: Option<&str>) -> Cow<'a, str> {
+    let latin1 = encoding.is_some_and(|encoding| {
+        ["ISO-8859-1", "ISO8859-1", "LATIN1", "LATIN-1"]
+            .iter()
+            .any(|name| encoding.eq_ignore_ascii_case(name))
+    });
+    match latin1 {
+        true if !text.is_ascii() => Cow::Owned(text.iter().map(|&c| char::from(c)).collect()),
+        _ => String::from_utf8_lossy(text),
+    }
+}
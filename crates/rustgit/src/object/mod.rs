@@ -2,18 +2,28 @@
 
 mod blob;
 mod commit;
+mod date;
 mod header;
+mod kvlm;
+mod tag;
 mod tree;
 
 pub use {
-    blob::Blob,
-    commit::{Author, Commit},
+    blob::{hash_blob_file, Blob},
+    commit::{read_commit_object, Author, Commit},
+    date::parse_approxidate,
     header::{read_header, ObjectHeader},
-    tree::{read_tree_object, Tree, TreeEntry},
+    tag::{read_tag_object, Tag},
+    tree::{
+        read_tree_object, Tree, TreeEntry, WriteTreeError, GITLINK_MODE, SYMLINK_MODE, TREE_MODE,
+    },
 };
 
+use crate::config::{Config, ConfigError};
+use crate::oid::ObjectId;
 use crate::parse_utils::ParseU64Error;
 use chrono::Local;
+use date::parse_date;
 use std::fmt::{Display, Formatter};
 use thiserror::Error;
 
@@ -37,6 +47,15 @@ pub enum ObjectReadError {
 
     #[error("error while reading content of the object")]
     ContentReadError(std::io::Error),
+
+    #[error("failed to open object {0}")]
+    OpenError(ObjectId, #[source] std::io::Error),
+
+    #[error("object content is not valid utf8")]
+    Utf8Error(#[from] std::str::Utf8Error),
+
+    #[error("invalid {0} object: {1}")]
+    InvalidObject(ObjectType, &'static str),
 }
 
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
@@ -44,6 +63,7 @@ pub enum ObjectType {
     Blob,
     Tree,
     Commit,
+    Tag,
 }
 
 impl ObjectType {
@@ -52,6 +72,7 @@ impl ObjectType {
             b"blob" => Some(ObjectType::Blob),
             b"tree" => Some(ObjectType::Tree),
             b"commit" => Some(ObjectType::Commit),
+            b"tag" => Some(ObjectType::Tag),
             _ => None,
         }
     }
@@ -64,10 +85,22 @@ impl Display for ObjectType {
             Blob => write!(f, "blob"),
             Tree => write!(f, "tree"),
             Commit => write!(f, "commit"),
+            Tag => write!(f, "tag"),
         }
     }
 }
 
+// The first paragraph of a commit or tag message, joined into a single line
+fn summary(message: &str) -> String {
+    let message = message.trim_start_matches('\n');
+    let end = message.find("\n\n").unwrap_or(message.len());
+    message[..end]
+        .lines()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 pub trait Object {
     /// Convert the object to an in-memory buffer
     fn to_buffer(&self) -> ObjectBuffer;
@@ -83,7 +116,7 @@ impl ObjectBuffer {
     // Given the type of object and content of a file, create a valid git object
     pub fn new(typ: ObjectType, content: &[u8]) -> Self {
         let mut data = format!("{} {}\0", typ, content.len()).into_bytes();
-        data.extend_from_slice(&content);
+        data.extend_from_slice(content);
         Self {
             data: data.into_boxed_slice(),
         }
@@ -92,20 +125,51 @@ impl ObjectBuffer {
     pub fn data(&self) -> &[u8] {
         &self.data
     }
+
+    pub(crate) fn into_data(self) -> Box<[u8]> {
+        self.data
+    }
 }
 
 fn get_env_var(key: &str) -> Option<String> {
     std::env::var(key).ok()
 }
-pub fn get_author() -> Author {
-    // TODO: don't hardcode author names
-    let author_name = get_env_var("GIT_AUTHOR_NAME").unwrap_or("lesley lai".to_string());
-    let author_email =
-        get_env_var("GIT_AUTHOR_EMAIL").unwrap_or("lesley@lesleylai.info".to_string());
-
-    Author {
-        name: author_name,
-        email: author_email,
-        time: Local::now(),
-    }
+
+// The identity of a role, "author" or "committer", from the environment variables
+// `GIT_<ROLE>_NAME`, `GIT_<ROLE>_EMAIL` and `GIT_<ROLE>_DATE`, then from `<role>.name` and
+// `<role>.email`, then from `user.name` and `user.email`. The date defaults to now
+fn get_identity(config: &Config, role: &str) -> Result<Author, ConfigError> {
+    let variable = |key: &str| format!("GIT_{}_{}", role.to_uppercase(), key.to_uppercase());
+    let lookup = |key: &str| {
+        get_env_var(&variable(key))
+            .or_else(|| config.get(&format!("{}.{}", role, key)).map(str::to_string))
+            .or_else(|| config.get(&format!("user.{}", key)).map(str::to_string))
+            .ok_or(ConfigError::IdentityUnknown)
+    };
+
+    let time = match get_env_var(&variable("date")) {
+        Some(date) => parse_date(&date).ok_or(ConfigError::InvalidDate(date))?,
+        None => Local::now().fixed_offset(),
+    };
+    Ok(Author {
+        name: lookup("name")?,
+        email: lookup("email")?,
+        time,
+    })
+}
+
+/// The identity of the author of new commits
+///
+/// It comes from `GIT_AUTHOR_NAME`, `GIT_AUTHOR_EMAIL` and `GIT_AUTHOR_DATE`, then from
+/// `author.name` and `author.email`, then from `user.name` and `user.email`
+pub fn get_author(config: &Config) -> Result<Author, ConfigError> {
+    get_identity(config, "author")
+}
+
+/// The identity of the committer of new commits and of the tagger of new tags
+///
+/// It comes from `GIT_COMMITTER_NAME`, `GIT_COMMITTER_EMAIL` and `GIT_COMMITTER_DATE`, then from
+/// `committer.name` and `committer.email`, then from `user.name` and `user.email`
+pub fn get_committer(config: &Config) -> Result<Author, ConfigError> {
+    get_identity(config, "committer")
 }
@@ -0,0 +1,256 @@
+use crate::{
+    object::{
+        kvlm::{decode, parse_kvlm, write_header, Kvlm},
+        read_header, summary, Author, Object, ObjectBuffer, ObjectHeader, ObjectReadError,
+        ObjectType,
+    },
+    object_reader::ObjectReader,
+    oid::ObjectId,
+    Repository,
+};
+use std::borrow::Cow;
+use std::io::Read;
+
+// Signatures of signed tags are appended to the message
+const SIGNATURE_MARKERS: [&str; 3] = [
+    "-----BEGIN PGP SIGNATURE-----",
+    "-----BEGIN PGP MESSAGE-----",
+    "-----BEGIN SSH SIGNATURE-----",
+];
+
+/// In memory data representation of a git tag object (an annotated tag)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    object: ObjectId,
+    typ: ObjectType,
+    name: String,
+    tagger: Option<Author>,
+    // The tagger line as it is in the object, so that writing a parsed tag gives back the same
+    // bytes
+    raw_tagger: Option<Vec<u8>>,
+    // Headers we don't interpret, in their original order
+    extra_headers: Vec<(String, Vec<u8>)>,
+    message: Vec<u8>,
+    signature: Option<Vec<u8>>,
+}
+
+impl Tag {
+    /// Create a new annotated tag. Like git, a trailing newline is added to the message
+    pub fn new(
+        object: ObjectId,
+        typ: ObjectType,
+        name: String,
+        tagger: Author,
+        message: String,
+    ) -> Self {
+        Tag {
+            object,
+            typ,
+            name,
+            raw_tagger: Some(tagger.to_string().into_bytes()),
+            tagger: Some(tagger),
+            extra_headers: vec![],
+            message: format!("{message}\n").into_bytes(),
+            signature: None,
+        }
+    }
+
+    /// The tagged object
+    pub fn object(&self) -> ObjectId {
+        self.object
+    }
+
+    /// Type of the tagged object
+    pub fn target_type(&self) -> ObjectType {
+        self.typ
+    }
+
+    /// Name of the tag
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Some very old tags do not have a tagger
+    pub fn tagger(&self) -> Option<&Author> {
+        self.tagger.as_ref()
+    }
+
+    /// Headers other than object, type, tag and tagger, with their raw values
+    pub fn extra_headers(&self) -> &[(String, Vec<u8>)] {
+        &self.extra_headers
+    }
+
+    /// The tag message, without the signature
+    pub fn message(&self) -> Cow<'_, str> {
+        decode(&self.message, None)
+    }
+
+    /// The tag message as bytes, without the signature
+    pub fn raw_message(&self) -> &[u8] {
+        &self.message
+    }
+
+    /// The first paragraph of the message, joined into a single line
+    pub fn summary(&self) -> String {
+        summary(&self.message())
+    }
+
+    /// The ASCII-armored signature of a signed tag
+    pub fn signature(&self) -> Option<Cow<'_, str>> {
+        self.signature
+            .as_deref()
+            .map(|signature| decode(signature, None))
+    }
+
+    /// Parse the content of a tag object (without the object header)
+    pub fn parse(content: &[u8]) -> Result<Tag, ObjectReadError> {
+        let invalid = |reason| ObjectReadError::InvalidObject(ObjectType::Tag, reason);
+
+        let Kvlm { headers, message } = parse_kvlm(content, ObjectType::Tag)?;
+
+        let mut object = None;
+        let mut typ = None;
+        let mut name = None;
+        let mut tagger = None;
+        let mut extra_headers = vec![];
+        for (key, value) in headers {
+            match key.as_str() {
+                "object" if object.is_none() => {
+                    object = Some(
+                        std::str::from_utf8(&value)
+                            .ok()
+                            .and_then(|value| ObjectId::from_unvalidated_sh1_hex_string(value).ok())
+                            .ok_or(invalid("invalid object id"))?,
+                    )
+                }
+                "type" if typ.is_none() => {
+                    typ = Some(ObjectType::parse(&value).ok_or(invalid("unknown object type"))?)
+                }
+                "tag" if name.is_none() => {
+                    name = Some(String::from_utf8(value).map_err(|_| invalid("invalid tag name"))?)
+                }
+                "tagger" if tagger.is_none() => {
+                    let author = Author::parse(&decode(&value, None));
+                    tagger = Some((author.ok_or(invalid("malformed tagger"))?, value))
+                }
+                _ => extra_headers.push((key, value)),
+            }
+        }
+        let (tagger, raw_tagger) = tagger.unzip();
+
+        // the signature starts at the first line that is a signature marker
+        let signature_start = (0..message.len()).find(|&position| {
+            (position == 0 || message[position - 1] == b'\n')
+                && SIGNATURE_MARKERS
+                    .iter()
+                    .any(|marker| message[position..].starts_with(marker.as_bytes()))
+        });
+        let (message, signature) = match signature_start {
+            Some(position) => (
+                message[..position].to_vec(),
+                Some(message[position..].to_vec()),
+            ),
+            None => (message, None),
+        };
+
+        Ok(Tag {
+            object: object.ok_or(invalid("missing object"))?,
+            typ: typ.ok_or(invalid("missing type"))?,
+            name: name.ok_or(invalid("missing tag name"))?,
+            tagger,
+            raw_tagger,
+            extra_headers,
+            message,
+            signature,
+        })
+    }
+}
+
+impl Object for Tag {
+    fn to_buffer(&self) -> ObjectBuffer {
+        let mut content = vec![];
+        write_header(&mut content, "object", self.object.to_string().as_bytes());
+        write_header(&mut content, "type", self.typ.to_string().as_bytes());
+        write_header(&mut content, "tag", self.name.as_bytes());
+        if let Some(tagger) = &self.raw_tagger {
+            write_header(&mut content, "tagger", tagger);
+        }
+        for (key, value) in &self.extra_headers {
+            write_header(&mut content, key, value);
+        }
+        content.push(b'\n');
+        content.extend_from_slice(&self.message);
+        if let Some(signature) = &self.signature {
+            content.extend_from_slice(signature);
+        }
+
+        ObjectBuffer::new(ObjectType::Tag, &content)
+    }
+}
+
+/// Read a tag object
+pub fn read_tag_object(reader: &mut ObjectReader) -> Result<Tag, ObjectReadError> {
+    let ObjectHeader { typ, size } = read_header(reader)?;
+    if typ != ObjectType::Tag {
+        return Err(ObjectReadError::MismatchObjectType(ObjectType::Tag, typ));
+    }
+
+    let mut content = Vec::with_capacity(size);
+    reader
+        .read_to_end(&mut content)
+        .map_err(ObjectReadError::ContentReadError)?;
+    Tag::parse(&content)
+}
+
+impl Repository {
+    /// Read a tag object from the database
+    pub fn read_tag(&self, oid: ObjectId) -> Result<Tag, ObjectReadError> {
+        let mut reader = self
+            .object_reader(oid)
+            .map_err(|err| ObjectReadError::OpenError(oid, err))?;
+        read_tag_object(&mut reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNED_TAG: &str = concat!(
+        "object 9bedf67800b2923982bdf60c89c57ce6ce2d9a1c\n",
+        "type commit\n",
+        "tag v1.0\n",
+        "tagger A U Thor <author@example.com> 1112911993 -0700\n",
+        "\n",
+        "Version 1.0\n",
+        "-----BEGIN PGP SIGNATURE-----\n",
+        "\n",
+        "iQEzBAABCAAdFiEE\n",
+        "-----END PGP SIGNATURE-----\n",
+    );
+
+    #[test]
+    fn parse() {
+        let tag = Tag::parse(SIGNED_TAG.as_bytes()).unwrap();
+
+        assert_eq!(
+            tag.object().to_string(),
+            "9bedf67800b2923982bdf60c89c57ce6ce2d9a1c"
+        );
+        assert_eq!(tag.target_type(), ObjectType::Commit);
+        assert_eq!(tag.name(), "v1.0");
+        assert_eq!(tag.tagger().unwrap().name, "A U Thor");
+        assert_eq!(tag.message(), "Version 1.0\n");
+        assert!(tag
+            .signature()
+            .unwrap()
+            .starts_with("-----BEGIN PGP SIGNATURE-----\n"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let tag = Tag::parse(SIGNED_TAG.as_bytes()).unwrap();
+        let expected = ObjectBuffer::new(ObjectType::Tag, SIGNED_TAG.as_bytes());
+        assert_eq!(tag.to_buffer().data(), expected.data());
+    }
+}
@@ -1,4 +1,6 @@
-use crate::index::Index;
+use crate::database::DatabaseWriteError;
+use crate::index::{CacheTree, Index, IndexReadError};
+use crate::lockfile::{Lockfile, LockfileError};
 use crate::object::{read_header, Object, ObjectBuffer, ObjectHeader, ObjectReadError, ObjectType};
 use crate::object_reader::ObjectReader;
 use crate::oid::ObjectId;
@@ -8,8 +10,18 @@ use std::collections::BTreeMap;
 use std::{
     io,
     io::{BufRead, Read},
+    path::{Path, PathBuf},
 };
 
+/// Mode of tree entries that are themselves trees
+pub const TREE_MODE: u32 = 0o040000;
+
+/// Mode of symbolic links, whose blob is the target of the link
+pub const SYMLINK_MODE: u32 = 0o120000;
+
+/// Mode of submodules, whose oid is a commit of another repository
+pub const GITLINK_MODE: u32 = 0o160000;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct TreeEntry {
     pub name: String,
@@ -61,7 +73,7 @@ pub fn read_tree_object(reader: &mut ObjectReader) -> Result<Tree, ObjectReadErr
     if typ != ObjectType::Tree {
         return Err(ObjectReadError::MismatchObjectType(ObjectType::Tree, typ));
     }
-    read_tree_content(reader).map_err(|err| ObjectReadError::ContentReadError(err))
+    read_tree_content(reader).map_err(ObjectReadError::ContentReadError)
 }
 
 fn read_tree_content(reader: &mut ObjectReader) -> io::Result<Tree> {
@@ -146,45 +158,127 @@ impl TreeBuilder {
         }
     }
 
-    // returns the index to the root tree
-    fn make_trees(self, trees: &mut Vec<Tree>) -> usize {
-        let mut root = Tree::new();
+    // Write the tree and the subtrees whose id is not already in `cache`, and return the
+    // up-to-date cache of the tree
+    fn write_trees(
+        self,
+        repository: &Repository,
+        cache: Option<&CacheTree>,
+    ) -> Result<CacheTree, DatabaseWriteError> {
+        if let Some(cache) = cache.filter(|cache| cache.oid().is_some()) {
+            return Ok(cache.clone());
+        }
 
+        let mut tree = Tree::new();
+        let mut children = vec![];
+        let mut entry_count = 0;
         for (name, entry) in self.entries {
             match entry {
-                TreeBuilderEntry::Blob(blob) => root.entries.push(blob),
+                TreeBuilderEntry::Blob(blob) => {
+                    entry_count += 1;
+                    tree.entries.push(blob);
+                }
                 TreeBuilderEntry::Tree(child) => {
-                    let child_index = child.make_trees(trees);
-                    let buffer = trees[child_index].to_buffer();
-                    let oid = ObjectId::from_object_buffer(&buffer);
-                    root.entries.push(TreeEntry {
-                        name: name[0..name.len() - 1].to_string(), /* remove the trailing slash */
-                        oid,
-                        mode: 0o040000,
-                    })
+                    let name = name[0..name.len() - 1].to_string(); /* remove the trailing slash */
+                    let child_cache = child
+                        .write_trees(repository, cache.and_then(|cache| cache.child(&name)))?;
+                    entry_count += child_cache.entry_count().expect("written trees are valid");
+                    tree.entries.push(TreeEntry {
+                        name: name.clone(),
+                        oid: child_cache.oid().expect("written trees are valid"),
+                        mode: TREE_MODE,
+                    });
+                    children.push((name, child_cache));
                 }
             }
         }
 
-        let root_index = trees.len();
-        trees.push(root);
-        root_index
+        let oid = repository.write_object(&tree)?;
+        Ok(CacheTree::new(oid, entry_count, children))
     }
 }
 
+/// An error raised when writing the index as a tree
+#[derive(thiserror::Error, Debug)]
+pub enum WriteTreeError {
+    #[error(transparent)]
+    IndexReadError(#[from] IndexReadError),
+
+    #[error(transparent)]
+    DatabaseWriteError(#[from] DatabaseWriteError),
+
+    #[error(transparent)]
+    LockfileError(#[from] LockfileError),
+
+    #[error("{}: unmerged, can't write a tree", .0.display())]
+    Unmerged(PathBuf),
+
+    #[error("IO Error")]
+    IOError(#[from] io::Error),
+}
+
 impl Repository {
-    /// Write the index as a tree. Including all the children trees. Returns the `ObjectId` of the root tree
-    pub fn write_tree(&self) -> ObjectId {
-        // TODO: error handling
+    /// Read a tree object from the database
+    pub fn read_tree(&self, oid: ObjectId) -> Result<Tree, ObjectReadError> {
+        let mut reader = self
+            .object_reader(oid)
+            .map_err(|err| ObjectReadError::OpenError(oid, err))?;
+        read_tree_object(&mut reader)
+    }
 
-        let index = Index::open(&self.git_dir.join("index")).unwrap();
+    /// Recursively list all the entries of a tree that are not trees themselves, keyed by their
+    /// path relative to the root tree
+    pub fn read_tree_recursive(
+        &self,
+        oid: ObjectId,
+    ) -> Result<BTreeMap<PathBuf, TreeEntry>, ObjectReadError> {
+        let mut entries = BTreeMap::new();
+        self.read_tree_recursive_impl(oid, Path::new(""), &mut entries)?;
+        Ok(entries)
+    }
 
-        let entries: Vec<_> = index.iter().collect();
+    fn read_tree_recursive_impl(
+        &self,
+        oid: ObjectId,
+        prefix: &Path,
+        entries: &mut BTreeMap<PathBuf, TreeEntry>,
+    ) -> Result<(), ObjectReadError> {
+        for entry in self.read_tree(oid)?.entries {
+            let path = prefix.join(&entry.name);
+            if entry.mode == TREE_MODE {
+                self.read_tree_recursive_impl(entry.oid, &path, entries)?;
+            } else {
+                entries.insert(path, entry);
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the index as a tree. Including all the children trees. Returns the `ObjectId` of the root tree
+    ///
+    /// The trees of the directories that didn't change since the last time are reused from the
+    /// cache tree of the index, which is then updated
+    pub fn write_tree(&self) -> Result<ObjectId, WriteTreeError> {
+        let index_path = self.index_path();
+        // like git, still write the tree when the index is locked, only without updating its cache
+        let lock = Lockfile::new(&index_path).ok();
+        let mut index = Index::open(&index_path)?;
 
         let mut tree_builder = TreeBuilder::new();
 
-        // Create in-memory trees
-        for entry in &entries {
+        // a tree can't record the stages of a merge conflict
+        if let Some(conflict) = index.conflicts().next() {
+            return Err(WriteTreeError::Unmerged(conflict.path.to_path_buf()));
+        }
+
+        // Create in-memory trees. Like git, paths added with intent-to-add are left out, and the
+        // cache tree is then not updated
+        let mut intent_to_add = false;
+        for entry in index.iter() {
+            if entry.flags.intent_to_add {
+                intent_to_add = true;
+                continue;
+            }
             let mut path: Vec<_> = entry
                 .path
                 .components()
@@ -201,16 +295,16 @@ impl Repository {
             tree_builder.add_entry(&path, name.clone(), TreeEntry { name, oid, mode })
         }
 
-        let mut trees = vec![];
-        let root_index = tree_builder.make_trees(&mut trees);
+        let cache_tree = tree_builder.write_trees(self, index.cache_tree())?;
+        let oid = cache_tree.oid().expect("written trees are valid");
 
-        for tree in &trees {
-            let tree_buffer = tree.to_buffer();
-            let oid = ObjectId::from_object_buffer(&tree_buffer);
-            self.write_object_buffer(oid, &tree_buffer).unwrap();
+        if let Some(mut lock) = lock.filter(|_| !intent_to_add) {
+            if index.cache_tree() != Some(&cache_tree) {
+                index.set_cache_tree(cache_tree);
+                index.write_to(&mut lock)?;
+                lock.commit()?;
+            }
         }
-
-        let root_buffer = trees[root_index].to_buffer();
-        ObjectId::from_object_buffer(&root_buffer)
+        Ok(oid)
     }
 }
@@ -1,34 +1,59 @@
 use flate2::read::ZlibDecoder;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, BufReader, Cursor, Read};
 
 /// A reader for git objects
 ///
 /// This object handles buffered file reading and zlib decoding
-pub struct ObjectReader(BufReader<ZlibDecoder<File>>);
+pub struct ObjectReader(Source);
+
+enum Source {
+    /// A zlib-compressed loose object file
+    Loose(BufReader<ZlibDecoder<File>>),
+
+    /// An object that is already decoded in memory, such as an object resolved from a pack
+    InMemory(Cursor<Box<[u8]>>),
+}
 
 impl ObjectReader {
     pub(crate) fn from_file(file: File) -> Self {
-        ObjectReader(BufReader::new(ZlibDecoder::new(file)))
+        ObjectReader(Source::Loose(BufReader::new(ZlibDecoder::new(file))))
+    }
+
+    /// Create a reader from the bytes of an object, including its header
+    pub(crate) fn from_bytes(data: Box<[u8]>) -> Self {
+        ObjectReader(Source::InMemory(Cursor::new(data)))
     }
 }
 
 impl Read for ObjectReader {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        self.0.read(buf)
+        match &mut self.0 {
+            Source::Loose(reader) => reader.read(buf),
+            Source::InMemory(reader) => reader.read(buf),
+        }
     }
 }
 
 impl BufRead for ObjectReader {
     fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
-        self.0.fill_buf()
+        match &mut self.0 {
+            Source::Loose(reader) => reader.fill_buf(),
+            Source::InMemory(reader) => reader.fill_buf(),
+        }
     }
 
     fn consume(&mut self, amt: usize) {
-        self.0.consume(amt)
+        match &mut self.0 {
+            Source::Loose(reader) => reader.consume(amt),
+            Source::InMemory(reader) => reader.consume(amt),
+        }
     }
 
     fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> std::io::Result<usize> {
-        self.0.read_until(byte, buf)
+        match &mut self.0 {
+            Source::Loose(reader) => reader.read_until(byte, buf),
+            Source::InMemory(reader) => reader.read_until(byte, buf),
+        }
     }
 }
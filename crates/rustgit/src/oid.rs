@@ -4,7 +4,7 @@ use sha1::Digest;
 use std::fmt::{Debug, Display, Formatter};
 
 /// 20-bytes raw hash
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct ObjectId(pub [u8; 20]);
 
 // 40-char Hex string
@@ -31,9 +31,17 @@ pub enum SHA1ValidationError {
 }
 
 impl ObjectId {
+    /// The all-zero id, which stands for a missing object, e.g. the old value of a reference in
+    /// the reflog entry of its creation
+    pub const NULL: ObjectId = ObjectId([0; 20]);
+
+    pub fn is_null(&self) -> bool {
+        *self == Self::NULL
+    }
+
     /// Compute a hash from a git object
     pub fn from_object_buffer(object: &ObjectBuffer) -> Self {
-        Self::from_data(&object.data())
+        Self::from_data(object.data())
     }
 
     pub fn from_data(data: &[u8]) -> Self {
@@ -41,11 +49,7 @@ impl ObjectId {
         hasher.update(data);
         let output = hasher.finalize();
 
-        ObjectId(
-            output
-                .try_into()
-                .expect("Sha1 hash should be 20 bytes long"),
-        )
+        ObjectId(output.into())
     }
 
     pub fn from_unvalidated_sh1_hex_string(s: &str) -> Result<Self, SHA1ValidationError> {
@@ -81,10 +85,15 @@ impl Display for ObjectId {
     }
 }
 
-impl Sha1HashHexString {
-    pub fn from_str(s: &str) -> Result<Self, SHA1ValidationError> {
+impl std::str::FromStr for Sha1HashHexString {
+    type Err = SHA1ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, SHA1ValidationError> {
         Self::from_u8_slice(s.as_bytes())
     }
+}
+
+impl Sha1HashHexString {
     pub fn from_u8_slice(bytes: &[u8]) -> Result<Self, SHA1ValidationError> {
         let data: [u8; 40] = trim_whitespace(bytes).try_into().map_err(|_| {
             SHA1ValidationError::InvalidHexString(format!("{}", String::from_utf8_lossy(bytes)))
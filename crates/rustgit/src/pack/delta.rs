@@ -0,0 +1,108 @@
+// Git delta format used by OFS_DELTA and REF_DELTA pack entries
+//
+// A delta starts with the size of the base object and the size of the result object, both
+// encoded as little-endian base-128 varints. It is followed by a list of instructions:
+// - 1xxxxxxx: copy a range of the base object. The low 4 bits tell which offset bytes
+//   follow, the next 3 bits tell which size bytes follow
+// - 0xxxxxxx: insert the next xxxxxxx bytes of the delta itself
+
+use std::io;
+
+fn invalid_delta() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "corrupted delta in pack file")
+}
+
+fn read_size(delta: &[u8], position: &mut usize) -> io::Result<usize> {
+    let mut size = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *delta.get(*position).ok_or_else(invalid_delta)?;
+        *position += 1;
+        size |= usize::from(byte & 0x7f) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(size);
+        }
+    }
+}
+
+/// Reconstruct an object from its base and a delta
+pub(crate) fn apply_delta(base: &[u8], delta: &[u8]) -> io::Result<Vec<u8>> {
+    let mut position = 0;
+    let base_size = read_size(delta, &mut position)?;
+    if base_size != base.len() {
+        return Err(invalid_delta());
+    }
+    let result_size = read_size(delta, &mut position)?;
+
+    let mut result = Vec::with_capacity(result_size);
+    while let Some(&instruction) = delta.get(position) {
+        position += 1;
+
+        if instruction & 0x80 != 0 {
+            // copy from base
+            let mut read_field = |bits: u8, byte_count: usize| -> io::Result<usize> {
+                let mut value = 0;
+                for i in 0..byte_count {
+                    if bits & (1 << i) != 0 {
+                        let byte = *delta.get(position).ok_or_else(invalid_delta)?;
+                        position += 1;
+                        value |= usize::from(byte) << (8 * i);
+                    }
+                }
+                Ok(value)
+            };
+            let offset = read_field(instruction & 0x0f, 4)?;
+            let size = match read_field((instruction >> 4) & 0x07, 3)? {
+                0 => 0x10000,
+                size => size,
+            };
+
+            let end = offset.checked_add(size).ok_or_else(invalid_delta)?;
+            result.extend_from_slice(base.get(offset..end).ok_or_else(invalid_delta)?);
+        } else if instruction != 0 {
+            // insert literal data
+            let end = position + usize::from(instruction);
+            result.extend_from_slice(delta.get(position..end).ok_or_else(invalid_delta)?);
+            position = end;
+        } else {
+            // reserved
+            return Err(invalid_delta());
+        }
+    }
+
+    if result.len() != result_size {
+        return Err(invalid_delta());
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_and_insert() {
+        let base = b"hello world";
+        let delta = [
+            11,          // base size
+            13,          // result size
+            0b1001_0000, // copy, no offset byte, 1 size byte
+            6,           // size = 6: "hello "
+            4,           // insert 4 bytes
+            b'r',
+            b'u',
+            b's',
+            b't',
+            0b1001_0001, // copy, 1 offset byte, 1 size byte
+            8,           // offset = 8
+            3,           // size = 3: "rld"
+        ];
+        assert_eq!(apply_delta(base, &delta).unwrap(), b"hello rustrld");
+    }
+
+    #[test]
+    fn mismatched_base_size() {
+        assert!(apply_delta(b"abc", &[4, 0]).is_err());
+    }
+}
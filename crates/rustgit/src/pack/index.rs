@@ -0,0 +1,124 @@
+// Pack index (.idx) files, version 2
+//
+// Layout:
+// - 4-byte magic number \377tOc and 4-byte version number (= 2)
+// - 256-entry fan-out table
+// - sorted table of object ids
+// - table of CRC32 checksums (ignored)
+// - table of 4-byte offsets into the pack file
+// - table of 8-byte offsets for packs larger than 2 GiB
+// - pack checksum and index checksum
+
+use crate::oid::ObjectId;
+use crate::read_ext::ReadExt;
+use std::{
+    fs::File,
+    io,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+const IDX_SIGNATURE: &[u8; 4] = b"\xfftOc";
+const LARGE_OFFSET_FLAG: u32 = 0x8000_0000;
+
+pub(crate) struct PackIndex {
+    // sorted, so that we can binary search through it
+    oids: Vec<ObjectId>,
+    offsets: Vec<u64>,
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl PackIndex {
+    pub(crate) fn open(path: &Path) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let signature = reader.read_exact_4()?;
+        if &signature != IDX_SIGNATURE {
+            return Err(invalid_data(format!(
+                "{} is not a version 2 pack index",
+                path.display()
+            )));
+        }
+        let version = u32::from_be_bytes(reader.read_exact_4()?);
+        if version != 2 {
+            return Err(invalid_data(format!(
+                "unsupported pack index version {version} in {}",
+                path.display()
+            )));
+        }
+
+        let mut fan_out = [0u32; 256];
+        for entry in fan_out.iter_mut() {
+            *entry = u32::from_be_bytes(reader.read_exact_4()?);
+        }
+        let object_count = fan_out[255] as usize;
+
+        let mut oids = Vec::with_capacity(object_count);
+        for _ in 0..object_count {
+            oids.push(ObjectId(reader.read_exact_n::<20>()?));
+        }
+
+        // skip CRC32 checksums
+        io::copy(
+            &mut reader.by_ref().take(4 * object_count as u64),
+            &mut io::sink(),
+        )?;
+
+        let small_offsets = (0..object_count)
+            .map(|_| Ok(u32::from_be_bytes(reader.read_exact_4()?)))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let large_offset_count = small_offsets
+            .iter()
+            .filter(|&&offset| offset & LARGE_OFFSET_FLAG != 0)
+            .count();
+        let large_offsets = (0..large_offset_count)
+            .map(|_| Ok(u64::from_be_bytes(reader.read_exact_n::<8>()?)))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let offsets = small_offsets
+            .into_iter()
+            .map(|offset| {
+                if offset & LARGE_OFFSET_FLAG == 0 {
+                    Ok(u64::from(offset))
+                } else {
+                    let index = (offset & !LARGE_OFFSET_FLAG) as usize;
+                    large_offsets.get(index).copied().ok_or_else(|| {
+                        invalid_data(format!("bad large offset in {}", path.display()))
+                    })
+                }
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok(PackIndex { oids, offsets })
+    }
+
+    pub(crate) fn object_count(&self) -> usize {
+        self.oids.len()
+    }
+
+    /// Find the offset of an object in the pack file
+    pub(crate) fn find_offset(&self, oid: ObjectId) -> Option<u64> {
+        self.oids
+            .binary_search(&oid)
+            .ok()
+            .map(|index| self.offsets[index])
+    }
+
+    /// All the objects whose hex representation starts with `prefix`
+    pub(crate) fn oids_with_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = ObjectId> + 'a {
+        let start = self
+            .oids
+            .partition_point(|oid| oid.to_string().as_str() < prefix);
+        self.oids[start..]
+            .iter()
+            .take_while(move |oid| oid.to_string().starts_with(prefix))
+            .copied()
+    }
+}
@@ -0,0 +1,219 @@
+// Packfiles at .git/objects/pack
+//
+// Each pack-*.pack file comes with a pack-*.idx file that maps object ids to offsets in the pack.
+// A pack entry is either an undeltified object, or a delta against another object (OFS_DELTA
+// refers to its base by a relative offset in the same pack, REF_DELTA by its object id).
+
+mod delta;
+mod index;
+
+use crate::object::ObjectType;
+use crate::oid::ObjectId;
+use crate::read_ext::ReadExt;
+use delta::apply_delta;
+use flate2::read::ZlibDecoder;
+use index::PackIndex;
+use std::{
+    fs,
+    fs::File,
+    io,
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+const PACK_SIGNATURE: &[u8; 4] = b"PACK";
+
+// Object type numbers used in pack entry headers
+const OBJ_COMMIT: u8 = 1;
+const OBJ_TREE: u8 = 2;
+const OBJ_BLOB: u8 = 3;
+const OBJ_TAG: u8 = 4;
+const OBJ_OFS_DELTA: u8 = 6;
+const OBJ_REF_DELTA: u8 = 7;
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+enum PackEntry {
+    Base(ObjectType, Vec<u8>),
+    OfsDelta { base_offset: u64, delta: Vec<u8> },
+    RefDelta { base: ObjectId, delta: Vec<u8> },
+}
+
+/// The base object of a delta chain that lives outside of this pack
+pub(crate) type ExternalBase = (ObjectType, Vec<u8>);
+
+pub(crate) struct Pack {
+    pack_path: PathBuf,
+    index: PackIndex,
+}
+
+impl Pack {
+    /// Open a pack given the path of its .idx file
+    pub(crate) fn open(index_path: &Path) -> io::Result<Self> {
+        let pack_path = index_path.with_extension("pack");
+        let index = PackIndex::open(index_path)?;
+
+        let mut pack_file = File::open(&pack_path)?;
+        let signature = pack_file.read_exact_4()?;
+        let version = u32::from_be_bytes(pack_file.read_exact_4()?);
+        if &signature != PACK_SIGNATURE || !(version == 2 || version == 3) {
+            return Err(invalid_data(format!(
+                "{} is not a valid pack file",
+                pack_path.display()
+            )));
+        }
+
+        Ok(Pack { pack_path, index })
+    }
+
+    /// Open all the packs inside a `.git/objects/pack` folder
+    pub(crate) fn open_all(pack_dir: &Path) -> io::Result<Vec<Pack>> {
+        let entries = match fs::read_dir(pack_dir) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            entries => entries?,
+        };
+
+        let mut packs = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "idx")
+                && path.with_extension("pack").exists()
+            {
+                packs.push(Pack::open(&path)?);
+            }
+        }
+        Ok(packs)
+    }
+
+    pub(crate) fn contains(&self, oid: ObjectId) -> bool {
+        self.index.find_offset(oid).is_some()
+    }
+
+    pub(crate) fn object_count(&self) -> usize {
+        self.index.object_count()
+    }
+
+    pub(crate) fn oids_with_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = ObjectId> + 'a {
+        self.index.oids_with_prefix(prefix)
+    }
+
+    /// Read and fully resolve an object in the pack
+    ///
+    /// Returns `None` if the object is not in this pack. `read_external` is used to look up
+    /// REF_DELTA bases that are not in this pack.
+    pub(crate) fn read_object(
+        &self,
+        oid: ObjectId,
+        read_external: impl Fn(ObjectId) -> io::Result<ExternalBase>,
+    ) -> io::Result<Option<(ObjectType, Vec<u8>)>> {
+        let Some(mut offset) = self.index.find_offset(oid) else {
+            return Ok(None);
+        };
+
+        let mut reader = BufReader::new(File::open(&self.pack_path)?);
+
+        // Walk down the delta chain until we find a base object
+        let mut deltas = vec![];
+        let (typ, mut data) = loop {
+            match read_entry(&mut reader, offset)? {
+                PackEntry::Base(typ, data) => break (typ, data),
+                PackEntry::OfsDelta { base_offset, delta } => {
+                    deltas.push(delta);
+                    offset = base_offset;
+                }
+                PackEntry::RefDelta { base, delta } => {
+                    deltas.push(delta);
+                    match self.index.find_offset(base) {
+                        Some(base_offset) => offset = base_offset,
+                        None => break read_external(base)?,
+                    }
+                }
+            }
+        };
+
+        for delta in deltas.iter().rev() {
+            data = apply_delta(&data, delta)?;
+        }
+
+        Ok(Some((typ, data)))
+    }
+}
+
+fn read_byte(reader: &mut impl Read) -> io::Result<u8> {
+    Ok(reader.read_exact_n::<1>()?[0])
+}
+
+fn read_entry(reader: &mut BufReader<File>, offset: u64) -> io::Result<PackEntry> {
+    reader.seek(SeekFrom::Start(offset))?;
+
+    // type and size header: 1TTTSSSS 1SSSSSSS ... 0SSSSSSS
+    let mut byte = read_byte(reader)?;
+    let type_id = (byte >> 4) & 0x07;
+    let mut size = u64::from(byte & 0x0f);
+    let mut shift = 4;
+    while byte & 0x80 != 0 {
+        byte = read_byte(reader)?;
+        size |= u64::from(byte & 0x7f) << shift;
+        shift += 7;
+    }
+
+    let entry = match type_id {
+        OBJ_COMMIT | OBJ_TREE | OBJ_BLOB | OBJ_TAG => {
+            let typ = match type_id {
+                OBJ_COMMIT => ObjectType::Commit,
+                OBJ_TREE => ObjectType::Tree,
+                OBJ_BLOB => ObjectType::Blob,
+                _ => ObjectType::Tag,
+            };
+            PackEntry::Base(typ, inflate(reader, size)?)
+        }
+        OBJ_OFS_DELTA => {
+            // offset encoding where each continuation adds one, so that there is no redundancy
+            let mut byte = read_byte(reader)?;
+            let mut relative_offset = u64::from(byte & 0x7f);
+            while byte & 0x80 != 0 {
+                byte = read_byte(reader)?;
+                relative_offset = ((relative_offset + 1) << 7) | u64::from(byte & 0x7f);
+            }
+            let base_offset = offset
+                .checked_sub(relative_offset)
+                .ok_or_else(|| invalid_data(format!("bad delta base offset at {offset}")))?;
+            PackEntry::OfsDelta {
+                base_offset,
+                delta: inflate(reader, size)?,
+            }
+        }
+        OBJ_REF_DELTA => {
+            let base = ObjectId(reader.read_exact_n::<20>()?);
+            PackEntry::RefDelta {
+                base,
+                delta: inflate(reader, size)?,
+            }
+        }
+        _ => {
+            return Err(invalid_data(format!(
+                "unsupported object type {type_id} in pack at offset {offset}"
+            )))
+        }
+    };
+    Ok(entry)
+}
+
+// Decompress a zlib stream which should inflate to exactly `size` bytes
+fn inflate(reader: &mut impl BufRead, size: u64) -> io::Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(size as usize);
+    ZlibDecoder::new(reader)
+        .take(size)
+        .read_to_end(&mut output)?;
+    if output.len() as u64 != size {
+        return Err(invalid_data(
+            "pack entry is smaller than its declared size".to_string(),
+        ));
+    }
+    Ok(output)
+}
@@ -0,0 +1,174 @@
+// Reading .git/packed-refs
+//
+// The file starts with an optional `# pack-refs with: <traits>` line, followed by one
+// `<oid> <refname>` line per reference. An annotated tag can be followed by a `^<oid>` line that
+// records the object the tag peels to.
+
+use crate::lockfile::Lockfile;
+use crate::oid::ObjectId;
+use crate::references::ReferenceError;
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    path::Path,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PackedRef {
+    pub name: String,
+    pub oid: ObjectId,
+    pub peeled: Option<ObjectId>,
+}
+
+/// The content of a packed-refs file, sorted by name
+pub(crate) struct PackedRefs {
+    // The `# pack-refs with:` line, kept as is when the file is written back
+    header: Option<String>,
+    refs: Vec<PackedRef>,
+}
+
+impl PackedRefs {
+    /// Read the packed-refs file. A missing file is the same as an empty one
+    pub(crate) fn open(path: &Path) -> Result<Self, ReferenceError> {
+        match fs::read_to_string(path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(PackedRefs {
+                header: None,
+                refs: vec![],
+            }),
+            Err(e) => Err(e.into()),
+            Ok(content) => Self::parse(&content),
+        }
+    }
+
+    fn parse(content: &str) -> Result<Self, ReferenceError> {
+        let mut header = None;
+        let mut refs: Vec<PackedRef> = vec![];
+        for line in content.lines() {
+            if line.starts_with("# pack-refs with:") {
+                header = Some(line.to_string());
+            }
+            if line.starts_with('#') || line.is_empty() {
+                continue;
+            }
+
+            if let Some(peeled) = line.strip_prefix('^') {
+                let last = refs
+                    .last_mut()
+                    .ok_or_else(|| ReferenceError::MalformedPackedRefs(line.to_string()))?;
+                last.peeled = Some(ObjectId::from_unvalidated_sh1_hex_string(peeled)?);
+            } else {
+                let (oid, name) = line
+                    .split_once(' ')
+                    .ok_or_else(|| ReferenceError::MalformedPackedRefs(line.to_string()))?;
+                refs.push(PackedRef {
+                    name: name.to_string(),
+                    oid: ObjectId::from_unvalidated_sh1_hex_string(oid)?,
+                    peeled: None,
+                });
+            }
+        }
+
+        // git normally writes the file sorted, but doesn't have to
+        refs.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(PackedRefs { header, refs })
+    }
+
+    fn position(&self, name: &str) -> Result<usize, usize> {
+        self.refs
+            .binary_search_by(|packed_ref| packed_ref.name.as_str().cmp(name))
+    }
+
+    pub(crate) fn find(&self, name: &str) -> Option<&PackedRef> {
+        self.position(name).ok().map(|index| &self.refs[index])
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &PackedRef> {
+        self.refs.iter()
+    }
+
+    /// Remove a reference, returning whether it was there
+    pub(crate) fn remove(&mut self, name: &str) -> bool {
+        match self.position(name) {
+            Ok(index) => {
+                self.refs.remove(index);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Write the references to the locked packed-refs file
+    pub(crate) fn write_to(&self, lockfile: &mut Lockfile) -> Result<(), ReferenceError> {
+        let mut content = String::new();
+        if let Some(header) = &self.header {
+            content.push_str(header);
+            content.push('\n');
+        }
+        for packed_ref in &self.refs {
+            content.push_str(&format!("{} {}\n", packed_ref.oid, packed_ref.name));
+            if let Some(peeled) = packed_ref.peeled {
+                content.push_str(&format!("^{}\n", peeled));
+            }
+        }
+        lockfile.write_all(content.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let content = "# pack-refs with: peeled fully-peeled sorted
+0f46983e0baf73ba9bf82a7317223d2eebc728d8 refs/heads/main
+3b18e512dba79e4c8300dd08aeb37f8e728b8dad refs/tags/v1.0
+^0f46983e0baf73ba9bf82a7317223d2eebc728d8
+";
+        let packed_refs = PackedRefs::parse(content).unwrap();
+
+        let main = packed_refs.find("refs/heads/main").unwrap();
+        assert_eq!(
+            main.oid.to_string(),
+            "0f46983e0baf73ba9bf82a7317223d2eebc728d8"
+        );
+        assert_eq!(main.peeled, None);
+
+        let tag = packed_refs.find("refs/tags/v1.0").unwrap();
+        assert_eq!(tag.peeled, Some(main.oid));
+
+        assert!(packed_refs.find("refs/heads/other").is_none());
+        assert_eq!(packed_refs.iter().count(), 2);
+    }
+
+    #[test]
+    fn remove() {
+        let content = "# pack-refs with: peeled fully-peeled sorted \n\
+0f46983e0baf73ba9bf82a7317223d2eebc728d8 refs/heads/main
+3b18e512dba79e4c8300dd08aeb37f8e728b8dad refs/tags/v1.0
+^0f46983e0baf73ba9bf82a7317223d2eebc728d8
+";
+        let mut packed_refs = PackedRefs::parse(content).unwrap();
+        assert!(packed_refs.remove("refs/heads/main"));
+        assert!(!packed_refs.remove("refs/heads/main"));
+        assert!(packed_refs.find("refs/heads/main").is_none());
+
+        let path = test_utils::test_path!().join("packed-refs");
+        let mut lockfile = Lockfile::new(&path).unwrap();
+        packed_refs.write_to(&mut lockfile).unwrap();
+        lockfile.commit().unwrap();
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "# pack-refs with: peeled fully-peeled sorted \n\
+3b18e512dba79e4c8300dd08aeb37f8e728b8dad refs/tags/v1.0
+^0f46983e0baf73ba9bf82a7317223d2eebc728d8
+"
+        );
+    }
+
+    #[test]
+    fn peeled_line_without_ref() {
+        assert!(PackedRefs::parse("^0f46983e0baf73ba9bf82a7317223d2eebc728d8\n").is_err());
+    }
+}
@@ -23,7 +23,7 @@ pub(crate) fn parse_usize(bytes: &[u8]) -> Result<usize, ParseU64Error> {
         n = n
             .checked_mul(10)
             .and_then(|n| n.checked_add(digit))
-            .ok_or_else(|| ParseU64Error::NumberTooBig)?;
+            .ok_or(ParseU64Error::NumberTooBig)?;
     }
     Ok(n)
 }
@@ -0,0 +1,46 @@
+//! The order of paths in git: by their bytes, like `cache_name_compare`
+//!
+//! `Path` compares paths component by component, which puts `a/c` before `a-z` and `a.b`, as
+//! if `/` sorted before every other byte. git compares the bytes, so `a-z` < `a.b` < `a/c`.
+
+use std::{
+    cmp::Ordering,
+    path::{Path, PathBuf},
+};
+
+/// Compare two paths by their bytes
+pub(crate) fn compare_paths(a: &Path, b: &Path) -> Ordering {
+    a.as_os_str()
+        .as_encoded_bytes()
+        .cmp(b.as_os_str().as_encoded_bytes())
+}
+
+/// A path that sorts by its bytes, to key the maps that must be in git's order
+#[derive(Debug, Clone)]
+pub(crate) struct GitPath(pub PathBuf);
+
+impl From<&Path> for GitPath {
+    fn from(path: &Path) -> Self {
+        GitPath(path.to_path_buf())
+    }
+}
+
+impl PartialEq for GitPath {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for GitPath {}
+
+impl PartialOrd for GitPath {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for GitPath {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_paths(&self.0, &other.0)
+    }
+}
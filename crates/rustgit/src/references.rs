@@ -1,7 +1,17 @@
-///! Contains an abstraction to git references
+//! Contains an abstraction to git references
+use crate::config::ConfigError;
+use crate::lockfile::{Lockfile, LockfileError};
+use crate::object::{read_header, ObjectHeader, ObjectReadError, ObjectType, Tag};
 use crate::oid::{ObjectId, SHA1ValidationError};
+use crate::packed_refs::PackedRefs;
+use crate::refname::{is_valid_ref_name, ANY_REFERENCE};
 use crate::Repository;
-use std::{fs, io::ErrorKind};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    io::{ErrorKind, Read, Write},
+    path::Path,
+};
 
 /// A Ref is a variable that holds a single object identifier.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -13,6 +23,16 @@ pub enum Ref {
     Symbolic(String),
 }
 
+/// A reference together with its fully-qualified name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub name: String,
+    pub target: Ref,
+
+    /// The object an annotated tag peels to, when it is recorded in packed-refs
+    pub peeled: Option<ObjectId>,
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ReferenceError {
     #[error("Reference does not exist: {0}")]
@@ -22,22 +42,128 @@ pub enum ReferenceError {
 
     #[error("SHA1 Error")]
     SHA1Error(#[from] SHA1ValidationError),
+
+    #[error(transparent)]
+    LockfileError(#[from] LockfileError),
+
+    #[error("failed to read the object a reference points to")]
+    ObjectReadError(#[from] ObjectReadError),
+
+    #[error("malformed line in packed-refs: {0}")]
+    MalformedPackedRefs(String),
+
+    #[error(transparent)]
+    ConfigError(#[from] ConfigError),
+
+    #[error("cannot lock ref '{0}': reference already exists")]
+    AlreadyExists(String),
+
+    #[error("cannot lock ref '{0}': unable to resolve reference '{0}'")]
+    Unresolvable(String),
+
+    #[error("cannot lock ref '{name}': is at {actual} but expected {expected}")]
+    UnexpectedValue {
+        name: String,
+        actual: ObjectId,
+        expected: ObjectId,
+    },
+
+    #[error("cannot lock ref '{name}': '{existing}' exists; cannot create '{name}'")]
+    NameConflict { name: String, existing: String },
+
+    #[error("multiple updates for ref '{0}' not allowed")]
+    MultipleUpdates(String),
+
+    #[error("cannot process '{0}' and '{1}' at the same time")]
+    ConflictingUpdates(String, String),
+
+    #[error("refusing to update ref with bad name '{0}'")]
+    InvalidName(String),
 }
 
 type RefResult<T> = Result<T, ReferenceError>;
 
+// Like git, refuse to write a reference whose name is not valid, such as `../config`
+fn check_name(name: &str) -> RefResult<()> {
+    match is_valid_ref_name(name, ANY_REFERENCE) {
+        true => Ok(()),
+        false => Err(ReferenceError::InvalidName(name.to_string())),
+    }
+}
+
+// Recursively collect the names of the loose references inside a folder
+pub(crate) fn collect_loose_reference_names(
+    dir: &Path,
+    name_prefix: &str,
+    names: &mut Vec<String>,
+) -> RefResult<()> {
+    let entries = match fs::read_dir(dir) {
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        entries => entries?,
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let Some(file_name) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        let name = format!("{name_prefix}/{file_name}");
+
+        if entry.file_type()?.is_dir() {
+            collect_loose_reference_names(&entry.path(), &name, names)?;
+        } else if !file_name.ends_with(".lock") {
+            names.push(name);
+        }
+    }
+    Ok(())
+}
+
+/// Like git, remove the directories that deleting the reference `name` under `root` left
+/// empty, such as `refs/heads/feature/`, but keep `refs/heads/` itself. `root` is the git
+/// directory or the one of the reflogs
+pub(crate) fn remove_empty_directories(root: &Path, name: &str) {
+    for dir in Path::new(name).ancestors().skip(1) {
+        if dir.components().count() <= 2 || fs::remove_dir(root.join(dir)).is_err() {
+            break;
+        }
+    }
+}
+
 impl Repository {
     /// Given a name, trying to find the corresponding reference
     /// Returns None if no references exist
+    ///
+    /// Loose references under `.git/` take precedence over the ones in `.git/packed-refs`. Like
+    /// git, there is no reference with an invalid name
     pub fn try_find_reference(&self, name: &str) -> RefResult<Option<Ref>> {
+        if !is_valid_ref_name(name, ANY_REFERENCE) {
+            return Ok(None);
+        }
+        if let Some(reference) = self.try_find_loose_reference(name)? {
+            return Ok(Some(reference));
+        }
+
+        let packed_refs = PackedRefs::open(&self.git_dir.join("packed-refs"))?;
+        Ok(packed_refs
+            .find(name)
+            .map(|packed_ref| Ref::Peeled(packed_ref.oid)))
+    }
+
+    fn try_find_loose_reference(&self, name: &str) -> RefResult<Option<Ref>> {
+        if !is_valid_ref_name(name, ANY_REFERENCE) {
+            return Ok(None);
+        }
         let ref_path = self.git_dir.join(name);
-        let ref_content = match fs::read_to_string(ref_path) {
+        let ref_content = match fs::read_to_string(&ref_path) {
             Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            // `refs/heads/a` is a directory when there are branches such as `a/b`, and
+            // `refs/heads/a/b` goes through a file when `a` is a branch
+            Err(_) if !ref_path.is_file() => return Ok(None),
             ref_content => ref_content,
         }?;
 
-        let reference = if ref_content.starts_with("ref: ") {
-            Ref::Symbolic(ref_content[5..].trim().to_string())
+        let reference = if let Some(name) = ref_content.strip_prefix("ref: ") {
+            Ref::Symbolic(name.trim().to_string())
         } else {
             let oid = ObjectId::from_unvalidated_sh1_hex_string(ref_content.trim())?;
             Ref::Peeled(oid)
@@ -45,17 +171,393 @@ impl Repository {
         Ok(Some(reference))
     }
 
+    /// Returns all the references whose name starts with `prefix` (e.g. `refs/heads/`), sorted
+    /// by name. Use an empty prefix to get all references under `refs/`
+    pub fn references(&self, prefix: &str) -> RefResult<impl Iterator<Item = Reference>> {
+        let mut references = BTreeMap::new();
+
+        let packed_refs = PackedRefs::open(&self.git_dir.join("packed-refs"))?;
+        for packed_ref in packed_refs.iter() {
+            if packed_ref.name.starts_with(prefix) {
+                let reference = Reference {
+                    name: packed_ref.name.clone(),
+                    target: Ref::Peeled(packed_ref.oid),
+                    peeled: packed_ref.peeled,
+                };
+                references.insert(packed_ref.name.clone(), reference);
+            }
+        }
+
+        let mut loose_names = vec![];
+        collect_loose_reference_names(&self.git_dir.join("refs"), "refs", &mut loose_names)?;
+        for name in loose_names {
+            if !name.starts_with(prefix) {
+                continue;
+            }
+            // loose references override packed ones
+            if let Some(target) = self.try_find_loose_reference(&name)? {
+                let reference = Reference {
+                    name: name.clone(),
+                    target,
+                    peeled: None,
+                };
+                references.insert(name, reference);
+            }
+        }
+
+        Ok(references.into_values())
+    }
+
+    // The object a reference points to, `ObjectId::NULL` if it doesn't exist
+    fn reference_value(&self, name: &str) -> RefResult<ObjectId> {
+        match self.resolve_reference(&Ref::Symbolic(name.to_string())) {
+            Err(ReferenceError::NotExist(_)) => Ok(ObjectId::NULL),
+            result => result,
+        }
+    }
+
+    /// The name of the reference `name` ends up referring to once symbolic references are
+    /// followed, e.g. the current branch for HEAD. The reference doesn't have to exist
+    pub fn referent(&self, name: &str) -> RefResult<String> {
+        let mut name = name.to_string();
+        // like git, give up on cycles of symbolic references
+        for _ in 0..5 {
+            match self.try_find_reference(&name)? {
+                Some(Ref::Symbolic(target)) => name = target,
+                _ => break,
+            }
+        }
+        Ok(name)
+    }
+
+    /// Start a transaction, whose updates are recorded with `message` in the reflogs
+    pub fn transaction(&self, message: &str) -> RefTransaction<'_> {
+        RefTransaction {
+            repository: self,
+            updates: vec![],
+            message: message.to_string(),
+        }
+    }
+
+    /// Point a reference to an object, creating it if it doesn't exist, and record the update
+    /// with `message` in its reflog. Like git, an update of the branch HEAD refers to is also
+    /// recorded in the reflog of HEAD
+    pub fn update_reference(&self, name: &str, oid: ObjectId, message: &str) -> RefResult<()> {
+        let mut transaction = self.transaction(message);
+        transaction.update(name, oid, None);
+        transaction.commit()
+    }
+
+    /// Like `update_reference`, but the reflogs record `old` as the previous value, e.g. for a
+    /// renamed reference
+    pub(crate) fn write_reference(
+        &self,
+        name: &str,
+        oid: ObjectId,
+        old: ObjectId,
+        message: &str,
+    ) -> RefResult<()> {
+        check_name(name)?;
+        let mut lockfile = Lockfile::new(&self.git_dir.join(name))?;
+        writeln!(lockfile, "{}", oid)?;
+        lockfile.commit()?;
+        self.log_reference_update(name, old, oid, message)
+    }
+
+    // Record an update in the reflog of the reference, and in the one of HEAD if it refers to it
+    fn log_reference_update(
+        &self,
+        name: &str,
+        old: ObjectId,
+        new: ObjectId,
+        message: &str,
+    ) -> RefResult<()> {
+        self.append_reflog(name, old, new, message)?;
+        if name != "HEAD"
+            && self.try_find_loose_reference("HEAD")? == Some(Ref::Symbolic(name.to_string()))
+        {
+            self.append_reflog("HEAD", old, new, message)?;
+        }
+        Ok(())
+    }
+
+    // Like git, a reference can't be created next to a reference that is one of its folders,
+    // or where there is a folder of references
+    fn check_name_conflicts(&self, name: &str) -> RefResult<()> {
+        let conflict = |existing: String| ReferenceError::NameConflict {
+            name: name.to_string(),
+            existing,
+        };
+
+        for parent in Path::new(name).ancestors().skip(1) {
+            let Some(parent) = parent.to_str().filter(|parent| !parent.is_empty()) else {
+                continue;
+            };
+            if self.try_find_reference(parent)?.is_some() {
+                return Err(conflict(parent.to_string()));
+            }
+        }
+
+        match self.references(&format!("{}/", name))?.next() {
+            Some(child) => Err(conflict(child.name)),
+            None => Ok(()),
+        }
+    }
+
+    /// Delete a reference, both its loose file and its entry in `.git/packed-refs`, but not its
+    /// reflog. Returns whether it existed
+    pub(crate) fn remove_reference(&self, name: &str) -> RefResult<bool> {
+        check_name(name)?;
+        let ref_path = self.git_dir.join(name);
+        let ref_lock = Lockfile::new(&ref_path)?;
+        let loose = match fs::remove_file(&ref_path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => false,
+            result => result.map(|_| true)?,
+        };
+
+        let packed_refs_path = self.git_dir.join("packed-refs");
+        let mut packed_refs_lock = Lockfile::new(&packed_refs_path)?;
+        let mut packed_refs = PackedRefs::open(&packed_refs_path)?;
+        let packed = packed_refs.remove(name);
+        if packed {
+            packed_refs.write_to(&mut packed_refs_lock)?;
+            packed_refs_lock.commit()?;
+        }
+        drop(ref_lock);
+
+        remove_empty_directories(&self.git_dir, name);
+        Ok(loose || packed)
+    }
+
+    /// Delete a reference with its reflog. Returns whether it existed
+    pub fn delete_reference(&self, name: &str) -> RefResult<bool> {
+        let existed = self.remove_reference(name)?;
+        self.delete_reflog(name)?;
+        Ok(existed)
+    }
+
+    /// Make a symbolic reference, usually HEAD, refer to another reference, and record the
+    /// change of the object it points to with `message` in its reflog. Nothing is recorded
+    /// while the other reference doesn't exist yet
+    pub fn update_symbolic_reference(
+        &self,
+        name: &str,
+        target: &str,
+        message: &str,
+    ) -> RefResult<()> {
+        check_name(name)?;
+        check_name(target)?;
+        let mut lockfile = Lockfile::new(&self.git_dir.join(name))?;
+        let old = self.reference_value(name)?;
+        writeln!(lockfile, "ref: {}", target)?;
+        lockfile.commit()?;
+
+        let new = self.reference_value(target)?;
+        if !new.is_null() {
+            self.append_reflog(name, old, new, message)?;
+        }
+        Ok(())
+    }
+
     /// Given a reference, recursively try to find the underlying object id
+    ///
+    /// Annotated tags are peeled until reaching an object that is not a tag
     pub fn peel_reference(&self, reference: &Ref) -> RefResult<ObjectId> {
+        self.peel_tags(self.resolve_reference(reference)?)
+    }
+
+    /// Follow symbolic references until reaching an object id. Unlike `peel_reference`,
+    /// annotated tags are not peeled
+    pub fn resolve_reference(&self, reference: &Ref) -> RefResult<ObjectId> {
         match reference {
             Ref::Peeled(oid) => Ok(*oid),
             Ref::Symbolic(name) => {
                 let inner = self.try_find_reference(name)?;
                 match inner {
-                    Some(inner) => self.peel_reference(&inner),
+                    Some(inner) => self.resolve_reference(&inner),
                     None => Err(ReferenceError::NotExist(name.clone())),
                 }
             }
         }
     }
+
+    /// Follow annotated tags until reaching an object that is not a tag
+    pub fn peel_tags(&self, mut oid: ObjectId) -> RefResult<ObjectId> {
+        loop {
+            let mut reader = self.object_reader(oid)?;
+            let ObjectHeader { typ, size } = read_header(&mut reader)?;
+            if typ != ObjectType::Tag {
+                return Ok(oid);
+            }
+
+            let mut content = Vec::with_capacity(size);
+            reader.read_to_end(&mut content)?;
+            oid = Tag::parse(&content)?.object();
+        }
+    }
+}
+
+/// What a transaction does to a reference
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RefChange {
+    Update(ObjectId),
+    Delete,
+    Verify,
+}
+
+#[derive(Debug)]
+struct RefUpdate {
+    name: String,
+    change: RefChange,
+
+    /// The value the reference must have beforehand, `ObjectId::NULL` if it must not exist
+    expected: Option<ObjectId>,
+}
+
+/// Updates of several references that are applied all together or not at all
+///
+/// Every reference is locked and checked against its expected value before any of them is
+/// changed. If a check fails, the locks are released and nothing is changed
+pub struct RefTransaction<'a> {
+    repository: &'a Repository,
+    updates: Vec<RefUpdate>,
+    message: String,
+}
+
+impl RefTransaction<'_> {
+    fn push(&mut self, name: &str, change: RefChange, expected: Option<ObjectId>) -> &mut Self {
+        self.updates.push(RefUpdate {
+            name: name.to_string(),
+            change,
+            expected,
+        });
+        self
+    }
+
+    /// Point a reference to `new`, provided that it holds `expected` if given
+    pub fn update(&mut self, name: &str, new: ObjectId, expected: Option<ObjectId>) -> &mut Self {
+        self.push(name, RefChange::Update(new), expected)
+    }
+
+    /// Create a reference that must not exist yet
+    pub fn create(&mut self, name: &str, new: ObjectId) -> &mut Self {
+        self.push(name, RefChange::Update(new), Some(ObjectId::NULL))
+    }
+
+    /// Delete a reference and its reflog, provided that it holds `expected` if given
+    pub fn delete(&mut self, name: &str, expected: Option<ObjectId>) -> &mut Self {
+        self.push(name, RefChange::Delete, expected)
+    }
+
+    /// Check that a reference holds `expected` without changing it. `ObjectId::NULL` checks that
+    /// it doesn't exist
+    pub fn verify(&mut self, name: &str, expected: ObjectId) -> &mut Self {
+        self.push(name, RefChange::Verify, Some(expected))
+    }
+
+    // Check that a locked reference holds its expected value, and return its current value
+    fn check(&self, update: &RefUpdate) -> RefResult<ObjectId> {
+        let actual = self.repository.reference_value(&update.name)?;
+        let name = update.name.clone();
+        match update.expected {
+            Some(expected) if expected == actual => Ok(actual),
+            Some(expected) if expected.is_null() => Err(ReferenceError::AlreadyExists(name)),
+            Some(_) if actual.is_null() => Err(ReferenceError::Unresolvable(name)),
+            Some(expected) => Err(ReferenceError::UnexpectedValue {
+                name,
+                actual,
+                expected,
+            }),
+            // like git, deleting a missing reference is not an error
+            None => Ok(actual),
+        }
+    }
+
+    /// Apply the updates
+    pub fn commit(self) -> RefResult<()> {
+        let repository = self.repository;
+
+        let mut names = HashSet::new();
+        for update in &self.updates {
+            check_name(&update.name)?;
+            if !names.insert(update.name.as_str()) {
+                return Err(ReferenceError::MultipleUpdates(update.name.clone()));
+            }
+        }
+        // one reference would be a folder of the other
+        for update in &self.updates {
+            for parent in Path::new(&update.name).ancestors().skip(1) {
+                if let Some(parent) = parent.to_str().filter(|parent| names.contains(parent)) {
+                    let (parent, name) = (parent.to_string(), update.name.clone());
+                    return Err(ReferenceError::ConflictingUpdates(parent, name));
+                }
+            }
+        }
+        let deleted: HashSet<&str> = self
+            .updates
+            .iter()
+            .filter(|update| update.change == RefChange::Delete)
+            .map(|update| update.name.as_str())
+            .collect();
+
+        // lock everything, then check the values while nobody else can change them
+        let mut locks = vec![];
+        for update in &self.updates {
+            if let RefChange::Update(_) = update.change {
+                repository.check_name_conflicts(&update.name)?;
+            }
+            locks.push(Lockfile::new(&repository.git_dir.join(&update.name))?);
+        }
+        let packed_refs_path = repository.git_dir.join("packed-refs");
+        let packed_refs_lock = match deleted.is_empty() {
+            true => None,
+            false => Some(Lockfile::new(&packed_refs_path)?),
+        };
+        let mut olds = vec![];
+        for update in &self.updates {
+            olds.push(self.check(update)?);
+        }
+
+        // write every new value before any of them replaces the current one
+        for (update, lock) in self.updates.iter().zip(&mut locks) {
+            if let RefChange::Update(new) = update.change {
+                writeln!(lock, "{}", new)?;
+            }
+        }
+
+        // the deleted references disappear from packed-refs before their loose files, so that
+        // their packed values never show up again
+        if let Some(mut lock) = packed_refs_lock {
+            let mut packed_refs = PackedRefs::open(&packed_refs_path)?;
+            let mut changed = false;
+            for name in &deleted {
+                changed |= packed_refs.remove(name);
+            }
+            if changed {
+                packed_refs.write_to(&mut lock)?;
+                lock.commit()?;
+            }
+        }
+
+        for ((update, lock), old) in self.updates.iter().zip(locks).zip(olds) {
+            let name = &update.name;
+            match update.change {
+                RefChange::Update(new) => {
+                    lock.commit()?;
+                    repository.log_reference_update(name, old, new, &self.message)?;
+                }
+                RefChange::Delete => {
+                    match fs::remove_file(repository.git_dir.join(name)) {
+                        Err(e) if e.kind() == ErrorKind::NotFound => {}
+                        result => result?,
+                    }
+                    drop(lock);
+                    remove_empty_directories(&repository.git_dir, name);
+                    repository.delete_reflog(name)?;
+                }
+                RefChange::Verify => {}
+            }
+        }
+        Ok(())
+    }
 }
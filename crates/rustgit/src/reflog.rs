@@ -0,0 +1,308 @@
+//! Record the successive values of references in `.git/logs/`, so that they can be brought back
+//!
+//! Like git, updates are logged for HEAD and the branches, remote-tracking branches and notes
+//! unless `core.logAllRefUpdates` is false, for every reference under `refs/` when it is
+//! `always`, and for any reference whose reflog already exists.
+
+use crate::config::{parse_bool, ConfigError};
+use crate::lockfile::{Lockfile, LockfileError};
+use crate::object::{get_committer, Author};
+use crate::oid::ObjectId;
+use crate::references::{collect_loose_reference_names, remove_empty_directories, ReferenceError};
+use crate::refname::{is_valid_ref_name, ANY_REFERENCE};
+use crate::Repository;
+use chrono::{DateTime, FixedOffset, Local};
+use std::{
+    fmt::{Display, Formatter},
+    fs,
+    io::{self, ErrorKind, Write},
+    path::PathBuf,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReflogError {
+    #[error(transparent)]
+    LockfileError(#[from] LockfileError),
+
+    #[error("IO Error")]
+    IOError(#[from] io::Error),
+}
+
+/// An update of a reference, as recorded in its reflog
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReflogEntry {
+    /// The previous value, `ObjectId::NULL` when the reference was created
+    pub old: ObjectId,
+    pub new: ObjectId,
+
+    /// Who updated the reference, and when
+    pub committer: Author,
+    pub message: String,
+}
+
+impl ReflogEntry {
+    /// Parse a line of a reflog: `<old> <new> <committer>\t<message>`
+    pub fn parse(line: &str) -> Option<ReflogEntry> {
+        let (old, rest) = line.split_once(' ')?;
+        let (new, rest) = rest.split_once(' ')?;
+        let (committer, message) = rest.split_once('\t').unwrap_or((rest, ""));
+        Some(ReflogEntry {
+            old: ObjectId::from_unvalidated_sh1_hex_string(old).ok()?,
+            new: ObjectId::from_unvalidated_sh1_hex_string(new).ok()?,
+            committer: Author::parse(committer)?,
+            message: message.to_string(),
+        })
+    }
+}
+
+impl Display for ReflogEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {}\t{}",
+            self.old, self.new, self.committer, self.message
+        )
+    }
+}
+
+// An entry of a reflog as read from the file, with or without its newline. A message that is not
+// UTF-8 is read lossily rather than making the entry unreadable
+fn parse_line(line: &[u8]) -> Option<ReflogEntry> {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    ReflogEntry::parse(&String::from_utf8_lossy(line))
+}
+
+// Like git, a message is kept on a single line
+fn normalize_message(message: &str) -> String {
+    message.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+impl Repository {
+    pub(crate) fn reflog_path(&self, name: &str) -> PathBuf {
+        self.git_dir.join("logs").join(name)
+    }
+
+    /// Whether updates of a reference are recorded
+    pub fn reflog_exists(&self, name: &str) -> bool {
+        is_valid_ref_name(name, ANY_REFERENCE) && self.reflog_path(name).is_file()
+    }
+
+    // The lines of a reflog with their newlines, none if there is no reflog
+    fn reflog_lines(&self, name: &str) -> io::Result<Vec<Vec<u8>>> {
+        if !is_valid_ref_name(name, ANY_REFERENCE) {
+            return Ok(vec![]);
+        }
+        let content = match fs::read(self.reflog_path(name)) {
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            content => content?,
+        };
+        Ok(content
+            .split_inclusive(|&byte| byte == b'\n')
+            .map(<[u8]>::to_vec)
+            .collect())
+    }
+
+    /// The entries of the reflog of a reference, oldest first. Empty if there is no reflog.
+    /// Malformed lines are skipped, and messages that are not UTF-8 are read lossily
+    pub fn reflog(&self, name: &str) -> io::Result<Vec<ReflogEntry>> {
+        Ok(self
+            .reflog_lines(name)?
+            .iter()
+            .filter_map(|line| parse_line(line))
+            .collect())
+    }
+
+    /// The references that have a reflog, HEAD first then sorted by name
+    pub fn reflog_names(&self) -> Result<Vec<String>, ReferenceError> {
+        let mut names = vec![];
+        collect_loose_reference_names(&self.git_dir.join("logs/refs"), "refs", &mut names)?;
+        names.sort();
+        if self.reflog_exists("HEAD") {
+            names.insert(0, "HEAD".to_string());
+        }
+        Ok(names)
+    }
+
+    /// The branch, or the commit of a detached HEAD, that the `n`-th last checkout moved from,
+    /// as in `@{-n}`. None if there weren't that many checkouts
+    pub fn previous_branch(&self, n: usize) -> io::Result<Option<String>> {
+        let reflog = self.reflog("HEAD")?;
+        let mut checkouts = reflog.iter().rev().filter_map(|entry| {
+            let (from, _) = entry
+                .message
+                .strip_prefix("checkout: moving from ")?
+                .split_once(" to ")?;
+            Some(from.to_string())
+        });
+        Ok(n.checked_sub(1).and_then(|n| checkouts.nth(n)))
+    }
+
+    fn should_write_reflog(&self, name: &str) -> Result<bool, ConfigError> {
+        if self.reflog_exists(name) {
+            return Ok(true);
+        }
+        let config = self.config()?;
+        let log_all = config.get("core.logAllRefUpdates");
+        if log_all.is_some_and(|value| value.eq_ignore_ascii_case("always")) {
+            return Ok(name == "HEAD" || name.starts_with("refs/"));
+        }
+        if log_all.and_then(|value| parse_bool(Some(value))) == Some(false) {
+            return Ok(false);
+        }
+        Ok(name == "HEAD"
+            || ["refs/heads/", "refs/remotes/", "refs/notes/"]
+                .iter()
+                .any(|prefix| name.starts_with(prefix)))
+    }
+
+    /// Record the update of a reference in its reflog, if its updates are logged
+    pub(crate) fn append_reflog(
+        &self,
+        name: &str,
+        old: ObjectId,
+        new: ObjectId,
+        message: &str,
+    ) -> Result<(), ReferenceError> {
+        if !self.should_write_reflog(name)? {
+            return Ok(());
+        }
+
+        // like git, a missing identity doesn't prevent recording the update
+        let committer = match get_committer(&self.config()?) {
+            Err(ConfigError::IdentityUnknown) => Author {
+                name: String::new(),
+                email: String::new(),
+                time: Local::now().fixed_offset(),
+            },
+            committer => committer?,
+        };
+        let entry = ReflogEntry {
+            old,
+            new,
+            committer,
+            message: normalize_message(message),
+        };
+
+        let path = self.reflog_path(name);
+        fs::create_dir_all(path.parent().unwrap())?;
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        writeln!(file, "{}", entry)?;
+        Ok(())
+    }
+
+    // Rewrite a reflog without the entries that `remove` is true for, given each entry and its
+    // position from the newest one, and return how many were removed. The other lines are kept
+    // byte for byte, malformed ones included
+    fn rewrite_reflog(
+        &self,
+        name: &str,
+        remove: impl Fn(&ReflogEntry, usize) -> bool,
+    ) -> Result<usize, ReflogError> {
+        if !is_valid_ref_name(name, ANY_REFERENCE) {
+            return Ok(0);
+        }
+        let mut lockfile = Lockfile::new(&self.reflog_path(name))?;
+        let lines = self.reflog_lines(name)?;
+        let entries: Vec<Option<ReflogEntry>> = lines.iter().map(|line| parse_line(line)).collect();
+        let mut position = entries.iter().flatten().count();
+        let mut removed = 0;
+        let mut content = vec![];
+        for (line, entry) in lines.iter().zip(&entries) {
+            if let Some(entry) = entry {
+                position -= 1;
+                if remove(entry, position) {
+                    removed += 1;
+                    continue;
+                }
+            }
+            content.extend_from_slice(line);
+        }
+        if removed == 0 {
+            return Ok(0);
+        }
+
+        lockfile.write_all(&content)?;
+        lockfile.commit()?;
+        Ok(removed)
+    }
+
+    /// Remove the entries of a reflog older than `expire`, and return how many were removed
+    pub fn expire_reflog(
+        &self,
+        name: &str,
+        expire: DateTime<FixedOffset>,
+    ) -> Result<usize, ReflogError> {
+        self.rewrite_reflog(name, |entry, _| entry.committer.time < expire)
+    }
+
+    /// Remove the `n`-th entry of a reflog, counting from the newest one like `<name>@{n}`.
+    /// Returns whether it existed
+    pub fn delete_reflog_entry(&self, name: &str, n: usize) -> Result<bool, ReflogError> {
+        let removed = self.rewrite_reflog(name, |_, position| position == n)?;
+        Ok(removed > 0)
+    }
+
+    /// Delete the reflog of a reference, along with the directories it leaves empty
+    pub(crate) fn delete_reflog(&self, name: &str) -> io::Result<()> {
+        let path = self.reflog_path(name);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            // the reflog of `a/b` goes through a file when `a` has one
+            Err(_) if !path.is_file() => {}
+            result => result?,
+        }
+        remove_empty_directories(&self.git_dir.join("logs"), name);
+        Ok(())
+    }
+
+    /// Move the reflog of a renamed reference. It goes through a temporary file, as `a` can
+    /// become `a/b` and the other way around
+    pub(crate) fn rename_reflog(&self, old_name: &str, new_name: &str) -> io::Result<()> {
+        let logs_dir = self.git_dir.join("logs");
+        let reflog = self.reflog_path(old_name);
+        if !reflog.exists() {
+            return Ok(());
+        }
+        let temporary = logs_dir.join("refs/.tmp-renamed-log");
+        fs::rename(reflog, &temporary)?;
+        remove_empty_directories(&logs_dir, old_name);
+
+        let new_reflog = self.reflog_path(new_name);
+        fs::create_dir_all(new_reflog.parent().unwrap())?;
+        fs::rename(temporary, new_reflog)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let line = "0000000000000000000000000000000000000000 \
+                    8ab686eafeb1f44702738c8b0f24f2567c36da6d \
+                    A U Thor <author@example.com> 1112911993 -0700\tcommit (initial): first";
+        let entry = ReflogEntry::parse(line).unwrap();
+        assert!(entry.old.is_null());
+        assert_eq!(
+            entry.new.to_string(),
+            "8ab686eafeb1f44702738c8b0f24f2567c36da6d"
+        );
+        assert_eq!(entry.committer.email, "author@example.com");
+        assert_eq!(entry.message, "commit (initial): first");
+        assert_eq!(entry.to_string(), line);
+
+        assert_eq!(ReflogEntry::parse("not a reflog entry"), None);
+    }
+
+    #[test]
+    fn message() {
+        assert_eq!(
+            normalize_message("commit: subject\n\nbody "),
+            "commit: subject body"
+        );
+    }
+}
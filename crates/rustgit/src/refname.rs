@@ -0,0 +1,193 @@
+//! The rules of git's `check-ref-format` for the names of references
+//!
+//! A name is made of components separated by slashes. No component can be empty, start with a
+//! `.` or end with `.lock`, and the name can't contain `..`, `@{`, control characters, spaces or
+//! any of `~^:?*[\`. It can't end with a `.` or be the single character `@` either.
+
+use crate::wildmatch::wildmatch;
+
+/// Relaxations of the rules
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct RefNameOptions {
+    /// Accept a name with a single component, such as `HEAD`
+    pub allow_onelevel: bool,
+
+    /// Accept a single `*`, as in the patterns of refspecs
+    pub refspec_pattern: bool,
+}
+
+/// The options used to check the references that are read or written, which can be one-level
+/// names like `HEAD` or `ORIG_HEAD`
+pub(crate) const ANY_REFERENCE: RefNameOptions = RefNameOptions {
+    allow_onelevel: true,
+    refspec_pattern: false,
+};
+
+// Check a component and return its length, None if it is invalid. `star_allowed` is cleared
+// once a `*` has been used
+fn component_length(name: &[u8], star_allowed: &mut bool) -> Option<usize> {
+    let mut last = 0u8;
+    let mut length = 0;
+    for &c in name {
+        match c {
+            b'/' => break,
+            b'.' if last == b'.' => return None,
+            b'{' if last == b'@' => return None,
+            b'*' if *star_allowed => *star_allowed = false,
+            b'*' | b'~' | b'^' | b':' | b'?' | b'[' | b'\\' | b' ' | 0x7f => return None,
+            c if c < 0x20 => return None,
+            _ => {}
+        }
+        last = c;
+        length += 1;
+    }
+
+    let component = &name[..length];
+    if component.is_empty() || component[0] == b'.' || component.ends_with(b".lock") {
+        return None;
+    }
+    Some(length)
+}
+
+/// Whether `name` is a valid reference name, like `git check-ref-format`
+pub fn is_valid_ref_name(name: &str, options: RefNameOptions) -> bool {
+    if name == "@" || name.ends_with('.') {
+        return false;
+    }
+
+    let mut star_allowed = options.refspec_pattern;
+    let mut rest = name.as_bytes();
+    let mut component_count = 0;
+    loop {
+        let Some(length) = component_length(rest, &mut star_allowed) else {
+            return false;
+        };
+        component_count += 1;
+        match rest.get(length) {
+            Some(_) => rest = &rest[length + 1..],
+            None => break,
+        }
+    }
+    options.allow_onelevel || component_count > 1
+}
+
+/// Remove the leading slashes of a name and collapse its consecutive ones, like
+/// `git check-ref-format --normalize` does before checking it
+pub fn normalize_ref_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.trim_start_matches('/').chars() {
+        if c != '/' || !normalized.ends_with('/') {
+            normalized.push(c);
+        }
+    }
+    normalized
+}
+
+/// Whether `name` can be the name of a branch, `refs/heads/<name>`
+pub fn is_valid_branch_name(name: &str) -> bool {
+    !name.starts_with('-')
+        && name != "HEAD"
+        && is_valid_ref_name(&format!("refs/heads/{name}"), RefNameOptions::default())
+}
+
+/// Whether a reference is selected by a pattern of `for-each-ref`, which is either a glob such
+/// as `refs/tags/v1.*` or a name matching whole components, e.g. `refs/heads` selects
+/// `refs/heads/main` but `refs/heads/ma` doesn't
+pub fn matches_ref_pattern(name: &str, pattern: &str) -> bool {
+    let prefix_matches = name
+        .strip_prefix(pattern)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/') || pattern.ends_with('/'));
+    prefix_matches || wildmatch(pattern.as_bytes(), name.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ref_names() {
+        let options = RefNameOptions::default();
+        for name in [
+            "refs/heads/main",
+            "refs/tags/v1.0",
+            "refs/heads/feature/a-b_c",
+            "heads/a.b",
+            "refs/heads/@",
+            "refs/heads/a@b",
+        ] {
+            assert!(is_valid_ref_name(name, options), "{}", name);
+        }
+
+        for name in [
+            "main",
+            "@",
+            "",
+            "refs/heads/a..b",
+            "../../etc/passwd",
+            "/refs/heads/main",
+            "refs/heads/main/",
+            "refs//heads/main",
+            "refs/heads/.hidden",
+            "refs/heads/main.lock",
+            "refs/heads/main.",
+            "refs/heads/a@{1}",
+            "refs/heads/a b",
+            "refs/heads/a\tb",
+            "refs/heads/a~1",
+            "refs/heads/a^",
+            "refs/heads/a:b",
+            "refs/heads/a?",
+            "refs/heads/a*",
+            "refs/heads/[a]",
+            "refs/heads/a\\b",
+            "refs/heads/a\u{7f}",
+        ] {
+            assert!(!is_valid_ref_name(name, options), "{}", name);
+        }
+    }
+
+    #[test]
+    fn options() {
+        let onelevel = RefNameOptions {
+            allow_onelevel: true,
+            ..Default::default()
+        };
+        assert!(is_valid_ref_name("HEAD", onelevel));
+        assert!(!is_valid_ref_name("..", onelevel));
+
+        let pattern = RefNameOptions {
+            refspec_pattern: true,
+            ..Default::default()
+        };
+        assert!(is_valid_ref_name("refs/heads/*", pattern));
+        assert!(is_valid_ref_name("refs/heads/feature-*", pattern));
+        assert!(!is_valid_ref_name("refs/*/*", pattern));
+    }
+
+    #[test]
+    fn normalize() {
+        assert_eq!(normalize_ref_name("//refs//heads/main"), "refs/heads/main");
+        // a trailing slash is kept, and makes the name invalid
+        assert_eq!(normalize_ref_name("refs/heads/main/"), "refs/heads/main/");
+    }
+
+    #[test]
+    fn patterns() {
+        assert!(matches_ref_pattern("refs/heads/main", "refs/heads"));
+        assert!(matches_ref_pattern("refs/heads/main", "refs/heads/"));
+        assert!(matches_ref_pattern("refs/heads/main", "refs/heads/main"));
+        assert!(!matches_ref_pattern("refs/heads/main", "refs/heads/ma"));
+        assert!(matches_ref_pattern("refs/tags/v1.0", "refs/tags/v1.*"));
+        assert!(!matches_ref_pattern("refs/heads/a/b", "refs/heads/*"));
+        assert!(matches_ref_pattern("refs/heads/a/b", "refs/heads/**"));
+    }
+
+    #[test]
+    fn branch_names() {
+        assert!(is_valid_branch_name("main"));
+        assert!(is_valid_branch_name("feature/a"));
+        assert!(!is_valid_branch_name("-x"));
+        assert!(!is_valid_branch_name("HEAD"));
+        assert!(!is_valid_branch_name("a..b"));
+    }
+}
@@ -19,13 +19,17 @@ pub enum RepositorySearchError {
 }
 
 impl Repository {
-    /// Creates a new Git repository in the given folder.
-    pub fn init(path: &Path) -> std::io::Result<Repository> {
+    /// Creates a new Git repository in the given folder. HEAD refers to `initial_branch`, which
+    /// is created by the first commit
+    pub fn init(path: &Path, initial_branch: &str) -> std::io::Result<Repository> {
         let git_dir = path.join(".git");
         fs::create_dir(&git_dir)?;
-        fs::create_dir(&git_dir.join("objects"))?;
-        fs::create_dir(&git_dir.join("refs"))?;
-        fs::write(&git_dir.join("HEAD"), "ref: refs/heads/main\n")?;
+        fs::create_dir(git_dir.join("objects"))?;
+        fs::create_dir(git_dir.join("refs"))?;
+        fs::write(
+            git_dir.join("HEAD"),
+            format!("ref: refs/heads/{initial_branch}\n"),
+        )?;
 
         Ok(Self::open(path.to_path_buf(), git_dir))
     }
@@ -0,0 +1,204 @@
+//! Move HEAD to another commit, optionally resetting the index and the working tree to it
+
+use crate::checkout::CheckoutError;
+use crate::config::ConfigError;
+use crate::diff::DiffError;
+use crate::index::{EntryMetadata, Index, IndexReadError};
+use crate::lockfile::{Lockfile, LockfileError};
+use crate::object::{hash_blob_file, ObjectReadError, TreeEntry};
+use crate::oid::ObjectId;
+use crate::references::ReferenceError;
+use crate::Repository;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io,
+    path::{Path, PathBuf},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ResetError {
+    #[error(transparent)]
+    ObjectReadError(#[from] ObjectReadError),
+
+    #[error(transparent)]
+    IndexReadError(#[from] IndexReadError),
+
+    #[error(transparent)]
+    ReferenceError(#[from] ReferenceError),
+
+    #[error(transparent)]
+    DiffError(#[from] DiffError),
+
+    #[error(transparent)]
+    LockfileError(#[from] LockfileError),
+
+    #[error(transparent)]
+    CheckoutError(#[from] CheckoutError),
+
+    #[error(transparent)]
+    ConfigError(#[from] ConfigError),
+
+    #[error("IO Error")]
+    IOError(#[from] io::Error),
+
+    #[error("Cannot do a soft reset in the middle of a merge.")]
+    SoftResetDuringMerge,
+}
+
+/// What is reset along with HEAD
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ResetMode {
+    /// Only move HEAD, or the branch it refers to
+    Soft,
+
+    /// Also reset the index, keeping the working tree
+    #[default]
+    Mixed,
+
+    /// Also reset the index and the working tree, discarding the local changes
+    Hard,
+}
+
+impl Repository {
+    // The index entry of a version of a file. Like git refreshing the index after a reset, the
+    // entry gets the stat data of the file of the working tree if it matches
+    fn index_metadata(&self, path: &Path, entry: &TreeEntry) -> io::Result<EntryMetadata> {
+        let full_path = self.repository_dir.join(path);
+        if full_path.is_file() {
+            let metadata = EntryMetadata::from_path(&full_path)?;
+            if metadata.mode == entry.mode && hash_blob_file(&full_path)? == entry.oid {
+                return Ok(metadata);
+            }
+        }
+        Ok(EntryMetadata {
+            mode: entry.mode,
+            ..EntryMetadata::default()
+        })
+    }
+
+    // Make the index entries of the `paths` match the `target` files, leaving the working tree
+    // alone
+    fn reset_index_paths<'a>(
+        &self,
+        index: &mut Index,
+        paths: impl IntoIterator<Item = &'a PathBuf>,
+        target: &BTreeMap<PathBuf, TreeEntry>,
+    ) -> io::Result<()> {
+        for path in paths {
+            let entry = target.get(path);
+            let staged = index
+                .get(path, 0)
+                .map(|staged| (staged.oid, staged.metadata.mode));
+            let conflicted = (1..=3).any(|stage| index.get(path, stage).is_some());
+            if !conflicted && staged == entry.map(|entry| (entry.oid, entry.mode)) {
+                continue;
+            }
+
+            index.remove(path);
+            if let Some(entry) = entry {
+                let metadata = self.index_metadata(path, entry)?;
+                index.add(path.clone(), entry.oid, metadata);
+            }
+        }
+        Ok(())
+    }
+
+    /// Point HEAD, or the branch it refers to, to a commit, remembering the previous one as
+    /// `ORIG_HEAD`. Depending on the `mode`, the index and the working tree are also reset to
+    /// the tree of the commit. A merge in progress is forgotten, and `message` is recorded in
+    /// the reflogs
+    ///
+    /// Without a commit, HEAD stays on its unborn branch and the index and the working tree are
+    /// reset to an empty tree
+    pub fn reset(
+        &self,
+        commit: Option<ObjectId>,
+        mode: ResetMode,
+        message: &str,
+    ) -> Result<(), ResetError> {
+        let index_path = self.index_path();
+        let mut index_lock = Lockfile::new(&index_path)?;
+        let mut index = Index::open(&index_path)?;
+
+        if mode == ResetMode::Soft
+            && (self.merge_head()?.is_some() || index.conflicts().next().is_some())
+        {
+            return Err(ResetError::SoftResetDuringMerge);
+        }
+
+        if mode != ResetMode::Soft {
+            let tree = match commit {
+                Some(commit) => Some(self.read_commit(commit)?.tree()),
+                None => None,
+            };
+            let target = self.tree_entries(tree)?;
+            let mut paths: BTreeSet<PathBuf> = target.keys().cloned().collect();
+            paths.extend(index.iter().map(|entry| entry.path.to_path_buf()));
+
+            if mode == ResetMode::Hard {
+                let mut changed: BTreeSet<PathBuf> = self
+                    .diff_index_to_worktree()?
+                    .into_iter()
+                    .map(|change| change.path)
+                    .collect();
+                changed.extend(
+                    index
+                        .conflicts()
+                        .map(|conflict| conflict.path.to_path_buf()),
+                );
+                let symlinks = self.has_symlinks()?;
+                for path in &paths {
+                    let staged = index
+                        .get(path, 0)
+                        .map(|entry| (entry.oid, entry.metadata.mode));
+                    let entry = target.get(path);
+                    if changed.contains(path)
+                        || staged != entry.map(|entry| (entry.oid, entry.mode))
+                    {
+                        self.checkout_path(&mut index, path, entry, symlinks)?;
+                    }
+                }
+            } else {
+                self.reset_index_paths(&mut index, &paths, &target)?;
+            }
+            index.write_to(&mut index_lock)?;
+            index_lock.commit()?;
+        }
+
+        if let Some(commit) = commit {
+            if !self.head()?.is_unborn() {
+                self.update_reference("ORIG_HEAD", self.head_id()?, "")?;
+            }
+            self.update_head(commit, message)?;
+        }
+        self.clear_merge_state()?;
+        Ok(())
+    }
+
+    /// Reset the index entries of the files in the `paths` to their version in a commit, HEAD
+    /// and the working tree being left alone. Files that are not in the commit, or all of them
+    /// if there is no commit, are removed from the index
+    pub fn reset_paths(
+        &self,
+        commit: Option<ObjectId>,
+        paths: &[PathBuf],
+    ) -> Result<(), ResetError> {
+        let index_path = self.index_path();
+        let mut index_lock = Lockfile::new(&index_path)?;
+        let mut index = Index::open(&index_path)?;
+
+        let tree = match commit {
+            Some(commit) => Some(self.read_commit(commit)?.tree()),
+            None => None,
+        };
+        let target = self.tree_entries(tree)?;
+        let mut matching: BTreeSet<PathBuf> = target.keys().cloned().collect();
+        matching.extend(index.iter().map(|entry| entry.path.to_path_buf()));
+        matching.retain(|path| paths.iter().any(|prefix| path.starts_with(prefix)));
+
+        self.reset_index_paths(&mut index, &matching, &target)?;
+        index.write_to(&mut index_lock)?;
+        index_lock.commit()?;
+        Ok(())
+    }
+}
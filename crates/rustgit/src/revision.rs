@@ -0,0 +1,463 @@
+//! Resolve revision specifiers such as `main~2`, `v1.0^{tree}`, `HEAD:src/lib.rs` or `a..b`
+
+use crate::config::ConfigError;
+use crate::database::ObjectPrefixError;
+use crate::index::IndexReadError;
+use crate::object::{
+    parse_approxidate, read_header, ObjectHeader, ObjectReadError, ObjectType, TREE_MODE,
+};
+use crate::oid::ObjectId;
+use crate::references::{Ref, ReferenceError};
+use crate::Repository;
+use chrono::Local;
+use std::path::Path;
+
+/// What a revision specifier denotes
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Revision {
+    /// A single object
+    Single(ObjectId),
+
+    /// `exclude..include`: the commits reachable from `include` but not from `exclude`
+    Range {
+        exclude: ObjectId,
+        include: ObjectId,
+    },
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RevisionError {
+    #[error("unknown revision '{0}'")]
+    UnknownRevision(String),
+
+    #[error("invalid revision syntax '{0}'")]
+    InvalidSyntax(String),
+
+    #[error(transparent)]
+    ObjectPrefixError(#[from] ObjectPrefixError),
+
+    #[error("path '{path}' does not exist in '{revision}'")]
+    PathNotFound { revision: String, path: String },
+
+    #[error("no upstream configured for branch '{0}'")]
+    NoUpstream(String),
+
+    #[error("log for '{name}' only has {count} entries")]
+    ReflogTooShort { name: String, count: usize },
+
+    #[error(transparent)]
+    ReferenceError(#[from] ReferenceError),
+
+    #[error(transparent)]
+    ObjectReadError(#[from] ObjectReadError),
+
+    #[error(transparent)]
+    IndexReadError(#[from] IndexReadError),
+
+    #[error(transparent)]
+    ConfigError(#[from] ConfigError),
+
+    #[error("IO Error")]
+    IOError(#[from] std::io::Error),
+}
+
+type RevisionResult<T> = Result<T, RevisionError>;
+
+fn is_hex(s: &str) -> bool {
+    s.bytes()
+        .all(|c| c.is_ascii_digit() || (b'a'..=b'f').contains(&c))
+}
+
+// An omitted side of a range is HEAD
+fn or_head(side: &str) -> &str {
+    if side.is_empty() {
+        "HEAD"
+    } else {
+        side
+    }
+}
+
+// Map a remote reference to the local one through a fetch refspec `[+]<src>:<dst>`, where both
+// sides may contain a single `*`
+fn map_refspec(refspec: &str, remote_ref: &str) -> Option<String> {
+    let refspec = refspec.strip_prefix('+').unwrap_or(refspec);
+    let (src, dst) = refspec.split_once(':')?;
+    match src.split_once('*') {
+        None => (src == remote_ref).then(|| dst.to_string()),
+        Some((prefix, suffix)) => {
+            let matched = remote_ref.strip_prefix(prefix)?.strip_suffix(suffix)?;
+            Some(dst.replacen('*', matched, 1))
+        }
+    }
+}
+
+// Parse the number after `~` or `^`, which defaults to 1
+fn parse_count(digits: &str, spec: &str) -> RevisionResult<usize> {
+    if digits.is_empty() {
+        return Ok(1);
+    }
+    digits
+        .parse()
+        .map_err(|_| RevisionError::InvalidSyntax(spec.to_string()))
+}
+
+// The position of the `:` between a revision and a path, which can't be inside the braces of a
+// suffix such as `@{2026-10-18 07:00:00}`
+fn path_separator(spec: &str) -> Option<usize> {
+    let mut in_braces = false;
+    for (i, c) in spec.char_indices() {
+        match c {
+            '{' => in_braces = true,
+            '}' => in_braces = false,
+            ':' if !in_braces => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+impl Repository {
+    /// Resolve a revision specifier, which can also be a range `a..b`
+    pub fn rev_parse(&self, spec: &str) -> RevisionResult<Revision> {
+        if let Some((exclude, include)) = spec.split_once("..") {
+            if include.starts_with('.') {
+                // symmetric difference `a...b`
+                return Err(RevisionError::InvalidSyntax(spec.to_string()));
+            }
+            return Ok(Revision::Range {
+                exclude: self.resolve_revision(or_head(exclude))?,
+                include: self.resolve_revision(or_head(include))?,
+            });
+        }
+        Ok(Revision::Single(self.resolve_revision(spec)?))
+    }
+
+    /// Resolve a revision specifier that denotes a single object
+    pub fn resolve_revision(&self, spec: &str) -> RevisionResult<ObjectId> {
+        if let Some(path) = spec.strip_prefix(':') {
+            return self.resolve_index_path(path);
+        }
+        if let Some(separator) = path_separator(spec) {
+            let (revision, path) = (&spec[..separator], &spec[separator + 1..]);
+            let tree = self.peel(self.resolve_revision(revision)?, ObjectType::Tree)?;
+            return self.resolve_tree_path(tree, revision, path);
+        }
+
+        // the base name ends with the first suffix
+        let base_end = spec
+            .find(['~', '^'])
+            .into_iter()
+            .chain(spec.find("@{"))
+            .min()
+            .unwrap_or(spec.len());
+        let (base, mut suffixes) = spec.split_at(base_end);
+
+        let mut oid = if let Some(rest) = suffixes.strip_prefix("@{") {
+            let (selector, rest) = rest
+                .split_once('}')
+                .ok_or_else(|| RevisionError::InvalidSyntax(spec.to_string()))?;
+            suffixes = rest;
+            if selector.eq_ignore_ascii_case("upstream") || selector.eq_ignore_ascii_case("u") {
+                self.resolve_upstream(base)?
+            } else {
+                self.resolve_reflog(base, selector, spec)?
+            }
+        } else {
+            self.resolve_base(base)?
+                .ok_or_else(|| RevisionError::UnknownRevision(spec.to_string()))?
+        };
+
+        while !suffixes.is_empty() {
+            if let Some(rest) = suffixes.strip_prefix("^{") {
+                let (typ, rest) = rest
+                    .split_once('}')
+                    .ok_or_else(|| RevisionError::InvalidSyntax(spec.to_string()))?;
+                oid = match typ {
+                    "" => self.peel_tags(oid)?,
+                    "tag" => self.peel(oid, ObjectType::Tag)?,
+                    "commit" => self.peel(oid, ObjectType::Commit)?,
+                    "tree" => self.peel(oid, ObjectType::Tree)?,
+                    "blob" => self.peel(oid, ObjectType::Blob)?,
+                    _ => return Err(RevisionError::InvalidSyntax(spec.to_string())),
+                };
+                suffixes = rest;
+                continue;
+            }
+
+            let operator = suffixes.as_bytes()[0];
+            let rest = &suffixes[1..];
+            let digits_end = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let count = parse_count(&rest[..digits_end], spec)?;
+            suffixes = &rest[digits_end..];
+
+            match operator {
+                // `~n`: the n-th first-parent ancestor
+                b'~' => {
+                    for _ in 0..count {
+                        oid = self.nth_parent(oid, 1, spec)?;
+                    }
+                }
+                // `^0` peels to a commit, `^n` is the n-th parent
+                b'^' if count == 0 => oid = self.peel(oid, ObjectType::Commit)?,
+                b'^' => oid = self.nth_parent(oid, count, spec)?,
+                _ => return Err(RevisionError::InvalidSyntax(spec.to_string())),
+            }
+        }
+
+        Ok(oid)
+    }
+
+    /// Find the full name of a reference given a short name, trying the same locations as git
+    /// in order: `<name>`, `refs/<name>`, `refs/tags/<name>`, `refs/heads/<name>`,
+    /// `refs/remotes/<name>` and `refs/remotes/<name>/HEAD`
+    pub fn dwim_reference(&self, name: &str) -> Result<Option<String>, ReferenceError> {
+        // only full names and pseudo refs such as HEAD or ORIG_HEAD are taken as is
+        let is_pseudo_ref = name.bytes().all(|c| c.is_ascii_uppercase() || c == b'_');
+        let candidates = [
+            (is_pseudo_ref || name.starts_with("refs/")).then(|| name.to_string()),
+            Some(format!("refs/{name}")),
+            Some(format!("refs/tags/{name}")),
+            Some(format!("refs/heads/{name}")),
+            Some(format!("refs/remotes/{name}")),
+            Some(format!("refs/remotes/{name}/HEAD")),
+        ];
+
+        for candidate in candidates.into_iter().flatten() {
+            if self.try_find_reference(&candidate)?.is_some() {
+                return Ok(Some(candidate));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The shortest name that `dwim_reference` finds the reference `name` with, e.g. `main` for
+    /// `refs/heads/main` unless there is also a tag `main`, in which case it is `heads/main`
+    pub fn shorten_reference(&self, name: &str) -> Result<String, ReferenceError> {
+        let candidates = [
+            name.strip_prefix("refs/remotes/")
+                .and_then(|name| name.strip_suffix("/HEAD")),
+            name.strip_prefix("refs/remotes/"),
+            name.strip_prefix("refs/heads/"),
+            name.strip_prefix("refs/tags/"),
+            name.strip_prefix("refs/"),
+        ];
+        for candidate in candidates.into_iter().flatten() {
+            if self.dwim_reference(candidate)?.as_deref() == Some(name) {
+                return Ok(candidate.to_string());
+            }
+        }
+        Ok(name.to_string())
+    }
+
+    /// The full name of the reference a branch tracks, given by `branch.<name>.remote` and
+    /// `branch.<name>.merge`, which is a remote-tracking branch unless the remote is `.`. None
+    /// if the branch has no upstream
+    pub fn upstream_reference(&self, branch: &str) -> Result<Option<String>, ConfigError> {
+        let config = self.config()?;
+        let remote = config.get(&format!("branch.{branch}.remote"));
+        let merge = config.get(&format!("branch.{branch}.merge"));
+        let (Some(remote), Some(merge)) = (remote, merge) else {
+            return Ok(None);
+        };
+
+        // "." is the local repository itself, otherwise the fetch refspecs of the remote tell
+        // where its branches are stored
+        if remote == "." {
+            return Ok(Some(merge.to_string()));
+        }
+        let upstream = config
+            .get_all(&format!("remote.{remote}.fetch"))
+            .find_map(|refspec| map_refspec(refspec, merge));
+        Ok(upstream)
+    }
+
+    /// Follow an object until reaching one of type `typ`: tags are followed to their target and
+    /// commits to their tree. Peeling to a tag only succeeds if the object is a tag itself
+    pub fn peel(&self, mut oid: ObjectId, typ: ObjectType) -> RevisionResult<ObjectId> {
+        loop {
+            let object_type = self.object_type(oid)?;
+            if object_type == typ {
+                return Ok(oid);
+            }
+            oid = match object_type {
+                ObjectType::Tag => self.read_tag(oid)?.object(),
+                ObjectType::Commit if typ == ObjectType::Tree => self.read_commit(oid)?.tree(),
+                _ => return Err(ObjectReadError::MismatchObjectType(typ, object_type).into()),
+            };
+        }
+    }
+
+    /// The type of an object, read from its header
+    pub fn object_type(&self, oid: ObjectId) -> Result<ObjectType, ObjectReadError> {
+        let mut reader = self
+            .object_reader(oid)
+            .map_err(|err| ObjectReadError::OpenError(oid, err))?;
+        let ObjectHeader { typ, .. } = read_header(&mut reader)?;
+        Ok(typ)
+    }
+
+    // Resolve the part of a revision before any suffix. Returns None if nothing matches
+    fn resolve_base(&self, base: &str) -> RevisionResult<Option<ObjectId>> {
+        let base = if base == "@" { "HEAD" } else { base };
+
+        if base.len() == 40 && is_hex(base) {
+            let oid = ObjectId::from_unvalidated_sh1_hex_string(base)
+                .expect("a 40 hex digits string is a valid object id");
+            return Ok(self.object_reader(oid).is_ok().then_some(oid));
+        }
+
+        if let Some(name) = self.dwim_reference(base)? {
+            let reference = Ref::Symbolic(name);
+            return match self.resolve_reference(&reference) {
+                Ok(oid) => Ok(Some(oid)),
+                // e.g. HEAD of a repository without commits
+                Err(ReferenceError::NotExist(_)) => Ok(None),
+                Err(e) => Err(e.into()),
+            };
+        }
+
+        match self.resolve_object_prefix(base) {
+            Ok(oid) => Ok(Some(oid)),
+            Err(ObjectPrefixError::InvalidPrefix(_)) | Err(ObjectPrefixError::NotFound(_)) => {
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // The object the upstream of a branch points to. An empty branch means the current one
+    fn resolve_upstream(&self, branch: &str) -> RevisionResult<ObjectId> {
+        let branch_ref = match branch {
+            "" | "@" | "HEAD" => self
+                .head()?
+                .referent_name()
+                .ok_or_else(|| RevisionError::NoUpstream("HEAD".to_string()))?
+                .to_string(),
+            _ => format!("refs/heads/{branch}"),
+        };
+        let branch = branch_ref
+            .strip_prefix("refs/heads/")
+            .unwrap_or(&branch_ref);
+
+        let no_upstream = || RevisionError::NoUpstream(branch.to_string());
+        let upstream_ref = self.upstream_reference(branch)?.ok_or_else(no_upstream)?;
+        match self.resolve_reference(&Ref::Symbolic(upstream_ref)) {
+            Err(ReferenceError::NotExist(_)) => Err(no_upstream()),
+            result => Ok(result?),
+        }
+    }
+
+    // `<base>@{<n>}` is the n-th previous value of a reference and `<base>@{<date>}` its value at
+    // a date, as recorded in its reflog. An empty base is the current branch
+    fn resolve_reflog(&self, base: &str, selector: &str, spec: &str) -> RevisionResult<ObjectId> {
+        let head = self.head()?;
+        let name = match base {
+            "" => head.referent_name().unwrap_or("HEAD").to_string(),
+            "@" => "HEAD".to_string(),
+            _ => self
+                .dwim_reference(base)?
+                .ok_or_else(|| RevisionError::UnknownRevision(spec.to_string()))?,
+        };
+        if !self.reflog_exists(&name) {
+            return Err(RevisionError::UnknownRevision(spec.to_string()));
+        }
+        let entries = self.reflog(&name)?;
+
+        if !selector.is_empty() && selector.bytes().all(|c| c.is_ascii_digit()) {
+            let n: usize = selector
+                .parse()
+                .map_err(|_| RevisionError::InvalidSyntax(spec.to_string()))?;
+            // an expired reflog still knows the current value
+            if n == 0 && entries.is_empty() {
+                return Ok(self.resolve_reference(&Ref::Symbolic(name))?);
+            }
+            return match entries.len().checked_sub(n + 1) {
+                Some(i) => Ok(entries[i].new),
+                // the oldest entry also tells the value before it
+                None if n == entries.len() && !entries[0].old.is_null() => Ok(entries[0].old),
+                None => Err(RevisionError::ReflogTooShort {
+                    name: name
+                        .strip_prefix("refs/heads/")
+                        .unwrap_or(&name)
+                        .to_string(),
+                    count: entries.len(),
+                }),
+            };
+        }
+
+        let date = parse_approxidate(selector, Local::now().fixed_offset())
+            .ok_or_else(|| RevisionError::InvalidSyntax(spec.to_string()))?;
+        let entry = entries
+            .iter()
+            .rev()
+            .find(|entry| entry.committer.time <= date);
+        match (entry, entries.first()) {
+            (Some(entry), _) => Ok(entry.new),
+            // like git, a date older than the reflog gives the oldest known value
+            (None, Some(oldest)) if !oldest.old.is_null() => Ok(oldest.old),
+            (None, Some(oldest)) => Ok(oldest.new),
+            (None, None) => Ok(self.resolve_reference(&Ref::Symbolic(name))?),
+        }
+    }
+
+    fn nth_parent(&self, oid: ObjectId, n: usize, spec: &str) -> RevisionResult<ObjectId> {
+        let commit = self.read_commit(self.peel(oid, ObjectType::Commit)?)?;
+        commit
+            .parents()
+            .get(n - 1)
+            .copied()
+            .ok_or_else(|| RevisionError::UnknownRevision(spec.to_string()))
+    }
+
+    // `:<path>` or `:<stage>:<path>` names a blob in the index. Like git, only a stage from 0
+    // to 3 is one, so `:a:b` is the path `a:b`
+    fn resolve_index_path(&self, path: &str) -> RevisionResult<ObjectId> {
+        let (stage, path) = match path.split_once(':') {
+            Some(("0", path)) => (0, path),
+            Some(("1", path)) => (1, path),
+            Some(("2", path)) => (2, path),
+            Some(("3", path)) => (3, path),
+            _ => (0, path),
+        };
+        let index = self.index()?;
+        let entry =
+            index
+                .get(Path::new(path), stage)
+                .ok_or_else(|| RevisionError::PathNotFound {
+                    revision: "index".to_string(),
+                    path: path.to_string(),
+                })?;
+        Ok(entry.oid)
+    }
+
+    // Walk down a tree following a `/` separated path
+    fn resolve_tree_path(
+        &self,
+        tree: ObjectId,
+        revision: &str,
+        path: &str,
+    ) -> RevisionResult<ObjectId> {
+        let not_found = || RevisionError::PathNotFound {
+            revision: revision.to_string(),
+            path: path.to_string(),
+        };
+
+        let mut oid = tree;
+        let mut mode = TREE_MODE;
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            if mode != TREE_MODE {
+                return Err(not_found());
+            }
+            let entry = self
+                .read_tree(oid)?
+                .iter()
+                .find(|entry| entry.name == name)
+                .cloned()
+                .ok_or_else(not_found)?;
+            (oid, mode) = (entry.oid, entry.mode);
+        }
+        Ok(oid)
+    }
+}
@@ -0,0 +1,544 @@
+//! Walk the commit history
+
+use crate::object::{Commit, ObjectReadError, TREE_MODE};
+use crate::oid::ObjectId;
+use crate::Repository;
+use chrono::{DateTime, FixedOffset};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+/// The order in which commits are given back
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Sorting {
+    /// Most recent committer date first, as commits are discovered. Like `git log`, a parent
+    /// with a later date than its child can be shown before it
+    #[default]
+    Default,
+
+    /// Most recent committer date first, but never a parent before all its children
+    Date,
+
+    /// Never a parent before all its children, and keep the commits of a line of history
+    /// together
+    Topological,
+}
+
+/// A commit given back by a `RevWalk`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalkedCommit {
+    pub oid: ObjectId,
+    pub commit: Commit,
+
+    /// The parents of the commit in the history given back by the walk, which are the parents
+    /// of the commit unless the walk rewrites them, see `RevWalk::rewrite_parents`
+    pub parents: Vec<ObjectId>,
+}
+
+// The commits reachable from the included commits, most recent committer date first, read as
+// they are needed. Hidden commits go through the same queue so that they hide their ancestors
+// before those come out of it
+#[derive(Default)]
+struct Discovery {
+    queue: BinaryHeap<(i64, Reverse<usize>, ObjectId)>,
+    // the commits of the queue
+    commits: HashMap<ObjectId, Commit>,
+    // commits with the same date come out in the order they were queued
+    counter: usize,
+    seen: HashSet<ObjectId>,
+    hidden: HashSet<ObjectId>,
+    // the number of commits of the queue that are not hidden
+    interesting: usize,
+}
+
+impl Discovery {
+    // Queue a commit unless it was already seen, and hide it and its ancestors if asked
+    fn push(
+        &mut self,
+        repository: &Repository,
+        oid: ObjectId,
+        hide: bool,
+    ) -> Result<(), ObjectReadError> {
+        if hide && self.hidden.insert(oid) && self.commits.contains_key(&oid) {
+            self.interesting -= 1;
+        }
+        if !self.seen.insert(oid) {
+            return Ok(());
+        }
+        let commit = repository.read_commit(oid)?;
+        let time = commit.committer().time.timestamp();
+        self.queue.push((time, Reverse(self.counter), oid));
+        self.counter += 1;
+        self.commits.insert(oid, commit);
+        if !self.hidden.contains(&oid) {
+            self.interesting += 1;
+        }
+        Ok(())
+    }
+}
+
+enum WalkState {
+    // commits are given back as they are discovered
+    Lazy(Discovery),
+    // all the commits were read up front, to be sorted or reversed
+    Sorted(std::vec::IntoIter<WalkedCommit>),
+}
+
+/// Configure a history traversal, then iterate over it to get the commits
+///
+/// In the default order the history is read as the commits are given back, so that walking a
+/// few commits of a long history stays cheap. The date and topological orders, and reversed
+/// walks, read all the commits to show before giving back the first one
+pub struct RevWalk<'r> {
+    repository: &'r Repository,
+    included: Vec<ObjectId>,
+    excluded: Vec<ObjectId>,
+    sorting: Sorting,
+    reverse: bool,
+    max_count: Option<usize>,
+    author: Option<String>,
+    since: Option<DateTime<FixedOffset>>,
+    until: Option<DateTime<FixedOffset>>,
+    paths: Vec<PathBuf>,
+    rewrite_parents: bool,
+    // set up when the first commit is asked for
+    state: Option<WalkState>,
+    // the number of commits given back by a lazy walk
+    shown: usize,
+}
+
+impl<'r> RevWalk<'r> {
+    pub fn new(repository: &'r Repository) -> Self {
+        RevWalk {
+            repository,
+            included: vec![],
+            excluded: vec![],
+            sorting: Sorting::Default,
+            reverse: false,
+            max_count: None,
+            author: None,
+            since: None,
+            until: None,
+            paths: vec![],
+            rewrite_parents: false,
+            state: None,
+            shown: 0,
+        }
+    }
+
+    /// Start the walk from a commit
+    pub fn push(&mut self, oid: ObjectId) -> &mut Self {
+        self.included.push(oid);
+        self
+    }
+
+    /// Hide a commit and all its ancestors, like `^rev` or the left side of `a..b`
+    pub fn hide(&mut self, oid: ObjectId) -> &mut Self {
+        self.excluded.push(oid);
+        self
+    }
+
+    pub fn sorting(&mut self, sorting: Sorting) -> &mut Self {
+        self.sorting = sorting;
+        self
+    }
+
+    /// Give back the commits in reverse order. Applied after `max_count`
+    pub fn reverse(&mut self, reverse: bool) -> &mut Self {
+        self.reverse = reverse;
+        self
+    }
+
+    pub fn max_count(&mut self, max_count: usize) -> &mut Self {
+        self.max_count = Some(max_count);
+        self
+    }
+
+    /// Only keep the commits whose author `Name <email>` contains `pattern`
+    pub fn author(&mut self, pattern: &str) -> &mut Self {
+        self.author = Some(pattern.to_string());
+        self
+    }
+
+    /// Only keep the commits committed at or after `date`
+    pub fn since(&mut self, date: DateTime<FixedOffset>) -> &mut Self {
+        self.since = Some(date);
+        self
+    }
+
+    /// Only keep the commits committed at or before `date`
+    pub fn until(&mut self, date: DateTime<FixedOffset>) -> &mut Self {
+        self.until = Some(date);
+        self
+    }
+
+    /// Only keep the commits that change one of the paths (files or directories)
+    pub fn paths(&mut self, paths: impl IntoIterator<Item = PathBuf>) -> &mut Self {
+        self.paths.extend(paths);
+        self
+    }
+
+    /// Give back the parents of each commit among the commits of the walk, ignoring
+    /// `max_count`, to draw the history as a graph. Like git, a parent that is left out because
+    /// it doesn't change the paths is replaced by its nearest ancestor that does, and the other
+    /// parents that are not walked are dropped. All the commits are read up front
+    pub fn rewrite_parents(&mut self, rewrite: bool) -> &mut Self {
+        self.rewrite_parents = rewrite;
+        self
+    }
+
+    // The parents of a commit that the walk follows. When limited to paths, a merge that has
+    // the same content as one of its parents for those paths only follows that parent
+    fn followed_parents(&self, commit: &Commit) -> Result<Vec<ObjectId>, ObjectReadError> {
+        if !self.paths.is_empty() && commit.parents().len() > 1 {
+            for &parent in commit.parents() {
+                let parent_tree = self.repository.read_commit(parent)?.tree();
+                if !self.paths_differ(commit.tree(), Some(parent_tree))? {
+                    return Ok(vec![parent]);
+                }
+            }
+        }
+        Ok(commit.parents().to_vec())
+    }
+
+    // The next commit of the walk, before the filters and the limits. Like git's default order,
+    // this is the most recent commit discovered so far
+    fn next_discovered(
+        &self,
+        discovery: &mut Discovery,
+    ) -> Result<Option<WalkedCommit>, ObjectReadError> {
+        // once only hidden commits are left, nothing else can be shown
+        while discovery.interesting > 0 {
+            let (_, _, oid) = discovery
+                .queue
+                .pop()
+                .expect("queued commits are interesting");
+            let commit = discovery
+                .commits
+                .remove(&oid)
+                .expect("queued commits are read");
+            if discovery.hidden.contains(&oid) {
+                for &parent in commit.parents() {
+                    discovery.push(self.repository, parent, true)?;
+                }
+                continue;
+            }
+            discovery.interesting -= 1;
+            for parent in self.followed_parents(&commit)? {
+                discovery.push(self.repository, parent, false)?;
+            }
+            let parents = commit.parents().to_vec();
+            return Ok(Some(WalkedCommit {
+                oid,
+                commit,
+                parents,
+            }));
+        }
+        Ok(None)
+    }
+
+    // Start the walk. The default order is given back as the commits are discovered, the other
+    // orders and reversed walks need all the commits first
+    fn start(&self) -> Result<WalkState, ObjectReadError> {
+        let mut discovery = Discovery::default();
+        for &oid in &self.excluded {
+            discovery.push(self.repository, oid, true)?;
+        }
+        for &oid in &self.included {
+            discovery.push(self.repository, oid, false)?;
+        }
+        if self.sorting == Sorting::Default && !self.reverse && !self.rewrite_parents {
+            return Ok(WalkState::Lazy(discovery));
+        }
+
+        let mut discovered = vec![];
+        while let Some(walked) = self.next_discovered(&mut discovery)? {
+            discovered.push(walked);
+        }
+        if self.sorting != Sorting::Default {
+            let mut commits: HashMap<ObjectId, Commit> = discovered
+                .into_iter()
+                .map(|walked| (walked.oid, walked.commit))
+                .collect();
+            discovered = self
+                .topological_order(&commits)
+                .into_iter()
+                .map(|oid| {
+                    let commit = commits.remove(&oid).expect("sorted commits are discovered");
+                    let parents = commit.parents().to_vec();
+                    WalkedCommit {
+                        oid,
+                        commit,
+                        parents,
+                    }
+                })
+                .collect();
+        }
+
+        let mut shown = vec![];
+        if self.rewrite_parents {
+            shown = self.rewritten(discovered)?;
+            if let Some(max_count) = self.max_count {
+                shown.truncate(max_count);
+            }
+        } else {
+            for walked in discovered {
+                if self.limit_reached(shown.len()) {
+                    break;
+                }
+                if self.is_shown(&walked.commit)? {
+                    shown.push(walked);
+                }
+            }
+        }
+        if self.reverse {
+            shown.reverse();
+        }
+        Ok(WalkState::Sorted(shown.into_iter()))
+    }
+
+    // The commits to show among the discovered ones, with their parents rewritten
+    fn rewritten(
+        &self,
+        discovered: Vec<WalkedCommit>,
+    ) -> Result<Vec<WalkedCommit>, ObjectReadError> {
+        // whether each discovered commit is shown, and if not whether only the paths hide it
+        let mut shown = HashSet::new();
+        let mut same_paths = HashMap::new();
+        for walked in &discovered {
+            if !self.matches_filters(&walked.commit) {
+                continue;
+            }
+            if self.changes_paths(&walked.commit)? {
+                shown.insert(walked.oid);
+            } else {
+                let parent = self.followed_parents(&walked.commit)?.first().copied();
+                same_paths.insert(walked.oid, parent);
+            }
+        }
+
+        let rewrite = |mut oid: ObjectId| -> Option<ObjectId> {
+            loop {
+                if shown.contains(&oid) {
+                    return Some(oid);
+                }
+                oid = (*same_paths.get(&oid)?)?;
+            }
+        };
+        let mut commits = vec![];
+        for mut walked in discovered {
+            if !shown.contains(&walked.oid) {
+                continue;
+            }
+            let mut parents = vec![];
+            for parent in self.followed_parents(&walked.commit)? {
+                if let Some(parent) = rewrite(parent) {
+                    if !parents.contains(&parent) {
+                        parents.push(parent);
+                    }
+                }
+            }
+            walked.parents = parents;
+            commits.push(walked);
+        }
+        Ok(commits)
+    }
+
+    fn limit_reached(&self, shown: usize) -> bool {
+        self.max_count.is_some_and(|max_count| shown >= max_count)
+    }
+
+    fn next_commit(&mut self) -> Result<Option<WalkedCommit>, ObjectReadError> {
+        let mut state = match self.state.take() {
+            Some(state) => state,
+            None => self.start()?,
+        };
+        let next = match &mut state {
+            WalkState::Sorted(commits) => commits.next(),
+            WalkState::Lazy(discovery) => loop {
+                if self.limit_reached(self.shown) {
+                    break None;
+                }
+                match self.next_discovered(discovery)? {
+                    Some(walked) if self.is_shown(&walked.commit)? => {
+                        self.shown += 1;
+                        break Some(walked);
+                    }
+                    Some(_) => continue,
+                    None => break None,
+                }
+            },
+        };
+        self.state = Some(state);
+        Ok(next)
+    }
+
+    // The parents followed by the walk, among the commits already read
+    fn followed_parents_cached(
+        &self,
+        commit: &Commit,
+        commits: &HashMap<ObjectId, Commit>,
+    ) -> Vec<ObjectId> {
+        commit
+            .parents()
+            .iter()
+            .copied()
+            .filter(|parent| commits.contains_key(parent))
+            .collect()
+    }
+
+    // Kahn's algorithm: a commit is ready once all its children are given back. Date order
+    // takes the most recent ready commit, topological order the last one that became ready
+    fn topological_order(&self, commits: &HashMap<ObjectId, Commit>) -> Vec<ObjectId> {
+        let mut children_count: HashMap<ObjectId, usize> = HashMap::new();
+        for commit in commits.values() {
+            for parent in self.followed_parents_cached(commit, commits) {
+                *children_count.entry(parent).or_default() += 1;
+            }
+        }
+
+        let mut tips: Vec<ObjectId> = vec![];
+        for &oid in &self.included {
+            if commits.contains_key(&oid)
+                && !children_count.contains_key(&oid)
+                && !tips.contains(&oid)
+            {
+                tips.push(oid);
+            }
+        }
+
+        // like git, the most recent tip comes first
+        tips.sort_by_key(|oid| Reverse(commits[oid].committer().time.timestamp()));
+
+        let mut order = vec![];
+        let mut counter = 0;
+        let mut by_date = BinaryHeap::new();
+        let mut stack: Vec<ObjectId> = vec![];
+        let mut push_ready = |oid: ObjectId, by_date: &mut BinaryHeap<_>, stack: &mut Vec<_>| {
+            let time = commits[&oid].committer().time.timestamp();
+            by_date.push((time, Reverse(counter), oid));
+            counter += 1;
+            stack.push(oid);
+        };
+        // the first tip is the first to be given back
+        for oid in tips.into_iter().rev() {
+            push_ready(oid, &mut by_date, &mut stack);
+        }
+
+        loop {
+            let next = match self.sorting {
+                Sorting::Date => by_date.pop().map(|(_, _, oid)| oid),
+                _ => stack.pop(),
+            };
+            let Some(oid) = next else {
+                break;
+            };
+            order.push(oid);
+            for parent in self.followed_parents_cached(&commits[&oid], commits) {
+                let count = children_count
+                    .get_mut(&parent)
+                    .expect("parents are counted");
+                *count -= 1;
+                if *count == 0 {
+                    push_ready(parent, &mut by_date, &mut stack);
+                }
+            }
+        }
+        order
+    }
+
+    fn is_shown(&self, commit: &Commit) -> Result<bool, ObjectReadError> {
+        Ok(self.matches_filters(commit) && self.changes_paths(commit)?)
+    }
+
+    // Whether a commit matches the dates and the author of the walk
+    fn matches_filters(&self, commit: &Commit) -> bool {
+        let time = commit.committer().time;
+        if self.since.is_some_and(|since| time < since)
+            || self.until.is_some_and(|until| time > until)
+        {
+            return false;
+        }
+
+        match &self.author {
+            Some(pattern) => {
+                let author = commit.author();
+                format!("{} <{}>", author.name, author.email).contains(pattern.as_str())
+            }
+            None => true,
+        }
+    }
+
+    // Whether a commit changes the paths of the walk, which all commits do without paths
+    fn changes_paths(&self, commit: &Commit) -> Result<bool, ObjectReadError> {
+        if self.paths.is_empty() {
+            return Ok(true);
+        }
+        // a commit is shown when it is different from all its parents for the given paths
+        match commit.parents() {
+            [] => self.paths_differ(commit.tree(), None),
+            parents => {
+                for &parent in parents {
+                    let parent_tree = self.repository.read_commit(parent)?.tree();
+                    if !self.paths_differ(commit.tree(), Some(parent_tree))? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    // Whether one of the paths has a different content in the two trees. A missing tree is empty
+    fn paths_differ(
+        &self,
+        tree: ObjectId,
+        other: Option<ObjectId>,
+    ) -> Result<bool, ObjectReadError> {
+        for path in &self.paths {
+            let entry = self.entry_at_path(tree, path)?;
+            let other_entry = match other {
+                Some(other) => self.entry_at_path(other, path)?,
+                None => None,
+            };
+            if entry != other_entry {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    // The object id and mode at a path of a tree, if it exists
+    fn entry_at_path(
+        &self,
+        tree: ObjectId,
+        path: &Path,
+    ) -> Result<Option<(ObjectId, u32)>, ObjectReadError> {
+        let mut entry = (tree, TREE_MODE);
+        for name in path.iter() {
+            if entry.1 != TREE_MODE {
+                return Ok(None);
+            }
+            let tree = self.repository.read_tree(entry.0)?;
+            let found = tree
+                .iter()
+                .find(|tree_entry| name == tree_entry.name.as_str())
+                .map(|tree_entry| (tree_entry.oid, tree_entry.mode));
+            match found {
+                Some(found) => entry = found,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(entry))
+    }
+}
+
+impl Iterator for RevWalk<'_> {
+    type Item = Result<WalkedCommit, ObjectReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_commit().transpose()
+    }
+}
@@ -0,0 +1,214 @@
+//! Compare HEAD, the index and the working tree
+
+use crate::config::ConfigError;
+use crate::diff::DiffError;
+use crate::ignore::Ignore;
+use crate::index::IndexReadError;
+use crate::references::ReferenceError;
+use crate::Repository;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// How the two sides of a merge changed a path with conflicts, from the stages of the path in
+/// the index
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UnmergedKind {
+    /// Only the common ancestor is left, stage 1
+    BothDeleted,
+    /// Only our version, stage 2
+    AddedByUs,
+    /// The common ancestor and our version
+    DeletedByThem,
+    /// Only their version, stage 3
+    AddedByThem,
+    /// The common ancestor and their version
+    DeletedByUs,
+    /// Our version and their version
+    BothAdded,
+    /// All three stages
+    BothModified,
+}
+
+impl UnmergedKind {
+    fn from_stages(base: bool, ours: bool, theirs: bool) -> Option<UnmergedKind> {
+        match (base, ours, theirs) {
+            (true, false, false) => Some(UnmergedKind::BothDeleted),
+            (false, true, false) => Some(UnmergedKind::AddedByUs),
+            (true, true, false) => Some(UnmergedKind::DeletedByThem),
+            (false, false, true) => Some(UnmergedKind::AddedByThem),
+            (true, false, true) => Some(UnmergedKind::DeletedByUs),
+            (false, true, true) => Some(UnmergedKind::BothAdded),
+            (true, true, true) => Some(UnmergedKind::BothModified),
+            (false, false, false) => None,
+        }
+    }
+}
+
+/// Result of comparing HEAD, the index and the working tree
+#[derive(Debug, Default)]
+pub struct Status {
+    /// Differences between the tree of HEAD and the index
+    pub staged: BTreeMap<PathBuf, ChangeKind>,
+
+    /// Differences between the index and the working tree. Only the paths added with
+    /// intent-to-add are `ChangeKind::Added`
+    pub unstaged: BTreeMap<PathBuf, ChangeKind>,
+
+    /// Paths with merge conflicts, which are neither staged nor unstaged changes
+    pub unmerged: BTreeMap<PathBuf, UnmergedKind>,
+
+    /// Files of the working tree that are neither in the index nor ignored.
+    ///
+    /// A directory that doesn't contain any tracked file is reported as a whole, with a trailing
+    /// slash
+    pub untracked: BTreeSet<PathBuf>,
+}
+
+impl Status {
+    /// Nothing is staged or unmerged, and the working tree matches the index
+    pub fn is_clean(&self) -> bool {
+        self.staged.is_empty()
+            && self.unstaged.is_empty()
+            && self.unmerged.is_empty()
+            && self.untracked.is_empty()
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum StatusError {
+    #[error(transparent)]
+    IndexReadError(#[from] IndexReadError),
+
+    #[error(transparent)]
+    ReferenceError(#[from] ReferenceError),
+
+    #[error(transparent)]
+    DiffError(#[from] DiffError),
+
+    #[error(transparent)]
+    ConfigError(#[from] ConfigError),
+
+    #[error("IO Error")]
+    IOError(#[from] io::Error),
+}
+
+// Recursively find the files that are neither tracked nor ignored
+fn collect_untracked(
+    dir: &Path,
+    repository_dir: &Path,
+    tracked_files: &BTreeSet<&Path>,
+    tracked_dirs: &BTreeSet<&Path>,
+    ignore: &mut Ignore,
+    untracked: &mut BTreeSet<PathBuf>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let relative_path = path
+            .strip_prefix(repository_dir)
+            .expect("path should be inside the repository");
+
+        // symbolic links are files, even when they point to a directory
+        if entry.file_type()?.is_dir() {
+            if relative_path == Path::new(".git") {
+                continue;
+            }
+
+            if tracked_dirs.contains(relative_path) {
+                collect_untracked(
+                    &path,
+                    repository_dir,
+                    tracked_files,
+                    tracked_dirs,
+                    ignore,
+                    untracked,
+                )?;
+            } else if contains_files(&path, repository_dir, ignore)? {
+                let mut name = relative_path.as_os_str().to_os_string();
+                name.push("/");
+                untracked.insert(PathBuf::from(name));
+            }
+        } else if !tracked_files.contains(relative_path)
+            && !ignore.is_ignored(relative_path, false)?
+        {
+            untracked.insert(relative_path.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+// git does not show empty directories, nor the ones with only ignored files
+fn contains_files(dir: &Path, repository_dir: &Path, ignore: &mut Ignore) -> io::Result<bool> {
+    let relative_dir = dir
+        .strip_prefix(repository_dir)
+        .expect("path should be inside the repository");
+    if ignore.is_ignored(relative_dir, true)? {
+        return Ok(false);
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let relative_path = path.strip_prefix(repository_dir).unwrap();
+        let contains = if entry.file_type()?.is_dir() {
+            contains_files(&path, repository_dir, ignore)?
+        } else {
+            !ignore.is_ignored(relative_path, false)?
+        };
+        if contains {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+impl Repository {
+    /// Compute the staged changes, the unstaged changes, the paths with conflicts and the
+    /// untracked files
+    pub fn status(&self) -> Result<Status, StatusError> {
+        let mut status = Status::default();
+
+        for change in self.diff_tree_to_index(self.head_tree()?)? {
+            status.staged.insert(change.path.clone(), change.kind());
+        }
+        for change in self.diff_index_to_worktree()? {
+            status.unstaged.insert(change.path.clone(), change.kind());
+        }
+
+        let index = self.index()?;
+        for conflict in index.conflicts() {
+            let kind = UnmergedKind::from_stages(
+                conflict.base.is_some(),
+                conflict.ours.is_some(),
+                conflict.theirs.is_some(),
+            );
+            if let Some(kind) = kind {
+                status.unmerged.insert(conflict.path.to_path_buf(), kind);
+            }
+        }
+        let tracked_files: BTreeSet<&Path> = index.iter().map(|entry| entry.path).collect();
+        let tracked_dirs: BTreeSet<&Path> = tracked_files
+            .iter()
+            .flat_map(|path| path.ancestors().skip(1))
+            .collect();
+        collect_untracked(
+            &self.repository_dir,
+            &self.repository_dir,
+            &tracked_files,
+            &tracked_dirs,
+            &mut self.ignore()?,
+            &mut status.untracked,
+        )?;
+
+        Ok(status)
+    }
+}
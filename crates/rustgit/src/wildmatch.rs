@@ -0,0 +1,299 @@
+// Glob matching of paths, following git's wildmatch with `WM_PATHNAME`
+//
+// `*`, `?` and bracket expressions such as `[a-z]`, `[!0-9]` or `[[:alpha:]]` don't match slashes.
+// `**` matches across directories when it is a whole path component: `**/x`, `x/**` and `x/**/y`,
+// where `/**/` can also match a single slash. A backslash escapes the next character.
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Outcome {
+    Matched,
+    NoMatch,
+    // the text is too short for the pattern, so a `*` consuming more of it can't help
+    AbortAll,
+    // a `*` reached a slash, which only an earlier `**` can get past
+    AbortToStarStar,
+}
+
+fn is_glob_special(c: u8) -> bool {
+    matches!(c, b'*' | b'?' | b'[' | b'\\')
+}
+
+fn in_class(class: &[u8], c: u8) -> Option<bool> {
+    let matched = match class {
+        b"alnum" => c.is_ascii_alphanumeric(),
+        b"alpha" => c.is_ascii_alphabetic(),
+        b"blank" => c == b' ' || c == b'\t',
+        b"cntrl" => c.is_ascii_control(),
+        b"digit" => c.is_ascii_digit(),
+        b"graph" => c.is_ascii_graphic(),
+        b"lower" => c.is_ascii_lowercase(),
+        b"print" => c.is_ascii_graphic() || c == b' ',
+        b"punct" => c.is_ascii_punctuation(),
+        b"space" => c.is_ascii_whitespace() || c == b'\x0b',
+        b"upper" => c.is_ascii_uppercase(),
+        b"xdigit" => c.is_ascii_hexdigit(),
+        _ => return None,
+    };
+    Some(matched)
+}
+
+// Match a bracket expression starting after its `[` at `p` against `t`. Returns whether it
+// matched and the position of its closing `]`
+fn match_bracket(pattern: &[u8], mut p: usize, t: u8) -> Result<(bool, usize), Outcome> {
+    let at = |i: usize| pattern.get(i).copied().unwrap_or(0);
+
+    let mut p_ch = at(p);
+    let negated = p_ch == b'!' || p_ch == b'^';
+    if negated {
+        p += 1;
+        p_ch = at(p);
+    }
+    let mut prev_ch = 0;
+    let mut matched = false;
+    loop {
+        if p_ch == 0 {
+            return Err(Outcome::AbortAll);
+        }
+        if p_ch == b'\\' {
+            p += 1;
+            p_ch = at(p);
+            if p_ch == 0 {
+                return Err(Outcome::AbortAll);
+            }
+            matched |= t == p_ch;
+        } else if p_ch == b'-' && prev_ch != 0 && at(p + 1) != 0 && at(p + 1) != b']' {
+            p += 1;
+            p_ch = at(p);
+            if p_ch == b'\\' {
+                p += 1;
+                p_ch = at(p);
+                if p_ch == 0 {
+                    return Err(Outcome::AbortAll);
+                }
+            }
+            matched |= prev_ch <= t && t <= p_ch;
+            p_ch = 0;
+        } else if p_ch == b'[' && at(p + 1) == b':' {
+            let start = p + 2;
+            let end = start
+                + pattern[start..]
+                    .iter()
+                    .position(|&c| c == b']')
+                    .ok_or(Outcome::AbortAll)?;
+            if end == start || pattern[end - 1] != b':' {
+                // not a `[:class:]`, the `[` is a plain character
+                matched |= t == b'[';
+            } else {
+                matched |= in_class(&pattern[start..end - 1], t).ok_or(Outcome::AbortAll)?;
+                p = end;
+                p_ch = 0;
+            }
+        } else {
+            matched |= t == p_ch;
+        }
+
+        prev_ch = p_ch;
+        p += 1;
+        p_ch = at(p);
+        if p_ch == b']' {
+            return Ok((matched != negated, p));
+        }
+    }
+}
+
+fn dowild(pattern: &[u8], mut p: usize, text: &[u8], mut t: usize) -> Outcome {
+    let at = |s: &[u8], i: usize| s.get(i).copied().unwrap_or(0);
+
+    while p < pattern.len() {
+        let mut p_ch = pattern[p];
+        let t_ch = at(text, t);
+        if t_ch == 0 && p_ch != b'*' {
+            return Outcome::AbortAll;
+        }
+        match p_ch {
+            b'?' => {
+                if t_ch == b'/' {
+                    return Outcome::NoMatch;
+                }
+            }
+            b'*' => {
+                p += 1;
+                let match_slash = if at(pattern, p) == b'*' {
+                    let star_start = p - 1;
+                    while at(pattern, p) == b'*' {
+                        p += 1;
+                    }
+                    let whole_component = star_start == 0 || pattern[star_start - 1] == b'/';
+                    let next = at(pattern, p);
+                    if whole_component
+                        && (next == 0
+                            || next == b'/'
+                            || (next == b'\\' && at(pattern, p + 1) == b'/'))
+                    {
+                        if next == b'/' && dowild(pattern, p + 1, text, t) == Outcome::Matched {
+                            return Outcome::Matched;
+                        }
+                        true
+                    } else {
+                        false
+                    }
+                } else {
+                    false
+                };
+
+                if p == pattern.len() {
+                    // a trailing `*` doesn't match the slashes that `**` matches
+                    if !match_slash && text[t..].contains(&b'/') {
+                        return Outcome::NoMatch;
+                    }
+                    return Outcome::Matched;
+                }
+                if !match_slash && pattern[p] == b'/' {
+                    // `*/` matches up to the next slash
+                    match text[t..].iter().position(|&c| c == b'/') {
+                        Some(slash) => {
+                            t += slash + 1;
+                            p += 1;
+                            continue;
+                        }
+                        None => return Outcome::NoMatch,
+                    }
+                }
+
+                loop {
+                    if t == text.len() {
+                        break;
+                    }
+                    // skip ahead to the literal that follows the star
+                    let literal = pattern[p];
+                    if !is_glob_special(literal) {
+                        while t < text.len()
+                            && (match_slash || text[t] != b'/')
+                            && text[t] != literal
+                        {
+                            t += 1;
+                        }
+                        if at(text, t) != literal {
+                            // stopped at a slash that only an earlier `**` can get past
+                            if !match_slash && t < text.len() {
+                                return Outcome::AbortToStarStar;
+                            }
+                            return Outcome::AbortAll;
+                        }
+                    }
+                    match dowild(pattern, p, text, t) {
+                        Outcome::NoMatch => {
+                            if !match_slash && text[t] == b'/' {
+                                return Outcome::AbortToStarStar;
+                            }
+                        }
+                        Outcome::AbortToStarStar if match_slash => {}
+                        matched => return matched,
+                    }
+                    t += 1;
+                }
+                return Outcome::AbortAll;
+            }
+            b'[' => match match_bracket(pattern, p + 1, t_ch) {
+                Ok((matched, end)) => {
+                    if !matched || t_ch == b'/' {
+                        return Outcome::NoMatch;
+                    }
+                    p = end;
+                }
+                Err(abort) => return abort,
+            },
+            _ => {
+                if p_ch == b'\\' {
+                    p += 1;
+                    p_ch = at(pattern, p);
+                }
+                if t_ch != p_ch {
+                    return Outcome::NoMatch;
+                }
+            }
+        }
+        p += 1;
+        t += 1;
+    }
+
+    if t == text.len() {
+        Outcome::Matched
+    } else {
+        Outcome::NoMatch
+    }
+}
+
+/// Whether a path matches a glob, like git's wildmatch with `WM_PATHNAME`
+pub(crate) fn wildmatch(pattern: &[u8], text: &[u8]) -> bool {
+    dowild(pattern, 0, text, 0) == Outcome::Matched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns() {
+        // from git's t3070-wildmatch
+        for (pattern, text, expected) in [
+            ("foo", "foo", true),
+            ("???", "foo", true),
+            ("*", "foo", true),
+            ("f*", "foo", true),
+            ("*f", "foo", false),
+            ("*foo*", "foo", true),
+            ("*ob*a*r*", "foobar", true),
+            ("\\*", "*", true),
+            ("\\*", "foo", false),
+            ("[ten]", "ten", false),
+            ("**[!te]", "ten", true),
+            ("t[a-g]n", "ten", true),
+            ("t[!a-g]n", "ten", false),
+            ("t[^a-g]n", "ton", true),
+            ("]", "]", true),
+            ("a[]]b", "a]b", true),
+            ("a[]-]b", "a-b", true),
+            ("foo*bar", "foo/baz/bar", false),
+            ("foo**bar", "foo/baz/bar", false),
+            ("foo/**/bar", "foo/baz/bar", true),
+            ("foo/**/bar", "foo/bar", true),
+            ("foo/**/**/bar", "foo/b/a/z/bar", true),
+            ("foo?bar", "foo/bar", false),
+            ("foo[/]bar", "foo/bar", false),
+            ("**/foo", "foo", true),
+            ("**/foo", "XXX/foo", true),
+            ("**/foo", "bar/baz/foo", true),
+            ("*/foo", "bar/baz/foo", false),
+            ("**/bar*", "foo/bar/baz", false),
+            ("**/bar/*", "deep/foo/bar/baz", true),
+            ("**/bar/*", "deep/foo/bar/baz/", false),
+            ("**/bar/**", "deep/foo/bar/baz/", true),
+            ("**/bar/*", "deep/foo/bar", false),
+            ("**/bar/**", "deep/foo/bar/", true),
+            ("*/bar/**", "foo/bar/baz/x", true),
+            ("**/foo", "foo/bar", false),
+            ("[[:alpha:]][[:digit:]][[:upper:]]", "a1B", true),
+            ("[[:digit:][:upper:][:space:]]", "a", false),
+            ("[a-c[:digit:]x-z]", "5", true),
+            ("[[:digit:]", "1", false),
+            ("a[b", "a[b", false),
+            ("*.c", "foo/bar.c", false),
+            ("doc/**/*.pdf", "doc/a/b/manual.pdf", true),
+            ("**/*.c", "a/b/c.c", true),
+            (
+                "-*-*-*-*-*-*-12-*-*-*-m-*-*-*",
+                "-adobe-courier-bold-o-normal--12-120-75-75-m-70-iso8859-1",
+                true,
+            ),
+        ] {
+            assert_eq!(
+                wildmatch(pattern.as_bytes(), text.as_bytes()),
+                expected,
+                "{} {}",
+                pattern,
+                text
+            );
+        }
+    }
+}
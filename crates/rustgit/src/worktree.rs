@@ -0,0 +1,121 @@
+//! Update the files of the working tree
+
+use crate::config::ConfigError;
+use crate::index::EntryMetadata;
+use crate::object::{GITLINK_MODE, SYMLINK_MODE};
+use crate::Repository;
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+#[cfg(unix)]
+fn set_executable(path: &Path, executable: bool) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = fs::metadata(path)?.permissions();
+    let mode = permissions.mode();
+    // like git, give the execute permission to whoever can read the file
+    let mode = if executable {
+        mode | (mode & 0o444) >> 2
+    } else {
+        mode & !0o111
+    };
+    permissions.set_mode(mode);
+    fs::set_permissions(path, permissions)
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path, _executable: bool) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &[u8], path: &Path) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    std::os::unix::fs::symlink(std::ffi::OsStr::from_bytes(target), path)
+}
+
+// like git without symbolic links, write the target in a plain file
+#[cfg(not(unix))]
+fn create_symlink(target: &[u8], path: &Path) -> io::Result<()> {
+    fs::write(path, target)
+}
+
+/// Stat a file of the working tree for an index entry of mode `mode`. Like git, when symbolic
+/// links are not supported, a link checked out as a plain file keeps the mode of a link
+pub(crate) fn worktree_metadata(
+    full_path: &Path,
+    mode: u32,
+    symlinks: bool,
+) -> io::Result<EntryMetadata> {
+    let mut metadata = EntryMetadata::from_path(full_path)?;
+    if !symlinks && mode == SYMLINK_MODE && metadata.mode != SYMLINK_MODE {
+        metadata.mode = SYMLINK_MODE;
+    }
+    Ok(metadata)
+}
+
+impl Repository {
+    /// Whether symbolic links are checked out as such, `core.symlinks`, which is true by default
+    /// on Unix. Otherwise they are checked out as plain files that contain their target
+    pub(crate) fn has_symlinks(&self) -> Result<bool, ConfigError> {
+        Ok(self
+            .config()?
+            .get_bool("core.symlinks")?
+            .unwrap_or(cfg!(unix)))
+    }
+
+    /// Write a file of the working tree, `path` being relative to the repository. Missing
+    /// directories are created. A symbolic link is created as such when `symlinks` is set, see
+    /// `has_symlinks`. Like git without `--recurse-submodules`, a gitlink is only an empty
+    /// directory where the submodule can be checked out
+    pub(crate) fn write_worktree_file(
+        &self,
+        path: &Path,
+        mode: u32,
+        content: &[u8],
+        symlinks: bool,
+    ) -> io::Result<()> {
+        let full_path = self.repository_dir.join(path);
+        if mode == GITLINK_MODE {
+            return fs::create_dir_all(full_path);
+        }
+        fs::create_dir_all(full_path.parent().unwrap())?;
+
+        // replace the file rather than writing through it, in case it is a hard link
+        match fs::remove_file(&full_path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            result => result?,
+        }
+        if mode == SYMLINK_MODE && symlinks {
+            return create_symlink(content, &full_path);
+        }
+        fs::write(&full_path, content)?;
+        set_executable(&full_path, mode == 0o100755)
+    }
+
+    /// Remove a file of the working tree, along with the directories it leaves empty. The
+    /// directory of a gitlink is only removed when empty, like git does
+    pub(crate) fn remove_worktree_file(&self, path: &Path) -> io::Result<()> {
+        let full_path = self.repository_dir.join(path);
+        match fs::remove_file(&full_path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(_) if full_path.is_dir() => {
+                if fs::remove_dir(&full_path).is_err() {
+                    return Ok(());
+                }
+            }
+            result => result?,
+        }
+        for dir in path.ancestors().skip(1) {
+            if dir.as_os_str().is_empty() || fs::remove_dir(self.repository_dir.join(dir)).is_err()
+            {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
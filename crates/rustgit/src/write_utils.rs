@@ -1,6 +1,6 @@
 use crate::database::DatabaseWriteError;
 use crate::is_executable::IsExecutable;
-use crate::object::ObjectType;
+use crate::object::{hash_blob_file, ObjectType};
 use crate::repository::Repository;
 use crate::{object::ObjectBuffer, oid::ObjectId};
 use std::{fs, path::Path};
@@ -46,12 +46,7 @@ pub fn write_tree(repository: &Repository, path: &Path) -> Result<ObjectId, Data
                 0o100644
             };
 
-            let content = fs::read_to_string(child_path.to_str().unwrap())
-                .map_err(to_database_write_error(child_path.clone()))?;
-            let blob = ObjectBuffer::new(ObjectType::Blob, content.as_bytes());
-            let object_hash = ObjectId::from_object_buffer(&blob);
-
-            object_hash
+            hash_blob_file(&child_path).map_err(to_database_write_error(child_path.clone()))?
         } else if child_path.is_dir() {
             if child_path.ends_with(".git") {
                 // Ignore .git directory!
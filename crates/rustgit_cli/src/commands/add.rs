@@ -1,8 +1,8 @@
 use anyhow::Context;
 use clap::Args;
+use rustgit::ignore::Ignore;
 use rustgit::index::{EntryMetadata, Index};
 use rustgit::lockfile::Lockfile;
-use rustgit::object::Blob;
 use rustgit::Repository;
 use std::collections::BTreeSet;
 use std::fs;
@@ -11,6 +11,10 @@ use std::path::{Path, PathBuf};
 
 #[derive(Args, Debug)]
 pub struct AddArgs {
+    /// Allow adding otherwise ignored files
+    #[clap(short = 'f', long)]
+    force: bool,
+
     pathspecs: Vec<String>,
 }
 
@@ -36,65 +40,54 @@ fn parse_pathspec(pathspec: &str, current_dir: &Path, repo_path: &Path) -> anyho
     Ok(path)
 }
 
-#[cfg(windows)]
-fn get_metadata(path: &Path) -> anyhow::Result<EntryMetadata> {
-    let metadata = fs::metadata(path)?;
-
-    Ok(EntryMetadata {
-        ctime_seconds: 0,     // TODO
-        ctime_nanoseconds: 0, // TODO
-        mtime_seconds: 0,     // TODO
-        mtime_nanoseconds: 0, // TODO
-        dev: 0,
-        ino: 0,
-        mode: 0o100644, // TODO
-        uid: 0,
-        gid: 0,
-        file_size: metadata.len() as u32,
-    })
-}
-
-#[cfg(unix)]
-fn get_metadata(path: &Path) -> anyhow::Result<EntryMetadata> {
-    use std::os::unix::fs::MetadataExt;
-
-    let metadata = fs::metadata(path)?;
-
-    Ok(EntryMetadata {
-        ctime_seconds: metadata.ctime() as u32,
-        ctime_nanoseconds: metadata.ctime_nsec() as u32,
-        mtime_seconds: metadata.mtime() as u32,
-        mtime_nanoseconds: metadata.mtime_nsec() as u32,
-        dev: metadata.dev() as u32,
-        ino: metadata.ino() as u32,
-        mode: metadata.mode(),
-        uid: metadata.uid(),
-        gid: metadata.gid(),
-        file_size: metadata.size() as u32,
-    })
+// Where the files to add are searched
+struct FileSearch<'a> {
+    repository_dir: &'a Path,
+    index: &'a Index,
+    ignore: Option<Ignore>,
 }
 
-// Recursively search all files in a path
-fn add_files_inside(
-    path: PathBuf,
-    repository_dir: &Path,
-    output: &mut BTreeSet<PathBuf>,
-) -> anyhow::Result<()> {
-    // Ignore .git folder
-    if path.ends_with(".git") {
-        return Ok(());
+impl FileSearch<'_> {
+    // Tracked files are added even when they are ignored
+    fn is_ignored(&mut self, path: &Path, is_dir: bool) -> anyhow::Result<bool> {
+        let Some(ignore) = &mut self.ignore else {
+            return Ok(false);
+        };
+        let tracked = if is_dir {
+            self.index.iter().any(|entry| entry.path.starts_with(path))
+        } else {
+            self.index.get(path, 0).is_some()
+        };
+        Ok(!tracked && ignore.is_ignored(path, is_dir)?)
     }
 
-    if path.is_file() {
-        output.insert(path.strip_prefix(repository_dir)?.to_path_buf());
-    } else if path.is_dir() {
-        for entry in fs::read_dir(path)? {
-            add_files_inside(entry?.path(), repository_dir, output)?;
+    // Recursively search all files in a path that are not ignored
+    fn add_files_inside(
+        &mut self,
+        path: PathBuf,
+        output: &mut BTreeSet<PathBuf>,
+    ) -> anyhow::Result<()> {
+        // Ignore .git folder
+        if path.ends_with(".git") {
+            return Ok(());
         }
-    } else {
-        anyhow::bail!("Doesn't know how to handle symlink");
+
+        let relative_path = path.strip_prefix(self.repository_dir)?;
+        if path.is_file() {
+            if !self.is_ignored(relative_path, false)? {
+                output.insert(relative_path.to_path_buf());
+            }
+        } else if path.is_dir() {
+            if relative_path.as_os_str().is_empty() || !self.is_ignored(relative_path, true)? {
+                for entry in fs::read_dir(path)? {
+                    self.add_files_inside(entry?.path(), output)?;
+                }
+            }
+        } else {
+            anyhow::bail!("Doesn't know how to handle symlink");
+        }
+        Ok(())
     }
-    Ok(())
 }
 
 pub fn add(args: AddArgs) -> anyhow::Result<()> {
@@ -110,21 +103,43 @@ pub fn add(args: AddArgs) -> anyhow::Result<()> {
         .map(|pathspec| parse_pathspec(pathspec, &current_dir, &repo.repository_dir))
         .collect::<anyhow::Result<Vec<_>>>()?;
 
+    let mut index = Index::open(&repo.git_dir.join("index"))?;
+
     let mut files: BTreeSet<_> = BTreeSet::new();
-    for path in paths {
-        add_files_inside(path, &repo.repository_dir, &mut files)?;
+    // the paths given explicitly that are ignored
+    let mut ignored_paths = vec![];
+    let mut search = FileSearch {
+        repository_dir: &repo.repository_dir,
+        index: &index,
+        ignore: (!args.force).then(|| repo.ignore()).transpose()?,
+    };
+    for (path, pathspec) in paths.into_iter().zip(&args.pathspecs) {
+        let relative_path = path.strip_prefix(&repo.repository_dir)?;
+        if !relative_path.as_os_str().is_empty()
+            && search.is_ignored(relative_path, path.is_dir())?
+        {
+            ignored_paths.push(pathspec);
+            continue;
+        }
+        search.add_files_inside(path, &mut files)?;
     }
 
-    let mut index = Index::open(&repo.git_dir.join("index"))?;
-
     for file_path in files {
-        let body = fs::read_to_string(&file_path)?;
-        let blob = Blob::new(body.into_bytes().into_boxed_slice());
-        let oid = repo.write_object(&blob)?;
-        let metadata = get_metadata(&file_path)?;
+        let oid = repo.write_blob_file(&file_path)?;
+        let metadata = EntryMetadata::from_path(&file_path)?;
 
         index.add(file_path, oid, metadata)
     }
     index.write_to(&mut index_lockfile)?;
-    index_lockfile.commit().context("commit lockfile")
+    index_lockfile.commit().context("commit lockfile")?;
+
+    if !ignored_paths.is_empty() {
+        let paths: Vec<&str> = ignored_paths.iter().map(|path| path.as_str()).collect();
+        anyhow::bail!(
+            "The following paths are ignored by one of your .gitignore files:\n{}\n\
+             hint: Use -f if you really want to add them.",
+            paths.join("\n")
+        );
+    }
+    Ok(())
 }
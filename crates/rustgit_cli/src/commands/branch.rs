@@ -0,0 +1,250 @@
+use anyhow::{bail, Context};
+use clap::Args;
+use rustgit::{
+    branch::{Branch, BranchError, BranchKind},
+    head::Head,
+    object::ObjectType,
+    oid::ObjectId,
+    references::Ref,
+    refname::is_valid_branch_name,
+    Repository,
+};
+
+#[derive(Args, Debug)]
+pub struct BranchArgs {
+    /// Show the object id and the subject of the commit each branch points to
+    #[clap(short = 'v', long)]
+    verbose: bool,
+
+    /// List both local and remote-tracking branches
+    #[clap(short = 'a', long, conflicts_with = "remotes")]
+    all: bool,
+
+    /// List the remote-tracking branches
+    #[clap(short = 'r', long)]
+    remotes: bool,
+
+    /// Print the name of the current branch, nothing when HEAD is detached
+    #[clap(long, conflicts_with_all = ["verbose", "all", "remotes", "rename", "delete"])]
+    show_current: bool,
+
+    /// Rename a branch, the current one if only the new name is given
+    #[clap(short = 'm', long = "move", conflicts_with = "delete")]
+    rename: bool,
+
+    /// Rename a branch even if the new name already exists
+    #[clap(short = 'M', conflicts_with = "delete")]
+    force_rename: bool,
+
+    /// Delete fully merged branches
+    #[clap(short = 'd', long)]
+    delete: bool,
+
+    /// Delete branches, even if they are not fully merged
+    #[clap(short = 'D')]
+    force_delete: bool,
+
+    /// Reset the branch to the start point if it already exists. With -d, delete branches even
+    /// if they are not fully merged. With -m, rename even if the new name already exists
+    #[clap(short = 'f', long)]
+    force: bool,
+
+    /// The branches to create, rename or delete, followed by the start point of a new branch
+    names: Vec<String>,
+}
+
+// The name of a branch as listed: remote-tracking branches are prefixed by `remotes/` when
+// listed along with the local ones
+fn display_name(branch: &Branch, all: bool) -> String {
+    match branch.kind {
+        BranchKind::Remote if all => format!("remotes/{}", branch.name),
+        _ => branch.name.clone(),
+    }
+}
+
+fn commit_line(repository: &Repository, oid: ObjectId) -> anyhow::Result<String> {
+    let commit = repository.read_commit(oid)?;
+    Ok(format!(
+        "{} {}",
+        repository.abbreviate(oid, repository.default_abbrev_length()?)?,
+        commit.summary()
+    ))
+}
+
+fn list_branches(repository: &Repository, args: &BranchArgs) -> anyhow::Result<()> {
+    let mut kinds = vec![];
+    if !args.remotes {
+        kinds.push(BranchKind::Local);
+    }
+    if args.remotes || args.all {
+        kinds.push(BranchKind::Remote);
+    }
+    let mut branches = vec![];
+    for kind in kinds {
+        branches.extend(repository.branches(kind)?);
+    }
+
+    let head = repository.head()?;
+    // like git, a detached HEAD is listed first
+    let detached = match head {
+        Head::Detached(oid) if !args.remotes => Some((
+            format!(
+                "(HEAD detached at {})",
+                repository.abbreviate(oid, repository.default_abbrev_length()?)?
+            ),
+            oid,
+        )),
+        _ => None,
+    };
+
+    let width = branches
+        .iter()
+        .map(|branch| display_name(branch, args.all).len())
+        .chain(detached.iter().map(|(name, _)| name.len()))
+        .max()
+        .unwrap_or(0);
+
+    if let Some((name, oid)) = detached {
+        match args.verbose {
+            true => println!("* {:width$} {}", name, commit_line(repository, oid)?),
+            false => println!("* {}", name),
+        }
+    }
+    for branch in &branches {
+        let name = display_name(branch, args.all);
+        let is_head = Some(branch.reference_name().as_str()) == head.referent_name();
+        let marker = if is_head { '*' } else { ' ' };
+
+        match &branch.target {
+            Ref::Symbolic(target) => {
+                let target = target.strip_prefix("refs/remotes/").unwrap_or(target);
+                println!("{} {} -> {}", marker, name, target);
+            }
+            Ref::Peeled(oid) if args.verbose => {
+                println!(
+                    "{} {:width$} {}",
+                    marker,
+                    name,
+                    commit_line(repository, *oid)?
+                );
+            }
+            Ref::Peeled(_) => println!("{} {}", marker, name),
+        }
+    }
+    Ok(())
+}
+
+fn create_branch(repository: &Repository, args: &BranchArgs) -> anyhow::Result<()> {
+    let (name, start_point) = match args.names.as_slice() {
+        [name] => (name, None),
+        [name, start_point] => (name, Some(start_point.as_str())),
+        _ => bail!("too many arguments to create a branch"),
+    };
+    if !is_valid_branch_name(name) {
+        bail!("'{}' is not a valid branch name", name);
+    }
+
+    let start_point = match start_point {
+        Some(start_point) => start_point.to_string(),
+        None => match repository.head()? {
+            Head::Unborn(branch) => {
+                let branch = branch.strip_prefix("refs/heads/").unwrap_or(&branch);
+                bail!("not a valid object name: '{}'", branch)
+            }
+            _ => "HEAD".to_string(),
+        },
+    };
+    let target = repository
+        .resolve_revision(&start_point)
+        .ok()
+        .and_then(|oid| repository.peel(oid, ObjectType::Commit).ok())
+        .with_context(|| format!("not a valid object name: '{}'", start_point))?;
+
+    let exists = repository
+        .try_find_reference(&format!("refs/heads/{}", name))?
+        .is_some();
+    let message = match exists {
+        true => format!("branch: Reset to {}", start_point),
+        false => format!("branch: Created from {}", start_point),
+    };
+    repository.create_branch(name, target, args.force, &message)?;
+    Ok(())
+}
+
+fn rename_branch(repository: &Repository, args: &BranchArgs) -> anyhow::Result<()> {
+    let (old_name, new_name) = match args.names.as_slice() {
+        [new_name] => (
+            repository
+                .current_branch()?
+                .context("cannot rename the current branch while not on any")?,
+            new_name,
+        ),
+        [old_name, new_name] => (old_name.clone(), new_name),
+        _ => bail!("too many arguments for a rename operation"),
+    };
+
+    let force = args.force_rename || args.force;
+    match repository.rename_branch(&old_name, new_name, force) {
+        Err(BranchError::NotFound(name)) => bail!("No branch named '{}'.", name),
+        result => Ok(result?),
+    }
+}
+
+fn delete_branches(repository: &Repository, args: &BranchArgs) -> anyhow::Result<()> {
+    if args.names.is_empty() {
+        bail!("branch name required");
+    }
+
+    let force = args.force_delete || args.force;
+    let mut failed = false;
+    for name in &args.names {
+        match repository.delete_branch(name, force) {
+            Ok(oid) => println!(
+                "Deleted branch {} (was {}).",
+                name,
+                repository.abbreviate(oid, repository.default_abbrev_length()?)?
+            ),
+            Err(BranchError::NotFound(name)) => {
+                eprintln!("error: branch '{}' not found.", name);
+                failed = true;
+            }
+            Err(BranchError::NotFullyMerged(name)) => {
+                eprintln!(
+                    "error: The branch '{}' is not fully merged.\n\
+                     If you are sure you want to delete it, run 'rustgit branch -D {}'.",
+                    name, name
+                );
+                failed = true;
+            }
+            Err(e @ BranchError::DeleteCurrent { .. }) => {
+                eprintln!("error: {}", e);
+                failed = true;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+pub fn branch(args: BranchArgs) -> anyhow::Result<()> {
+    let repository = Repository::search_and_open(&std::env::current_dir()?)?;
+
+    if args.show_current {
+        if let Some(branch) = repository.current_branch()? {
+            println!("{}", branch);
+        }
+        Ok(())
+    } else if args.delete || args.force_delete {
+        delete_branches(&repository, &args)
+    } else if args.rename || args.force_rename {
+        rename_branch(&repository, &args)
+    } else if args.names.is_empty() {
+        list_branches(&repository, &args)
+    } else {
+        create_branch(&repository, &args)
+    }
+}
@@ -2,7 +2,6 @@ use anyhow::Context;
 use clap::Args;
 use rustgit::{
     object::{read_header, ObjectHeader, ObjectType},
-    oid::ObjectId,
     Repository,
 };
 use std::io::{prelude::*, Write};
@@ -20,8 +19,7 @@ pub fn cat_file(args: CatFileArgs) -> anyhow::Result<()> {
 
     let repository = Repository::search_and_open(&std::env::current_dir()?)?;
 
-    // TODO: support shortest unique object hashes
-    let object_hash = ObjectId::from_unvalidated_sh1_hex_string(&args.object_hash)?;
+    let object_hash = repository.resolve_revision(&args.object_hash)?;
     let mut decoder = repository.object_reader(object_hash)?;
 
     let ObjectHeader { typ, size } = read_header(&mut decoder)?;
@@ -30,8 +28,7 @@ pub fn cat_file(args: CatFileArgs) -> anyhow::Result<()> {
         unimplemented!("cat-file for non-blob is not implemented yet");
     }
 
-    let mut output = vec![];
-    output.resize(size, 0);
+    let mut output = vec![0; size];
     decoder.read_exact(&mut output)?;
 
     let n = decoder
@@ -0,0 +1,71 @@
+use super::log::repository_path;
+use clap::Args;
+use rustgit::Repository;
+use std::path::Path;
+
+#[derive(Args, Debug)]
+pub struct CheckIgnoreArgs {
+    /// Output the pattern matching each path, with the file and line it comes from
+    #[clap(short = 'v', long)]
+    verbose: bool,
+
+    /// Only set the exit status, without printing anything
+    #[clap(short = 'q', long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// With --verbose, also show the paths that don't match any pattern
+    #[clap(short = 'n', long, requires = "verbose")]
+    non_matching: bool,
+
+    /// Also check the paths that are tracked
+    #[clap(long)]
+    no_index: bool,
+
+    /// The paths to check
+    #[clap(required = true)]
+    pathnames: Vec<String>,
+}
+
+pub fn check_ignore(args: CheckIgnoreArgs) -> anyhow::Result<()> {
+    let current_dir = std::env::current_dir()?;
+    let repository = Repository::search_and_open(&current_dir)?;
+    let index = repository.index()?;
+    let mut ignore = repository.ignore()?;
+
+    let mut any_ignored = false;
+    for pathname in &args.pathnames {
+        let path = repository_path(
+            Path::new(pathname),
+            &current_dir,
+            &repository.repository_dir,
+        );
+        let is_dir = repository.repository_dir.join(&path).is_dir();
+
+        // like git, tracked files are never ignored
+        let tracked = !args.no_index && index.get(&path, 0).is_some();
+        let pattern = match tracked {
+            true => None,
+            false => ignore.matching_pattern(&path, is_dir)?,
+        };
+        any_ignored |= pattern.as_ref().is_some_and(|pattern| !pattern.negated);
+
+        match pattern {
+            _ if args.quiet => {}
+            Some(pattern) if args.verbose => println!(
+                "{}:{}:{}\t{}",
+                pattern.source.display(),
+                pattern.line,
+                pattern.pattern,
+                pathname
+            ),
+            Some(pattern) if !pattern.negated => println!("{}", pathname),
+            None if args.non_matching => println!("::\t{}", pathname),
+            _ => {}
+        }
+    }
+
+    if !any_ignored {
+        std::process::exit(1);
+    }
+    Ok(())
+}
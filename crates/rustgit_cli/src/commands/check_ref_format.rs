@@ -0,0 +1,71 @@
+use anyhow::bail;
+use clap::Args;
+use rustgit::{
+    refname::{is_valid_branch_name, is_valid_ref_name, normalize_ref_name, RefNameOptions},
+    Repository,
+};
+
+#[derive(Args, Debug)]
+pub struct CheckRefFormatArgs {
+    /// Accept a name with a single component, such as `HEAD`
+    #[clap(long)]
+    allow_onelevel: bool,
+
+    /// Accept a single `*`, as in the patterns of refspecs
+    #[clap(long)]
+    refspec_pattern: bool,
+
+    /// Remove the leading slashes and collapse the consecutive ones, then print the name if it
+    /// is valid
+    #[clap(long)]
+    normalize: bool,
+
+    /// Check that the name is valid for a branch and print it, `@{-<n>}` being the branch
+    /// checked out before the n-th last checkout
+    #[clap(long, conflicts_with_all = ["allow_onelevel", "refspec_pattern", "normalize"])]
+    branch: bool,
+
+    /// The name to check
+    #[clap(value_name = "refname", allow_hyphen_values = true)]
+    name: String,
+}
+
+// Expand `@{-<n>}` to the branch it refers to, None if there is no such branch
+fn expand_previous_branch(name: &str) -> anyhow::Result<Option<String>> {
+    let n = name
+        .strip_prefix("@{-")
+        .and_then(|n| n.strip_suffix('}'))
+        .and_then(|n| n.parse::<usize>().ok());
+    let Some(n) = n else {
+        return Ok(Some(name.to_string()));
+    };
+
+    let repository = Repository::search_and_open(&std::env::current_dir()?)?;
+    Ok(repository.previous_branch(n)?)
+}
+
+pub fn check_ref_format(args: CheckRefFormatArgs) -> anyhow::Result<()> {
+    if args.branch {
+        match expand_previous_branch(&args.name)? {
+            Some(branch) if is_valid_branch_name(&branch) => println!("{}", branch),
+            _ => bail!("'{}' is not a valid branch name", args.name),
+        }
+        return Ok(());
+    }
+
+    let name = match args.normalize {
+        true => normalize_ref_name(&args.name),
+        false => args.name,
+    };
+    let options = RefNameOptions {
+        allow_onelevel: args.allow_onelevel,
+        refspec_pattern: args.refspec_pattern,
+    };
+    if !is_valid_ref_name(&name, options) {
+        std::process::exit(1);
+    }
+    if args.normalize {
+        println!("{}", name);
+    }
+    Ok(())
+}
@@ -0,0 +1,237 @@
+use anyhow::bail;
+use clap::Args;
+use rustgit::{
+    checkout::{CheckoutError, CheckoutTarget},
+    head::Head,
+    object::ObjectType,
+    oid::ObjectId,
+    refname::is_valid_branch_name,
+    status::ChangeKind,
+    Repository,
+};
+use std::{collections::BTreeMap, path::PathBuf};
+
+#[derive(Args, Debug)]
+pub struct CheckoutArgs {
+    /// Create a new branch starting at the given commit and switch to it
+    #[clap(short = 'b')]
+    new_branch: Option<String>,
+
+    /// Detach HEAD at the commit, even if a branch is given
+    #[clap(long)]
+    detach: bool,
+
+    /// The branch to switch to, or a commit to detach HEAD at. With -b, the start point of the
+    /// new branch
+    target: Option<String>,
+}
+
+/// Where to switch to
+pub(super) enum Destination {
+    Branch(String),
+    NewBranch {
+        name: String,
+        start_point: Option<String>,
+    },
+    Detached(String),
+}
+
+const DETACHED_HEAD_ADVICE: &str = "\
+You are in 'detached HEAD' state. You can look around, make experimental
+changes and commit them, and you can discard any commits you make in this
+state without impacting any branches by switching back to a branch.
+
+If you want to create a new branch to retain commits you create, you may
+do so (now or later) by using -c with the switch command. Example:
+
+  rustgit switch -c <new-branch-name>
+
+Turn off this advice by setting config variable advice.detachedHead to false
+";
+
+fn resolve_commit(repository: &Repository, spec: &str) -> anyhow::Result<ObjectId> {
+    let oid = repository.resolve_revision(spec)?;
+    Ok(repository.peel(oid, ObjectType::Commit)?)
+}
+
+fn describe_commit(repository: &Repository, oid: ObjectId) -> anyhow::Result<String> {
+    Ok(format!(
+        "{} {}",
+        repository.abbreviate(oid, repository.default_abbrev_length()?)?,
+        repository.read_commit(oid)?.summary()
+    ))
+}
+
+// Like git, list the files with local changes that were carried over
+fn print_local_changes(repository: &Repository) -> anyhow::Result<()> {
+    let status = repository.status()?;
+    let mut changes: BTreeMap<&PathBuf, ChangeKind> = status
+        .staged
+        .iter()
+        .map(|(path, kind)| (path, *kind))
+        .collect();
+    for (path, kind) in &status.unstaged {
+        match (changes.get(path), kind) {
+            // added then removed from the working tree: nothing changed compared to HEAD
+            (Some(ChangeKind::Added), ChangeKind::Deleted) => {
+                changes.remove(path);
+            }
+            (Some(ChangeKind::Added), _) => {}
+            _ => {
+                changes.insert(path, *kind);
+            }
+        }
+    }
+    for (path, kind) in changes {
+        let letter = match kind {
+            ChangeKind::Added => 'A',
+            ChangeKind::Modified => 'M',
+            ChangeKind::Deleted => 'D',
+        };
+        println!("{}\t{}", letter, path.display());
+    }
+    Ok(())
+}
+
+// Print the errors that git reports without `fatal:`, and exit like git
+fn report_checkout_error(e: CheckoutError) -> anyhow::Error {
+    match e {
+        CheckoutError::LocalChanges(_) => {
+            eprintln!(
+                "error: {}\nPlease commit your changes or stash them before you switch branches.\n\
+                 Aborting",
+                e
+            );
+            std::process::exit(1);
+        }
+        CheckoutError::UntrackedFiles(_) => {
+            eprintln!(
+                "error: {}\nPlease move or remove them before you switch branches.\nAborting",
+                e
+            );
+            std::process::exit(1);
+        }
+        CheckoutError::UntrackedDirectories(_) => {
+            eprintln!("error: {}\n\nAborting", e);
+            std::process::exit(1);
+        }
+        CheckoutError::UnmergedFiles => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        e => e.into(),
+    }
+}
+
+/// Switch HEAD to a branch or a commit, updating the index and the working tree
+pub(super) fn switch_to(repository: &Repository, destination: Destination) -> anyhow::Result<()> {
+    let old_head = repository.head()?;
+    // like git, the reflog of HEAD tells where HEAD moved from
+    let from = match &old_head {
+        Head::Detached(oid) => oid.to_string(),
+        head => {
+            let name = head.referent_name().unwrap();
+            name.strip_prefix("refs/heads/").unwrap_or(name).to_string()
+        }
+    };
+
+    let (commit, target, spec) = match &destination {
+        Destination::Branch(name) => {
+            let reference = format!("refs/heads/{}", name);
+            let commit = resolve_commit(repository, &reference)?;
+            (commit, CheckoutTarget::Branch(reference), name)
+        }
+        Destination::NewBranch { name, start_point } => {
+            if !is_valid_branch_name(name) {
+                bail!("'{}' is not a valid branch name", name);
+            }
+            let reference = format!("refs/heads/{}", name);
+            if repository.try_find_reference(&reference)?.is_some() {
+                bail!("a branch named '{}' already exists", name);
+            }
+            // a new branch on an unborn HEAD is born with the next commit
+            if start_point.is_none() && old_head.is_unborn() {
+                let message = format!("checkout: moving from {} to {}", from, name);
+                repository.update_symbolic_reference("HEAD", &reference, &message)?;
+                eprintln!("Switched to a new branch '{}'", name);
+                return Ok(());
+            }
+            let start_point = start_point.as_deref().unwrap_or("HEAD");
+            let commit = resolve_commit(repository, start_point)?;
+            (commit, CheckoutTarget::Branch(reference), name)
+        }
+        Destination::Detached(spec) => {
+            let commit = resolve_commit(repository, spec)?;
+            (commit, CheckoutTarget::Detached, spec)
+        }
+    };
+
+    if let Destination::NewBranch { name, start_point } = &destination {
+        let start_point = start_point.as_deref().unwrap_or("HEAD");
+        let message = format!("branch: Created from {}", start_point);
+        repository.create_branch(name, commit, false, &message)?;
+    }
+    let message = format!("checkout: moving from {} to {}", from, spec);
+    let result = repository.checkout(commit, &target, &message);
+    if let (Err(_), Destination::NewBranch { name, .. }) = (&result, &destination) {
+        // the new branch is only kept if it could be checked out
+        repository.delete_branch(name, true)?;
+    }
+    result.map_err(report_checkout_error)?;
+    print_local_changes(repository)?;
+
+    if let Head::Detached(old_commit) = old_head {
+        if old_commit != commit {
+            eprintln!(
+                "Previous HEAD position was {}",
+                describe_commit(repository, old_commit)?
+            );
+        }
+    }
+    match (&destination, &target) {
+        (Destination::NewBranch { name, .. }, _) => {
+            eprintln!("Switched to a new branch '{}'", name);
+        }
+        (_, CheckoutTarget::Branch(reference)) if old_head.referent_name() == Some(reference) => {
+            eprintln!("Already on '{}'", spec);
+        }
+        (_, CheckoutTarget::Branch(_)) => eprintln!("Switched to branch '{}'", spec),
+        (_, CheckoutTarget::Detached) => {
+            let advice = repository
+                .config()?
+                .get_bool("advice.detachedHead")?
+                .unwrap_or(true);
+            if advice && !matches!(old_head, Head::Detached(_)) {
+                eprintln!("Note: switching to '{}'.\n\n{}", spec, DETACHED_HEAD_ADVICE);
+            }
+            eprintln!("HEAD is now at {}", describe_commit(repository, commit)?);
+        }
+    }
+    Ok(())
+}
+
+pub fn checkout(args: CheckoutArgs) -> anyhow::Result<()> {
+    let repository = Repository::search_and_open(&std::env::current_dir()?)?;
+
+    let destination = match (args.new_branch, args.target) {
+        (Some(name), start_point) => Destination::NewBranch { name, start_point },
+        (None, None) => bail!("you must specify a branch or a commit to check out"),
+        (None, Some(target)) => {
+            let is_branch = repository
+                .try_find_reference(&format!("refs/heads/{}", target))?
+                .is_some();
+            if is_branch && !args.detach {
+                Destination::Branch(target)
+            } else if resolve_commit(&repository, &target).is_ok() {
+                Destination::Detached(target)
+            } else {
+                eprintln!(
+                    "error: pathspec '{}' did not match any file(s) known to git",
+                    target
+                );
+                std::process::exit(1);
+            }
+        }
+    };
+    switch_to(&repository, destination)
+}
@@ -1,6 +1,5 @@
 use clap::Args;
-use rustgit::{lockfile::Lockfile, object::Commit, references::ReferenceError, Repository};
-use std::io::Write;
+use rustgit::{head::Head, object::Commit, oid::ObjectId, references::ReferenceError, Repository};
 
 #[derive(Args, Debug)]
 pub struct CommitArgs {
@@ -10,14 +9,19 @@ pub struct CommitArgs {
 
 pub fn commit(args: CommitArgs) -> anyhow::Result<()> {
     let repository = Repository::search_and_open(&std::env::current_dir()?)?;
-    let working_dir = std::env::current_dir()?;
 
     let CommitArgs { message } = args;
 
     // TODO: check whether we have something to commit (is working tree clean?)
 
-    // git write-tree
-    let tree_sha = rustgit::write_utils::write_tree(&repository, &working_dir)?;
+    // concluding a merge, whose conflicts must have been resolved
+    let merge_head = repository.merge_head()?;
+    if merge_head.is_some() && repository.index()?.conflicts().next().is_some() {
+        anyhow::bail!("Committing is not possible because you have unmerged files.");
+    }
+
+    // git write-tree, from the index so that only what is staged gets committed
+    let tree_sha = repository.write_tree()?;
 
     // get current commit
     let parent_commit_sha = match repository.head_id() {
@@ -26,27 +30,34 @@ pub fn commit(args: CommitArgs) -> anyhow::Result<()> {
         Err(e) => Err(e),
     }?;
 
-    // git commit-tree
-    let author = rustgit::object::get_author();
-    let commit_hash =
-        repository.write_object(&Commit::new(tree_sha, parent_commit_sha, author, message))?;
-
-    // update-ref for the current branch
-    let head_path = repository.git_dir.join("HEAD");
-    let _head_lock = Lockfile::new(&head_path)?;
-
-    let head_content = std::fs::read_to_string(head_path)?;
-
-    if head_content.starts_with("ref: ") {
-        let reference = head_content[5..].trim();
-        let reference_path = repository.git_dir.join(reference);
-        let mut reference_lock = Lockfile::new(&reference_path)?;
-        reference_lock.write_all(&commit_hash.to_hex_string().0)?;
-        reference_lock.commit()?;
-    } else {
-        // TODO: detached head
+    // TODO: detached head
+    if let Head::Detached(_) = repository.head()? {
         anyhow::bail!("`rustgit commit` on detached head is not supported");
     }
 
+    // git commit-tree
+    let config = repository.config()?;
+    let author = rustgit::object::get_author(&config)?;
+    let committer = rustgit::object::get_committer(&config)?;
+    let kind = if merge_head.is_some() {
+        " (merge)"
+    } else if parent_commit_sha.is_none() {
+        " (initial)"
+    } else {
+        ""
+    };
+    let parents = parent_commit_sha.into_iter().chain(merge_head).collect();
+    let commit = Commit::new(tree_sha, parents, author, committer, message);
+    let reflog_message = format!("commit{}: {}", kind, commit.summary());
+    let commit_hash = repository.write_object(&commit)?;
+
+    // update-ref for the current branch, unless it moved since we read it
+    let branch = repository.referent("HEAD")?;
+    let expected = parent_commit_sha.unwrap_or(ObjectId::NULL);
+    let mut transaction = repository.transaction(&reflog_message);
+    transaction.update(&branch, commit_hash, Some(expected));
+    transaction.commit()?;
+    repository.clear_merge_state()?;
+
     Ok(())
 }
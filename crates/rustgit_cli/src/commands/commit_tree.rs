@@ -1,10 +1,14 @@
 use clap::Args;
-use rustgit::{object::Commit, oid::ObjectId, Repository};
+use rustgit::{
+    object::{Commit, ObjectType},
+    Repository,
+};
 
 #[derive(Args, Debug)]
 pub struct CommitTreeArgs {
+    /// A parent of the new commit, which can be repeated for a merge commit
     #[clap(short = 'p')]
-    parent_commit_sha: Option<String>,
+    parent_commit_shas: Vec<String>,
 
     #[clap(short = 'm')]
     message: String,
@@ -13,19 +17,29 @@ pub struct CommitTreeArgs {
 }
 
 pub fn commit_tree(args: CommitTreeArgs) -> anyhow::Result<()> {
-    let tree_sha = ObjectId::from_unvalidated_sh1_hex_string(&args.tree_sha)?;
-
-    let parent_commit_sha = args
-        .parent_commit_sha
-        .map(|sha| ObjectId::from_unvalidated_sh1_hex_string(&sha))
-        .transpose()?;
-
     let repository = Repository::search_and_open(&std::env::current_dir()?)?;
-    let author = rustgit::object::get_author();
+
+    let tree_sha = repository.resolve_revision(&args.tree_sha)?;
+    let tree_sha = repository.peel(tree_sha, ObjectType::Tree)?;
+
+    // like git, a parent given twice is only recorded once
+    let mut parent_commit_shas = vec![];
+    for revision in &args.parent_commit_shas {
+        let oid = repository.resolve_revision(revision)?;
+        let oid = repository.peel(oid, ObjectType::Commit)?;
+        if parent_commit_shas.contains(&oid) {
+            eprintln!("error: duplicate parent {} ignored", oid);
+        } else {
+            parent_commit_shas.push(oid);
+        }
+    }
+
+    let config = repository.config()?;
     let commit_hash = repository.write_object(&Commit::new(
         tree_sha,
-        parent_commit_sha,
-        author,
+        parent_commit_shas,
+        rustgit::object::get_author(&config)?,
+        rustgit::object::get_committer(&config)?,
         args.message,
     ))?;
 
@@ -0,0 +1,269 @@
+use clap::{Args, ValueEnum};
+use rustgit::config::{
+    expand_path, parse_bool, parse_int, Config, ConfigEntry, ConfigError, ConfigFile,
+};
+use rustgit::Repository;
+use std::path::PathBuf;
+use std::process::exit;
+
+#[derive(Args, Debug)]
+#[clap(group = clap::ArgGroup::new("scope"), group = clap::ArgGroup::new("action"))]
+pub struct ConfigArgs {
+    /// Use the global config file of the user
+    #[clap(long, group = "scope")]
+    global: bool,
+
+    /// Use the system-wide config file
+    #[clap(long, group = "scope")]
+    system: bool,
+
+    /// Use the config file of the repository. This is the default when writing
+    #[clap(long, group = "scope")]
+    local: bool,
+
+    /// Use the given config file
+    #[clap(short = 'f', long, value_name = "file", group = "scope")]
+    file: Option<PathBuf>,
+
+    /// Follow `include.path` when reading a specific file. This is the default when reading
+    /// all the config files
+    #[clap(long, overrides_with = "no_includes")]
+    includes: bool,
+
+    /// Don't follow `include.path`
+    #[clap(long, overrides_with = "includes")]
+    no_includes: bool,
+
+    /// Interpret and write values as the given type
+    #[clap(long = "type", value_enum, value_name = "type")]
+    typ: Option<ValueType>,
+
+    /// Same as `--type=bool`
+    #[clap(long, conflicts_with_all = ["typ", "int", "path"])]
+    bool: bool,
+
+    /// Same as `--type=int`
+    #[clap(long, conflicts_with_all = ["typ", "path"])]
+    int: bool,
+
+    /// Same as `--type=path`
+    #[clap(long, conflicts_with = "typ")]
+    path: bool,
+
+    /// Get the last value of a variable
+    #[clap(long, group = "action")]
+    get: bool,
+
+    /// Get all the values of a multi-valued variable
+    #[clap(long, group = "action")]
+    get_all: bool,
+
+    /// Set a variable, replacing its value
+    #[clap(long, group = "action")]
+    set: bool,
+
+    /// Add a value to a variable without replacing its existing values
+    #[clap(long, group = "action")]
+    add: bool,
+
+    /// Remove a variable
+    #[clap(long, group = "action")]
+    unset: bool,
+
+    /// Remove all the values of a multi-valued variable
+    #[clap(long, group = "action")]
+    unset_all: bool,
+
+    /// List all the variables and their values
+    #[clap(short = 'l', long, group = "action", conflicts_with_all = ["name", "value"])]
+    list: bool,
+
+    /// The name of the variable, e.g. `user.name` or `remote.origin.url`
+    name: Option<String>,
+
+    /// The value to set
+    value: Option<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ValueType {
+    Bool,
+    Int,
+    Path,
+}
+
+impl ConfigArgs {
+    fn value_type(&self) -> Option<ValueType> {
+        if self.bool {
+            Some(ValueType::Bool)
+        } else if self.int {
+            Some(ValueType::Int)
+        } else if self.path {
+            Some(ValueType::Path)
+        } else {
+            self.typ
+        }
+    }
+
+    // The file to read from, or None to read all of them
+    fn file_to_read(&self) -> anyhow::Result<Option<PathBuf>> {
+        if self.global {
+            Ok(Config::global_path())
+        } else if self.system || self.local || self.file.is_some() {
+            self.file_to_write().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn file_to_write(&self) -> anyhow::Result<PathBuf> {
+        if let Some(file) = &self.file {
+            Ok(file.clone())
+        } else if self.global {
+            Config::global_path().ok_or_else(|| anyhow::anyhow!("$HOME not set"))
+        } else if self.system {
+            Config::system_path().ok_or_else(|| anyhow::anyhow!("system config is disabled"))
+        } else {
+            let repository = Repository::search_and_open(&std::env::current_dir()?)?;
+            Ok(repository.config_path())
+        }
+    }
+}
+
+// Show a value as the given type
+fn format_value(entry: &ConfigEntry, value_type: Option<ValueType>) -> Result<String, ConfigError> {
+    let value = entry.value.as_deref();
+    let invalid = |expected| ConfigError::InvalidValue {
+        name: entry.name.to_string(),
+        value: value.unwrap_or_default().to_string(),
+        expected,
+    };
+    match value_type {
+        None => Ok(value.unwrap_or_default().to_string()),
+        Some(ValueType::Bool) => parse_bool(value)
+            .map(|b| b.to_string())
+            .ok_or_else(|| invalid("boolean")),
+        Some(ValueType::Int) => parse_int(value.unwrap_or_default())
+            .map(|n| n.to_string())
+            .ok_or_else(|| invalid("numeric")),
+        Some(ValueType::Path) => Ok(expand_path(value.unwrap_or_default()).display().to_string()),
+    }
+}
+
+// Write values of a type in their canonical form, e.g. `yes` as `true` and `1k` as `1024`
+fn normalize_value(
+    name: &str,
+    value: &str,
+    value_type: Option<ValueType>,
+) -> Result<String, ConfigError> {
+    let invalid = |expected| ConfigError::InvalidValue {
+        name: name.to_string(),
+        value: value.to_string(),
+        expected,
+    };
+    match value_type {
+        Some(ValueType::Bool) => parse_bool(Some(value))
+            .map(|b| b.to_string())
+            .ok_or_else(|| invalid("boolean")),
+        Some(ValueType::Int) => parse_int(value)
+            .map(|n| n.to_string())
+            .ok_or_else(|| invalid("numeric")),
+        None | Some(ValueType::Path) => Ok(value.to_string()),
+    }
+}
+
+fn read_config(args: &ConfigArgs) -> anyhow::Result<Config> {
+    let git_dir = Repository::search_and_open(&std::env::current_dir()?)
+        .ok()
+        .map(|repository| repository.git_dir);
+    match args.file_to_read()? {
+        Some(path) if args.includes => Ok(Config::open(&path, git_dir.as_deref())?),
+        Some(path) => Ok(ConfigFile::open(&path)?.into()),
+        None => Ok(Config::open_all(git_dir.as_deref())?),
+    }
+}
+
+fn required<'a>(value: &'a Option<String>, what: &str) -> anyhow::Result<&'a str> {
+    value
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("missing {}", what))
+}
+
+fn config_impl(args: &ConfigArgs) -> anyhow::Result<()> {
+    let value_type = args.value_type();
+
+    if args.list {
+        for entry in read_config(args)?.entries() {
+            match &entry.value {
+                Some(value) => println!("{}={}", entry.name, value),
+                None => println!("{}", entry.name),
+            }
+        }
+        return Ok(());
+    }
+
+    let name = required(&args.name, "variable name")?;
+    let writing = args.set || args.add || args.unset || args.unset_all;
+    if !writing && (args.get || args.get_all || args.value.is_none()) {
+        if args.value.is_some() {
+            anyhow::bail!("value patterns are not supported");
+        }
+        rustgit::config::ConfigName::parse(name)?;
+
+        let config = read_config(args)?;
+        let entries: Vec<&ConfigEntry> = config.get_entries(name).collect();
+        let shown = if args.get_all {
+            &entries[..]
+        } else {
+            &entries[entries.len().saturating_sub(1)..]
+        };
+        if shown.is_empty() {
+            exit(1);
+        }
+        for entry in shown {
+            println!("{}", format_value(entry, value_type)?);
+        }
+        return Ok(());
+    }
+
+    let mut file = ConfigFile::open(&args.file_to_write()?)?;
+    if args.unset || args.unset_all {
+        if args.value.is_some() {
+            anyhow::bail!("value patterns are not supported");
+        }
+        if args.unset {
+            file.unset(name)?;
+        } else if file.unset_all(name)? == 0 {
+            return Err(ConfigError::NotFound(name.to_string()).into());
+        }
+    } else {
+        let value = normalize_value(name, required(&args.value, "value")?, value_type)?;
+        if args.add {
+            file.add(name, &value)?;
+        } else {
+            file.set(name, &value)?;
+        }
+    }
+    file.save()?;
+    Ok(())
+}
+
+pub fn config(args: ConfigArgs) -> anyhow::Result<()> {
+    // like git, report errors about the variables with their own exit code
+    let result = config_impl(&args);
+    if let Err(e) = &result {
+        match e.downcast_ref::<ConfigError>() {
+            Some(ConfigError::InvalidName(_)) => {
+                eprintln!("error: {}", e);
+                exit(1);
+            }
+            Some(ConfigError::MultipleValues(_)) => {
+                eprintln!("warning: {}", e);
+                exit(5);
+            }
+            Some(ConfigError::NotFound(_)) => exit(5),
+            _ => {}
+        }
+    }
+    result
+}
@@ -0,0 +1,435 @@
+use clap::{Args, ValueEnum};
+use rustgit::{
+    diff::{
+        diff_lines, is_binary, split_lines, unified_hunks, DiffAlgorithm, DiffFile, DiffLine, Edit,
+        FileChange,
+    },
+    object::ObjectType,
+    oid::ObjectId,
+    Repository,
+};
+use std::io::Write;
+
+#[derive(ValueEnum, Debug, Copy, Clone)]
+enum Algorithm {
+    #[value(alias = "default")]
+    Myers,
+    Minimal,
+    Patience,
+    Histogram,
+}
+
+impl From<Algorithm> for DiffAlgorithm {
+    fn from(algorithm: Algorithm) -> Self {
+        match algorithm {
+            // the Myers diff of rustgit is always minimal
+            Algorithm::Myers | Algorithm::Minimal => DiffAlgorithm::Myers,
+            Algorithm::Patience => DiffAlgorithm::Patience,
+            Algorithm::Histogram => DiffAlgorithm::Histogram,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    /// Compare the index with HEAD, or with the given commit
+    #[clap(long, visible_alias = "staged")]
+    cached: bool,
+
+    /// Show a diffstat instead of the patch
+    #[clap(long)]
+    stat: bool,
+
+    /// Show only the names of the changed files
+    #[clap(long)]
+    name_only: bool,
+
+    /// Generate diffs with <n> lines of context
+    #[clap(short = 'U', long = "unified", value_name = "n", default_value_t = 3)]
+    context: usize,
+
+    /// Choose a diff algorithm, `diff.algorithm` or myers by default
+    #[clap(long, value_enum)]
+    diff_algorithm: Option<Algorithm>,
+
+    /// Produce the smallest possible diff
+    #[clap(long, overrides_with_all = ["patience", "histogram", "diff_algorithm"])]
+    minimal: bool,
+
+    /// Generate the diff with the patience algorithm
+    #[clap(long, overrides_with_all = ["minimal", "histogram", "diff_algorithm"])]
+    patience: bool,
+
+    /// Generate the diff with the histogram algorithm
+    #[clap(long, overrides_with_all = ["minimal", "patience", "diff_algorithm"])]
+    histogram: bool,
+
+    /// Compare the index with the working tree when empty, or two commits when two are given
+    #[clap(value_name = "commit", num_args = 0..=2)]
+    commits: Vec<String>,
+}
+
+// The algorithm chosen by the options, then by `diff.algorithm`
+fn diff_algorithm(repository: &Repository, args: &DiffArgs) -> anyhow::Result<DiffAlgorithm> {
+    let algorithm = if args.minimal {
+        Algorithm::Minimal
+    } else if args.patience {
+        Algorithm::Patience
+    } else if args.histogram {
+        Algorithm::Histogram
+    } else if let Some(algorithm) = args.diff_algorithm {
+        algorithm
+    } else {
+        match repository.config()?.get("diff.algorithm") {
+            Some(value) => Algorithm::from_str(value, true).map_err(|_| {
+                anyhow::anyhow!("unknown value for config 'diff.algorithm': {}", value)
+            })?,
+            None => Algorithm::Myers,
+        }
+    };
+    Ok(algorithm.into())
+}
+
+fn resolve_tree(repository: &Repository, revision: &str) -> anyhow::Result<ObjectId> {
+    let oid = repository.resolve_revision(revision)?;
+    Ok(repository.peel(oid, ObjectType::Tree)?)
+}
+
+fn abbreviate(repository: &Repository, oid: Option<ObjectId>) -> std::io::Result<String> {
+    match oid {
+        Some(oid) => repository.abbreviate(oid, repository.default_abbrev_length()?),
+        None => Ok("0".repeat(repository.default_abbrev_length()?)),
+    }
+}
+
+// Content of both sides of a change, empty for a missing side
+fn read_contents(
+    repository: &Repository,
+    change: &FileChange,
+) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    let read = |file: &Option<DiffFile>| -> anyhow::Result<Vec<u8>> {
+        match file {
+            Some(file) => Ok(repository.read_diff_file(&change.path, file)?),
+            None => Ok(vec![]),
+        }
+    };
+    Ok((read(&change.old)?, read(&change.new)?))
+}
+
+fn write_patch(
+    out: &mut impl Write,
+    repository: &Repository,
+    change: &FileChange,
+    algorithm: DiffAlgorithm,
+    args: &DiffArgs,
+) -> anyhow::Result<()> {
+    let path = change.path.display();
+    writeln!(out, "diff --git a/{} b/{}", path, path)?;
+
+    match (&change.old, &change.new) {
+        (None, Some(new)) => writeln!(out, "new file mode {:06o}", new.mode)?,
+        (Some(old), None) => writeln!(out, "deleted file mode {:06o}", old.mode)?,
+        (Some(old), Some(new)) if old.mode != new.mode => {
+            writeln!(out, "old mode {:06o}", old.mode)?;
+            writeln!(out, "new mode {:06o}", new.mode)?;
+        }
+        _ => {}
+    }
+
+    let old_oid = change.old.map(|file| file.oid);
+    let new_oid = change.new.map(|file| file.oid);
+    if old_oid == new_oid {
+        // only the mode changed
+        return Ok(());
+    }
+    write!(
+        out,
+        "index {}..{}",
+        abbreviate(repository, old_oid)?,
+        abbreviate(repository, new_oid)?
+    )?;
+    match (&change.old, &change.new) {
+        (Some(old), Some(new)) if old.mode == new.mode => writeln!(out, " {:06o}", old.mode)?,
+        _ => writeln!(out)?,
+    }
+
+    let old_name = match change.old {
+        Some(_) => format!("a/{}", path),
+        None => "/dev/null".to_string(),
+    };
+    let new_name = match change.new {
+        Some(_) => format!("b/{}", path),
+        None => "/dev/null".to_string(),
+    };
+
+    let (old_content, new_content) = read_contents(repository, change)?;
+    if is_binary(&old_content) || is_binary(&new_content) {
+        writeln!(out, "Binary files {} and {} differ", old_name, new_name)?;
+        return Ok(());
+    }
+
+    let old_lines = split_lines(&old_content);
+    let new_lines = split_lines(&new_content);
+    let edits = diff_lines(&old_lines, &new_lines, algorithm);
+    let hunks = unified_hunks(&old_lines, &new_lines, &edits, args.context);
+    if hunks.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "--- {}", old_name)?;
+    writeln!(out, "+++ {}", new_name)?;
+    for hunk in hunks {
+        write!(out, "{}", hunk)?;
+        if let Some(function_context) = hunk.function_context {
+            out.write_all(b" ")?;
+            out.write_all(function_context)?;
+        }
+        writeln!(out)?;
+
+        for line in hunk.lines {
+            let (prefix, content) = match line {
+                DiffLine::Context(content) => (b' ', content),
+                DiffLine::Deletion(content) => (b'-', content),
+                DiffLine::Addition(content) => (b'+', content),
+            };
+            out.write_all(&[prefix])?;
+            out.write_all(content)?;
+            if !content.ends_with(b"\n") {
+                out.write_all(b"\n\\ No newline at end of file\n")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+enum FileStat {
+    Text { insertions: usize, deletions: usize },
+    Binary { old_size: usize, new_size: usize },
+}
+
+fn file_stat(
+    repository: &Repository,
+    change: &FileChange,
+    algorithm: DiffAlgorithm,
+) -> anyhow::Result<FileStat> {
+    let (old_content, new_content) = read_contents(repository, change)?;
+    if is_binary(&old_content) || is_binary(&new_content) {
+        return Ok(FileStat::Binary {
+            old_size: old_content.len(),
+            new_size: new_content.len(),
+        });
+    }
+
+    let edits = diff_lines(
+        &split_lines(&old_content),
+        &split_lines(&new_content),
+        algorithm,
+    );
+    let count = |kind: Edit| edits.iter().filter(|&&edit| edit == kind).count();
+    Ok(FileStat::Text {
+        insertions: count(Edit::Insert),
+        deletions: count(Edit::Delete),
+    })
+}
+
+fn decimal_width(n: usize) -> usize {
+    n.to_string().len()
+}
+
+// Same as git: scale a number of changed lines to the width of the graph
+fn scale_linear(n: usize, width: usize, max_change: usize) -> usize {
+    if n == 0 {
+        0
+    } else {
+        1 + n * (width - 1) / max_change
+    }
+}
+
+// The number of columns taken by a path, counting each character as one column
+fn display_width(name: &str) -> usize {
+    name.chars().count()
+}
+
+// Mirror the layout of `git diff --stat` on an 80 columns terminal
+fn write_stat(
+    out: &mut impl Write,
+    repository: &Repository,
+    changes: &[FileChange],
+    algorithm: DiffAlgorithm,
+) -> anyhow::Result<()> {
+    const WIDTH: usize = 80;
+
+    let mut stats = vec![];
+    for change in changes {
+        stats.push((
+            change.path.to_string_lossy(),
+            file_stat(repository, change, algorithm)?,
+        ));
+    }
+
+    let max_len = stats
+        .iter()
+        .map(|(name, _)| display_width(name))
+        .max()
+        .unwrap_or(0);
+    let mut max_change = 0;
+    let mut number_width = 0;
+    let mut bin_width = 0;
+    for (_, stat) in &stats {
+        match stat {
+            FileStat::Text {
+                insertions,
+                deletions,
+            } => max_change = max_change.max(insertions + deletions),
+            FileStat::Binary { old_size, new_size } => {
+                bin_width = bin_width.max(14 + decimal_width(*old_size) + decimal_width(*new_size));
+                // align the counts with "Bin"
+                number_width = 3;
+            }
+        }
+    }
+    number_width = number_width.max(decimal_width(max_change));
+
+    let mut graph_width = if max_change + 4 > bin_width {
+        max_change
+    } else {
+        bin_width - 4
+    };
+    let mut name_width = max_len;
+    if name_width + number_width + 6 + graph_width > WIDTH {
+        if graph_width + number_width + 6 > WIDTH * 3 / 8 {
+            graph_width = (WIDTH * 3 / 8).saturating_sub(number_width + 6).max(6);
+        }
+        if name_width > WIDTH - number_width - 6 - graph_width {
+            name_width = WIDTH - number_width - 6 - graph_width;
+        } else {
+            graph_width = WIDTH - number_width - 6 - name_width;
+        }
+    }
+
+    let (mut total_insertions, mut total_deletions) = (0, 0);
+    for (name, stat) in &stats {
+        let width = display_width(name);
+        let (prefix, name) = if width > name_width {
+            // keep the end of the path, starting from a directory boundary if possible
+            let skipped = width - name_width.saturating_sub(3);
+            let start = name
+                .char_indices()
+                .nth(skipped)
+                .map_or(name.len(), |(start, _)| start);
+            let mut name = &name[start..];
+            if let Some(slash) = name.find('/') {
+                name = &name[slash..];
+            }
+            ("...", name)
+        } else {
+            ("", &name[..])
+        };
+        let padding = name_width.saturating_sub(prefix.len() + display_width(name));
+        write!(out, " {}{}{} |", prefix, name, " ".repeat(padding))?;
+
+        match *stat {
+            FileStat::Binary { old_size, new_size } => {
+                writeln!(
+                    out,
+                    " {:>width$} {} -> {} bytes",
+                    "Bin",
+                    old_size,
+                    new_size,
+                    width = number_width
+                )?;
+            }
+            FileStat::Text {
+                insertions,
+                deletions,
+            } => {
+                total_insertions += insertions;
+                total_deletions += deletions;
+
+                let (mut add, mut del) = (insertions, deletions);
+                if graph_width <= max_change {
+                    let mut total = scale_linear(add + del, graph_width, max_change);
+                    if total < 2 && add > 0 && del > 0 {
+                        total = 2;
+                    }
+                    if add < del {
+                        add = scale_linear(add, graph_width, max_change);
+                        del = total - add;
+                    } else {
+                        del = scale_linear(del, graph_width, max_change);
+                        add = total - del;
+                    }
+                }
+                let separator = if insertions + deletions > 0 { " " } else { "" };
+                writeln!(
+                    out,
+                    " {:>width$}{}{}{}",
+                    insertions + deletions,
+                    separator,
+                    "+".repeat(add),
+                    "-".repeat(del),
+                    width = number_width
+                )?;
+            }
+        }
+    }
+
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+    write!(out, " {} file{} changed", stats.len(), plural(stats.len()),)?;
+    if total_insertions > 0 || total_deletions == 0 {
+        write!(
+            out,
+            ", {} insertion{}(+)",
+            total_insertions,
+            plural(total_insertions)
+        )?;
+    }
+    if total_deletions > 0 || total_insertions == 0 {
+        write!(
+            out,
+            ", {} deletion{}(-)",
+            total_deletions,
+            plural(total_deletions)
+        )?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+pub fn diff(args: DiffArgs) -> anyhow::Result<()> {
+    let repository = Repository::search_and_open(&std::env::current_dir()?)?;
+
+    let changes = match (args.cached, args.commits.as_slice()) {
+        (false, []) => repository.diff_index_to_worktree()?,
+        (true, []) => repository.diff_tree_to_index(repository.head_tree()?)?,
+        (true, [commit]) => {
+            let tree = resolve_tree(&repository, commit)?;
+            repository.diff_tree_to_index(Some(tree))?
+        }
+        (false, [old, new]) => {
+            let old = resolve_tree(&repository, old)?;
+            let new = resolve_tree(&repository, new)?;
+            repository.diff_trees(Some(old), Some(new))?
+        }
+        (false, [_]) => anyhow::bail!("comparing a commit with the working tree is not supported"),
+        _ => anyhow::bail!("--cached accepts at most one commit"),
+    };
+
+    let algorithm = diff_algorithm(&repository, &args)?;
+    let mut out = std::io::stdout().lock();
+    if args.name_only {
+        for change in &changes {
+            writeln!(out, "{}", change.path.display())?;
+        }
+    } else if args.stat {
+        if !changes.is_empty() {
+            write_stat(&mut out, &repository, &changes, algorithm)?;
+        }
+    } else {
+        for change in &changes {
+            write_patch(&mut out, &repository, change, algorithm, &args)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,260 @@
+use super::log::format_date;
+use anyhow::{anyhow, bail};
+use chrono::{DateTime, FixedOffset};
+use clap::Args;
+use rustgit::{
+    object::ObjectType, oid::ObjectId, references::ReferenceError, refname::matches_ref_pattern,
+    Repository,
+};
+use std::{cmp::Ordering, io::Write};
+
+#[derive(Args, Debug)]
+pub struct ForEachRefArgs {
+    /// Stop after showing <count> references
+    #[clap(long, value_name = "count")]
+    count: Option<usize>,
+
+    /// The field to sort on, in descending order when prefixed with `-`. When given several
+    /// times, the last key is the primary one. Defaults to `refname`
+    #[clap(long, value_name = "key", allow_hyphen_values = true)]
+    sort: Vec<String>,
+
+    /// How to show each reference: `%(<field>)` is replaced by the field, `%%` by `%` and
+    /// `%xx` by the character of hex code xx. The fields are `refname`, `objectname`,
+    /// `objecttype`, `upstream`, `contents:subject` and `committerdate`
+    #[clap(
+        long,
+        value_name = "format",
+        default_value = "%(objectname) %(objecttype)\t%(refname)"
+    )]
+    format: String,
+
+    /// Only show the references matching one of the patterns, either a glob or a name made of
+    /// whole components, such as `refs/heads`
+    #[clap(value_name = "pattern")]
+    patterns: Vec<String>,
+}
+
+// A field of a reference. `short` abbreviates a name or an object id
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Atom {
+    RefName { short: bool },
+    ObjectName { short: bool },
+    ObjectType,
+    Upstream { short: bool },
+    Subject,
+    CommitterDate,
+}
+
+fn parse_atom(atom: &str) -> anyhow::Result<Atom> {
+    let (name, argument) = match atom.split_once(':') {
+        Some((name, argument)) => (name, Some(argument)),
+        None => (atom, None),
+    };
+    let short = || match argument {
+        None => Ok(false),
+        Some("short") => Ok(true),
+        Some(argument) => Err(anyhow!("unrecognized %({}) argument: {}", atom, argument)),
+    };
+    let no_argument = |atom: Atom| match argument {
+        None => Ok(atom),
+        Some(_) => Err(anyhow!("%({}) does not take arguments", name)),
+    };
+
+    match name {
+        "refname" => Ok(Atom::RefName { short: short()? }),
+        "objectname" => Ok(Atom::ObjectName { short: short()? }),
+        "upstream" => Ok(Atom::Upstream { short: short()? }),
+        "objecttype" => no_argument(Atom::ObjectType),
+        "subject" => no_argument(Atom::Subject),
+        "contents" if argument == Some("subject") => Ok(Atom::Subject),
+        "committerdate" => no_argument(Atom::CommitterDate),
+        _ => bail!("unknown field name: {}", atom),
+    }
+}
+
+enum Part {
+    Literal(Vec<u8>),
+    Atom(Atom),
+}
+
+// The character of a `%xx` escape at the start of `format`
+fn hex_escape(format: &[u8]) -> Option<u8> {
+    match format {
+        [b'%', high, low, ..] if high.is_ascii_hexdigit() && low.is_ascii_hexdigit() => {
+            let hex = [*high, *low];
+            u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()
+        }
+        _ => None,
+    }
+}
+
+fn parse_format(format: &str) -> anyhow::Result<Vec<Part>> {
+    let bytes = format.as_bytes();
+    let mut parts = vec![];
+    let mut literal = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"%(") {
+            let Some(length) = format[i..].find(')') else {
+                bail!("malformed format string {}", &format[i..]);
+            };
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(Part::Atom(parse_atom(&format[i + 2..i + length])?));
+            i += length + 1;
+        } else if bytes[i..].starts_with(b"%%") {
+            literal.push(b'%');
+            i += 2;
+        } else if let Some(c) = hex_escape(&bytes[i..]) {
+            literal.push(c);
+            i += 3;
+        } else {
+            literal.push(bytes[i]);
+            i += 1;
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(Part::Literal(literal));
+    }
+    Ok(parts)
+}
+
+// The value of a field. Dates are compared by time rather than by their text
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Value {
+    Text(String),
+    Date(Option<DateTime<FixedOffset>>),
+}
+
+impl Value {
+    fn to_text(&self) -> String {
+        match self {
+            Value::Text(text) => text.clone(),
+            Value::Date(Some(time)) => format_date(time),
+            Value::Date(None) => String::new(),
+        }
+    }
+}
+
+// A reference to show, with the object it resolves to
+struct Entry {
+    name: String,
+    oid: ObjectId,
+}
+
+fn field(repository: &Repository, entry: &Entry, atom: Atom) -> anyhow::Result<Value> {
+    let value = match atom {
+        Atom::RefName { short: false } => entry.name.clone(),
+        Atom::RefName { short: true } => repository.shorten_reference(&entry.name)?,
+        Atom::ObjectName { short: false } => entry.oid.to_string(),
+        Atom::ObjectName { short: true } => {
+            repository.abbreviate(entry.oid, repository.default_abbrev_length()?)?
+        }
+        Atom::ObjectType => repository.object_type(entry.oid)?.to_string(),
+        Atom::Upstream { short } => {
+            let upstream = match entry.name.strip_prefix("refs/heads/") {
+                Some(branch) => repository.upstream_reference(branch)?,
+                None => None,
+            };
+            match upstream {
+                Some(upstream) if short => repository.shorten_reference(&upstream)?,
+                Some(upstream) => upstream,
+                None => String::new(),
+            }
+        }
+        Atom::Subject => match repository.object_type(entry.oid)? {
+            ObjectType::Commit => repository.read_commit(entry.oid)?.summary(),
+            ObjectType::Tag => repository.read_tag(entry.oid)?.summary(),
+            _ => String::new(),
+        },
+        Atom::CommitterDate => {
+            let time = match repository.object_type(entry.oid)? {
+                ObjectType::Commit => Some(repository.read_commit(entry.oid)?.committer().time),
+                _ => None,
+            };
+            return Ok(Value::Date(time));
+        }
+    };
+    Ok(Value::Text(value))
+}
+
+// The references under `refs/` matching one of the patterns, sorted by name. Like git, symbolic
+// references that don't resolve are left out
+fn matching_references(repository: &Repository, patterns: &[String]) -> anyhow::Result<Vec<Entry>> {
+    let mut entries = vec![];
+    for reference in repository.references("")? {
+        let matches = patterns.is_empty()
+            || patterns
+                .iter()
+                .any(|pattern| matches_ref_pattern(&reference.name, pattern));
+        if !matches {
+            continue;
+        }
+        let oid = match repository.resolve_reference(&reference.target) {
+            Err(ReferenceError::NotExist(_)) => continue,
+            oid => oid?,
+        };
+        entries.push(Entry {
+            name: reference.name,
+            oid,
+        });
+    }
+    Ok(entries)
+}
+
+pub fn for_each_ref(args: ForEachRefArgs) -> anyhow::Result<()> {
+    let repository = Repository::search_and_open(&std::env::current_dir()?)?;
+
+    let format = parse_format(&args.format)?;
+    // the last key is the primary one
+    let mut sort_keys = vec![];
+    for key in args.sort.iter().rev() {
+        let (key, descending) = match key.strip_prefix('-') {
+            Some(key) => (key, true),
+            None => (key.as_str(), false),
+        };
+        sort_keys.push((parse_atom(key)?, descending));
+    }
+
+    let entries = matching_references(&repository, &args.patterns)?;
+    let mut sorted = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let values = sort_keys
+            .iter()
+            .map(|&(atom, _)| field(&repository, &entry, atom))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        sorted.push((entry, values));
+    }
+    // ties are broken by name, which the references are already sorted by
+    sorted.sort_by(|(_, a), (_, b)| {
+        a.iter()
+            .zip(b)
+            .zip(&sort_keys)
+            .map(|((a, b), &(_, descending))| match descending {
+                true => b.cmp(a),
+                false => a.cmp(b),
+            })
+            .find(|&ordering| ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    });
+
+    let mut stdout = std::io::stdout().lock();
+    let count = args.count.unwrap_or(usize::MAX);
+    for (entry, _) in sorted.iter().take(count) {
+        let mut line = vec![];
+        for part in &format {
+            match part {
+                Part::Literal(literal) => line.extend_from_slice(literal),
+                Part::Atom(atom) => {
+                    let value = field(&repository, entry, *atom)?;
+                    line.extend_from_slice(value.to_text().as_bytes());
+                }
+            }
+        }
+        line.push(b'\n');
+        stdout.write_all(&line)?;
+    }
+    Ok(())
+}
@@ -1,10 +1,11 @@
 use clap::Args;
 use rustgit::{
-    object::{ObjectBuffer, ObjectType},
+    object::{hash_blob_file, ObjectBuffer, ObjectType},
     oid::ObjectId,
     Repository,
 };
-use std::fs;
+use std::io::Read;
+use std::path::Path;
 
 #[derive(Args, Debug)]
 #[group(required = true, multiple = false)]
@@ -25,20 +26,31 @@ pub struct HashObjectArgs {
 }
 
 pub fn hash_object(args: HashObjectArgs) -> anyhow::Result<()> {
-    let body = if args.group.stdin {
-        std::io::read_to_string(std::io::stdin())?
+    let repository = if args.perform_write {
+        Some(Repository::search_and_open(&std::env::current_dir()?)?)
     } else {
-        fs::read_to_string(args.group.filename.unwrap())?
+        None
     };
 
-    let blob = ObjectBuffer::new(ObjectType::Blob, body.as_bytes());
-    let oid = ObjectId::from_object_buffer(&blob);
+    let oid = if args.group.stdin {
+        // the size of the content must be known before hashing it
+        let mut body = vec![];
+        std::io::stdin().read_to_end(&mut body)?;
+        let blob = ObjectBuffer::new(ObjectType::Blob, &body);
+        let oid = ObjectId::from_object_buffer(&blob);
+        if let Some(repository) = &repository {
+            repository.write_object_buffer(oid, &blob)?;
+        }
+        oid
+    } else {
+        // large files are streamed rather than read in memory
+        let path = Path::new(args.group.filename.as_deref().unwrap());
+        match &repository {
+            Some(repository) => repository.write_blob_file(path)?,
+            None => hash_blob_file(path)?,
+        }
+    };
     println!("{}", oid.to_hex_string());
 
-    if args.perform_write {
-        let repository = Repository::search_and_open(&std::env::current_dir()?)?;
-        repository.write_object_buffer(oid, &blob)?;
-    }
-
     Ok(())
 }
@@ -1,8 +1,24 @@
-use rustgit::Repository;
+use clap::Args;
+use rustgit::{config::Config, Repository};
 
-pub fn init() -> std::io::Result<()> {
+#[derive(Args, Debug)]
+pub struct InitArgs {
+    /// Use the specified name for the initial branch. Defaults to `init.defaultBranch`, or
+    /// `main` if it is not set
+    #[clap(short = 'b', long)]
+    initial_branch: Option<String>,
+}
+
+pub fn init(args: InitArgs) -> anyhow::Result<()> {
     let current_dir = &std::env::current_dir()?;
-    let repo = Repository::init(&current_dir)?;
+    let initial_branch = match args.initial_branch {
+        Some(initial_branch) => initial_branch,
+        None => Config::open_all(None)?
+            .get("init.defaultBranch")
+            .unwrap_or("main")
+            .to_string(),
+    };
+    let repo = Repository::init(current_dir, &initial_branch)?;
     println!(
         "Initialized empty Git repository in {}",
         repo.git_dir.display()
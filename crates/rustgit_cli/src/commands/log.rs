@@ -0,0 +1,659 @@
+use chrono::{DateTime, FixedOffset, Local};
+use clap::Args;
+use rustgit::{
+    head::Head,
+    object::{parse_approxidate, Author, Commit, ObjectReadError},
+    oid::ObjectId,
+    revision::Revision,
+    revwalk::{RevWalk, Sorting, WalkedCommit},
+    Repository,
+};
+use std::{
+    io::Write,
+    path::{Component, Path, PathBuf},
+};
+
+#[derive(Args, Debug)]
+pub struct LogArgs {
+    /// Show each commit on a single line, with an abbreviated object id
+    #[clap(long)]
+    oneline: bool,
+
+    /// Draw the history graph on the left of the output. Implies --topo-order
+    #[clap(long, conflicts_with = "reverse")]
+    graph: bool,
+
+    /// Pretty-print the commits: oneline, short, medium, full, raw, format:<string> or
+    /// tformat:<string>
+    #[clap(long, visible_alias = "pretty", value_name = "format")]
+    format: Option<String>,
+
+    /// Limit the number of commits to output
+    #[clap(short = 'n', long, value_name = "number")]
+    max_count: Option<usize>,
+
+    /// Output the commits in reverse order
+    #[clap(long)]
+    reverse: bool,
+
+    /// Show no parents before all of their children, and avoid mixing lines of history
+    #[clap(long, conflicts_with = "date_order")]
+    topo_order: bool,
+
+    /// Show no parents before all of their children, otherwise in commit timestamp order
+    #[clap(long)]
+    date_order: bool,
+
+    /// Only show the commits whose author matches the pattern
+    #[clap(long, value_name = "pattern")]
+    author: Option<String>,
+
+    /// Only show the commits more recent than a date, e.g. `2024-01-31 12:00:00 +0100`,
+    /// `@1706698800` or `2 weeks ago`
+    #[clap(long, visible_alias = "after", value_name = "date", value_parser = parse_date)]
+    since: Option<DateTime<FixedOffset>>,
+
+    /// Only show the commits older than a date
+    #[clap(long, visible_alias = "before", value_name = "date", value_parser = parse_date)]
+    until: Option<DateTime<FixedOffset>>,
+
+    /// Show the commits reachable from these revisions, `^rev` and `a..b` exclude commits.
+    /// Defaults to HEAD
+    #[clap(value_name = "revision")]
+    revisions: Vec<String>,
+
+    /// Only show the commits that change these paths
+    #[clap(last = true, value_name = "path")]
+    paths: Vec<PathBuf>,
+}
+
+// Parse the dates accepted by --since and --until, like git's approxidate
+fn parse_date(date: &str) -> Result<DateTime<FixedOffset>, String> {
+    parse_approxidate(date, Local::now().fixed_offset())
+        .ok_or_else(|| format!("invalid date '{}'", date.trim()))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Pretty {
+    Oneline,
+    Short,
+    Medium,
+    Full,
+    Raw,
+    /// A user format. `terminator` is whether each commit is followed by a newline (tformat),
+    /// rather than separated from the next one (format)
+    Format {
+        template: String,
+        terminator: bool,
+    },
+}
+
+impl Pretty {
+    fn parse(format: &str) -> anyhow::Result<Pretty> {
+        Ok(match format {
+            "oneline" => Pretty::Oneline,
+            "short" => Pretty::Short,
+            "medium" => Pretty::Medium,
+            "full" => Pretty::Full,
+            "raw" => Pretty::Raw,
+            _ => {
+                if let Some(template) = format.strip_prefix("format:") {
+                    Pretty::Format {
+                        template: template.to_string(),
+                        terminator: false,
+                    }
+                } else if let Some(template) = format.strip_prefix("tformat:") {
+                    Pretty::Format {
+                        template: template.to_string(),
+                        terminator: true,
+                    }
+                } else if format.contains('%') {
+                    Pretty::Format {
+                        template: format.to_string(),
+                        terminator: true,
+                    }
+                } else {
+                    anyhow::bail!("invalid --pretty format: {}", format)
+                }
+            }
+        })
+    }
+
+    // Whether a newline is written after each commit, rather than between two commits
+    fn has_terminator(&self) -> bool {
+        matches!(
+            self,
+            Pretty::Oneline
+                | Pretty::Format {
+                    terminator: true,
+                    ..
+                }
+        )
+    }
+}
+
+pub(super) fn format_date(time: &DateTime<FixedOffset>) -> String {
+    time.format("%a %b %-d %H:%M:%S %Y %z").to_string()
+}
+
+fn format_identity(identity: &Author) -> String {
+    format!("{} <{}>", identity.name, identity.email)
+}
+
+// Shows a commit in the requested format
+struct Formatter<'r> {
+    repository: &'r Repository,
+    pretty: Pretty,
+    abbrev_commit: bool,
+}
+
+impl Formatter<'_> {
+    fn abbreviate(&self, oid: ObjectId) -> std::io::Result<String> {
+        self.repository
+            .abbreviate(oid, self.repository.default_abbrev_length()?)
+    }
+
+    fn commit_id(&self, oid: ObjectId) -> std::io::Result<String> {
+        if self.abbrev_commit {
+            self.abbreviate(oid)
+        } else {
+            Ok(oid.to_string())
+        }
+    }
+
+    // The text shown for a commit. Only multi-line formats end with a newline
+    fn format(&self, oid: ObjectId, commit: &Commit) -> std::io::Result<String> {
+        if let Pretty::Format { template, .. } = &self.pretty {
+            return self.expand(template, oid, commit);
+        }
+        if self.pretty == Pretty::Oneline {
+            return Ok(format!("{} {}", self.commit_id(oid)?, commit.summary()));
+        }
+
+        let mut text = format!("commit {}\n", self.commit_id(oid)?);
+        if self.pretty == Pretty::Raw {
+            text += &format!("tree {}\n", commit.tree());
+            for parent in commit.parents() {
+                text += &format!("parent {}\n", parent);
+            }
+            text += &format!("author {}\n", commit.author());
+            text += &format!("committer {}\n", commit.committer());
+        } else {
+            if commit.parents().len() > 1 {
+                let mut parents = vec![];
+                for &parent in commit.parents() {
+                    parents.push(self.abbreviate(parent)?);
+                }
+                text += &format!("Merge: {}\n", parents.join(" "));
+            }
+            text += &format!("Author: {}\n", format_identity(commit.author()));
+            match self.pretty {
+                Pretty::Medium => {
+                    text += &format!("Date:   {}\n", format_date(&commit.author().time))
+                }
+                Pretty::Full => {
+                    text += &format!("Commit: {}\n", format_identity(commit.committer()))
+                }
+                _ => {}
+            }
+        }
+        text.push('\n');
+
+        let message = match self.pretty {
+            Pretty::Short => commit.summary(),
+            _ => commit.message().trim_end().to_string(),
+        };
+        for line in message.lines() {
+            text += &format!("    {}\n", line);
+        }
+        Ok(text)
+    }
+
+    // Expand the placeholders of a user format
+    fn expand(&self, template: &str, oid: ObjectId, commit: &Commit) -> std::io::Result<String> {
+        let mut text = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('%') {
+            text += &rest[..start];
+            rest = &rest[start + 1..];
+            match self.placeholder(rest, oid, commit)? {
+                Some((length, value)) => {
+                    text += &value;
+                    rest = &rest[length..];
+                }
+                // unknown placeholders are kept as is
+                None => text.push('%'),
+            }
+        }
+        text += rest;
+        Ok(text)
+    }
+
+    // The length and the value of the placeholder at the start of `placeholder`
+    fn placeholder(
+        &self,
+        placeholder: &str,
+        oid: ObjectId,
+        commit: &Commit,
+    ) -> std::io::Result<Option<(usize, String)>> {
+        let abbreviate_all = |oids: &[ObjectId]| -> std::io::Result<String> {
+            let mut abbreviated = vec![];
+            for &oid in oids {
+                abbreviated.push(self.abbreviate(oid)?);
+            }
+            Ok(abbreviated.join(" "))
+        };
+
+        let mut chars = placeholder.chars();
+        let value = match chars.next() {
+            Some('H') => oid.to_string(),
+            Some('h') => self.abbreviate(oid)?,
+            Some('T') => commit.tree().to_string(),
+            Some('t') => self.abbreviate(commit.tree())?,
+            Some('P') => {
+                let parents: Vec<String> =
+                    commit.parents().iter().map(ObjectId::to_string).collect();
+                parents.join(" ")
+            }
+            Some('p') => abbreviate_all(commit.parents())?,
+            Some('s') => commit.summary(),
+            Some('b') => commit.body().to_string(),
+            Some('B') => commit.message().to_string(),
+            Some('n') => "\n".to_string(),
+            Some('%') => "%".to_string(),
+            Some(c @ ('a' | 'c')) => {
+                let identity = if c == 'a' {
+                    commit.author()
+                } else {
+                    commit.committer()
+                };
+                let value = match chars.next() {
+                    Some('n') => identity.name.clone(),
+                    Some('e') => identity.email.clone(),
+                    Some('d') => format_date(&identity.time),
+                    Some('t') => identity.time.timestamp().to_string(),
+                    Some('i') => identity.time.format("%Y-%m-%d %H:%M:%S %z").to_string(),
+                    Some('I') => identity.time.format("%Y-%m-%dT%H:%M:%S%:z").to_string(),
+                    _ => return Ok(None),
+                };
+                return Ok(Some((2, value)));
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some((1, value)))
+    }
+}
+
+// The lines of the graph drawn next to a commit
+struct GraphLines {
+    // Drawn before the commit, next to the blank separator line
+    separator: String,
+
+    // The commit line, then the lines that open the columns of a merge and move the columns
+    // that lead to the same commit together
+    lines: Vec<String>,
+
+    // Drawn next to the remaining lines of the commit text
+    padding: String,
+}
+
+// For each character of a graph line, the column its edge leads to
+type Mapping = Vec<Option<usize>>;
+
+// A simplified version of the graph of `git log --graph`
+#[derive(Default)]
+struct Graph {
+    // The commit expected in each column
+    columns: Vec<ObjectId>,
+
+    // The mapping of the last line that moved columns, if the previous commit moved any
+    last_collapse: Option<Mapping>,
+}
+
+fn pad(mut line: String, width: usize) -> String {
+    while line.len() < width {
+        line.push(' ');
+    }
+    line
+}
+
+fn column_lines(count: usize) -> String {
+    vec!["|"; count].join(" ")
+}
+
+fn is_mapping_correct(mapping: &Mapping) -> bool {
+    mapping.iter().enumerate().all(|(i, target)| match target {
+        Some(target) => *target == i / 2,
+        None => true,
+    })
+}
+
+// Draw the lines that move the edges to the characters of their columns, by at most one
+// column per line. Port of the collapsing lines of git's graph.c
+fn collapse_lines(mut mapping: Mapping, width: usize) -> (Vec<String>, Option<Mapping>) {
+    let mut lines = vec![];
+    let mut last_mapping = None;
+    while !is_mapping_correct(&mapping) {
+        let mut new_mapping: Mapping = vec![None; mapping.len()];
+        // at most one edge moves horizontally across other columns on each line
+        let mut horizontal_edge: Option<(usize, usize)> = None;
+        for (i, &target) in mapping.iter().enumerate() {
+            let Some(target) = target else {
+                continue;
+            };
+            let edge = if 2 * target == i {
+                new_mapping[i] = Some(target);
+                continue;
+            } else if new_mapping[i - 1].is_none() {
+                // nothing on the left, move left by one
+                new_mapping[i - 1] = Some(target);
+                i
+            } else if new_mapping[i - 1] == Some(target) {
+                // merge with the edge on the left, that leads to the same commit
+                continue;
+            } else {
+                // cross the edge on the left
+                new_mapping[i - 2] = Some(target);
+                i - 1
+            };
+            if horizontal_edge.is_none() {
+                horizontal_edge = Some((edge, target));
+                for j in (2 * target + 3..i.saturating_sub(2)).step_by(2) {
+                    new_mapping[j] = Some(target);
+                }
+            }
+        }
+        while new_mapping.last() == Some(&None) {
+            new_mapping.pop();
+        }
+
+        let drawn_mapping = new_mapping.clone();
+        let mut line = String::new();
+        let mut used_horizontal = false;
+        for (i, target) in drawn_mapping.iter().enumerate() {
+            match (*target, horizontal_edge) {
+                (None, _) => line.push(' '),
+                (Some(target), _) if 2 * target == i => line.push('|'),
+                (Some(target), Some((edge, edge_target)))
+                    if target == edge_target && i + 1 != edge =>
+                {
+                    // only the first segment continues on the next line
+                    if i != 2 * target + 3 {
+                        new_mapping[i] = None;
+                    }
+                    used_horizontal = true;
+                    line.push('_');
+                }
+                (Some(_), edge) => {
+                    if used_horizontal && edge.is_some_and(|(edge, _)| i < edge) {
+                        new_mapping[i] = None;
+                    }
+                    line.push('/');
+                }
+            }
+        }
+        lines.push(pad(line, width));
+        last_mapping = Some(drawn_mapping);
+        mapping = new_mapping;
+    }
+    (lines, last_mapping)
+}
+
+impl Graph {
+    // Advance the graph past a commit, given its parents in the walked history
+    fn update(&mut self, oid: ObjectId, parents: &[ObjectId]) -> GraphLines {
+        let index = match self.columns.iter().position(|&column| column == oid) {
+            Some(index) => index,
+            None => {
+                self.columns.push(oid);
+                self.columns.len() - 1
+            }
+        };
+        let old_count = self.columns.len();
+
+        // the commit is replaced by its parents, a commit without parents disappears
+        let mut new_columns: Vec<Option<ObjectId>> =
+            self.columns.iter().copied().map(Some).collect();
+        if parents.is_empty() {
+            new_columns[index] = None;
+        } else {
+            new_columns.splice(index..=index, parents.iter().copied().map(Some));
+        }
+        let width = 2 * old_count.max(new_columns.len());
+
+        // columns leading to the same commit are merged into the leftmost one
+        let mut columns = vec![];
+        let mut mapping: Mapping = vec![None; 2 * new_columns.len()];
+        for (i, oid) in new_columns.iter().enumerate() {
+            let Some(oid) = oid else {
+                continue;
+            };
+            let target = match columns.iter().position(|column| column == oid) {
+                Some(target) => target,
+                None => {
+                    columns.push(*oid);
+                    columns.len() - 1
+                }
+            };
+            mapping[2 * i] = Some(target);
+        }
+        while mapping.last() == Some(&None) {
+            mapping.pop();
+        }
+
+        let separator = pad(column_lines(old_count), width);
+
+        let mut commit_line = String::new();
+        for column in 0..old_count {
+            if column == index {
+                commit_line.push('*');
+                if parents.len() > 2 {
+                    commit_line += &"-".repeat(2 * (parents.len() - 2) - 1);
+                    commit_line.push('.');
+                }
+            } else if column > index && parents.len() > 2 {
+                commit_line.push('\\');
+            } else if self.last_collapse.as_ref().is_some_and(|last_collapse| {
+                // keep drawing an edge that is still moving left
+                last_collapse.get(2 * column + 1) == Some(&Some(column))
+                    && mapping
+                        .get(2 * column)
+                        .is_some_and(|target| target.is_some_and(|target| target < column))
+            }) {
+                commit_line.push('/');
+            } else {
+                commit_line.push('|');
+            }
+            commit_line.push(' ');
+        }
+        let mut lines = vec![pad(commit_line.trim_end().to_string(), width)];
+
+        if parents.len() > 1 {
+            let mut line = "| ".repeat(index) + "|\\";
+            line += &" \\".repeat(parents.len() - 2 + old_count - index - 1);
+            lines.push(pad(line, width));
+        }
+
+        let (collapse_lines, last_collapse) = collapse_lines(mapping, width);
+        lines.extend(collapse_lines);
+        self.last_collapse = last_collapse;
+
+        self.columns = columns;
+        GraphLines {
+            separator,
+            lines,
+            padding: pad(column_lines(self.columns.len()), width),
+        }
+    }
+}
+
+// Express a path given on the command line relative to the repository root
+pub(super) fn repository_path(path: &Path, current_dir: &Path, repository_dir: &Path) -> PathBuf {
+    let mut absolute = PathBuf::new();
+    for component in current_dir.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                absolute.pop();
+            }
+            component => absolute.push(component),
+        }
+    }
+    absolute
+        .strip_prefix(repository_dir)
+        .map(Path::to_path_buf)
+        .unwrap_or(absolute)
+}
+
+// The commits are written as the walk gives them back
+fn write_commits(
+    out: &mut impl Write,
+    formatter: &Formatter,
+    commits: impl Iterator<Item = Result<WalkedCommit, ObjectReadError>>,
+) -> anyhow::Result<()> {
+    let terminator = formatter.pretty.has_terminator();
+    for (i, walked) in commits.enumerate() {
+        let WalkedCommit { oid, commit, .. } = walked?;
+        if i > 0 && !terminator {
+            writeln!(out)?;
+        }
+        write!(out, "{}", formatter.format(oid, &commit)?)?;
+        if terminator {
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+// Same as git, the text of a commit is written next to the graph lines of the commit, and the
+// graph lines left once the text is written are shown on their own
+fn write_graph(
+    out: &mut impl Write,
+    formatter: &Formatter,
+    commits: &[WalkedCommit],
+) -> anyhow::Result<()> {
+    let terminator = formatter.pretty.has_terminator();
+    let mut graph = Graph::default();
+    let mut newline_terminated = false;
+    for (i, walked) in commits.iter().enumerate() {
+        let WalkedCommit {
+            oid,
+            commit,
+            parents,
+        } = walked;
+        let graph_lines = graph.update(*oid, parents);
+
+        if i > 0 && !terminator {
+            // the separator either ends the last line of the previous commit or is a line of its own
+            if newline_terminated {
+                write!(out, "{}", graph_lines.separator)?;
+            }
+            writeln!(out)?;
+        }
+
+        let text = formatter.format(*oid, commit)?;
+        let mut lines = graph_lines.lines.iter();
+        for (i, text_line) in text.split_inclusive('\n').enumerate() {
+            let graph_line = lines.next().unwrap_or(&graph_lines.padding);
+            if i == 0 || !text_line.is_empty() {
+                write!(out, "{}{}", graph_line, text_line)?;
+            }
+        }
+        if text.is_empty() {
+            write!(out, "{}", lines.next().expect("there is a commit line"))?;
+        }
+
+        newline_terminated = text.ends_with('\n');
+        let remainder: Vec<&str> = lines.map(String::as_str).collect();
+        if !remainder.is_empty() {
+            if !newline_terminated {
+                writeln!(out)?;
+            }
+            write!(out, "{}", remainder.join("\n"))?;
+            if newline_terminated {
+                writeln!(out)?;
+            }
+        }
+
+        if terminator {
+            if newline_terminated {
+                write!(out, "{}", graph_lines.padding)?;
+            }
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn log(args: LogArgs) -> anyhow::Result<()> {
+    let current_dir = std::env::current_dir()?;
+    let repository = Repository::search_and_open(&current_dir)?;
+
+    let mut walk = RevWalk::new(&repository);
+    if args.revisions.is_empty() {
+        if let Head::Unborn(branch) = repository.head()? {
+            anyhow::bail!(
+                "your current branch '{}' does not have any commits yet",
+                branch.strip_prefix("refs/heads/").unwrap_or(&branch)
+            )
+        }
+        walk.push(repository.head_id()?);
+    }
+    for revision in &args.revisions {
+        if let Some(excluded) = revision.strip_prefix('^') {
+            walk.hide(repository.resolve_revision(excluded)?);
+            continue;
+        }
+        match repository.rev_parse(revision)? {
+            Revision::Single(oid) => walk.push(oid),
+            Revision::Range { exclude, include } => walk.hide(exclude).push(include),
+        };
+    }
+
+    let sorting = if args.date_order {
+        Sorting::Date
+    } else if args.topo_order || args.graph {
+        Sorting::Topological
+    } else {
+        Sorting::Default
+    };
+    // the lanes of the graph go on to the commits left out by --max-count
+    walk.sorting(sorting)
+        .reverse(args.reverse)
+        .rewrite_parents(args.graph);
+    if let Some(max_count) = args.max_count {
+        walk.max_count(max_count);
+    }
+    if let Some(author) = &args.author {
+        walk.author(author);
+    }
+    if let Some(since) = args.since {
+        walk.since(since);
+    }
+    if let Some(until) = args.until {
+        walk.until(until);
+    }
+    walk.paths(
+        args.paths
+            .iter()
+            .map(|path| repository_path(path, &current_dir, &repository.repository_dir)),
+    );
+    let pretty = match &args.format {
+        Some(format) => Pretty::parse(format)?,
+        None if args.oneline => Pretty::Oneline,
+        None => Pretty::Medium,
+    };
+    let formatter = Formatter {
+        repository: &repository,
+        pretty,
+        abbrev_commit: args.oneline,
+    };
+
+    let mut out = std::io::stdout().lock();
+    if args.graph {
+        let commits = walk.collect::<Result<Vec<_>, _>>()?;
+        write_graph(&mut out, &formatter, &commits)
+    } else {
+        write_commits(&mut out, &formatter, walk)
+    }
+}
@@ -15,9 +15,10 @@ pub fn ls_files(args: LsFilesArgs) -> anyhow::Result<()> {
     for entry in index.iter() {
         if args.stage {
             println!(
-                "{:0>6o} {} 0\t{}",
+                "{:0>6o} {} {}\t{}",
                 entry.metadata.mode,
                 entry.oid,
+                entry.stage(),
                 entry.path.display()
             );
         } else {
@@ -1,5 +1,8 @@
 use clap::Args;
-use rustgit::{object::read_tree_object, oid::ObjectId, Repository};
+use rustgit::{
+    object::{read_tree_object, ObjectType},
+    Repository,
+};
 use std::io::prelude::*;
 
 #[derive(Args, Debug)]
@@ -16,7 +19,8 @@ pub struct LsTreeArgs {
 pub fn ls_tree(args: LsTreeArgs) -> anyhow::Result<()> {
     let repository = Repository::search_and_open(&std::env::current_dir()?)?;
 
-    let tree_hash = ObjectId::from_unvalidated_sh1_hex_string(&args.tree_ish)?;
+    let tree_hash = repository.resolve_revision(&args.tree_ish)?;
+    let tree_hash = repository.peel(tree_hash, ObjectType::Tree)?;
     let mut reader = repository.object_reader(tree_hash)?;
     let tree = read_tree_object(&mut reader)?;
 
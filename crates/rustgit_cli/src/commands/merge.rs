@@ -0,0 +1,149 @@
+use clap::Args;
+use rustgit::{
+    merge::{ConflictKind, FastForward, MergeOptions, MergeOutcome, TreeMerge},
+    Repository,
+};
+use std::{collections::BTreeSet, path::Path};
+
+#[derive(Args, Debug)]
+pub struct MergeArgs {
+    /// Always create a merge commit, even when the merge could be a fast-forward
+    #[clap(long = "no-ff", conflicts_with = "ff_only")]
+    no_ff: bool,
+
+    /// Refuse to merge unless the merge is a fast-forward
+    #[clap(long = "ff-only")]
+    ff_only: bool,
+
+    /// Abort the merge in progress, going back to the state before it
+    #[clap(long, conflicts_with_all = ["no_ff", "ff_only"])]
+    abort: bool,
+
+    /// The branch or commit to merge into HEAD
+    #[clap(required_unless_present = "abort")]
+    commit: Option<String>,
+}
+
+// The message of the merge commit, following the wording of `git fmt-merge-msg`
+fn merge_message(repository: &Repository, name: &str) -> anyhow::Result<String> {
+    let full_name = repository.dwim_reference(name)?;
+    let mut message = match full_name.as_deref() {
+        Some(full_name) if full_name.starts_with("refs/heads/") => {
+            format!("Merge branch '{}'", &full_name["refs/heads/".len()..])
+        }
+        Some(full_name) if full_name.starts_with("refs/tags/") => {
+            format!("Merge tag '{}'", &full_name["refs/tags/".len()..])
+        }
+        _ => format!("Merge commit '{}'", name),
+    };
+
+    if let Some(branch) = repository.head()?.referent_name() {
+        let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+        if branch != "main" && branch != "master" {
+            message.push_str(&format!(" into {}", branch));
+        }
+    }
+    Ok(message)
+}
+
+fn print_merge(merge: &TreeMerge, label: &str) {
+    let paths: BTreeSet<&Path> = merge
+        .auto_merged
+        .iter()
+        .map(|path| path.as_path())
+        .chain(
+            merge
+                .conflicts
+                .iter()
+                .map(|conflict| conflict.path.as_path()),
+        )
+        .collect();
+
+    for path in paths {
+        if merge.auto_merged.iter().any(|merged| merged == path) {
+            println!("Auto-merging {}", path.display());
+        }
+        let Some(conflict) = merge
+            .conflicts
+            .iter()
+            .find(|conflict| conflict.path == path)
+        else {
+            continue;
+        };
+        let path = path.display();
+        match &conflict.kind {
+            ConflictKind::Content => println!("CONFLICT (content): Merge conflict in {path}"),
+            ConflictKind::AddAdd => println!("CONFLICT (add/add): Merge conflict in {path}"),
+            ConflictKind::Binary => {
+                println!("warning: Cannot merge binary files: {path} (HEAD vs. {label})");
+                println!("CONFLICT (content): Merge conflict in {path}");
+            }
+            ConflictKind::ModifyDelete { deleted_by_us } => {
+                let (deleted, modified) = if *deleted_by_us {
+                    ("HEAD", label)
+                } else {
+                    (label, "HEAD")
+                };
+                println!(
+                    "CONFLICT (modify/delete): {path} deleted in {deleted} and modified in \
+                     {modified}.  Version {modified} of {path} left in tree."
+                );
+            }
+            ConflictKind::FileDirectory { original, ours } => {
+                let side = if *ours { "HEAD" } else { label };
+                println!(
+                    "CONFLICT (file/directory): directory in the way of {} from {side}; moving \
+                     it to {path} instead.",
+                    original.display()
+                );
+            }
+        }
+    }
+}
+
+pub fn merge(args: MergeArgs) -> anyhow::Result<()> {
+    let repository = Repository::search_and_open(&std::env::current_dir()?)?;
+
+    if args.abort {
+        repository.merge_abort()?;
+        return Ok(());
+    }
+
+    let name = args.commit.expect("required unless aborting");
+    let theirs = repository.resolve_revision(&name)?;
+    let options = MergeOptions {
+        fast_forward: if args.no_ff {
+            FastForward::Never
+        } else if args.ff_only {
+            FastForward::Only
+        } else {
+            FastForward::Allow
+        },
+        message: merge_message(&repository, &name)?,
+        label: name.clone(),
+    };
+
+    match repository.merge(theirs, &options)? {
+        MergeOutcome::UpToDate => println!("Already up to date."),
+        MergeOutcome::FastForward { from, to } => {
+            if let Some(from) = from {
+                println!(
+                    "Updating {}..{}",
+                    repository.abbreviate(from, repository.default_abbrev_length()?)?,
+                    repository.abbreviate(to, repository.default_abbrev_length()?)?
+                );
+            }
+            println!("Fast-forward");
+        }
+        MergeOutcome::Merged { merge, .. } => {
+            print_merge(&merge, &name);
+            println!("Merge made by the 'resolve' strategy.");
+        }
+        MergeOutcome::Conflicts(merge) => {
+            print_merge(&merge, &name);
+            println!("Automatic merge failed; fix conflicts and then commit the result.");
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
@@ -1,23 +1,53 @@
 mod add;
+mod branch;
 mod cat_file;
+mod check_ignore;
+mod check_ref_format;
+mod checkout;
 mod commit;
 mod commit_tree;
+mod config;
+mod diff;
+mod for_each_ref;
 mod hash_object;
 mod init;
+mod log;
 mod ls_files;
 mod ls_tree;
+mod merge;
+mod reflog;
+mod reset;
 mod rev_parse;
+mod show_ref;
 mod status;
+mod switch;
+mod tag;
+mod update_ref;
 mod write_tree;
 
 pub use add::{add, AddArgs};
+pub use branch::{branch, BranchArgs};
 pub use cat_file::{cat_file, CatFileArgs};
+pub use check_ignore::{check_ignore, CheckIgnoreArgs};
+pub use check_ref_format::{check_ref_format, CheckRefFormatArgs};
+pub use checkout::{checkout, CheckoutArgs};
 pub use commit::{commit, CommitArgs};
 pub use commit_tree::{commit_tree, CommitTreeArgs};
+pub use config::{config, ConfigArgs};
+pub use diff::{diff, DiffArgs};
+pub use for_each_ref::{for_each_ref, ForEachRefArgs};
 pub use hash_object::{hash_object, HashObjectArgs};
-pub use init::init;
+pub use init::{init, InitArgs};
+pub use log::{log, LogArgs};
 pub use ls_files::{ls_files, LsFilesArgs};
 pub use ls_tree::{ls_tree, LsTreeArgs};
+pub use merge::{merge, MergeArgs};
+pub use reflog::{reflog, ReflogArgs};
+pub use reset::{reset, ResetArgs};
 pub use rev_parse::{rev_parse, RevParseArgs};
-pub use status::status;
+pub use show_ref::{show_ref, ShowRefArgs};
+pub use status::{status, StatusArgs};
+pub use switch::{switch, SwitchArgs};
+pub use tag::{tag, TagArgs};
+pub use update_ref::{update_ref, UpdateRefArgs};
 pub use write_tree::write_tree;
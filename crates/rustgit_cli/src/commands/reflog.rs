@@ -0,0 +1,188 @@
+use anyhow::bail;
+use chrono::{DateTime, FixedOffset, Local};
+use clap::{Args, Subcommand};
+use rustgit::{object::parse_approxidate, Repository};
+
+#[derive(Args, Debug)]
+#[clap(args_conflicts_with_subcommands = true)]
+pub struct ReflogArgs {
+    #[command(subcommand)]
+    command: Option<ReflogCommand>,
+
+    /// The reference whose reflog to show, HEAD by default
+    reference: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum ReflogCommand {
+    /// Show the entries of a reflog, newest first
+    Show {
+        /// The reference whose reflog to show, HEAD by default
+        reference: Option<String>,
+    },
+
+    /// Remove the old entries of reflogs
+    Expire {
+        /// Remove the entries older than this date, `gc.reflogExpire` or 90 days by default.
+        /// `all` removes every entry and `never` keeps them all
+        #[clap(long, value_name = "time")]
+        expire: Option<String>,
+
+        /// Expire the reflogs of all references
+        #[clap(long)]
+        all: bool,
+
+        /// The references whose reflogs to expire
+        references: Vec<String>,
+    },
+
+    /// Remove single entries from reflogs
+    Delete {
+        /// The entries to remove, such as `main@{2}`
+        #[clap(required = true)]
+        entries: Vec<String>,
+    },
+}
+
+// The full name of a reference given on the command line, `@` being HEAD
+fn reference_name(repository: &Repository, name: &str) -> anyhow::Result<Option<String>> {
+    match name {
+        "@" => Ok(Some("HEAD".to_string())),
+        _ => Ok(repository.dwim_reference(name)?),
+    }
+}
+
+fn show(repository: &Repository, reference: Option<String>) -> anyhow::Result<()> {
+    let reference = match reference.as_deref() {
+        None | Some("@") => "HEAD".to_string(),
+        Some(reference) => reference.to_string(),
+    };
+    let Some(name) = reference_name(repository, &reference)? else {
+        bail!(
+            "ambiguous argument '{}': unknown revision or path not in the working tree.\n\
+             Use '--' to separate paths from revisions, like this:\n\
+             'rustgit <command> [<revision>...] -- [<file>...]'",
+            reference
+        );
+    };
+
+    for (n, entry) in repository.reflog(&name)?.iter().rev().enumerate() {
+        println!(
+            "{} {}@{{{}}}: {}",
+            repository.abbreviate(entry.new, repository.default_abbrev_length()?)?,
+            reference,
+            n,
+            entry.message
+        );
+    }
+    Ok(())
+}
+
+// The date before which entries expire, None if they never do
+fn expire_date(
+    repository: &Repository,
+    expire: Option<String>,
+) -> anyhow::Result<Option<DateTime<FixedOffset>>> {
+    let expire = match expire {
+        Some(expire) => expire,
+        None => repository
+            .config()?
+            .get("gc.reflogExpire")
+            .unwrap_or("90.days.ago")
+            .to_string(),
+    };
+
+    let now = Local::now().fixed_offset();
+    match expire.as_str() {
+        "never" | "false" => Ok(None),
+        "all" => Ok(Some(now)),
+        _ => match parse_approxidate(&expire, now) {
+            Some(date) => Ok(Some(date)),
+            None => bail!("invalid timestamp '{}' given to '--expire'", expire),
+        },
+    }
+}
+
+fn expire(
+    repository: &Repository,
+    expire: Option<String>,
+    all: bool,
+    references: Vec<String>,
+) -> anyhow::Result<()> {
+    let date = expire_date(repository, expire)?;
+
+    let mut names = vec![];
+    let mut failed = false;
+    if all {
+        names = repository.reflog_names()?;
+    }
+    for reference in references {
+        match reference_name(repository, &reference)? {
+            Some(name) => names.push(name),
+            None => {
+                eprintln!("error: {} points nowhere!", reference);
+                failed = true;
+            }
+        }
+    }
+
+    if let Some(date) = date {
+        for name in names {
+            repository.expire_reflog(&name, date)?;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn delete(repository: &Repository, entries: Vec<String>) -> anyhow::Result<()> {
+    let mut failed = false;
+    for entry in entries {
+        let selector = entry
+            .strip_suffix('}')
+            .and_then(|entry| entry.split_once("@{"))
+            .and_then(|(reference, n)| Some((reference, n.parse::<usize>().ok()?)));
+        let Some((reference, n)) = selector else {
+            eprintln!("error: not a reflog: {}", entry);
+            failed = true;
+            continue;
+        };
+
+        let reference = if reference.is_empty() {
+            "HEAD"
+        } else {
+            reference
+        };
+        match reference_name(repository, reference)? {
+            Some(name) if repository.reflog_exists(&name) => {
+                repository.delete_reflog_entry(&name, n)?;
+            }
+            _ => {
+                eprintln!("error: no reflog for '{}'", entry);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+pub fn reflog(args: ReflogArgs) -> anyhow::Result<()> {
+    let repository = Repository::search_and_open(&std::env::current_dir()?)?;
+
+    match args.command {
+        None => show(&repository, args.reference),
+        Some(ReflogCommand::Show { reference }) => show(&repository, reference),
+        Some(ReflogCommand::Expire {
+            expire: date,
+            all,
+            references,
+        }) => expire(&repository, date, all, references),
+        Some(ReflogCommand::Delete { entries }) => delete(&repository, entries),
+    }
+}
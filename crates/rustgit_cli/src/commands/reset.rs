@@ -0,0 +1,165 @@
+use super::log::repository_path;
+use anyhow::bail;
+use clap::Args;
+use rustgit::{object::ObjectType, oid::ObjectId, reset::ResetMode, Repository};
+use std::path::{Path, PathBuf};
+
+#[derive(Args, Debug)]
+pub struct ResetArgs {
+    /// Only move HEAD, leaving the index and the working tree alone
+    #[clap(long, group = "mode")]
+    soft: bool,
+
+    /// Reset the index but not the working tree (the default)
+    #[clap(long, group = "mode")]
+    mixed: bool,
+
+    /// Reset the index and the working tree, discarding the local changes
+    #[clap(long, group = "mode")]
+    hard: bool,
+
+    /// Only report errors
+    #[clap(short = 'q', long)]
+    quiet: bool,
+
+    /// The commit to reset to, HEAD by default. It can be followed by paths without `--` when
+    /// it can't be mistaken for one, and a file that is not a revision is a path too
+    #[clap(value_name = "commit")]
+    arguments: Vec<String>,
+
+    /// Only reset the index entries of these paths, leaving HEAD alone
+    #[clap(last = true)]
+    paths: Vec<String>,
+}
+
+// The message of git when an argument could be a revision or a path
+fn ambiguous(spec: &str, reason: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "ambiguous argument '{}': {}\n\
+         Use '--' to separate paths from revisions, like this:\n\
+         'rustgit <command> [<revision>...] -- [<file>...]'",
+        spec,
+        reason
+    )
+}
+
+// Like git, split the arguments before `--` into the commit and the paths: the first one is the
+// commit when it is a revision but not a file, otherwise they are all paths and the first one
+// must be a file. A commit followed by `--` is always a commit
+fn split_arguments(
+    repository: &Repository,
+    args: &ResetArgs,
+) -> anyhow::Result<(Option<String>, Vec<String>)> {
+    let (revision, mut paths) = match args.arguments.as_slice() {
+        [] => (None, vec![]),
+        [revision] if !args.paths.is_empty() => (Some(revision.clone()), vec![]),
+        [first, rest @ ..] => {
+            let is_file = Path::new(first).exists();
+            match resolve_commit(repository, first) {
+                Some(_) if is_file => return Err(ambiguous(first, "both revision and filename")),
+                Some(_) => (Some(first.clone()), rest.to_vec()),
+                None if is_file => (None, args.arguments.clone()),
+                None => {
+                    return Err(ambiguous(
+                        first,
+                        "unknown revision or path not in the working tree.",
+                    ))
+                }
+            }
+        }
+    };
+    paths.extend(args.paths.iter().cloned());
+    Ok((revision, paths))
+}
+
+// Like git's refresh after a mixed reset, list what differs between the index and the working
+// tree
+fn print_unstaged_changes(repository: &Repository) -> anyhow::Result<()> {
+    let changes = repository.diff_index_to_worktree()?;
+    if !changes.is_empty() {
+        println!("Unstaged changes after reset:");
+    }
+    for change in changes {
+        let letter = if change.new.is_some() { 'M' } else { 'D' };
+        println!("{}\t{}", letter, change.path.display());
+    }
+    Ok(())
+}
+
+fn resolve_commit(repository: &Repository, spec: &str) -> Option<ObjectId> {
+    let oid = repository.resolve_revision(spec).ok()?;
+    repository.peel(oid, ObjectType::Commit).ok()
+}
+
+// The commit HEAD points to, None on an unborn branch
+fn head_commit(repository: &Repository) -> anyhow::Result<Option<ObjectId>> {
+    match repository.head()?.is_unborn() {
+        true => Ok(None),
+        false => Ok(Some(repository.head_id()?)),
+    }
+}
+
+fn reset_paths(
+    repository: &Repository,
+    args: &ResetArgs,
+    commit: Option<ObjectId>,
+    paths: &[String],
+) -> anyhow::Result<()> {
+    if args.soft {
+        bail!("Cannot do soft reset with paths.");
+    }
+    if args.hard {
+        bail!("Cannot do hard reset with paths.");
+    }
+
+    let current_dir = std::env::current_dir()?;
+    let paths: Vec<PathBuf> = paths
+        .iter()
+        .map(|path| repository_path(Path::new(path), &current_dir, &repository.repository_dir))
+        .collect();
+    repository.reset_paths(commit, &paths)?;
+    if !args.quiet {
+        print_unstaged_changes(repository)?;
+    }
+    Ok(())
+}
+
+pub fn reset(args: ResetArgs) -> anyhow::Result<()> {
+    let repository = Repository::search_and_open(&std::env::current_dir()?)?;
+
+    let (revision, paths) = split_arguments(&repository, &args)?;
+    let commit = match &revision {
+        None => head_commit(&repository)?,
+        Some(spec) => match resolve_commit(&repository, spec) {
+            Some(commit) => Some(commit),
+            None => bail!("Failed to resolve '{}' as a valid tree.", spec),
+        },
+    };
+    if !paths.is_empty() {
+        return reset_paths(&repository, &args, commit, &paths);
+    }
+
+    let mode = if args.soft {
+        ResetMode::Soft
+    } else if args.hard {
+        ResetMode::Hard
+    } else {
+        ResetMode::Mixed
+    };
+    let message = format!("reset: moving to {}", revision.as_deref().unwrap_or("HEAD"));
+    repository.reset(commit, mode, &message)?;
+
+    if args.quiet {
+        return Ok(());
+    }
+    match (mode, commit) {
+        (ResetMode::Hard, Some(commit)) => println!(
+            "HEAD is now at {} {}",
+            repository.abbreviate(commit, repository.default_abbrev_length()?)?,
+            repository.read_commit(commit)?.summary()
+        ),
+        (ResetMode::Mixed, _) => print_unstaged_changes(&repository)?,
+        _ => {}
+    }
+    Ok(())
+}
@@ -1,34 +1,59 @@
 use clap::Args;
-use rustgit::oid::Sha1HashHexString;
-
+use rustgit::oid::ObjectId;
+use rustgit::revision::{Revision, RevisionError};
 use rustgit::Repository;
 
 #[derive(Args, Debug)]
 pub struct RevParseArgs {
-    #[clap(name = "arg")]
-    arg: String,
+    /// Print the shortest unique abbreviation of the object ids, of at least <length> digits
+    #[clap(long, value_name = "length", num_args = 0..=1, require_equals = true, default_missing_value = "7")]
+    short: Option<usize>,
+
+    /// Revisions to resolve, e.g. `HEAD~2`, `v1.0^{tree}`, `main:src/lib.rs` or `a..b`
+    #[clap(name = "arg", required = true)]
+    args: Vec<String>,
 }
 
-fn rev_parse_impl(repo: &Repository, arg: &str) -> anyhow::Result<()> {
-    if arg == "HEAD" {
-        let head_hash = repo.head_id()?;
-        println!("{}", head_hash);
-    } else {
-        let sha1 = Sha1HashHexString::from_str(arg)?;
-        println!("{}", sha1);
+fn rev_parse_impl(repo: &Repository, arg: &str, short: Option<usize>) -> anyhow::Result<()> {
+    let display = |oid: ObjectId| -> std::io::Result<String> {
+        match short {
+            Some(length) => repo.abbreviate(oid, length),
+            None => Ok(oid.to_string()),
+        }
+    };
+
+    // `^<rev>` excludes the commits reachable from <rev>
+    if let Some(excluded) = arg.strip_prefix('^') {
+        println!("^{}", display(repo.resolve_revision(excluded)?)?);
+        return Ok(());
+    }
+
+    match repo.rev_parse(arg)? {
+        Revision::Single(oid) => println!("{}", display(oid)?),
+        Revision::Range { exclude, include } => {
+            println!("{}", display(include)?);
+            println!("^{}", display(exclude)?);
+        }
     }
     Ok(())
 }
 
 pub fn rev_parse(args: RevParseArgs) -> anyhow::Result<()> {
     let repository = Repository::search_and_open(&std::env::current_dir()?)?;
-    let arg = args.arg;
 
-    if rev_parse_impl(&repository, &arg).is_err() {
-        anyhow::bail!(
-            "ambiguous argument '{}': unknown revision or path not in the working tree.",
-            arg
-        )
+    for arg in &args.args {
+        let result = rev_parse_impl(&repository, arg, args.short);
+        if let Err(e) = &result {
+            if let Some(RevisionError::UnknownRevision(_) | RevisionError::PathNotFound { .. }) =
+                e.downcast_ref()
+            {
+                anyhow::bail!(
+                    "ambiguous argument '{}': unknown revision or path not in the working tree.",
+                    arg
+                )
+            }
+        }
+        result?;
     }
 
     Ok(())
@@ -0,0 +1,141 @@
+use anyhow::bail;
+use clap::Args;
+use rustgit::{
+    object::ObjectType,
+    oid::ObjectId,
+    references::{Ref, ReferenceError},
+    Repository,
+};
+
+#[derive(Args, Debug)]
+pub struct ShowRefArgs {
+    /// Only show branches, i.e. references under `refs/heads/`
+    #[clap(long)]
+    heads: bool,
+
+    /// Only show tags, i.e. references under `refs/tags/`
+    #[clap(long)]
+    tags: bool,
+
+    /// Show each of the full reference names given, failing if one of them doesn't exist
+    #[clap(long)]
+    verify: bool,
+
+    /// Show nothing, only the exit status tells whether references were found
+    #[clap(short = 'q', long)]
+    quiet: bool,
+
+    /// Only show the object names, abbreviated to <n> hex digits if given
+    #[clap(
+        short = 's',
+        long = "hash",
+        value_name = "n",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "40"
+    )]
+    hash: Option<usize>,
+
+    /// Also show the object each annotated tag peels to, as `<tag>^{}`
+    #[clap(short = 'd', long)]
+    dereference: bool,
+
+    /// Only show the references whose name is the pattern or ends with `/<pattern>`, or the
+    /// references to verify with --verify
+    #[clap(value_name = "pattern")]
+    patterns: Vec<String>,
+}
+
+// Whether a pattern of show-ref matches the end of a reference name on whole components
+fn matches_tail(name: &str, pattern: &str) -> bool {
+    name.strip_suffix(pattern)
+        .is_some_and(|rest| rest.is_empty() || rest.ends_with('/'))
+}
+
+struct Printer<'r> {
+    repository: &'r Repository,
+    args: &'r ShowRefArgs,
+}
+
+impl Printer<'_> {
+    fn print_line(&self, oid: ObjectId, name: &str) -> anyhow::Result<()> {
+        match self.args.hash {
+            Some(length) => println!("{}", self.repository.abbreviate(oid, length)?),
+            None => println!("{} {}", oid, name),
+        }
+        Ok(())
+    }
+
+    fn show(&self, oid: ObjectId, name: &str) -> anyhow::Result<()> {
+        if self.args.quiet {
+            return Ok(());
+        }
+        self.print_line(oid, name)?;
+        if self.args.dereference && self.repository.object_type(oid)? == ObjectType::Tag {
+            let peeled = self.repository.peel_tags(oid)?;
+            self.print_line(peeled, &format!("{}^{{}}", name))?;
+        }
+        Ok(())
+    }
+}
+
+fn verify(printer: &Printer, names: &[String]) -> anyhow::Result<()> {
+    if names.is_empty() {
+        bail!("--verify requires a reference");
+    }
+    let repository = printer.repository;
+    for name in names {
+        let oid = match name.starts_with("refs/") || name == "HEAD" {
+            true => match repository.resolve_reference(&Ref::Symbolic(name.clone())) {
+                Err(ReferenceError::NotExist(_)) => None,
+                oid => Some(oid?),
+            },
+            false => None,
+        };
+        match oid {
+            Some(oid) => printer.show(oid, name)?,
+            None if printer.args.quiet => std::process::exit(1),
+            None => bail!("'{}' - not a valid ref", name),
+        }
+    }
+    Ok(())
+}
+
+pub fn show_ref(args: ShowRefArgs) -> anyhow::Result<()> {
+    let repository = Repository::search_and_open(&std::env::current_dir()?)?;
+    let printer = Printer {
+        repository: &repository,
+        args: &args,
+    };
+    if args.verify {
+        return verify(&printer, &args.patterns);
+    }
+
+    let mut found = false;
+    for reference in repository.references("")? {
+        let name = &reference.name;
+        let kind_matches = !(args.heads || args.tags)
+            || (args.heads && name.starts_with("refs/heads/"))
+            || (args.tags && name.starts_with("refs/tags/"));
+        let pattern_matches = args.patterns.is_empty()
+            || args
+                .patterns
+                .iter()
+                .any(|pattern| matches_tail(name, pattern));
+        if !kind_matches || !pattern_matches {
+            continue;
+        }
+
+        // like git, symbolic references that don't resolve are left out
+        let oid = match repository.resolve_reference(&reference.target) {
+            Err(ReferenceError::NotExist(_)) => continue,
+            oid => oid?,
+        };
+        printer.show(oid, name)?;
+        found = true;
+    }
+    if !found {
+        std::process::exit(1);
+    }
+    Ok(())
+}
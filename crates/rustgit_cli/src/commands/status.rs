@@ -1,5 +1,21 @@
+use clap::Args;
+use rustgit::head::Head;
+use rustgit::status::{ChangeKind, Status, UnmergedKind};
 use rustgit::Repository;
+use std::collections::BTreeSet;
 use std::env::current_dir;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Args, Debug)]
+pub struct StatusArgs {
+    /// Give the output in the short-format
+    #[clap(short = 's', long)]
+    short: bool,
+
+    /// Give the output in an easy-to-parse format for scripts
+    #[clap(long, value_name = "version", num_args = 0..=1, default_missing_value = "v1")]
+    porcelain: Option<String>,
+}
 
 fn print_nothing_to_commit(has_no_commit_yet: bool) {
     print!("nothing to commit");
@@ -10,30 +26,258 @@ fn print_nothing_to_commit(has_no_commit_yet: bool) {
     }
 }
 
-pub fn status() -> anyhow::Result<()> {
-    let repository = Repository::search_and_open(&current_dir()?)
-        .map_err(|_| anyhow::anyhow!("not a git repository (or any of the parent directories)"))?;
+// Express a path relative to the repository root as a path relative to the current directory
+fn relative_to(path: &Path, current_dir: &Path) -> PathBuf {
+    let mut path_components = path.components().peekable();
+    let mut current_dir_components = current_dir.components().peekable();
+    while path_components.peek().is_some()
+        && path_components.peek() == current_dir_components.peek()
+    {
+        path_components.next();
+        current_dir_components.next();
+    }
 
-    let head = repository.head()?;
+    let mut relative: PathBuf = current_dir_components
+        .map(|_| Component::ParentDir)
+        .collect();
+    relative.extend(path_components);
+
+    // keep the trailing slash of untracked directories
+    if path.as_os_str().to_string_lossy().ends_with('/') {
+        relative.as_mut_os_string().push("/");
+    }
+    relative
+}
 
-    let head_ref_name = head
-        .referent_name()
-        .expect("git status for detached head is not implemented");
+fn change_label(kind: ChangeKind) -> &'static str {
+    match kind {
+        ChangeKind::Added => "new file:",
+        ChangeKind::Modified => "modified:",
+        ChangeKind::Deleted => "deleted:",
+    }
+}
+
+fn change_letter(kind: Option<&ChangeKind>) -> char {
+    match kind {
+        None => ' ',
+        Some(ChangeKind::Added) => 'A',
+        Some(ChangeKind::Modified) => 'M',
+        Some(ChangeKind::Deleted) => 'D',
+    }
+}
 
-    let branch = if head_ref_name.starts_with("refs/heads/") {
-        &head_ref_name[11..]
+fn unmerged_label(kind: UnmergedKind) -> &'static str {
+    match kind {
+        UnmergedKind::BothDeleted => "both deleted:",
+        UnmergedKind::AddedByUs => "added by us:",
+        UnmergedKind::DeletedByThem => "deleted by them:",
+        UnmergedKind::AddedByThem => "added by them:",
+        UnmergedKind::DeletedByUs => "deleted by us:",
+        UnmergedKind::BothAdded => "both added:",
+        UnmergedKind::BothModified => "both modified:",
+    }
+}
+
+fn unmerged_code(kind: UnmergedKind) -> &'static str {
+    match kind {
+        UnmergedKind::BothDeleted => "DD",
+        UnmergedKind::AddedByUs => "AU",
+        UnmergedKind::DeletedByThem => "UD",
+        UnmergedKind::AddedByThem => "UA",
+        UnmergedKind::DeletedByUs => "DU",
+        UnmergedKind::BothAdded => "AA",
+        UnmergedKind::BothModified => "UU",
+    }
+}
+
+// Same as git, the hint depends on whether the conflicts are about deleted files
+fn print_resolution_hint(status: &Status) {
+    let (mut both_deleted, mut deleted, mut other) = (false, false, false);
+    for kind in status.unmerged.values() {
+        match kind {
+            UnmergedKind::BothDeleted => both_deleted = true,
+            UnmergedKind::DeletedByThem | UnmergedKind::DeletedByUs => deleted = true,
+            _ => other = true,
+        }
+    }
+    if !both_deleted && !deleted {
+        println!("  (use \"rustgit add <file>...\" to mark resolution)");
+    } else if !deleted && !other {
+        println!("  (use \"rustgit rm <file>...\" to mark resolution)");
     } else {
-        unimplemented!("Head reference has a bad format: {}", head_ref_name);
-    };
+        println!("  (use \"rustgit add/rm <file>...\" as appropriate to mark resolution)");
+    }
+}
+
+fn print_long(
+    repository: &Repository,
+    status: &Status,
+    display_path: impl Fn(&Path) -> PathBuf,
+) -> anyhow::Result<()> {
+    let head = repository.head()?;
+    match &head {
+        Head::Detached(oid) => println!(
+            "HEAD detached at {}",
+            repository.abbreviate(*oid, repository.default_abbrev_length()?)?
+        ),
+        _ => {
+            let head_ref_name = head.referent_name().expect("HEAD should be symbolic");
+            let branch = head_ref_name
+                .strip_prefix("refs/heads/")
+                .unwrap_or(head_ref_name);
+            println!("On branch {}", branch);
+        }
+    }
 
-    println!("On branch {}", branch);
+    let merging = repository.merge_head()?.is_some();
+    if merging {
+        if status.unmerged.is_empty() {
+            println!("All conflicts fixed but you are still merging.");
+            println!("  (use \"rustgit commit\" to conclude merge)");
+        } else {
+            println!("You have unmerged paths.");
+            println!("  (fix conflicts and run \"rustgit commit\")");
+            println!("  (use \"rustgit merge --abort\" to abort the merge)");
+        }
+        println!();
+    }
 
     let has_no_commit_yet = head.is_unborn();
     if has_no_commit_yet {
         println!("\nNo commits yet\n");
     }
 
-    print_nothing_to_commit(has_no_commit_yet);
+    // like git, there is no hint to unstage during a merge
+    let print_unstage_hint = || match (merging, has_no_commit_yet) {
+        (true, _) => {}
+        (false, true) => println!("  (use \"rustgit rm --cached <file>...\" to unstage)"),
+        (false, false) => println!("  (use \"rustgit restore --staged <file>...\" to unstage)"),
+    };
+
+    if !status.staged.is_empty() {
+        println!("Changes to be committed:");
+        print_unstage_hint();
+        for (path, &kind) in &status.staged {
+            println!(
+                "\t{:<12}{}",
+                change_label(kind),
+                display_path(path).display()
+            );
+        }
+        println!();
+    }
+
+    if !status.unmerged.is_empty() {
+        println!("Unmerged paths:");
+        print_unstage_hint();
+        print_resolution_hint(status);
+        for (path, &kind) in &status.unmerged {
+            println!(
+                "\t{:<17}{}",
+                unmerged_label(kind),
+                display_path(path).display()
+            );
+        }
+        println!();
+    }
+
+    if !status.unstaged.is_empty() {
+        println!("Changes not staged for commit:");
+        if status
+            .unstaged
+            .values()
+            .any(|&kind| kind == ChangeKind::Deleted)
+        {
+            println!("  (use \"rustgit add/rm <file>...\" to update what will be committed)");
+        } else {
+            println!("  (use \"rustgit add <file>...\" to update what will be committed)");
+        }
+        println!("  (use \"rustgit restore <file>...\" to discard changes in working directory)");
+        for (path, &kind) in &status.unstaged {
+            println!(
+                "\t{:<12}{}",
+                change_label(kind),
+                display_path(path).display()
+            );
+        }
+        println!();
+    }
+
+    if !status.untracked.is_empty() {
+        println!("Untracked files:");
+        println!("  (use \"rustgit add <file>...\" to include in what will be committed)");
+        for path in &status.untracked {
+            println!("\t{}", display_path(path).display());
+        }
+        println!();
+    }
+
+    if status.staged.is_empty() {
+        if !status.unstaged.is_empty() || !status.unmerged.is_empty() {
+            println!(
+                "no changes added to commit (use \"rustgit add\" and/or \"rustgit commit -a\")"
+            );
+        } else if !status.untracked.is_empty() {
+            println!("nothing added to commit but untracked files present (use \"rustgit add\" to track)");
+        } else {
+            print_nothing_to_commit(has_no_commit_yet);
+        }
+    }
+    Ok(())
+}
+
+fn print_short(status: &Status, display_path: impl Fn(&Path) -> PathBuf) {
+    let changed_paths: BTreeSet<&PathBuf> = status
+        .staged
+        .keys()
+        .chain(status.unstaged.keys())
+        .chain(status.unmerged.keys())
+        .collect();
+    for path in changed_paths {
+        let code = match status.unmerged.get(path) {
+            Some(&kind) => unmerged_code(kind).to_string(),
+            None => format!(
+                "{}{}",
+                change_letter(status.staged.get(path)),
+                change_letter(status.unstaged.get(path))
+            ),
+        };
+        println!("{} {}", code, display_path(path).display());
+    }
+    for path in &status.untracked {
+        println!("?? {}", display_path(path).display());
+    }
+}
+
+pub fn status(args: StatusArgs) -> anyhow::Result<()> {
+    let current_dir = current_dir()?;
+    let repository = Repository::search_and_open(&current_dir)
+        .map_err(|_| anyhow::anyhow!("not a git repository (or any of the parent directories)"))?;
+
+    let status = repository.status()?;
+
+    if let Some(version) = args.porcelain {
+        anyhow::ensure!(
+            version == "v1",
+            "unsupported porcelain version '{}'",
+            version
+        );
+        // paths are always relative to the repository root
+        print_short(&status, Path::to_path_buf);
+        return Ok(());
+    }
+
+    let prefix = current_dir
+        .strip_prefix(&repository.repository_dir)
+        .unwrap_or(Path::new(""))
+        .to_path_buf();
+    let display_path = |path: &Path| relative_to(path, &prefix);
+
+    if args.short {
+        print_short(&status, display_path);
+    } else {
+        print_long(&repository, &status, display_path)?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,47 @@
+use super::checkout::{switch_to, Destination};
+use anyhow::bail;
+use clap::Args;
+use rustgit::Repository;
+
+#[derive(Args, Debug)]
+pub struct SwitchArgs {
+    /// Create a new branch starting at the given commit and switch to it
+    #[clap(short = 'c', long)]
+    create: Option<String>,
+
+    /// Detach HEAD at the given commit
+    #[clap(short = 'd', long, conflicts_with = "create")]
+    detach: bool,
+
+    /// The branch to switch to. With -c, the start point of the new branch, and with --detach,
+    /// the commit to detach HEAD at
+    target: Option<String>,
+}
+
+pub fn switch(args: SwitchArgs) -> anyhow::Result<()> {
+    let repository = Repository::search_and_open(&std::env::current_dir()?)?;
+
+    let destination = match (args.create, args.target) {
+        (Some(name), start_point) => Destination::NewBranch { name, start_point },
+        (None, target) if args.detach => Destination::Detached(target.unwrap_or("HEAD".into())),
+        (None, None) => bail!("missing branch or commit argument"),
+        (None, Some(target)) => {
+            let is_branch = repository
+                .try_find_reference(&format!("refs/heads/{}", target))?
+                .is_some();
+            if is_branch {
+                Destination::Branch(target)
+            } else if repository.resolve_revision(&target).is_ok() {
+                bail!(
+                    "a branch is expected, got commit '{}'\n\
+                     hint: If you want to detach HEAD at the commit, try again with the --detach \
+                     option.",
+                    target
+                );
+            } else {
+                bail!("invalid reference: {}", target);
+            }
+        }
+    };
+    switch_to(&repository, destination)
+}
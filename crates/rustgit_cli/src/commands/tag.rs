@@ -0,0 +1,107 @@
+use anyhow::Context;
+use chrono::Utc;
+use clap::Args;
+use rustgit::{
+    object::{get_committer, read_header, ObjectHeader, ObjectType, Tag},
+    oid::ObjectId,
+    refname::{is_valid_ref_name, RefNameOptions},
+    Repository,
+};
+
+#[derive(Args, Debug)]
+pub struct TagArgs {
+    /// Make an unsigned, annotated tag object
+    #[clap(short = 'a')]
+    annotate: bool,
+
+    /// Use the given tag message (implies -a)
+    #[clap(short = 'm')]
+    message: Option<String>,
+
+    /// Replace an existing tag with the given name
+    #[clap(short = 'f', long)]
+    force: bool,
+
+    /// List tags. This is the default when no tag name is given
+    #[clap(short = 'l', long)]
+    list: bool,
+
+    /// The name of the tag to create
+    tagname: Option<String>,
+
+    /// The object that the new tag will refer to. Defaults to HEAD
+    commit: Option<String>,
+}
+
+fn list_tags(repository: &Repository) -> anyhow::Result<()> {
+    for reference in repository.references("refs/tags/")? {
+        println!("{}", &reference.name["refs/tags/".len()..]);
+    }
+    Ok(())
+}
+
+// The reflog message of git, which names the tagged object: its summary and date for a commit
+fn reflog_message(
+    repository: &Repository,
+    target: ObjectId,
+    typ: ObjectType,
+) -> anyhow::Result<String> {
+    let description = match typ {
+        ObjectType::Commit => {
+            let commit = repository.read_commit(target)?;
+            let date = commit.committer().time.with_timezone(&Utc);
+            format!("{}, {}", commit.summary(), date.format("%Y-%m-%d"))
+        }
+        ObjectType::Tree => "tree object".to_string(),
+        ObjectType::Blob => "blob object".to_string(),
+        ObjectType::Tag => "other tag object".to_string(),
+    };
+    Ok(format!(
+        "tag: tagging {} ({})",
+        repository.abbreviate(target, repository.default_abbrev_length()?)?,
+        description
+    ))
+}
+
+pub fn tag(args: TagArgs) -> anyhow::Result<()> {
+    let repository = Repository::search_and_open(&std::env::current_dir()?)?;
+
+    let tagname = match args.tagname {
+        Some(tagname) if !args.list => tagname,
+        _ => return list_tags(&repository),
+    };
+
+    let ref_name = format!("refs/tags/{}", tagname);
+    if !is_valid_ref_name(&ref_name, RefNameOptions::default()) {
+        anyhow::bail!("'{}' is not a valid tag name.", tagname);
+    }
+    if !args.force && repository.try_find_reference(&ref_name)?.is_some() {
+        anyhow::bail!("tag '{}' already exists", tagname);
+    }
+
+    let target = repository.resolve_revision(args.commit.as_deref().unwrap_or("HEAD"))?;
+    let mut reader = repository.object_reader(target)?;
+    let ObjectHeader { typ, .. } = read_header(&mut reader)?;
+
+    let oid = if args.annotate || args.message.is_some() {
+        let message = args
+            .message
+            .context("no tag message given, use -m to provide one")?;
+
+        let tag = Tag::new(
+            target,
+            typ,
+            tagname,
+            get_committer(&repository.config()?)?,
+            message,
+        );
+        repository.write_object(&tag)?
+    } else {
+        target
+    };
+
+    let message = reflog_message(&repository, target, typ)?;
+    repository.update_reference(&ref_name, oid, &message)?;
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use anyhow::{anyhow, bail};
+use clap::Args;
+use rustgit::{oid::ObjectId, references::RefTransaction, Repository};
+use std::io::BufRead;
+
+#[derive(Args, Debug)]
+pub struct UpdateRefArgs {
+    /// The reason of the update, recorded in the reflogs
+    #[clap(short = 'm', value_name = "reason", default_value = "")]
+    message: String,
+
+    /// Delete the reference, provided that it holds the value given after it, if any
+    #[clap(short = 'd')]
+    delete: bool,
+
+    /// Update <ref> itself rather than the reference it refers to
+    #[clap(long)]
+    no_deref: bool,
+
+    /// Read updates from the standard input, one per line, and apply them all together or not
+    /// at all: `update <ref> <new> [<old>]`, `create <ref> <new>`, `delete <ref> [<old>]`,
+    /// `verify <ref> [<old>]` or `option no-deref`
+    #[clap(long, conflicts_with_all = ["delete", "reference"])]
+    stdin: bool,
+
+    /// The reference to update
+    #[clap(value_name = "ref", required_unless_present = "stdin")]
+    reference: Option<String>,
+
+    /// The new value of the reference
+    #[clap(value_name = "new-val", required_unless_present_any = ["stdin", "delete"])]
+    new: Option<String>,
+
+    /// The value the reference must have beforehand, empty if it must not exist
+    #[clap(value_name = "old-val")]
+    old: Option<String>,
+}
+
+// An object name, `ObjectId::NULL` for an empty value or the null id
+fn parse_value(repository: &Repository, value: &str) -> Option<ObjectId> {
+    if value.is_empty() || (value.len() == 40 && value.bytes().all(|c| c == b'0')) {
+        return Some(ObjectId::NULL);
+    }
+    repository.resolve_revision(value).ok()
+}
+
+fn reference_name(repository: &Repository, name: &str, no_deref: bool) -> anyhow::Result<String> {
+    match no_deref {
+        true => Ok(name.to_string()),
+        false => Ok(repository.referent(name)?),
+    }
+}
+
+fn update_ref_single(repository: &Repository, args: UpdateRefArgs) -> anyhow::Result<()> {
+    let reference = args.reference.unwrap();
+    let name = reference_name(repository, &reference, args.no_deref)?;
+
+    // with -d, the only value is the old one
+    let (new, old) = match args.delete {
+        true if args.old.is_some() => bail!("rustgit update-ref -d takes at most two arguments"),
+        true => (None, args.new),
+        false => (args.new, args.old),
+    };
+    let new = match new {
+        Some(new) => match parse_value(repository, &new) {
+            Some(oid) => Some(oid),
+            None => bail!("{}: not a valid SHA1", new),
+        },
+        None => None,
+    };
+    let old = match old {
+        Some(old) => match parse_value(repository, &old) {
+            Some(oid) => Some(oid),
+            None => bail!("{}: not a valid old SHA1", old),
+        },
+        None => None,
+    };
+
+    let mut transaction = repository.transaction(&args.message);
+    match new {
+        Some(new) if !new.is_null() => transaction.update(&name, new, old),
+        _ => transaction.delete(&name, old),
+    };
+    transaction.commit().map_err(|e| match args.delete {
+        true => anyhow!(e),
+        false => anyhow!("update_ref failed for ref '{}': {}", reference, e),
+    })?;
+    Ok(())
+}
+
+// Add the update of a line of `--stdin` to the transaction, and return whether it is the
+// `no-deref` option, which applies to the next update
+fn parse_command(
+    repository: &Repository,
+    transaction: &mut RefTransaction,
+    line: &str,
+    no_deref: bool,
+) -> anyhow::Result<bool> {
+    let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let max_values = match command {
+        "update" => 2,
+        "create" | "delete" | "verify" => 1,
+        "option" if rest == "no-deref" => return Ok(true),
+        "option" => bail!("option unknown: {}", rest),
+        _ => bail!("unknown command: {}", line),
+    };
+
+    let mut words = rest.split(' ');
+    let reference = match words.next() {
+        Some(reference) if !reference.is_empty() => reference,
+        _ => bail!("{}: missing <ref>", command),
+    };
+    let values: Vec<&str> = words.collect();
+    if values.len() > max_values {
+        bail!(
+            "{} {}: extra input: {}",
+            command,
+            reference,
+            values[max_values..].join(" ")
+        );
+    }
+    let name = reference_name(repository, reference, no_deref)?;
+
+    let value = |index: usize, kind: &str| -> anyhow::Result<Option<ObjectId>> {
+        let Some(value) = values.get(index) else {
+            return Ok(None);
+        };
+        parse_value(repository, value)
+            .map(Some)
+            .ok_or_else(|| anyhow!("{} {}: invalid <{}>: {}", command, reference, kind, value))
+    };
+    let missing_new = || anyhow!("{} {}: missing <newvalue>", command, reference);
+
+    match command {
+        "update" => {
+            let new = value(0, "newvalue")?.ok_or_else(missing_new)?;
+            let old = value(1, "oldvalue")?;
+            // like git, a null new value deletes the reference
+            match new.is_null() {
+                true => transaction.delete(&name, old),
+                false => transaction.update(&name, new, old),
+            };
+        }
+        "create" => match value(0, "newvalue")?.ok_or_else(missing_new)? {
+            new if new.is_null() => bail!("create {}: zero <newvalue>", reference),
+            new => {
+                transaction.create(&name, new);
+            }
+        },
+        "delete" => match value(0, "oldvalue")? {
+            Some(old) if old.is_null() => bail!("delete {}: zero <oldvalue>", reference),
+            old => {
+                transaction.delete(&name, old);
+            }
+        },
+        _ => {
+            transaction.verify(&name, value(0, "oldvalue")?.unwrap_or(ObjectId::NULL));
+        }
+    }
+    Ok(false)
+}
+
+fn update_ref_stdin(repository: &Repository, args: UpdateRefArgs) -> anyhow::Result<()> {
+    let mut transaction = repository.transaction(&args.message);
+    let mut no_deref_next = false;
+    for line in std::io::stdin().lock().lines() {
+        let line = line?;
+        let no_deref = args.no_deref || no_deref_next;
+        no_deref_next = parse_command(repository, &mut transaction, &line, no_deref)?;
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+pub fn update_ref(args: UpdateRefArgs) -> anyhow::Result<()> {
+    let repository = Repository::search_and_open(&std::env::current_dir()?)?;
+
+    match args.stdin {
+        true => update_ref_stdin(&repository, args),
+        false => update_ref_single(&repository, args),
+    }
+}
@@ -14,7 +14,7 @@ struct Cli {
 #[derive(Debug, Subcommand)]
 enum Command {
     /// Create an empty Git repository
-    Init,
+    Init(InitArgs),
 
     /// Add file contents to the index
     Add(AddArgs),
@@ -46,8 +46,53 @@ enum Command {
     /// Add file contents to the staging area
     Stage(AddArgs),
 
+    /// Show changes between the index, the working tree and commits
+    Diff(DiffArgs),
+
+    /// Show commit logs
+    Log(LogArgs),
+
     /// Show the working tree status
-    Status,
+    Status(StatusArgs),
+
+    /// Create a tag object
+    Tag(TagArgs),
+
+    /// Get and set repository or global options
+    Config(ConfigArgs),
+
+    /// Join two development histories together
+    Merge(MergeArgs),
+
+    /// Debug gitignore / exclude files
+    CheckIgnore(CheckIgnoreArgs),
+
+    /// List, create, or delete branches
+    Branch(BranchArgs),
+
+    /// Switch branches or detach HEAD at a commit
+    Checkout(CheckoutArgs),
+
+    /// Switch branches
+    Switch(SwitchArgs),
+
+    /// Reset current HEAD to the specified state
+    Reset(ResetArgs),
+
+    /// Manage reflog information
+    Reflog(ReflogArgs),
+
+    /// Update the object name stored in a ref safely
+    UpdateRef(UpdateRefArgs),
+
+    /// Ensures that a reference name is well formed
+    CheckRefFormat(CheckRefFormatArgs),
+
+    /// Output information on each ref
+    ForEachRef(ForEachRefArgs),
+
+    /// List references in a local repository
+    ShowRef(ShowRefArgs),
 }
 
 fn main() {
@@ -55,7 +100,7 @@ fn main() {
 
     use Command::*;
     let result = match args.command {
-        Init => init().map_err(anyhow::Error::from),
+        Init(args) => init(args),
         Add(args) => add(args),
         CatFile(args) => cat_file(args),
         HashObject(args) => hash_object(args),
@@ -65,8 +110,23 @@ fn main() {
         CommitTree(args) => commit_tree(args),
         Commit(args) => commit(args),
         RevParse(args) => rev_parse(args),
-        Status => status(),
+        Diff(args) => diff(args),
+        Log(args) => log(args),
+        Status(args) => status(args),
         Stage(args) => add(args),
+        Tag(args) => tag(args),
+        Config(args) => config(args),
+        Merge(args) => merge(args),
+        CheckIgnore(args) => check_ignore(args),
+        Branch(args) => branch(args),
+        Checkout(args) => checkout(args),
+        Switch(args) => switch(args),
+        Reset(args) => reset(args),
+        Reflog(args) => reflog(args),
+        UpdateRef(args) => update_ref(args),
+        CheckRefFormat(args) => check_ref_format(args),
+        ForEachRef(args) => for_each_ref(args),
+        ShowRef(args) => show_ref(args),
     };
     if let Err(e) = result {
         eprintln!("fatal: {}", e);
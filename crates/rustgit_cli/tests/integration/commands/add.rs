@@ -98,3 +98,39 @@ fn outside_of_repo() {
 
     rustgit().args(["stage", "../file.txt"]).assert().failure();
 }
+
+// Files that are not valid UTF-8, including one larger than the chunks files are streamed in
+#[test]
+fn binary_files() {
+    let working_dir = test_path!();
+
+    git(&working_dir).init();
+
+    let small: Vec<u8> = vec![0x89, b'P', b'N', b'G', 0, 0xff, 0xfe, b'\n'];
+    let large: Vec<u8> = (0..300_000u32).map(|i| (i * 7 % 251) as u8).collect();
+    fs::write(working_dir.join("image.png"), &small).unwrap();
+    fs::write(working_dir.join("large.bin"), &large).unwrap();
+    fs::write(working_dir.join("latin1.txt"), b"caf\xe9\n").unwrap();
+
+    rustgit(&working_dir).stage(["."]);
+
+    let stage = git(&working_dir).ls_files_stage();
+    for (name, content) in [
+        ("image.png", &small[..]),
+        ("large.bin", &large[..]),
+        ("latin1.txt", b"caf\xe9\n"),
+    ] {
+        let hash = git(&working_dir)
+            .args(["hash-object", name])
+            .assert()
+            .success();
+        let hash = String::from_utf8(hash.get_output().stdout.clone()).unwrap();
+        assert!(stage.contains(&format!("{} 0\t{}", hash.trim(), name)));
+
+        git(&working_dir)
+            .args(["cat-file", "blob", hash.trim()])
+            .assert()
+            .success()
+            .stdout(predicate::eq(content));
+    }
+}
@@ -0,0 +1,224 @@
+use crate::common::{git, rustgit};
+use assert_cmd::prelude::*;
+use std::{fs, path::Path};
+use test_utils::test_path;
+
+// main and feature at the first commit, topic one commit ahead, and a remote-tracking branch
+fn make_branches(working_dir: &Path) {
+    rustgit(working_dir).init();
+    fs::write(working_dir.join("a"), "a").unwrap();
+    rustgit(working_dir).stage(["a"]);
+    rustgit(working_dir).commit("first commit");
+    git(working_dir)
+        .args(["checkout", "-q", "-b", "topic"])
+        .assert()
+        .success();
+    fs::write(working_dir.join("b"), "b").unwrap();
+    rustgit(working_dir).stage(["b"]);
+    rustgit(working_dir).commit("second commit");
+    git(working_dir)
+        .args(["checkout", "-q", "main"])
+        .assert()
+        .success();
+
+    git(working_dir)
+        .args(["update-ref", "refs/remotes/origin/main", "main"])
+        .assert()
+        .success();
+    git(working_dir)
+        .args([
+            "symbolic-ref",
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/main",
+        ])
+        .assert()
+        .success();
+}
+
+fn branch_output(mut command: crate::common::GitCommand, args: &[&str]) -> String {
+    let assert = command.arg("branch").args(args).assert().success();
+    String::from_utf8(assert.get_output().stdout.clone()).unwrap()
+}
+
+#[test]
+fn list() {
+    let working_dir = test_path!();
+    make_branches(&working_dir);
+    rustgit(&working_dir)
+        .args(["branch", "feature"])
+        .assert()
+        .success();
+
+    for args in [
+        &[][..],
+        &["-v"],
+        &["-a"],
+        &["-a", "-v"],
+        &["-r"],
+        &["--show-current"],
+    ] {
+        assert_eq!(
+            branch_output(rustgit(&working_dir), args),
+            branch_output(git(&working_dir), args),
+            "{:?}",
+            args
+        );
+    }
+
+    let topic = git(&working_dir).rev_parse(["topic"]);
+    git(&working_dir)
+        .args(["checkout", "-q", "--detach"])
+        .arg(&*topic)
+        .assert()
+        .success();
+    for args in [&["-v"][..], &["--show-current"]] {
+        assert_eq!(
+            branch_output(rustgit(&working_dir), args),
+            branch_output(git(&working_dir), args),
+            "{:?}",
+            args
+        );
+    }
+}
+
+#[test]
+fn create() {
+    let working_dir = test_path!();
+    make_branches(&working_dir);
+
+    rustgit(&working_dir)
+        .args(["branch", "feature"])
+        .assert()
+        .success();
+    rustgit(&working_dir)
+        .args(["branch", "nested/feature", "topic~1"])
+        .assert()
+        .success();
+    assert_eq!(
+        git(&working_dir).rev_parse(["feature"]),
+        git(&working_dir).rev_parse(["main"])
+    );
+    assert_eq!(
+        git(&working_dir).rev_parse(["nested/feature"]),
+        git(&working_dir).rev_parse(["main"])
+    );
+
+    rustgit(&working_dir)
+        .args(["branch", "feature", "topic"])
+        .assert()
+        .failure()
+        .stderr("fatal: a branch named 'feature' already exists\n");
+    rustgit(&working_dir)
+        .args(["branch", "-f", "feature", "topic"])
+        .assert()
+        .success();
+    assert_eq!(
+        git(&working_dir).rev_parse(["feature"]),
+        git(&working_dir).rev_parse(["topic"])
+    );
+
+    rustgit(&working_dir)
+        .args(["branch", "-f", "main", "topic"])
+        .assert()
+        .failure();
+    rustgit(&working_dir)
+        .args(["branch", "other", "nope"])
+        .assert()
+        .failure()
+        .stderr("fatal: not a valid object name: 'nope'\n");
+}
+
+#[test]
+fn rename() {
+    let working_dir = test_path!();
+    make_branches(&working_dir);
+    git(&working_dir)
+        .args(["config", "branch.topic.remote", "origin"])
+        .assert()
+        .success();
+
+    rustgit(&working_dir)
+        .args(["branch", "-m", "topic", "topic/renamed"])
+        .assert()
+        .success();
+    assert_eq!(
+        branch_output(git(&working_dir), &[]),
+        "* main\n  topic/renamed\n"
+    );
+    assert!(working_dir
+        .join(".git/logs/refs/heads/topic/renamed")
+        .exists());
+    git(&working_dir)
+        .args(["config", "branch.topic/renamed.remote"])
+        .assert()
+        .success()
+        .stdout("origin\n");
+
+    rustgit(&working_dir)
+        .args(["branch", "-m", "topic/renamed", "main"])
+        .assert()
+        .failure()
+        .stderr("fatal: a branch named 'main' already exists\n");
+
+    // the current branch, after packing the references
+    git(&working_dir)
+        .args(["pack-refs", "--all"])
+        .assert()
+        .success();
+    rustgit(&working_dir)
+        .args(["branch", "-m", "trunk"])
+        .assert()
+        .success();
+    assert_eq!(
+        branch_output(git(&working_dir), &["--show-current"]),
+        "trunk\n"
+    );
+    assert_eq!(
+        branch_output(git(&working_dir), &[]),
+        "  topic/renamed\n* trunk\n"
+    );
+}
+
+#[test]
+fn delete() {
+    let working_dir = test_path!();
+    make_branches(&working_dir);
+    rustgit(&working_dir)
+        .args(["branch", "feature"])
+        .assert()
+        .success();
+
+    rustgit(&working_dir)
+        .args(["branch", "-d", "topic"])
+        .assert()
+        .code(1)
+        .stderr(
+            "error: The branch 'topic' is not fully merged.\n\
+             If you are sure you want to delete it, run 'rustgit branch -D topic'.\n",
+        );
+    rustgit(&working_dir)
+        .args(["branch", "-d", "main"])
+        .assert()
+        .code(1)
+        .stderr(format!(
+            "error: Cannot delete branch 'main' checked out at '{}'\n",
+            working_dir.display()
+        ));
+
+    let feature = git(&working_dir).rev_parse(["feature"]);
+    rustgit(&working_dir)
+        .args(["branch", "-d", "feature"])
+        .assert()
+        .success()
+        .stdout(format!("Deleted branch feature (was {}).\n", &feature[..7]));
+
+    git(&working_dir)
+        .args(["pack-refs", "--all"])
+        .assert()
+        .success();
+    rustgit(&working_dir)
+        .args(["branch", "-D", "topic"])
+        .assert()
+        .success();
+    assert_eq!(branch_output(git(&working_dir), &[]), "* main\n");
+}
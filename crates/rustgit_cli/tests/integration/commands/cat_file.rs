@@ -2,6 +2,7 @@ use crate::common::{git, rustgit};
 use assert_cmd::prelude::*;
 use predicates::prelude::*;
 use rustgit::oid::Sha1HashHexString;
+use std::{fs, path::Path};
 use test_utils::test_path;
 
 // cat-file -p <blob>
@@ -34,3 +35,166 @@ fn pretty_print_blob() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+// Commit two similar versions of a file so that git stores one of them as a delta when packing.
+// Returns the content and the hash of both versions
+fn commit_similar_blobs(working_dir: &Path) -> Vec<(String, Sha1HashHexString)> {
+    let git = || git(working_dir);
+    git().init();
+
+    let old_content: String = (0..100).map(|i| format!("line {i}\n")).collect();
+    let new_content = format!("{old_content}one more line\n");
+
+    for content in [&old_content, &new_content] {
+        fs::write(working_dir.join("file.txt"), content).unwrap();
+        git().stage(["file.txt"]);
+        git().commit("message");
+    }
+
+    vec![
+        (old_content, git().rev_parse(["HEAD~1:file.txt"])),
+        (new_content, git().rev_parse(["HEAD:file.txt"])),
+    ]
+}
+
+// cat-file -p <blob> when objects are in a pack with OFS_DELTA entries
+#[test]
+fn pretty_print_packed_blob() {
+    let working_dir = test_path!();
+    let blobs = commit_similar_blobs(&working_dir);
+
+    git(&working_dir).args(["gc", "--quiet"]).assert().success();
+
+    for (content, hash) in blobs {
+        rustgit(&working_dir)
+            .args(["cat-file", "-p", &hash])
+            .assert()
+            .success()
+            .stdout(predicate::eq(content));
+    }
+}
+
+// cat-file -p <blob> when objects are in a pack with REF_DELTA entries
+#[test]
+fn pretty_print_ref_delta_blob() {
+    let working_dir = test_path!();
+    let blobs = commit_similar_blobs(&working_dir);
+
+    git(&working_dir)
+        .args(["-c", "repack.useDeltaBaseOffset=false"])
+        .args(["repack", "-a", "-d", "-f", "--quiet"])
+        .assert()
+        .success();
+    git(&working_dir).args(["prune-packed"]).assert().success();
+
+    for (content, hash) in blobs {
+        rustgit(&working_dir)
+            .args(["cat-file", "-p", &hash])
+            .assert()
+            .success()
+            .stdout(predicate::eq(content));
+    }
+}
+
+// Write blobs until two of them share the same first 4 hex digits. Returns their hashes
+fn write_colliding_blobs(working_dir: &Path) -> (String, String) {
+    use rustgit::object::{ObjectBuffer, ObjectType};
+    use rustgit::oid::ObjectId;
+    use std::collections::HashMap;
+
+    let mut seen: HashMap<String, String> = HashMap::new();
+    for i in 0.. {
+        let content = format!("blob {i}\n");
+        let oid =
+            ObjectId::from_object_buffer(&ObjectBuffer::new(ObjectType::Blob, content.as_bytes()))
+                .to_string();
+        if let Some(previous) = seen.insert(oid[..4].to_string(), content.clone()) {
+            let mut hashes = vec![];
+            for content in [previous, content] {
+                let file = working_dir.join("blob");
+                fs::write(&file, content).unwrap();
+                let assert = git(working_dir)
+                    .args(["hash-object", "-w", "blob"])
+                    .assert()
+                    .success();
+                hashes.push(
+                    String::from_utf8_lossy(&assert.get_output().stdout)
+                        .trim()
+                        .to_string(),
+                );
+            }
+            return (hashes[0].clone(), hashes[1].clone());
+        }
+    }
+    unreachable!()
+}
+
+// cat-file -p <abbreviated sha>
+#[test]
+fn abbreviated_object_id() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    git(&working_dir).init();
+    let (first, second) = write_colliding_blobs(&working_dir);
+
+    // a longer prefix is unique
+    let unique_prefix = git(&working_dir)
+        .args(["rev-parse", "--short", &first])
+        .assert()
+        .success();
+    let unique_prefix = String::from_utf8_lossy(&unique_prefix.get_output().stdout)
+        .trim()
+        .to_string();
+    rustgit(&working_dir)
+        .args(["cat-file", "-p", &unique_prefix])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("blob "));
+
+    // the shortest unique abbreviation is the same as git
+    rustgit(&working_dir)
+        .args(["rev-parse", "--short", &first])
+        .assert()
+        .success()
+        .stdout(format!("{unique_prefix}\n"));
+    rustgit(&working_dir)
+        .args(["rev-parse", "--short=4", &second])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match("^[0-9a-f]{5,}\n$").unwrap());
+
+    // the 4 digits prefix is ambiguous, both candidates are listed
+    rustgit(&working_dir)
+        .args(["cat-file", "-p", &first[..4]])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("ambiguous"))
+        .stderr(predicate::str::contains(first.as_str()))
+        .stderr(predicate::str::contains(second.as_str()));
+
+    Ok(())
+}
+
+// cat-file -p <abbreviated sha> when the object is in a pack
+#[test]
+fn abbreviated_packed_object_id() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    git(&working_dir).init();
+    fs::write(working_dir.join("file.txt"), "packed")?;
+    git(&working_dir).stage(["file.txt"]);
+    git(&working_dir).commit("message");
+    git(&working_dir).args(["gc", "--quiet"]).assert().success();
+
+    let hash_object_cmd = git(&working_dir)
+        .args(["hash-object", "file.txt"])
+        .assert()
+        .success();
+    let hash = Sha1HashHexString::from_u8_slice(&hash_object_cmd.get_output().stdout)?;
+
+    rustgit(&working_dir)
+        .args(["cat-file", "-p", &hash[..8]])
+        .assert()
+        .success()
+        .stdout(predicate::eq("packed"));
+
+    Ok(())
+}
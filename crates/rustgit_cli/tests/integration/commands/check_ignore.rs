@@ -0,0 +1,141 @@
+use crate::common::{git, rustgit};
+use assert_cmd::prelude::*;
+use std::{fs, path::Path};
+use test_utils::test_path;
+
+// A working tree with nested .gitignore files, an exclude file and a global excludes file
+fn make_ignored_files(working_dir: &Path) {
+    rustgit(working_dir).init();
+    let files = [
+        (
+            ".gitignore",
+            "# build output\nbuild/\n*.log\n!keep.log\n/root.txt\ndoc/**/*.pdf\n",
+        ),
+        ("sub/.gitignore", "x.txt\n!important.log\n"),
+        (".git/info/exclude", "secret\n"),
+        ("global-ignore", "glob*\n"),
+        ("a.log", ""),
+        ("keep.log", ""),
+        ("root.txt", ""),
+        ("tracked.log", ""),
+        ("build/f", ""),
+        ("sub/x.txt", ""),
+        ("sub/root.txt", ""),
+        ("sub/important.log", ""),
+        ("sub/build/g", ""),
+        ("doc/a/b/manual.pdf", ""),
+        ("doc/manual.pdf", ""),
+        ("only-ignored/a.log", ""),
+        ("secret", ""),
+        ("globby", ""),
+        ("file", ""),
+    ];
+    for (path, content) in files {
+        let path = working_dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    let global_ignore = working_dir.join("global-ignore");
+    git(working_dir)
+        .args(["config", "core.excludesFile"])
+        .arg(&global_ignore)
+        .assert()
+        .success();
+    git(working_dir)
+        .args(["add", "-f", "tracked.log"])
+        .assert()
+        .success();
+}
+
+fn check_ignore(mut command: crate::common::GitCommand, args: &[&str]) -> (String, Option<i32>) {
+    let output = command.arg("check-ignore").args(args).assert();
+    let output = output.get_output();
+    (
+        String::from_utf8(output.stdout.clone()).unwrap(),
+        output.status.code(),
+    )
+}
+
+#[test]
+fn patterns() {
+    let working_dir = test_path!();
+    make_ignored_files(&working_dir);
+
+    let paths = [
+        "a.log",
+        "keep.log",
+        "root.txt",
+        "tracked.log",
+        "build",
+        "build/f",
+        "sub/x.txt",
+        "sub/root.txt",
+        "sub/important.log",
+        "sub/build/g",
+        "doc/a/b/manual.pdf",
+        "doc/manual.pdf",
+        "secret",
+        "globby",
+        "file",
+    ];
+    for args in [&["-v"][..], &["-v", "-n"], &[], &["-v", "--no-index"]] {
+        let args: Vec<&str> = args.iter().chain(&paths).copied().collect();
+        assert_eq!(
+            check_ignore(rustgit(&working_dir), &args),
+            check_ignore(git(&working_dir), &args),
+            "{:?}",
+            args
+        );
+    }
+
+    // paths relative to a subdirectory
+    let sub = working_dir.join("sub");
+    let args = ["-v", "x.txt", "../a.log", "../keep.log"];
+    assert_eq!(
+        check_ignore(rustgit(&sub), &args),
+        check_ignore(git(&sub), &args)
+    );
+    for args in [["-q", "file"], ["-q", "a.log"]] {
+        assert_eq!(
+            check_ignore(rustgit(&working_dir), &args),
+            check_ignore(git(&working_dir), &args)
+        );
+    }
+}
+
+#[test]
+fn status_and_add() {
+    let working_dir = test_path!();
+    make_ignored_files(&working_dir);
+
+    assert_eq!(
+        rustgit(&working_dir).status_porcelain(),
+        git(&working_dir).status_porcelain()
+    );
+
+    rustgit(&working_dir).stage(["."]);
+    assert_eq!(
+        rustgit(&working_dir).ls_files(),
+        ".gitignore\nfile\nkeep.log\nsub/.gitignore\nsub/important.log\nsub/root.txt\ntracked.log\n"
+    );
+
+    rustgit(&working_dir)
+        .args(["add", "a.log", "file"])
+        .assert()
+        .failure()
+        .stderr(
+            "fatal: The following paths are ignored by one of your .gitignore files:\n\
+             a.log\n\
+             hint: Use -f if you really want to add them.\n",
+        );
+    rustgit(&working_dir)
+        .args(["add", "-f", "a.log", "build"])
+        .assert()
+        .success();
+    let files = rustgit(&working_dir).ls_files();
+    assert!(
+        files.contains("a.log\n") && files.contains("build/f\n"),
+        "{}",
+        files
+    );
+}
@@ -0,0 +1,140 @@
+use crate::common::{git, rustgit};
+use assert_cmd::prelude::*;
+use std::fs;
+use test_utils::test_path;
+
+#[test]
+fn names() {
+    let working_dir = test_path!();
+    fs::create_dir_all(&working_dir).unwrap();
+
+    let names = [
+        "refs/heads/main",
+        "heads/feature/a-b",
+        "main",
+        "@",
+        "refs/heads/@",
+        "refs/heads/a..b",
+        "refs/heads/.hidden",
+        "refs/heads/main.lock",
+        "refs/heads/main.",
+        "refs/heads/a@{1}",
+        "refs/heads/a b",
+        "refs/heads/a~1",
+        "refs/heads/a:b",
+        "refs/heads/*",
+        "refs/*/*",
+        "refs/heads/a\\b",
+        "//refs//heads/main",
+        "refs/heads/main/",
+        "../../etc/passwd",
+    ];
+    let options: [&[&str]; 4] = [
+        &[],
+        &["--allow-onelevel"],
+        &["--refspec-pattern"],
+        &["--normalize"],
+    ];
+    for name in names {
+        for options in options {
+            let expected = git(&working_dir)
+                .arg("check-ref-format")
+                .args(options)
+                .arg(name)
+                .assert();
+            let expected = expected.get_output();
+            rustgit(&working_dir)
+                .arg("check-ref-format")
+                .args(options)
+                .arg(name)
+                .assert()
+                .code(expected.status.code().unwrap())
+                .stdout(String::from_utf8(expected.stdout.clone()).unwrap());
+        }
+    }
+}
+
+#[test]
+fn branch() {
+    let working_dir = test_path!();
+    rustgit(&working_dir).init();
+    fs::write(working_dir.join("a"), "a").unwrap();
+    rustgit(&working_dir).stage(["a"]);
+    rustgit(&working_dir).commit("first commit");
+    rustgit(&working_dir)
+        .args(["switch", "-c", "topic"])
+        .assert()
+        .success();
+    rustgit(&working_dir)
+        .args(["switch", "main"])
+        .assert()
+        .success();
+
+    for (name, expected) in [
+        ("feature/a", "feature/a"),
+        ("@{-1}", "topic"),
+        ("@{-2}", "main"),
+    ] {
+        rustgit(&working_dir)
+            .args(["check-ref-format", "--branch", name])
+            .assert()
+            .success()
+            .stdout(format!("{}\n", expected));
+    }
+    for name in ["a..b", "-x", "HEAD", "@{-3}"] {
+        rustgit(&working_dir)
+            .args(["check-ref-format", "--branch", name])
+            .assert()
+            .failure()
+            .stderr(format!("fatal: '{}' is not a valid branch name\n", name));
+    }
+}
+
+#[test]
+fn invalid_names() {
+    let working_dir = test_path!();
+    rustgit(&working_dir).init();
+    fs::write(working_dir.join("a"), "a").unwrap();
+    rustgit(&working_dir).stage(["a"]);
+    rustgit(&working_dir).commit("first commit");
+
+    rustgit(&working_dir)
+        .args(["branch", "a..b"])
+        .assert()
+        .failure()
+        .stderr("fatal: 'a..b' is not a valid branch name\n");
+    rustgit(&working_dir)
+        .args(["switch", "-c", "a b"])
+        .assert()
+        .failure()
+        .stderr("fatal: 'a b' is not a valid branch name\n");
+    rustgit(&working_dir)
+        .args(["branch", "-m", "main", "main.lock"])
+        .assert()
+        .failure()
+        .stderr("fatal: 'main.lock' is not a valid branch name\n");
+    rustgit(&working_dir)
+        .args(["tag", "v1^"])
+        .assert()
+        .failure()
+        .stderr("fatal: 'v1^' is not a valid tag name.\n");
+    rustgit(&working_dir)
+        .args(["update-ref", "../outside", "HEAD"])
+        .assert()
+        .failure()
+        .stderr(
+            "fatal: update_ref failed for ref '../outside': refusing to update ref with bad name \
+             '../outside'\n",
+        );
+    assert!(!working_dir.join("outside").exists());
+
+    // a name with `..` can't reach files outside of the references
+    rustgit(&working_dir)
+        .args(["rev-parse", "refs/../HEAD"])
+        .assert()
+        .failure();
+    rustgit(&working_dir)
+        .args(["reflog", "show", "refs/../HEAD"])
+        .assert()
+        .failure();
+}
@@ -0,0 +1,403 @@
+use crate::common::{git, rustgit};
+use assert_cmd::prelude::*;
+use std::{fs, path::Path};
+use test_utils::test_path;
+
+// main has `a`, `keep` and `d/s`. topic changes `a`, removes `d/s` and adds `b` and an
+// executable `run`
+fn make_branches(working_dir: &Path) {
+    rustgit(working_dir).init();
+    fs::create_dir_all(working_dir.join("d")).unwrap();
+    for (path, content) in [("a", "a\n"), ("keep", "keep\n"), ("d/s", "s\n")] {
+        fs::write(working_dir.join(path), content).unwrap();
+    }
+    rustgit(working_dir).stage(["."]);
+    rustgit(working_dir).commit("first commit");
+
+    git(working_dir)
+        .args(["checkout", "-q", "-b", "topic"])
+        .assert()
+        .success();
+    fs::write(working_dir.join("a"), "a2\n").unwrap();
+    fs::write(working_dir.join("b"), "b\n").unwrap();
+    fs::write(working_dir.join("run"), "#!/bin/sh\n").unwrap();
+    git(working_dir)
+        .args(["rm", "-q", "d/s"])
+        .assert()
+        .success();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(working_dir.join("run"), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    rustgit(working_dir).stage(["."]);
+    rustgit(working_dir).commit("second commit");
+    git(working_dir)
+        .args(["checkout", "-q", "main"])
+        .assert()
+        .success();
+}
+
+fn head(working_dir: &Path) -> String {
+    fs::read_to_string(working_dir.join(".git/HEAD")).unwrap()
+}
+
+#[test]
+fn switch_branches() {
+    let working_dir = test_path!();
+    make_branches(&working_dir);
+
+    rustgit(&working_dir)
+        .args(["switch", "topic"])
+        .assert()
+        .success()
+        .stderr("Switched to branch 'topic'\n");
+    assert_eq!(head(&working_dir), "ref: refs/heads/topic\n");
+    assert_eq!(fs::read_to_string(working_dir.join("a")).unwrap(), "a2\n");
+    assert!(!working_dir.join("d").exists());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(working_dir.join("run"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o111, 0o111);
+    }
+    assert_eq!(
+        git(&working_dir).write_tree(),
+        git(&working_dir).rev_parse(["topic^{tree}"])
+    );
+    // the index has fresh stat data
+    assert_eq!(git(&working_dir).status_porcelain(), "");
+
+    rustgit(&working_dir)
+        .args(["checkout", "main"])
+        .assert()
+        .success()
+        .stderr("Switched to branch 'main'\n");
+    assert_eq!(head(&working_dir), "ref: refs/heads/main\n");
+    assert_eq!(fs::read_to_string(working_dir.join("d/s")).unwrap(), "s\n");
+    assert!(!working_dir.join("b").exists());
+    assert_eq!(git(&working_dir).status_porcelain(), "");
+
+    rustgit(&working_dir)
+        .args(["switch", "main"])
+        .assert()
+        .success()
+        .stderr("Already on 'main'\n");
+}
+
+#[test]
+fn local_changes() {
+    let working_dir = test_path!();
+    make_branches(&working_dir);
+
+    // changes to the files that are the same on both branches are carried over
+    fs::write(working_dir.join("keep"), "changed\n").unwrap();
+    fs::write(working_dir.join("new"), "new\n").unwrap();
+    rustgit(&working_dir).stage(["new"]);
+    rustgit(&working_dir)
+        .args(["switch", "topic"])
+        .assert()
+        .success()
+        .stdout("M\tkeep\nA\tnew\n");
+    assert_eq!(
+        fs::read_to_string(working_dir.join("keep")).unwrap(),
+        "changed\n"
+    );
+    assert_eq!(git(&working_dir).status_porcelain(), " M keep\nA  new\n");
+
+    // but not to the files that differ
+    fs::write(working_dir.join("a"), "local\n").unwrap();
+    rustgit(&working_dir)
+        .args(["switch", "main"])
+        .assert()
+        .code(1)
+        .stderr(
+            "error: Your local changes to the following files would be overwritten by checkout:\n\
+             \ta\n\
+             Please commit your changes or stash them before you switch branches.\n\
+             Aborting\n",
+        );
+    rustgit(&working_dir).stage(["a"]);
+    rustgit(&working_dir)
+        .args(["switch", "main"])
+        .assert()
+        .code(1);
+    assert_eq!(head(&working_dir), "ref: refs/heads/topic\n");
+    assert_eq!(
+        fs::read_to_string(working_dir.join("a")).unwrap(),
+        "local\n"
+    );
+}
+
+#[test]
+fn untracked_files() {
+    let working_dir = test_path!();
+    make_branches(&working_dir);
+
+    fs::write(working_dir.join("b"), "untracked\n").unwrap();
+    rustgit(&working_dir)
+        .args(["checkout", "topic"])
+        .assert()
+        .code(1)
+        .stderr(
+            "error: The following untracked working tree files would be overwritten by checkout:\n\
+             \tb\n\
+             Please move or remove them before you switch branches.\n\
+             Aborting\n",
+        );
+    assert_eq!(head(&working_dir), "ref: refs/heads/main\n");
+
+    // ignored files can be overwritten
+    fs::create_dir_all(working_dir.join(".git/info")).unwrap();
+    fs::write(working_dir.join(".git/info/exclude"), "b\n").unwrap();
+    rustgit(&working_dir)
+        .args(["checkout", "topic"])
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(working_dir.join("b")).unwrap(), "b\n");
+}
+
+#[test]
+fn detached_head() {
+    let working_dir = test_path!();
+    make_branches(&working_dir);
+    let topic = git(&working_dir).rev_parse(["topic"]);
+    let main = git(&working_dir).rev_parse(["main"]);
+
+    git(&working_dir)
+        .args(["config", "advice.detachedHead", "false"])
+        .assert()
+        .success();
+    rustgit(&working_dir)
+        .args(["checkout", "topic~1"])
+        .assert()
+        .success()
+        .stderr(format!("HEAD is now at {} first commit\n", &main[..7]));
+    assert_eq!(head(&working_dir), format!("{}\n", &*main));
+    assert_eq!(git(&working_dir).status_porcelain(), "");
+
+    rustgit(&working_dir)
+        .args(["switch", "--detach", "topic"])
+        .assert()
+        .success()
+        .stderr(format!(
+            "Previous HEAD position was {} first commit\nHEAD is now at {} second commit\n",
+            &main[..7],
+            &topic[..7]
+        ));
+    rustgit(&working_dir)
+        .args(["switch", "topic~1"])
+        .assert()
+        .failure()
+        .stderr(
+            "fatal: a branch is expected, got commit 'topic~1'\n\
+             hint: If you want to detach HEAD at the commit, try again with the --detach option.\n",
+        );
+    rustgit(&working_dir)
+        .args(["checkout", "nope"])
+        .assert()
+        .code(1)
+        .stderr("error: pathspec 'nope' did not match any file(s) known to git\n");
+}
+
+#[test]
+fn new_branch() {
+    let working_dir = test_path!();
+    make_branches(&working_dir);
+
+    rustgit(&working_dir)
+        .args(["switch", "-c", "feature", "topic"])
+        .assert()
+        .success()
+        .stderr("Switched to a new branch 'feature'\n");
+    assert_eq!(head(&working_dir), "ref: refs/heads/feature\n");
+    assert_eq!(
+        git(&working_dir).rev_parse(["feature"]),
+        git(&working_dir).rev_parse(["topic"])
+    );
+    assert_eq!(fs::read_to_string(working_dir.join("b")).unwrap(), "b\n");
+
+    rustgit(&working_dir)
+        .args(["checkout", "-b", "feature"])
+        .assert()
+        .failure()
+        .stderr("fatal: a branch named 'feature' already exists\n");
+    rustgit(&working_dir)
+        .args(["checkout", "-b", "other"])
+        .assert()
+        .success();
+    assert_eq!(
+        git(&working_dir).rev_parse(["other"]),
+        git(&working_dir).rev_parse(["topic"])
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_and_gitlinks() {
+    use std::os::unix::fs::symlink;
+
+    let working_dir = test_path!();
+    rustgit(&working_dir).init();
+    fs::write(working_dir.join("a"), "a\n").unwrap();
+    rustgit(&working_dir).stage(["."]);
+    rustgit(&working_dir).commit("first commit");
+
+    git(&working_dir)
+        .args(["checkout", "-q", "-b", "topic"])
+        .assert()
+        .success();
+    symlink("a", working_dir.join("link")).unwrap();
+    symlink("missing", working_dir.join("broken")).unwrap();
+    git(&working_dir).stage(["link", "broken"]);
+    let submodule_commit = git(&working_dir).rev_parse(["HEAD"]);
+    git(&working_dir)
+        .args(["update-index", "--add", "--cacheinfo"])
+        .arg(format!("160000,{},sub", submodule_commit))
+        .assert()
+        .success();
+    git(&working_dir).commit("links");
+    git(&working_dir)
+        .args(["checkout", "-q", "main"])
+        .assert()
+        .success();
+
+    rustgit(&working_dir)
+        .args(["switch", "topic"])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_link(working_dir.join("link")).unwrap(),
+        Path::new("a")
+    );
+    assert_eq!(
+        fs::read_link(working_dir.join("broken")).unwrap(),
+        Path::new("missing")
+    );
+    // like git, a submodule that is not checked out is an empty directory
+    assert_eq!(fs::read_dir(working_dir.join("sub")).unwrap().count(), 0);
+    assert_eq!(rustgit(&working_dir).status_porcelain(), "");
+    assert_eq!(git(&working_dir).status_porcelain(), "");
+
+    rustgit(&working_dir)
+        .args(["switch", "main"])
+        .assert()
+        .success();
+    assert!(working_dir.join("link").symlink_metadata().is_err());
+    assert!(!working_dir.join("sub").exists());
+
+    // without symbolic links, a link is checked out as a file that contains its target
+    git(&working_dir)
+        .args(["config", "core.symlinks", "false"])
+        .assert()
+        .success();
+    rustgit(&working_dir)
+        .args(["switch", "topic"])
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(working_dir.join("link")).unwrap(), "a");
+    assert_eq!(rustgit(&working_dir).status_porcelain(), "");
+    assert_eq!(git(&working_dir).status_porcelain(), "");
+}
+
+// main has the directory `d` with `d/s`, which topic replaces with a file `d`, and topic adds
+// `g/new`, where main has no `g`
+fn make_directory_and_file(working_dir: &Path) {
+    rustgit(working_dir).init();
+    fs::create_dir_all(working_dir.join("d")).unwrap();
+    fs::write(working_dir.join("d/s"), "s\n").unwrap();
+    rustgit(working_dir).stage(["."]);
+    rustgit(working_dir).commit("first commit");
+
+    git(working_dir)
+        .args(["checkout", "-q", "-b", "topic"])
+        .assert()
+        .success();
+    git(working_dir)
+        .args(["rm", "-q", "d/s"])
+        .assert()
+        .success();
+    fs::write(working_dir.join("d"), "d\n").unwrap();
+    fs::create_dir_all(working_dir.join("g")).unwrap();
+    fs::write(working_dir.join("g/new"), "new\n").unwrap();
+    rustgit(working_dir).stage(["."]);
+    rustgit(working_dir).commit("second commit");
+    git(working_dir)
+        .args(["checkout", "-q", "main"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn directory_becomes_file() {
+    let working_dir = test_path!();
+    make_directory_and_file(&working_dir);
+
+    rustgit(&working_dir)
+        .args(["switch", "topic"])
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(working_dir.join("d")).unwrap(), "d\n");
+    assert_eq!(git(&working_dir).status_porcelain(), "");
+
+    rustgit(&working_dir)
+        .args(["switch", "main"])
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(working_dir.join("d/s")).unwrap(), "s\n");
+    assert!(!working_dir.join("g").exists());
+    assert_eq!(git(&working_dir).status_porcelain(), "");
+
+    // the untracked files of the directory would be lost
+    fs::write(working_dir.join("d/untracked"), "untracked\n").unwrap();
+    rustgit(&working_dir)
+        .args(["switch", "topic"])
+        .assert()
+        .code(1)
+        .stderr(
+            "error: Updating the following directories would lose untracked files in them:\n\
+             \td\n\
+             \n\
+             Aborting\n",
+        );
+    assert_eq!(head(&working_dir), "ref: refs/heads/main\n");
+    assert_eq!(fs::read_to_string(working_dir.join("d/s")).unwrap(), "s\n");
+
+    // the fast-forward of a merge does the same
+    fs::remove_file(working_dir.join("d/untracked")).unwrap();
+    rustgit(&working_dir)
+        .args(["merge", "topic"])
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(working_dir.join("d")).unwrap(), "d\n");
+    assert_eq!(git(&working_dir).status_porcelain(), "");
+}
+
+#[test]
+fn untracked_file_in_the_way_of_a_directory() {
+    let working_dir = test_path!();
+    make_directory_and_file(&working_dir);
+
+    fs::write(working_dir.join("g"), "untracked\n").unwrap();
+    rustgit(&working_dir)
+        .args(["switch", "topic"])
+        .assert()
+        .code(1)
+        .stderr(
+            "error: The following untracked working tree files would be overwritten by checkout:\n\
+             \tg\n\
+             Please move or remove them before you switch branches.\n\
+             Aborting\n",
+        );
+    // nothing was changed
+    assert_eq!(head(&working_dir), "ref: refs/heads/main\n");
+    assert_eq!(fs::read_to_string(working_dir.join("d/s")).unwrap(), "s\n");
+    assert_eq!(
+        fs::read_to_string(working_dir.join("g")).unwrap(),
+        "untracked\n"
+    );
+    assert_eq!(git(&working_dir).status_porcelain(), "?? g\n");
+}
@@ -1,5 +1,6 @@
-use crate::common::{git, head_sha, populate_folder, rustgit, InstaSettingsExt};
-use std::fs;
+use crate::common::{git, head_sha, populate_folder, rustgit, GitCommand, InstaSettingsExt};
+use assert_cmd::prelude::*;
+use std::{fs, str::from_utf8};
 use test_utils::test_path;
 
 #[test]
@@ -70,3 +71,53 @@ fn with_parent() {
         insta::assert_snapshot!(output);
     });
 }
+
+// A merge commit with a separate committer, dated in the formats git accepts
+#[test]
+fn merge_commit_with_committer() {
+    let working_dir = test_path!();
+    let git = || git(&working_dir);
+
+    git().init();
+    populate_folder(&working_dir);
+    git().stage(["."]);
+    let tree_hash = git().write_tree();
+    let first = git().commit_tree(tree_hash, None, "first");
+    let second = git().commit_tree(tree_hash, None, "second");
+
+    for (author_date, committer_date) in [
+        ("1112911993 -0700", "@1112912053 +0200"),
+        ("Thu, 07 Apr 2005 22:13:13 +0200", "2005-04-07T22:13:13Z"),
+        ("2005-04-07 22:13:13 -05:00", "2005-04-07T22:13:13.5+0530"),
+    ] {
+        let commit_tree = |command: GitCommand| {
+            let mut command = command
+                .env("GIT_AUTHOR_NAME", "Jane Doe")
+                .env("GIT_AUTHOR_EMAIL", "jane@doe.com")
+                .env("GIT_AUTHOR_DATE", author_date)
+                .env("GIT_COMMITTER_NAME", "John Doe")
+                .env("GIT_COMMITTER_EMAIL", "john@doe.com")
+                .env("GIT_COMMITTER_DATE", committer_date);
+            let output = command
+                .args(["commit-tree", &tree_hash, "-m", "merge"])
+                .args(["-p", &first, "-p", &second])
+                .assert()
+                .success();
+            from_utf8(&output.get_output().stdout).unwrap().to_string()
+        };
+        assert_eq!(
+            commit_tree(rustgit(&working_dir)),
+            commit_tree(git()),
+            "{} / {}",
+            author_date,
+            committer_date
+        );
+    }
+
+    rustgit(&working_dir)
+        .env("GIT_AUTHOR_DATE", "yesterday-ish")
+        .args(["commit-tree", &tree_hash, "-m", "invalid"])
+        .assert()
+        .failure()
+        .stderr("fatal: invalid date format: yesterday-ish\n");
+}
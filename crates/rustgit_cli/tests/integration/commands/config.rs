@@ -0,0 +1,210 @@
+use crate::common::{git, rustgit, GitCommand};
+use assert_cmd::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::str::from_utf8;
+use test_utils::test_path;
+
+// Run config with a home directory of its own and without the system config
+fn config_output(command: GitCommand, home: &Path, args: &[&str]) -> (String, Option<i32>) {
+    let mut command = command
+        .env("HOME", home)
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("GIT_CONFIG_GLOBAL");
+    let output = command
+        .arg("config")
+        .args(args)
+        .assert()
+        .get_output()
+        .clone();
+    (
+        from_utf8(&output.stdout).unwrap().to_string(),
+        output.status.code(),
+    )
+}
+
+fn assert_same_as_git(working_dir: &Path, home: &Path, args: &[&str]) {
+    assert_eq!(
+        config_output(rustgit(working_dir), home, args),
+        config_output(git(working_dir), home, args),
+        "rustgit config {:?}",
+        args
+    );
+}
+
+const CONFIG: &str = r#"[core]
+	bare = false
+	flag
+[a] m = 1 ; first
+	m = "2 # not a comment"
+[Sec "Sub.x"]
+	Key = 10k
+	path = ~/dir
+"#;
+
+#[test]
+fn get() {
+    let test_path = test_path!();
+    fs::write(test_path.join("config"), CONFIG).unwrap();
+
+    for args in [
+        &["--list"][..],
+        &["--get", "core.flag"],
+        &["--bool", "--get", "core.flag"],
+        &["core.bare"],
+        &["--get", "a.m"],
+        &["--get-all", "A.M"],
+        &["--get", "sec.Sub.x.key"],
+        &["--get", "sec.sub.x.key"],
+        &["--int", "--get", "sec.Sub.x.key"],
+        &["--type=bool", "--get", "sec.Sub.x.key"],
+        &["--path", "--get", "sec.Sub.x.path"],
+        &["--get", "core.missing"],
+        &["--get", "nosection"],
+    ] {
+        let args = [&["-f", "config"], args].concat();
+        assert_same_as_git(&test_path, &test_path, &args);
+    }
+}
+
+#[test]
+fn set_and_unset() {
+    let test_path = test_path!();
+    let rustgit_dir = test_path.join("rustgit");
+    let git_dir = test_path.join("git");
+
+    for dir in [&rustgit_dir, &git_dir] {
+        fs::create_dir(dir).unwrap();
+        fs::write(dir.join("config"), CONFIG).unwrap();
+    }
+
+    for args in [
+        &["core.bare", "true"][..],
+        &["new.key", "value"],
+        &["--add", "a.m", "3"],
+        &["a.m", "4"],
+        &["--unset", "a.m"],
+        &["--unset", "a.missing"],
+        &["--unset-all", "a.m"],
+        &["--int", "sec.Sub.x.key", "2k"],
+        &["--bool", "core.flag", "yes"],
+        &["foo.bar.Zed", " spaces and ; "],
+        &["--unset", "foo.bar.zed"],
+        &["--unset", "new.key"],
+        &["new.key", "value"],
+    ] {
+        let args = [&["-f", "config"], args].concat();
+        assert_eq!(
+            config_output(rustgit(&rustgit_dir), &test_path, &args),
+            config_output(git(&git_dir), &test_path, &args),
+            "rustgit config {:?}",
+            args
+        );
+        assert_eq!(
+            fs::read_to_string(rustgit_dir.join("config")).unwrap(),
+            fs::read_to_string(git_dir.join("config")).unwrap(),
+            "rustgit config {:?}",
+            args
+        );
+    }
+
+    // `--set` is the same as giving a value
+    rustgit(&rustgit_dir)
+        .args(["config", "-f", "config", "--set", "new.key", "other value"])
+        .assert()
+        .success();
+    git(&git_dir)
+        .args(["config", "-f", "config", "new.key", "other value"])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(rustgit_dir.join("config")).unwrap(),
+        fs::read_to_string(git_dir.join("config")).unwrap(),
+    );
+}
+
+#[test]
+fn layers() {
+    let test_path = test_path!();
+    let home = test_path.join("home");
+    let repo = test_path.join("repo");
+    fs::create_dir_all(home.join(".config/git")).unwrap();
+    fs::create_dir(&repo).unwrap();
+    rustgit(&repo).init();
+
+    fs::write(
+        home.join(".config/git/config"),
+        "[user]\n\tname = XDG\n\temail = xdg@example.com\n",
+    )
+    .unwrap();
+    fs::write(
+        home.join(".gitconfig"),
+        concat!(
+            "[user]\n\tname = Global\n",
+            "[include]\n\tpath = included\n",
+            "[includeIf \"gitdir:**/repo/\"]\n\tpath = ~/repo-only\n",
+            "[includeIf \"onbranch:other\"]\n\tpath = ~/other-branch\n",
+        ),
+    )
+    .unwrap();
+    fs::write(home.join("included"), "[a]\n\tincluded = yes\n").unwrap();
+    fs::write(home.join("repo-only"), "[a]\n\trepo = yes\n").unwrap();
+    fs::write(home.join("other-branch"), "[a]\n\tother = yes\n").unwrap();
+
+    let local = |args: &[&str]| {
+        let mut command = rustgit(&repo)
+            .env("HOME", &home)
+            .env("GIT_CONFIG_NOSYSTEM", "1");
+        command.arg("config").args(args).assert().success();
+    };
+    local(&["user.name", "Local"]);
+    local(&["--global", "a.global", "1"]);
+
+    for args in [
+        &["--list"][..],
+        &["--global", "--list"],
+        &["--global", "--includes", "--list"],
+        &["--local", "--list"],
+        &["user.name"],
+        &["--global", "user.name"],
+        &["--get-all", "user.name"],
+        &["a.repo"],
+        &["a.other"],
+    ] {
+        assert_same_as_git(&repo, &home, args);
+        assert_same_as_git(&test_path, &home, args);
+    }
+
+    // the variables of the environment come last
+    let environment = |command: GitCommand| {
+        command
+            .env("GIT_CONFIG_COUNT", "2")
+            .env("GIT_CONFIG_KEY_0", "user.name")
+            .env("GIT_CONFIG_VALUE_0", "Environment")
+            .env("GIT_CONFIG_KEY_1", "a.b")
+            .env("GIT_CONFIG_VALUE_1", "c")
+    };
+    assert_eq!(
+        config_output(environment(rustgit(&repo)), &home, &["--list"]),
+        config_output(environment(git(&repo)), &home, &["--list"]),
+    );
+
+    // new commits use the identity of the config
+    fs::write(repo.join("file"), "content").unwrap();
+    rustgit(&repo).stage(["file"]);
+    rustgit(&repo)
+        .env("HOME", &home)
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env_remove("GIT_AUTHOR_NAME")
+        .env_remove("GIT_AUTHOR_EMAIL")
+        .commit("commit");
+    let author = git(&repo)
+        .args(["log", "--format=%an <%ae>"])
+        .assert()
+        .success();
+    assert_eq!(
+        from_utf8(&author.get_output().stdout).unwrap(),
+        "Local <xdg@example.com>\n"
+    );
+}
@@ -0,0 +1,214 @@
+use crate::common::{git, rustgit, GitCommand};
+use assert_cmd::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::str::from_utf8;
+use test_utils::test_path;
+
+fn diff_output(mut command: GitCommand, args: &[&str]) -> String {
+    let assert = command.arg("diff").args(args).assert().success();
+    from_utf8(&assert.get_output().stdout).unwrap().to_string()
+}
+
+fn assert_same_as_git(working_dir: &Path, args: &[&str]) {
+    assert_eq!(
+        diff_output(rustgit(working_dir), args),
+        diff_output(git(working_dir), args),
+        "rustgit diff {:?}",
+        args
+    );
+}
+
+// Create a repository with a commit, then change files in the index and the working tree
+fn make_changes(working_dir: &Path) {
+    let git = || git(working_dir);
+
+    rustgit(working_dir).init();
+    fs::write(
+        working_dir.join("main.rs"),
+        "fn main() {\n    1\n    2\n    3\n    4\n    5\n    6\n    7\n    8\n    9\n}\n",
+    )
+    .unwrap();
+    fs::write(working_dir.join("no_newline"), "a\nb").unwrap();
+    fs::write(working_dir.join("binary"), b"bin\0ary").unwrap();
+    fs::create_dir(working_dir.join("dir")).unwrap();
+    fs::write(working_dir.join("dir/deleted"), "deleted\n").unwrap();
+    git().stage(["."]);
+    git().commit("initial commit");
+
+    // staged changes
+    fs::write(working_dir.join("binary"), b"bin\0ary 2").unwrap();
+    fs::write(working_dir.join("new"), "new\n").unwrap();
+    fs::write(working_dir.join("empty"), "").unwrap();
+    git().stage(["binary", "new", "empty"]);
+    git()
+        .args(["rm", "--cached", "-q", "dir/deleted"])
+        .assert()
+        .success();
+
+    // unstaged changes
+    fs::write(
+        working_dir.join("main.rs"),
+        "fn main() {\n    1\n    two\n    3\n    4\n    5\n    6\n    7\n    8\n    nine\n}\n",
+    )
+    .unwrap();
+    fs::write(working_dir.join("no_newline"), "a\nb\n").unwrap();
+    fs::write(working_dir.join("new"), "new\nnewer\n").unwrap();
+}
+
+#[test]
+fn worktree() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    make_changes(&working_dir);
+
+    assert_same_as_git(&working_dir, &[]);
+    assert_same_as_git(&working_dir, &["-U1"]);
+    assert_same_as_git(&working_dir, &["-U0"]);
+    assert_same_as_git(&working_dir, &["--stat"]);
+    assert_same_as_git(&working_dir, &["--name-only"]);
+
+    Ok(())
+}
+
+#[test]
+fn cached() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    make_changes(&working_dir);
+
+    assert_same_as_git(&working_dir, &["--cached"]);
+    assert_same_as_git(&working_dir, &["--staged", "--stat"]);
+    assert_same_as_git(&working_dir, &["--cached", "--name-only"]);
+
+    Ok(())
+}
+
+#[test]
+fn no_commits_yet() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+
+    rustgit(&working_dir).init();
+    fs::write(working_dir.join("a"), "a\n").unwrap();
+    git(&working_dir).stage(["a"]);
+
+    assert_same_as_git(&working_dir, &["--cached"]);
+    assert_same_as_git(&working_dir, &[]);
+
+    Ok(())
+}
+
+#[test]
+fn commits() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    let git = || git(&working_dir);
+
+    rustgit(&working_dir).init();
+    let numbers: String = (1..=100).map(|i| format!("{i}\n")).collect();
+    fs::write(working_dir.join("numbers"), &numbers).unwrap();
+    git().stage(["numbers"]);
+    git().commit("initial commit");
+    let first = git().rev_parse(["HEAD"]);
+
+    let odd_numbers: String = (1..=100).step_by(2).map(|i| format!("{i}\n")).collect();
+    fs::write(working_dir.join("numbers"), odd_numbers).unwrap();
+    git().stage(["numbers"]);
+    git().commit("remove even numbers");
+
+    assert_same_as_git(&working_dir, &[&first, "HEAD"]);
+    assert_same_as_git(&working_dir, &["--stat", &first, "HEAD"]);
+    assert_same_as_git(&working_dir, &["--cached", &first]);
+
+    Ok(())
+}
+
+#[test]
+fn stat_with_long_names() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    let git = || git(&working_dir);
+
+    rustgit(&working_dir).init();
+    // git quotes the paths that are not ASCII unless core.quotePath is false
+    git()
+        .args(["config", "core.quotePath", "false"])
+        .assert()
+        .success();
+    let dir = "r\u{e9}pertoire/tr\u{e8}s/long/\u{e0}/d\u{e9}couper/pour/tenir/dans/la/largeur";
+    fs::create_dir_all(working_dir.join(dir)).unwrap();
+    let names = [
+        format!("{dir}/fichier_num\u{e9}ro_un.txt"),
+        format!("{dir}/\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}"),
+        "court".to_string(),
+    ];
+    for name in &names {
+        fs::write(working_dir.join(name), "a\n").unwrap();
+    }
+    git().stage(["."]);
+    git().commit("initial commit");
+    for name in &names {
+        fs::write(working_dir.join(name), "b\n").unwrap();
+    }
+
+    assert_same_as_git(&working_dir, &["--stat"]);
+
+    Ok(())
+}
+
+#[test]
+fn algorithms() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    let git = || git(&working_dir);
+
+    rustgit(&working_dir).init();
+    let old = "int a() {\n    return 1;\n}\n\nint b() {\n    return 2;\n}\n";
+    fs::write(working_dir.join("code.c"), old).unwrap();
+    git().stage(["code.c"]);
+    git().commit("initial commit");
+
+    let new = "int b() {\n    return 2;\n}\n\nint a() {\n    return 1;\n}\n\nint c() {\n    return 3;\n}\n";
+    fs::write(working_dir.join("code.c"), new).unwrap();
+
+    for algorithm in ["myers", "default", "minimal", "patience", "histogram"] {
+        assert_same_as_git(&working_dir, &["--diff-algorithm", algorithm]);
+    }
+    for flag in ["--minimal", "--patience", "--histogram"] {
+        assert_same_as_git(&working_dir, &[flag]);
+    }
+    assert_same_as_git(&working_dir, &["--histogram", "--diff-algorithm", "myers"]);
+
+    git()
+        .args(["config", "diff.algorithm", "patience"])
+        .assert()
+        .success();
+    assert_same_as_git(&working_dir, &[]);
+    assert_same_as_git(&working_dir, &["--stat"]);
+    assert_same_as_git(&working_dir, &["--diff-algorithm", "myers"]);
+
+    Ok(())
+}
+
+#[test]
+fn path_order() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    let git = || git(&working_dir);
+
+    rustgit(&working_dir).init();
+    fs::create_dir(working_dir.join("index")).unwrap();
+    for name in ["index.rs", "index/mod.rs", "index-a", "indexes"] {
+        fs::write(working_dir.join(name), "a\n").unwrap();
+    }
+    git().stage(["."]);
+    git().commit("initial commit");
+    let first = git().rev_parse(["HEAD"]);
+    for name in ["index.rs", "index/mod.rs", "index-a", "indexes"] {
+        fs::write(working_dir.join(name), "b\n").unwrap();
+    }
+
+    // git orders the paths by their bytes: `index.rs` comes before `index/mod.rs`
+    assert_same_as_git(&working_dir, &["--name-only"]);
+    assert_same_as_git(&working_dir, &["--stat"]);
+    git().stage(["."]);
+    git().commit("change everything");
+    assert_same_as_git(&working_dir, &["--name-only", &first, "HEAD"]);
+    assert_same_as_git(&working_dir, &[&first, "HEAD"]);
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use crate::common::{git, rustgit, GitCommand};
+use assert_cmd::prelude::*;
+use std::{fs, path::Path};
+use test_utils::test_path;
+
+fn output(command: &mut GitCommand) -> String {
+    let assert = command.assert().success();
+    String::from_utf8(assert.get_output().stdout.clone()).unwrap()
+}
+
+// Three branches committed on different days, a lightweight and an annotated tag, and main
+// tracking topic
+fn make_references(working_dir: &Path) {
+    rustgit(working_dir).init();
+    for (day, branch) in [(3, "main"), (1, "old"), (2, "topic")] {
+        if branch != "main" {
+            git(working_dir)
+                .args(["switch", "-q", "--orphan", branch])
+                .assert()
+                .success();
+        }
+        fs::write(working_dir.join("a"), branch).unwrap();
+        git(working_dir).stage(["a"]);
+        let date = format!("2024-01-{:02}T12:00:00+0100", day);
+        git(working_dir)
+            .env("GIT_COMMITTER_DATE", &date)
+            .args(["commit", "-q", "-m", &format!("{branch} commit\n\nbody")])
+            .assert()
+            .success();
+    }
+    for args in [
+        &["switch", "-q", "main"][..],
+        &["tag", "light", "old"],
+        &["tag", "-a", "v1.0", "-m", "release\n\nnotes"],
+        &["config", "branch.main.remote", "."],
+        &["config", "branch.main.merge", "refs/heads/topic"],
+    ] {
+        git(working_dir).args(args).assert().success();
+    }
+}
+
+#[test]
+fn format() {
+    let working_dir = test_path!();
+    make_references(&working_dir);
+
+    for args in [
+        &["for-each-ref"][..],
+        &["for-each-ref", "refs/tags"],
+        &["for-each-ref", "refs/heads/ma", "refs/*/v1.*"],
+        &[
+            "for-each-ref",
+            "--format=%(refname:short)%09%(objectname:short) %(objecttype) %(upstream)|\
+             %(upstream:short)|%(contents:subject)|%(committerdate)%%%0a",
+        ],
+        &["for-each-ref", "--sort=-committerdate", "refs/heads"],
+        &["for-each-ref", "--sort=objecttype", "--sort=-refname"],
+        &["for-each-ref", "--sort=-refname", "--sort=objecttype"],
+        &["for-each-ref", "--sort=committerdate", "--count=2"],
+    ] {
+        assert_eq!(
+            output(rustgit(&working_dir).args(args)),
+            output(git(&working_dir).args(args)),
+            "rustgit {:?}",
+            args
+        );
+    }
+}
+
+#[test]
+fn invalid_format() {
+    let working_dir = test_path!();
+    make_references(&working_dir);
+
+    rustgit(&working_dir)
+        .args(["for-each-ref", "--format=%(name)"])
+        .assert()
+        .failure()
+        .stderr("fatal: unknown field name: name\n");
+    rustgit(&working_dir)
+        .args(["for-each-ref", "--sort=-nope"])
+        .assert()
+        .failure()
+        .stderr("fatal: unknown field name: nope\n");
+    rustgit(&working_dir)
+        .args(["for-each-ref", "--format=%(refname:long)"])
+        .assert()
+        .failure()
+        .stderr("fatal: unrecognized %(refname:long) argument: long\n");
+    rustgit(&working_dir)
+        .args(["for-each-ref", "--format=%(refname"])
+        .assert()
+        .failure()
+        .stderr("fatal: malformed format string %(refname\n");
+}
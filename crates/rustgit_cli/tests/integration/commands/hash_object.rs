@@ -7,6 +7,7 @@ use rustgit::oid::Sha1HashHexString;
 use std::{
     io::{Read, Write},
     process::Stdio,
+    str::FromStr,
 };
 
 #[test]
@@ -87,3 +88,45 @@ fn stdin() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+// hash-object of content that is not valid UTF-8, from a file and from stdin
+#[test]
+fn binary() -> anyhow::Result<()> {
+    let content: Vec<u8> = (0..200_000u32).map(|i| (i * 13 % 256) as u8).collect();
+
+    let working_dir = test_path!();
+
+    git(&working_dir).init();
+    std::fs::write(working_dir.join("file.bin"), &content)?;
+
+    let expected = git(&working_dir)
+        .args(["hash-object", "file.bin"])
+        .assert()
+        .success();
+    let expected = expected.get_output().stdout.clone();
+
+    let hash_object_cmd = rustgit(&working_dir)
+        .args(["hash-object", "-w", "file.bin"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(&expected[..]));
+    let hash = Sha1HashHexString::from_u8_slice(&hash_object_cmd.get_output().stdout)?;
+    git(&working_dir)
+        .args(["cat-file", "blob", &hash])
+        .assert()
+        .success()
+        .stdout(predicate::eq(&content[..]));
+
+    let mut child_process = rustgit(&working_dir)
+        .args(["hash-object", "--stdin"])
+        .as_command()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    child_process.stdin.take().unwrap().write_all(&content)?;
+    let output = child_process.wait_with_output()?;
+    assert!(output.status.success());
+    assert_eq!(output.stdout, expected);
+
+    Ok(())
+}
@@ -30,3 +30,19 @@ fn init() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn initial_branch() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+
+    rustgit(&working_dir)
+        .args(["init", "-b", "trunk"])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(working_dir.join(".git/HEAD"))?,
+        "ref: refs/heads/trunk\n"
+    );
+
+    Ok(())
+}
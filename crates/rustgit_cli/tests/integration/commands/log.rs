@@ -0,0 +1,234 @@
+use crate::common::{git, rustgit, GitCommand};
+use assert_cmd::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::str::from_utf8;
+use test_utils::test_path;
+
+fn log_output(mut command: GitCommand, args: &[&str]) -> String {
+    let assert = command.arg("log").args(args).assert().success();
+    from_utf8(&assert.get_output().stdout).unwrap().to_string()
+}
+
+fn assert_same_as_git(working_dir: &Path, args: &[&str]) {
+    assert_eq!(
+        log_output(rustgit(working_dir), args),
+        log_output(git(working_dir), args),
+        "rustgit log {:?}",
+        args
+    );
+}
+
+// Commit all the files of the working tree with a fixed date, so that the order is known
+fn commit_at(working_dir: &Path, day: u32, message: &str) {
+    let date = format!("2024-01-{:02}T12:00:00+0100", day);
+    git(working_dir).stage(["."]);
+    git(working_dir)
+        .env("GIT_AUTHOR_DATE", &date)
+        .env("GIT_COMMITTER_DATE", &date)
+        .args(["commit", "-q", "-m", message])
+        .assert()
+        .success();
+}
+
+// Create a history with two branches merged into main:
+//
+// *   merge side
+// |\
+// | * side 2
+// | * side 1
+// * | main 2
+// |/
+// * initial commit
+fn make_history(working_dir: &Path) {
+    rustgit(working_dir).init();
+    fs::write(working_dir.join("file"), "initial\n").unwrap();
+    commit_at(
+        working_dir,
+        1,
+        "initial commit\n\nwith a body\n\nof two paragraphs",
+    );
+
+    git(working_dir)
+        .args(["checkout", "-q", "-b", "side"])
+        .assert()
+        .success();
+    fs::create_dir(working_dir.join("dir")).unwrap();
+    fs::write(working_dir.join("dir/side"), "side\n").unwrap();
+    commit_at(working_dir, 2, "side 1");
+    fs::write(working_dir.join("file"), "side\n").unwrap();
+    commit_at(working_dir, 4, "side 2");
+
+    git(working_dir)
+        .args(["checkout", "-q", "main"])
+        .assert()
+        .success();
+    fs::write(working_dir.join("main.txt"), "main\n").unwrap();
+    commit_at(working_dir, 3, "main 2");
+
+    git(working_dir)
+        .env("GIT_AUTHOR_DATE", "2024-01-05T12:00:00+0000")
+        .env("GIT_COMMITTER_DATE", "2024-01-05T12:00:00+0000")
+        .args(["merge", "-q", "--no-ff", "side", "-m", "merge side"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn formats() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+
+    assert_same_as_git(&working_dir, &[]);
+    assert_same_as_git(&working_dir, &["--oneline"]);
+    for format in ["oneline", "short", "medium", "full", "raw"] {
+        assert_same_as_git(&working_dir, &[&format!("--format={}", format)]);
+    }
+    assert_same_as_git(
+        &working_dir,
+        &["--format=%H %h %T %t %P %p%n%an <%ae> %ad %at %ai%n%cn %ce %cI%n%s%n%b%%"],
+    );
+    assert_same_as_git(&working_dir, &["--format=format:%h %s"]);
+
+    Ok(())
+}
+
+#[test]
+fn graph() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+
+    assert_same_as_git(&working_dir, &["--graph"]);
+    assert_same_as_git(&working_dir, &["--graph", "--oneline"]);
+    assert_same_as_git(&working_dir, &["--graph", "--format=%h%n%b"]);
+
+    // the lanes go on to the commits that are not shown
+    assert_same_as_git(&working_dir, &["--graph", "--oneline", "-n", "2"]);
+    assert_same_as_git(&working_dir, &["--graph", "--oneline", "-n", "4"]);
+    assert_same_as_git(&working_dir, &["--graph", "-n", "1"]);
+    // the parents that don't change the paths are replaced by their ancestors
+    assert_same_as_git(
+        &working_dir,
+        &["--graph", "--oneline", "--", "main.txt", "dir"],
+    );
+    assert_same_as_git(
+        &working_dir,
+        &["--graph", "--oneline", "-n", "1", "--", "dir"],
+    );
+
+    Ok(())
+}
+
+#[test]
+fn ordering_and_limits() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+
+    assert_same_as_git(&working_dir, &["--oneline", "--topo-order"]);
+    assert_same_as_git(&working_dir, &["--oneline", "--date-order"]);
+    assert_same_as_git(&working_dir, &["--oneline", "--reverse"]);
+    assert_same_as_git(&working_dir, &["--oneline", "-n", "2"]);
+    assert_same_as_git(&working_dir, &["--oneline", "--max-count=3", "--reverse"]);
+    assert_same_as_git(&working_dir, &["--oneline", "--author=nobody"]);
+    assert_same_as_git(
+        &working_dir,
+        &[
+            "--oneline",
+            "--since=2024-01-02 12:00:00 +0100",
+            "--until=2024-01-04 00:00:00 +0000",
+        ],
+    );
+
+    Ok(())
+}
+
+#[test]
+fn revision_ranges() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+
+    assert_same_as_git(&working_dir, &["--oneline", "side"]);
+    assert_same_as_git(&working_dir, &["--oneline", "main~1..side"]);
+    assert_same_as_git(&working_dir, &["--oneline", "main", "^side"]);
+    assert_same_as_git(&working_dir, &["--oneline", "main~1", "side~1"]);
+
+    Ok(())
+}
+
+#[test]
+fn paths() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+
+    assert_same_as_git(&working_dir, &["--oneline", "--", "file"]);
+    assert_same_as_git(&working_dir, &["--oneline", "--", "dir"]);
+    assert_same_as_git(&working_dir, &["--oneline", "--", "main.txt", "dir/side"]);
+    assert_same_as_git(&working_dir, &["--oneline", "--", "missing"]);
+    assert_same_as_git(&working_dir.join("dir"), &["--oneline", "--", "side"]);
+
+    Ok(())
+}
+
+#[test]
+fn no_commits() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    rustgit(&working_dir).init();
+
+    rustgit(&working_dir)
+        .arg("log")
+        .assert()
+        .failure()
+        .stderr("fatal: your current branch 'main' does not have any commits yet\n");
+
+    Ok(())
+}
+
+#[test]
+fn abbreviations_grow_with_the_object_count() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    rustgit(&working_dir).init();
+    fs::write(working_dir.join("a"), "a").unwrap();
+    commit_at(&working_dir, 1, "first");
+
+    // a pack of more than 2^14 objects, for which git abbreviates object ids to 8 hex digits
+    let mut blobs = String::new();
+    for i in 0..17000 {
+        let content = i.to_string();
+        blobs += &format!("blob\ndata {}\n{}\n", content.len(), content);
+    }
+    git(&working_dir)
+        .arg("fast-import")
+        .arg("--quiet")
+        .assert_with_stdin(&blobs)
+        .success();
+
+    let oneline = log_output(rustgit(&working_dir), &["--oneline"]);
+    assert_eq!(oneline.split_once(' ').unwrap().0.len(), 8);
+    assert_same_as_git(&working_dir, &["--oneline"]);
+
+    Ok(())
+}
+
+#[test]
+fn only_the_needed_commits_are_read() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    rustgit(&working_dir).init();
+    for day in 1..=3 {
+        fs::write(working_dir.join("a"), day.to_string()).unwrap();
+        commit_at(&working_dir, day, &format!("commit {day}"));
+    }
+
+    // the walk stops before it reaches the missing root commit
+    let root = git(&working_dir).rev_parse(["HEAD~2"]);
+    fs::remove_file(
+        working_dir
+            .join(".git/objects")
+            .join(&root[..2])
+            .join(&root[2..]),
+    )
+    .unwrap();
+    assert_same_as_git(&working_dir, &["--oneline", "-n", "1"]);
+    rustgit(&working_dir).args(["log"]).assert().failure();
+
+    Ok(())
+}
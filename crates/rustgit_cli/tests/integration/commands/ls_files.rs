@@ -1,7 +1,12 @@
 use crate::common::{git, populate_folder, rustgit};
+use assert_cmd::prelude::*;
 use lazy_static::lazy_static;
-use std::{fs, path::PathBuf};
-use test_utils::TEST_DIR;
+use std::str::from_utf8;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use test_utils::{test_path, TEST_DIR};
 
 lazy_static! {
     static ref WORKING_DIR: PathBuf = {
@@ -33,3 +38,140 @@ fn stage() {
     let working_dir = &WORKING_DIR;
     insta::assert_snapshot!(rustgit(&working_dir).ls_files_stage());
 }
+
+// The version of the index file format, kept in its header
+fn index_version(working_dir: &Path) -> u32 {
+    let index = fs::read(working_dir.join(".git/index")).unwrap();
+    u32::from_be_bytes(index[4..8].try_into().unwrap())
+}
+
+// Indexes with extended flags (version 3) and compressed paths (version 4)
+#[test]
+fn index_versions() {
+    let working_dir = test_path!();
+    let git = || git(&working_dir);
+
+    git().init();
+    populate_folder(&working_dir);
+    git().stage(["."]);
+    fs::write(working_dir.join("new.txt"), "new").unwrap();
+    git().args(["add", "-N", "new.txt"]).assert().success();
+    git()
+        .args(["update-index", "--skip-worktree", "file1.txt"])
+        .assert()
+        .success();
+    assert_eq!(index_version(&working_dir), 3);
+
+    // a path with the intent to add isn't part of the tree yet
+    assert_eq!(rustgit(&working_dir).write_tree(), git().write_tree());
+
+    for version in ["4", "3"] {
+        git()
+            .args(["update-index", "--index-version", version])
+            .assert()
+            .success();
+        assert_eq!(
+            rustgit(&working_dir).ls_files_stage(),
+            git().ls_files_stage()
+        );
+
+        // rewriting the index keeps its version and flags
+        fs::write(working_dir.join("other.txt"), "other").unwrap();
+        rustgit(&working_dir).stage(["other.txt"]);
+        assert_eq!(index_version(&working_dir).to_string(), version);
+        let flags = git().args(["ls-files", "-v"]).assert().success();
+        let flags = from_utf8(&flags.get_output().stdout).unwrap();
+        assert!(flags.contains("S file1.txt\n"), "{}", flags);
+        git().args(["fsck", "--no-progress"]).assert().success();
+        git()
+            .args(["diff", "--cached", "--exit-code", "--", "new.txt"])
+            .assert()
+            .success();
+    }
+}
+
+// A repository in the middle of a merge with a conflict on `file`
+fn make_conflict(working_dir: &Path) {
+    let git = || git(working_dir);
+    rustgit(working_dir).init();
+    fs::write(working_dir.join("file"), "base\n").unwrap();
+    fs::write(working_dir.join("other"), "other\n").unwrap();
+    git().stage(["."]);
+    git().commit("base");
+
+    git()
+        .args(["checkout", "-q", "-b", "theirs"])
+        .assert()
+        .success();
+    fs::write(working_dir.join("file"), "theirs\n").unwrap();
+    git().stage(["file"]);
+    git().commit("theirs");
+
+    git().args(["checkout", "-q", "main"]).assert().success();
+    fs::write(working_dir.join("file"), "ours\n").unwrap();
+    git().stage(["file"]);
+    git().commit("ours");
+    git().args(["merge", "theirs"]).assert().failure();
+}
+
+// The resolve-undo extension, which ends the index here
+fn resolve_undo(working_dir: &Path) -> Vec<u8> {
+    let index = fs::read(working_dir.join(".git/index")).unwrap();
+    let start = index
+        .windows(4)
+        .rposition(|window| window == b"REUC")
+        .expect("the index should record the resolved conflict");
+    index[start..index.len() - 20].to_vec()
+}
+
+#[test]
+fn conflicts() {
+    let test_path = test_path!();
+    let rustgit_dir = test_path.join("rustgit");
+    let git_dir = test_path.join("git");
+    for dir in [&rustgit_dir, &git_dir] {
+        fs::create_dir(dir).unwrap();
+        make_conflict(dir);
+    }
+
+    let working_dir = &rustgit_dir;
+    assert_eq!(
+        rustgit(working_dir).ls_files_stage(),
+        git(working_dir).ls_files_stage()
+    );
+    assert_eq!(rustgit(working_dir).ls_files(), git(working_dir).ls_files());
+    for revision in [":1:file", ":2:file", ":3:file", ":other"] {
+        assert_eq!(
+            rustgit(working_dir).rev_parse([revision]),
+            git(working_dir).rev_parse([revision])
+        );
+    }
+    rustgit(working_dir)
+        .arg("write-tree")
+        .assert()
+        .failure()
+        .stderr("fatal: file: unmerged, can't write a tree\n");
+
+    // adding the file resolves the conflict, which can be undone
+    fs::write(rustgit_dir.join("file"), "resolved\n").unwrap();
+    fs::write(git_dir.join("file"), "resolved\n").unwrap();
+    rustgit(&rustgit_dir).stage(["file"]);
+    git(&git_dir).stage(["file"]);
+    assert_eq!(
+        rustgit(&rustgit_dir).ls_files_stage(),
+        git(&git_dir).ls_files_stage()
+    );
+    assert_eq!(resolve_undo(&rustgit_dir), resolve_undo(&git_dir));
+    for dir in [&rustgit_dir, &git_dir] {
+        git(dir).args(["checkout", "-m", "file"]).assert().success();
+    }
+    assert_eq!(
+        rustgit(&rustgit_dir).ls_files_stage(),
+        git(&git_dir).ls_files_stage()
+    );
+    assert_eq!(
+        rustgit(&rustgit_dir).ls_files_stage().lines().count(),
+        4,
+        "the conflict should be back"
+    );
+}
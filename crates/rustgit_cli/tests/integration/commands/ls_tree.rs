@@ -1,5 +1,5 @@
 use crate::common::{git, populate_folder, rustgit};
-use test_utils::TEST_DIR;
+use test_utils::{test_path, TEST_DIR};
 
 use assert_cmd::prelude::*;
 use lazy_static::lazy_static;
@@ -70,3 +70,28 @@ fn not_tree() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+// ls-tree <tree-sha> when objects are in a pack
+#[test]
+fn packed() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+
+    git(&working_dir).init();
+    populate_folder(&working_dir);
+    git(&working_dir).stage(["."]);
+    git(&working_dir).commit("message");
+    git(&working_dir).args(["gc", "--quiet"]).assert().success();
+
+    let expected = "040000 tree 91e1483644d087af54a6e8aac15a08c482bb9fb1    dir1
+040000 tree cf8e933fedbe540f9881ba4dc34b034785834227    dir2
+100644 blob b6fc4c620b67d95f953a5c1c1230aaab5db5a1b0    file1.txt";
+
+    let tree_hash = git(&working_dir).rev_parse(["HEAD^{tree}"]);
+    rustgit(&working_dir)
+        .args(["ls-tree", &tree_hash])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(expected));
+
+    Ok(())
+}
@@ -0,0 +1,275 @@
+use crate::common::{git, rustgit};
+use assert_cmd::prelude::*;
+use std::{fs, path::Path};
+use test_utils::test_path;
+
+// A repository on `main` with a `topic` branch, both changing `file` since their base
+fn make_branches(working_dir: &Path, ours: &str, theirs: &str) {
+    let git = || git(working_dir);
+    fs::create_dir_all(working_dir).unwrap();
+    rustgit(working_dir).init();
+    fs::write(working_dir.join("file"), "a\nb\nc\nd\ne\n").unwrap();
+    fs::write(working_dir.join("deleted"), "deleted\n").unwrap();
+    git().stage(["."]);
+    git().commit("base");
+
+    git()
+        .args(["checkout", "-q", "-b", "topic"])
+        .assert()
+        .success();
+    fs::write(working_dir.join("file"), theirs).unwrap();
+    fs::create_dir(working_dir.join("dir")).unwrap();
+    fs::write(working_dir.join("dir/added"), "added\n").unwrap();
+    git().args(["rm", "-q", "deleted"]).assert().success();
+    git().stage(["."]);
+    git().commit("topic");
+
+    git().args(["checkout", "-q", "main"]).assert().success();
+    fs::write(working_dir.join("file"), ours).unwrap();
+    git().stage(["file"]);
+    git().commit("main");
+}
+
+fn read(working_dir: &Path, path: &str) -> String {
+    fs::read_to_string(working_dir.join(path)).unwrap()
+}
+
+#[test]
+fn clean() {
+    let test_path = test_path!();
+    let (rustgit_dir, git_dir) = (test_path.join("rustgit"), test_path.join("git"));
+    for dir in [&rustgit_dir, &git_dir] {
+        make_branches(dir, "A\nb\nc\nd\ne\n", "a\nb\nc\nd\nE\n");
+    }
+    let parents = ["main", "topic"].map(|branch| git(&rustgit_dir).rev_parse([branch]));
+
+    rustgit(&rustgit_dir)
+        .args(["merge", "topic"])
+        .assert()
+        .success()
+        .stdout("Auto-merging file\nMerge made by the 'resolve' strategy.\n");
+    git(&git_dir).args(["merge", "topic"]).assert().success();
+
+    assert_eq!(
+        rustgit(&rustgit_dir).rev_parse(["HEAD^{tree}"]),
+        git(&git_dir).rev_parse(["HEAD^{tree}"])
+    );
+    assert_eq!(read(&rustgit_dir, "file"), "A\nb\nc\nd\nE\n");
+    assert!(!rustgit_dir.join("deleted").exists());
+    assert_eq!(
+        ["HEAD^1", "HEAD^2"].map(|revision| rustgit(&rustgit_dir).rev_parse([revision])),
+        parents
+    );
+    let message = git(&rustgit_dir).cat_file(["-p", "HEAD"]);
+    assert!(message.ends_with("\nMerge branch 'topic'\n"), "{}", message);
+    assert!(git(&rustgit_dir).status_porcelain().is_empty());
+}
+
+#[test]
+fn conflicts() {
+    let test_path = test_path!();
+    let (rustgit_dir, git_dir) = (test_path.join("rustgit"), test_path.join("git"));
+    for dir in [&rustgit_dir, &git_dir] {
+        make_branches(dir, "a\nB\nc\nd\ne\n", "a\nb2\nc\nd\ne\n");
+        fs::write(dir.join("deleted"), "modified\n").unwrap();
+        git(dir).stage(["deleted"]);
+        git(dir)
+            .args(["commit", "-q", "--amend", "-m", "main"])
+            .assert()
+            .success();
+    }
+
+    rustgit(&rustgit_dir)
+        .args(["merge", "topic"])
+        .assert()
+        .code(1)
+        .stdout(
+            "CONFLICT (modify/delete): deleted deleted in topic and modified in HEAD.  \
+             Version HEAD of deleted left in tree.\n\
+             Auto-merging file\n\
+             CONFLICT (content): Merge conflict in file\n\
+             Automatic merge failed; fix conflicts and then commit the result.\n",
+        );
+    git(&git_dir).args(["merge", "topic"]).assert().failure();
+
+    assert_eq!(
+        rustgit(&rustgit_dir).ls_files_stage(),
+        git(&git_dir).ls_files_stage()
+    );
+    assert_eq!(read(&rustgit_dir, "file"), read(&git_dir, "file"));
+    assert_eq!(read(&rustgit_dir, "deleted"), "modified\n");
+    assert_eq!(
+        git(&rustgit_dir).status_porcelain(),
+        git(&git_dir).status_porcelain()
+    );
+
+    rustgit(&rustgit_dir)
+        .args(["merge", "topic"])
+        .assert()
+        .failure()
+        .stderr("fatal: You have not concluded your merge (MERGE_HEAD exists).\n");
+
+    // committing the resolution concludes the merge, with the tree of the index
+    fs::write(rustgit_dir.join("file"), "resolved\n").unwrap();
+    rustgit(&rustgit_dir).stage(["file", "deleted"]);
+    fs::write(rustgit_dir.join("untracked"), "untracked\n").unwrap();
+    rustgit(&rustgit_dir).commit("Merge topic");
+    assert_eq!(
+        rustgit(&rustgit_dir).rev_parse(["HEAD^2"]),
+        git(&rustgit_dir).rev_parse(["topic"])
+    );
+    let tree = git(&rustgit_dir).cat_file(["-p", "HEAD^{tree}"]);
+    assert!(!tree.contains("untracked"), "{}", tree);
+    assert_eq!(read(&rustgit_dir, "untracked"), "untracked\n");
+    assert!(!rustgit_dir.join(".git/MERGE_HEAD").exists());
+}
+
+#[test]
+fn file_directory() {
+    let test_path = test_path!();
+    let (rustgit_dir, git_dir) = (test_path.join("rustgit"), test_path.join("git"));
+    for dir in [&rustgit_dir, &git_dir] {
+        let git = || git(dir);
+        make_branches(dir, "A\nb\nc\nd\ne\n", "a\nb\nc\nd\nE\n");
+        // `topic` has a file where `main` has a directory, and the other way around
+        git().args(["checkout", "-q", "topic"]).assert().success();
+        fs::write(dir.join("other"), "their file\n").unwrap();
+        git().stage(["other"]);
+        git()
+            .args(["commit", "-q", "--amend", "-m", "topic"])
+            .assert()
+            .success();
+        git().args(["checkout", "-q", "main"]).assert().success();
+        fs::write(dir.join("dir"), "our file\n").unwrap();
+        fs::create_dir(dir.join("other")).unwrap();
+        fs::write(dir.join("other/file"), "our directory\n").unwrap();
+        git().stage(["dir", "other"]);
+        git()
+            .args(["commit", "-q", "--amend", "-m", "main"])
+            .assert()
+            .success();
+    }
+
+    rustgit(&rustgit_dir)
+        .args(["merge", "topic"])
+        .assert()
+        .code(1)
+        .stdout(
+            "CONFLICT (file/directory): directory in the way of dir from HEAD; moving it to \
+             dir~HEAD instead.\n\
+             Auto-merging file\n\
+             CONFLICT (file/directory): directory in the way of other from topic; moving it to \
+             other~topic instead.\n\
+             Automatic merge failed; fix conflicts and then commit the result.\n",
+        );
+    git(&git_dir).args(["merge", "topic"]).assert().failure();
+
+    assert_eq!(
+        rustgit(&rustgit_dir).ls_files_stage(),
+        git(&git_dir).ls_files_stage()
+    );
+    assert_eq!(read(&rustgit_dir, "dir~HEAD"), "our file\n");
+    assert_eq!(read(&rustgit_dir, "dir/added"), "added\n");
+    assert_eq!(read(&rustgit_dir, "other~topic"), "their file\n");
+    assert_eq!(read(&rustgit_dir, "other/file"), "our directory\n");
+    assert_eq!(
+        git(&rustgit_dir).status_porcelain(),
+        git(&git_dir).status_porcelain()
+    );
+
+    // the directories go away again on abort
+    rustgit(&rustgit_dir)
+        .args(["merge", "--abort"])
+        .assert()
+        .success();
+    assert_eq!(read(&rustgit_dir, "dir"), "our file\n");
+    assert!(!rustgit_dir.join("dir~HEAD").exists());
+    assert!(!rustgit_dir.join("other~topic").exists());
+    assert!(git(&rustgit_dir).status_porcelain().is_empty());
+}
+
+#[test]
+fn abort() {
+    let working_dir = test_path!();
+    make_branches(&working_dir, "a\nB\nc\nd\ne\n", "a\nb2\nc\nd\ne\n");
+    let head = git(&working_dir).rev_parse(["HEAD"]);
+
+    rustgit(&working_dir)
+        .args(["merge", "--abort"])
+        .assert()
+        .failure()
+        .stderr("fatal: There is no merge to abort (MERGE_HEAD missing).\n");
+
+    rustgit(&working_dir)
+        .args(["merge", "topic"])
+        .assert()
+        .code(1);
+    rustgit(&working_dir)
+        .args(["merge", "--abort"])
+        .assert()
+        .success();
+    assert_eq!(git(&working_dir).rev_parse(["HEAD"]), head);
+    assert!(git(&working_dir).status_porcelain().is_empty());
+    assert!(!working_dir.join("dir").exists());
+    assert!(!working_dir.join(".git/MERGE_HEAD").exists());
+}
+
+#[test]
+fn fast_forward() {
+    let working_dir = test_path!();
+    make_branches(&working_dir, "a\nb\nc\nd\nE\n", "A\nb\nc\nd\ne\n");
+    let git = || git(&working_dir);
+    git()
+        .args(["reset", "-q", "--hard", "HEAD~"])
+        .assert()
+        .success();
+    let topic = git().rev_parse(["topic"]);
+
+    rustgit(&working_dir)
+        .args(["merge", "--no-ff", "--ff-only", "topic"])
+        .assert()
+        .failure();
+
+    rustgit(&working_dir)
+        .args(["merge", "--ff-only", "topic"])
+        .assert()
+        .success();
+    assert_eq!(git().rev_parse(["main"]), topic);
+    assert_eq!(read(&working_dir, "dir/added"), "added\n");
+    assert!(git().status_porcelain().is_empty());
+
+    rustgit(&working_dir)
+        .args(["merge", "topic"])
+        .assert()
+        .success()
+        .stdout("Already up to date.\n");
+
+    // forcing a merge commit
+    git()
+        .args(["reset", "-q", "--hard", "HEAD~"])
+        .assert()
+        .success();
+    rustgit(&working_dir)
+        .args(["merge", "--no-ff", "topic"])
+        .assert()
+        .success();
+    assert_eq!(git().rev_parse(["HEAD^2"]), topic);
+    assert_eq!(
+        git().rev_parse(["HEAD^{tree}"]),
+        git().rev_parse(["topic^{tree}"])
+    );
+
+    // a merge commit can't be a fast-forward
+    git()
+        .args(["checkout", "-q", "-b", "other", "HEAD~"])
+        .assert()
+        .success();
+    fs::write(working_dir.join("other"), "other\n").unwrap();
+    git().stage(["other"]);
+    git().commit("other");
+    rustgit(&working_dir)
+        .args(["merge", "--ff-only", "main"])
+        .assert()
+        .failure()
+        .stderr("fatal: Not possible to fast-forward, aborting.\n");
+}
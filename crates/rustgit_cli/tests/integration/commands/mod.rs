@@ -1,11 +1,25 @@
 mod add;
+mod branch;
 mod cat_file;
+mod check_ignore;
+mod check_ref_format;
+mod checkout;
 mod commit;
 mod commit_tree;
+mod config;
+mod diff;
+mod for_each_ref;
 mod hash_object;
 mod init;
+mod log;
 mod ls_files;
 mod ls_tree;
+mod merge;
+mod reflog;
+mod reset;
 mod rev_parse;
+mod show_ref;
 mod status;
+mod tag;
+mod update_ref;
 mod write_tree;
@@ -0,0 +1,250 @@
+use crate::common::{git, rustgit, GitCommand};
+use assert_cmd::prelude::*;
+use std::{fs, path::Path, str::from_utf8};
+use test_utils::test_path;
+
+fn stdout(command: &mut GitCommand) -> String {
+    let assert = command.assert().success();
+    from_utf8(&assert.get_output().stdout).unwrap().to_string()
+}
+
+// Commit on main, then on topic, merge topic into main and go back one commit
+fn make_history(working_dir: &Path) {
+    rustgit(working_dir).init();
+    fs::write(working_dir.join("a"), "a\n").unwrap();
+    rustgit(working_dir).stage(["a"]);
+    rustgit(working_dir).commit("first commit");
+    fs::write(working_dir.join("a"), "a2\n").unwrap();
+    rustgit(working_dir).stage(["a"]);
+    rustgit(working_dir).commit("second commit\n\nwith a body");
+
+    rustgit(working_dir)
+        .args(["switch", "-c", "topic"])
+        .assert()
+        .success();
+    fs::write(working_dir.join("b"), "b\n").unwrap();
+    rustgit(working_dir).stage(["b"]);
+    rustgit(working_dir).commit("third commit");
+    rustgit(working_dir)
+        .args(["switch", "main"])
+        .assert()
+        .success();
+    rustgit(working_dir)
+        .args(["merge", "topic"])
+        .assert()
+        .success();
+    rustgit(working_dir)
+        .args(["reset", "-q", "--hard", "HEAD~"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn record() {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+
+    let head = stdout(rustgit(&working_dir).arg("reflog"));
+    let main = stdout(rustgit(&working_dir).args(["reflog", "show", "main"]));
+    let topic = stdout(rustgit(&working_dir).args(["reflog", "show", "topic"]));
+    assert_eq!(head, stdout(git(&working_dir).arg("reflog")));
+    assert_eq!(
+        main,
+        stdout(git(&working_dir).args(["reflog", "show", "main"]))
+    );
+    assert_eq!(
+        topic,
+        stdout(git(&working_dir).args(["reflog", "show", "topic"]))
+    );
+
+    let messages = |reflog: &str| -> Vec<String> {
+        reflog
+            .lines()
+            .map(|line| line.split_once(": ").unwrap().1.to_string())
+            .collect()
+    };
+    assert_eq!(
+        messages(&head),
+        [
+            "reset: moving to HEAD~",
+            "merge topic: Fast-forward",
+            "checkout: moving from topic to main",
+            "commit: third commit",
+            "checkout: moving from main to topic",
+            "commit: second commit",
+            "commit (initial): first commit",
+        ]
+    );
+    assert_eq!(
+        messages(&topic),
+        ["commit: third commit", "branch: Created from HEAD"]
+    );
+
+    // tags are not logged by default
+    rustgit(&working_dir)
+        .args(["tag", "v1.0"])
+        .assert()
+        .success();
+    assert!(!working_dir.join(".git/logs/refs/tags/v1.0").exists());
+}
+
+#[test]
+fn resolve() {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+
+    for spec in [
+        "HEAD@{0}",
+        "HEAD@{2}",
+        "main@{1}",
+        "@{1}",
+        "main@{3}",
+        "topic@{1}~1",
+        "main@{1.hour.ago}",
+        "main@{now}",
+        "main@{2000-01-01}",
+        "main@{2099-01-01}~1",
+        "main@{2099-01-01 07:00:00}",
+        "main@{2099-01-01 07:00:00}:a",
+        "topic@{1}:a",
+    ] {
+        assert_eq!(
+            rustgit(&working_dir).rev_parse([spec]),
+            git(&working_dir).rev_parse([spec]),
+            "{}",
+            spec
+        );
+    }
+
+    // a day alone is at the current time of that day, so today is now
+    let today = chrono::Local::now().format("%Y-%m-%d");
+    for spec in [
+        format!("main@{{{}}}", today),
+        format!("topic@{{{}}}:b", today),
+    ] {
+        assert_eq!(
+            rustgit(&working_dir).rev_parse([&spec]),
+            git(&working_dir).rev_parse([&spec]),
+            "{}",
+            spec
+        );
+    }
+
+    rustgit(&working_dir)
+        .args(["rev-parse", "topic@{2}"])
+        .assert()
+        .failure()
+        .stderr("fatal: log for 'topic' only has 2 entries\n");
+}
+
+#[test]
+fn delete_and_expire() {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+    let before = stdout(rustgit(&working_dir).args(["reflog", "show", "main"]));
+
+    rustgit(&working_dir)
+        .args(["reflog", "delete", "main@{1}"])
+        .assert()
+        .success();
+    let mut expected: Vec<&str> = before.lines().collect();
+    expected.remove(1);
+    let after = stdout(git(&working_dir).args(["reflog", "show", "main"]));
+    let after: Vec<&str> = after.lines().collect();
+    assert_eq!(after.len(), expected.len());
+    assert_eq!(after[0], expected[0]);
+
+    rustgit(&working_dir)
+        .args(["reflog", "delete", "main"])
+        .assert()
+        .failure()
+        .stderr("error: not a reflog: main\n");
+
+    // nothing is older than 90 days
+    rustgit(&working_dir)
+        .args(["reflog", "expire", "--all"])
+        .assert()
+        .success();
+    assert_eq!(
+        stdout(rustgit(&working_dir).args(["reflog", "show", "main"]))
+            .lines()
+            .count(),
+        expected.len()
+    );
+
+    rustgit(&working_dir)
+        .args(["reflog", "expire", "--expire=all", "--all"])
+        .assert()
+        .success();
+    assert_eq!(stdout(rustgit(&working_dir).arg("reflog")), "");
+    assert_eq!(
+        fs::read_to_string(working_dir.join(".git/logs/refs/heads/topic")).unwrap(),
+        ""
+    );
+    // the current value is still known
+    assert_eq!(
+        rustgit(&working_dir).rev_parse(["main@{0}"]),
+        git(&working_dir).rev_parse(["main"])
+    );
+}
+
+// Write a message that is not UTF-8 and a malformed line before the entries of a reflog, and
+// return the lines written and the previous content
+fn prepend_unreadable_lines(path: &Path) -> (Vec<u8>, Vec<u8>) {
+    let content = fs::read(path).unwrap();
+    let first_line = content.split(|&byte| byte == b'\n').next().unwrap();
+    let tab = first_line.iter().position(|&byte| byte == b'\t').unwrap();
+    let mut prefix = first_line[..=tab].to_vec();
+    prefix.extend_from_slice(b"commit: caf\xe9\nmalformed line\n");
+    fs::write(path, [prefix.as_slice(), &content].concat()).unwrap();
+    (prefix, content)
+}
+
+#[test]
+fn non_utf8_message() {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+    prepend_unreadable_lines(&working_dir.join(".git/logs/refs/heads/main"));
+
+    let reflog_lines = |command: &mut GitCommand| {
+        let assert = command.args(["reflog", "show", "main"]).assert().success();
+        assert
+            .get_output()
+            .stdout
+            .split(|&byte| byte == b'\n')
+            .count()
+    };
+    assert_eq!(
+        reflog_lines(&mut rustgit(&working_dir)),
+        reflog_lines(&mut git(&working_dir))
+    );
+    assert_eq!(
+        rustgit(&working_dir).rev_parse(["main@{3}"]),
+        git(&working_dir).rev_parse(["main@{3}"])
+    );
+}
+
+#[test]
+fn rewrite_keeps_unreadable_lines() {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+    let path = working_dir.join(".git/logs/refs/heads/main");
+    let (prefix, content) = prepend_unreadable_lines(&path);
+
+    rustgit(&working_dir)
+        .args(["reflog", "delete", "main@{0}"])
+        .assert()
+        .success();
+    let last_line = content
+        .split_inclusive(|&byte| byte == b'\n')
+        .next_back()
+        .unwrap();
+    let expected = [&prefix, &content[..content.len() - last_line.len()]].concat();
+    assert_eq!(fs::read(&path).unwrap(), expected);
+
+    rustgit(&working_dir)
+        .args(["reflog", "expire", "--expire=all", "main"])
+        .assert()
+        .success();
+    assert_eq!(fs::read(&path).unwrap(), b"malformed line\n");
+}
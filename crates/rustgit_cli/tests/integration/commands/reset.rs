@@ -0,0 +1,170 @@
+use crate::common::{git, rustgit};
+use assert_cmd::prelude::*;
+use std::{fs, path::Path};
+use test_utils::test_path;
+
+// Two commits: the first has `a` and `d/s`, the second changes `a` and adds `b`
+fn make_history(working_dir: &Path) {
+    rustgit(working_dir).init();
+    fs::create_dir_all(working_dir.join("d")).unwrap();
+    fs::write(working_dir.join("a"), "a\n").unwrap();
+    fs::write(working_dir.join("d/s"), "s\n").unwrap();
+    rustgit(working_dir).stage(["."]);
+    rustgit(working_dir).commit("first commit");
+
+    fs::write(working_dir.join("a"), "a2\n").unwrap();
+    fs::write(working_dir.join("b"), "b\n").unwrap();
+    rustgit(working_dir).stage(["."]);
+    rustgit(working_dir).commit("second commit");
+}
+
+#[test]
+fn soft() {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+    let first = git(&working_dir).rev_parse(["HEAD~"]);
+    let second = git(&working_dir).rev_parse(["HEAD"]);
+
+    rustgit(&working_dir)
+        .args(["reset", "--soft", "HEAD~"])
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(git(&working_dir).rev_parse(["main"]), first);
+    assert_eq!(git(&working_dir).rev_parse(["ORIG_HEAD"]), second);
+    assert_eq!(git(&working_dir).status_porcelain(), "M  a\nA  b\n");
+}
+
+#[test]
+fn mixed() {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+    let first = git(&working_dir).rev_parse(["HEAD~"]);
+    fs::remove_file(working_dir.join("d/s")).unwrap();
+
+    rustgit(&working_dir)
+        .args(["reset", "HEAD~"])
+        .assert()
+        .success()
+        .stdout("Unstaged changes after reset:\nM\ta\nD\td/s\n");
+    assert_eq!(git(&working_dir).rev_parse(["HEAD"]), first);
+    assert_eq!(git(&working_dir).status_porcelain(), " M a\n D d/s\n?? b\n");
+    assert_eq!(fs::read_to_string(working_dir.join("a")).unwrap(), "a2\n");
+
+    // nothing left to unstage
+    rustgit(&working_dir)
+        .args(["reset", "-q", "--mixed"])
+        .assert()
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn hard() {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+    let first = git(&working_dir).rev_parse(["HEAD~"]);
+    fs::write(working_dir.join("a"), "local\n").unwrap();
+    fs::write(working_dir.join("new"), "new\n").unwrap();
+    fs::write(working_dir.join("untracked"), "untracked\n").unwrap();
+    rustgit(&working_dir).stage(["new"]);
+
+    rustgit(&working_dir)
+        .args(["reset", "--hard", &first[..7]])
+        .assert()
+        .success()
+        .stdout(format!("HEAD is now at {} first commit\n", &first[..7]));
+    assert_eq!(git(&working_dir).rev_parse(["HEAD"]), first);
+    assert_eq!(fs::read_to_string(working_dir.join("a")).unwrap(), "a\n");
+    assert!(!working_dir.join("b").exists());
+    assert!(!working_dir.join("new").exists());
+    assert_eq!(git(&working_dir).status_porcelain(), "?? untracked\n");
+
+    rustgit(&working_dir)
+        .args(["reset", "--hard", "ORIG_HEAD"])
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(working_dir.join("b")).unwrap(), "b\n");
+    assert_eq!(git(&working_dir).status_porcelain(), "?? untracked\n");
+}
+
+#[test]
+fn paths() {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+    let head = git(&working_dir).rev_parse(["HEAD"]);
+    fs::write(working_dir.join("a"), "local\n").unwrap();
+    fs::write(working_dir.join("d/s"), "local\n").unwrap();
+    fs::write(working_dir.join("new"), "new\n").unwrap();
+    rustgit(&working_dir).stage(["."]);
+
+    rustgit(&working_dir)
+        .args(["reset", "--", "d", "new"])
+        .assert()
+        .success()
+        .stdout("Unstaged changes after reset:\nM\td/s\n");
+    assert_eq!(git(&working_dir).rev_parse(["HEAD"]), head);
+    assert_eq!(
+        git(&working_dir).status_porcelain(),
+        "M  a\n M d/s\n?? new\n"
+    );
+
+    // the index entry can come from another commit, and a file can be given without `--`
+    rustgit(&working_dir)
+        .args(["reset", "-q", "HEAD~", "--", "a"])
+        .assert()
+        .success();
+    assert_eq!(
+        git(&working_dir).status_porcelain(),
+        "MM a\n M d/s\n?? new\n"
+    );
+    rustgit(&working_dir)
+        .args(["reset", "-q", "a"])
+        .assert()
+        .success();
+    assert_eq!(
+        git(&working_dir).status_porcelain(),
+        " M a\n M d/s\n?? new\n"
+    );
+
+    // the paths can follow a commit without `--`, unless the commit is also a file
+    rustgit(&working_dir)
+        .args(["reset", "-q", "HEAD~", "a"])
+        .assert()
+        .success();
+    assert_eq!(
+        git(&working_dir).status_porcelain(),
+        "MM a\n M d/s\n?? new\n"
+    );
+    fs::write(working_dir.join("HEAD"), "").unwrap();
+    rustgit(&working_dir)
+        .args(["reset", "HEAD", "a"])
+        .assert()
+        .failure()
+        .stderr(
+            "fatal: ambiguous argument 'HEAD': both revision and filename\n\
+             Use '--' to separate paths from revisions, like this:\n\
+             'rustgit <command> [<revision>...] -- [<file>...]'\n",
+        );
+    fs::remove_file(working_dir.join("HEAD")).unwrap();
+    rustgit(&working_dir)
+        .args(["reset", "missing", "--", "a"])
+        .assert()
+        .failure()
+        .stderr("fatal: Failed to resolve 'missing' as a valid tree.\n");
+
+    rustgit(&working_dir)
+        .args(["reset", "--hard", "--", "a"])
+        .assert()
+        .failure()
+        .stderr("fatal: Cannot do hard reset with paths.\n");
+    rustgit(&working_dir)
+        .args(["reset", "missing"])
+        .assert()
+        .failure()
+        .stderr(
+            "fatal: ambiguous argument 'missing': unknown revision or path not in the working \
+             tree.\nUse '--' to separate paths from revisions, like this:\n\
+             'rustgit <command> [<revision>...] -- [<file>...]'\n",
+        );
+}
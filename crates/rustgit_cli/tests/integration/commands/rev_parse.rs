@@ -1,11 +1,14 @@
-use crate::common::{git, populate_folder, rustgit};
-use test_utils::TEST_DIR;
+use crate::common::{git, head_sha, populate_folder, rustgit, GitCommand};
+use test_utils::{test_path, TEST_DIR};
 
 use assert_cmd::prelude::*;
 use lazy_static::lazy_static;
 use predicates::prelude::predicate;
 use rustgit::oid::Sha1HashHexString;
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 lazy_static! {
     static ref WORKING_DIR: PathBuf = {
@@ -25,12 +28,19 @@ lazy_static! {
 // git rev-parse <sha1>
 #[test]
 fn sha1() {
-    let sha1 = "e9fd0d28a8db6bf20521abb3f13e840cb963def1";
+    // the blob of file1.txt
+    let sha1 = "b6fc4c620b67d95f953a5c1c1230aaab5db5a1b0";
     rustgit(&WORKING_DIR)
         .args(["rev-parse", sha1])
         .assert()
         .success()
         .stdout(predicate::str::starts_with(sha1));
+
+    // the object must exist
+    rustgit(&WORKING_DIR)
+        .args(["rev-parse", "e9fd0d28a8db6bf20521abb3f13e840cb963def1"])
+        .assert()
+        .failure();
 }
 
 // git rev-parse HEAD
@@ -53,3 +63,159 @@ fn in_subfolder() {
         .success()
         .stdout(predicate::str::starts_with(&EXPECTED_HEAD_HASH.to_string()));
 }
+
+// git rev-parse HEAD, where the branch is in .git/packed-refs
+#[test]
+fn packed_head() {
+    let working_dir = test_path!();
+    git(&working_dir).init();
+    populate_folder(&working_dir);
+    git(&working_dir).stage(["."]);
+    git(&working_dir).commit("message");
+    git(&working_dir).args(["gc", "--quiet"]).assert().success();
+    assert!(!working_dir.join(".git/refs/heads/main").exists());
+
+    rustgit(&working_dir)
+        .args(["rev-parse", "HEAD"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            head_sha(&working_dir).to_string(),
+        ));
+}
+
+// Resolve a revision with git and rustgit
+fn assert_same_as_git(working_dir: &Path, revision: &str) {
+    let rev_parse = |mut command: GitCommand| {
+        let assert = command.args(["rev-parse", revision]).assert().success();
+        String::from_utf8(assert.get_output().stdout.clone()).unwrap()
+    };
+    assert_eq!(
+        rev_parse(rustgit(working_dir)),
+        rev_parse(git(working_dir)),
+        "rev-parse {}",
+        revision
+    );
+}
+
+// A merge commit, annotated and lightweight tags, and a remote-tracking upstream branch
+fn make_history(working_dir: &Path) {
+    let git = || git(working_dir);
+
+    rustgit(working_dir).init();
+    populate_folder(working_dir);
+    git().stage(["."]);
+    git().commit("first");
+    fs::write(working_dir.join("file1.txt"), "second").unwrap();
+    git().stage(["file1.txt"]);
+    git().commit("second");
+
+    git()
+        .args(["checkout", "-q", "-b", "side", "HEAD~1"])
+        .assert()
+        .success();
+    fs::write(working_dir.join("side.txt"), "side").unwrap();
+    git().stage(["side.txt"]);
+    git().commit("side");
+    git().args(["checkout", "-q", "main"]).assert().success();
+    git()
+        .args(["merge", "-q", "--no-edit", "side"])
+        .assert()
+        .success();
+
+    git()
+        .args(["tag", "-a", "v1.0", "-m", "v1.0", "HEAD~1"])
+        .assert()
+        .success();
+    git().args(["tag", "light"]).assert().success();
+    git()
+        .args(["update-ref", "refs/remotes/origin/main", "HEAD~1"])
+        .assert()
+        .success();
+    for (key, value) in [
+        ("remote.origin.fetch", "+refs/heads/*:refs/remotes/origin/*"),
+        ("branch.main.remote", "origin"),
+        ("branch.main.merge", "refs/heads/main"),
+    ] {
+        git().args(["config", key, value]).assert().success();
+    }
+}
+
+#[test]
+fn revision_syntax() {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+
+    for revision in [
+        "main",
+        "heads/side",
+        "refs/heads/side",
+        "v1.0",
+        "light",
+        "origin/main",
+        "v1.0^{}",
+        "v1.0^{commit}",
+        "v1.0^{tree}",
+        "v1.0^{tag}",
+        "HEAD~",
+        "HEAD~2",
+        "HEAD^2",
+        "HEAD^2~1",
+        "HEAD^0",
+        "@",
+        "@~1",
+        "HEAD:file1.txt",
+        "HEAD~1:dir1",
+        "HEAD:dir1/file_in_dir1_1",
+        ":file1.txt",
+        "@{upstream}",
+        "main@{u}~1",
+        "HEAD~2..HEAD",
+        "..side",
+        "^HEAD",
+    ] {
+        assert_same_as_git(&working_dir, revision);
+    }
+
+    let head = head_sha(&working_dir).to_string();
+    assert_same_as_git(&working_dir, &head[..7]);
+
+    // only a stage from 0 to 3 is a stage, the rest is a path with a colon
+    fs::write(working_dir.join("a:b"), "colon\n").unwrap();
+    fs::write(working_dir.join("4:b"), "stage 4\n").unwrap();
+    git(&working_dir).stage(["a:b", "4:b"]);
+    for revision in [":a:b", ":0:a:b", ":4:b", ":0:4:b"] {
+        assert_same_as_git(&working_dir, revision);
+    }
+}
+
+#[test]
+fn packed_refs_revision() {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+    git(&working_dir)
+        .args(["pack-refs", "--all"])
+        .assert()
+        .success();
+
+    for revision in ["v1.0", "v1.0^{}", "side~1", "origin/main", "@{u}"] {
+        assert_same_as_git(&working_dir, revision);
+    }
+}
+
+#[test]
+fn unknown_revision() {
+    rustgit(&WORKING_DIR)
+        .args(["rev-parse", "HEAD~10"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown revision"));
+    rustgit(&WORKING_DIR)
+        .args(["rev-parse", "HEAD:missing"])
+        .assert()
+        .failure();
+    rustgit(&WORKING_DIR)
+        .args(["rev-parse", "no-such-branch"])
+        .assert()
+        .failure();
+}
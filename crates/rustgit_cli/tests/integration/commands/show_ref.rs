@@ -0,0 +1,71 @@
+use crate::common::{git, rustgit};
+use assert_cmd::prelude::*;
+use std::{fs, path::Path};
+use test_utils::test_path;
+
+// A commit on main and topic, a lightweight tag and a packed annotated tag
+fn make_references(working_dir: &Path) {
+    rustgit(working_dir).init();
+    fs::write(working_dir.join("a"), "a").unwrap();
+    rustgit(working_dir).stage(["a"]);
+    rustgit(working_dir).commit("first commit");
+    for args in [
+        &["branch", "topic"][..],
+        &["tag", "-a", "v1", "-m", "release"],
+        &["pack-refs", "--all"],
+        &["tag", "light"],
+    ] {
+        git(working_dir).args(args).assert().success();
+    }
+}
+
+#[test]
+fn list() {
+    let working_dir = test_path!();
+    make_references(&working_dir);
+
+    for args in [
+        &["show-ref"][..],
+        &["show-ref", "--heads"],
+        &["show-ref", "--tags", "--heads"],
+        &["show-ref", "main", "v1"],
+        &["show-ref", "ain"],
+        &["show-ref", "-d", "--tags"],
+        &["show-ref", "--hash", "topic"],
+        &["show-ref", "--hash=9", "--heads"],
+        &["show-ref", "-q", "light"],
+        &["show-ref", "--tags", "main"],
+    ] {
+        let expected = git(&working_dir).args(args).assert();
+        let expected = expected.get_output();
+        rustgit(&working_dir)
+            .args(args)
+            .assert()
+            .code(expected.status.code().unwrap())
+            .stdout(String::from_utf8(expected.stdout.clone()).unwrap());
+    }
+}
+
+#[test]
+fn verify() {
+    let working_dir = test_path!();
+    make_references(&working_dir);
+    let head = git(&working_dir).rev_parse(["HEAD"]);
+
+    rustgit(&working_dir)
+        .args(["show-ref", "--verify", "HEAD", "refs/heads/topic"])
+        .assert()
+        .success()
+        .stdout(format!("{0} HEAD\n{0} refs/heads/topic\n", head));
+    rustgit(&working_dir)
+        .args(["show-ref", "--verify", "main"])
+        .assert()
+        .code(128)
+        .stderr("fatal: 'main' - not a valid ref\n");
+    rustgit(&working_dir)
+        .args(["show-ref", "--verify", "-q", "refs/heads/missing"])
+        .assert()
+        .code(1)
+        .stdout("")
+        .stderr("");
+}
@@ -1,4 +1,7 @@
-use crate::common::rustgit;
+use crate::common::{git, rustgit};
+use assert_cmd::prelude::*;
+use std::fs;
+use std::str::from_utf8;
 use test_utils::test_path;
 
 #[test]
@@ -12,3 +15,193 @@ fn status() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+// Create a repository with staged changes, unstaged changes and untracked files
+fn make_changes(working_dir: &std::path::Path) {
+    let git = || git(working_dir);
+
+    rustgit(working_dir).init();
+    fs::write(working_dir.join("a"), "a").unwrap();
+    fs::write(working_dir.join("b"), "b").unwrap();
+    fs::write(working_dir.join("c"), "c").unwrap();
+    fs::create_dir(working_dir.join("dir")).unwrap();
+    fs::write(working_dir.join("dir/d"), "d").unwrap();
+    git().stage(["."]);
+    git().commit("initial commit");
+
+    // staged and modified again
+    fs::write(working_dir.join("b"), "b2").unwrap();
+    git().stage(["b"]);
+    fs::write(working_dir.join("b"), "b3").unwrap();
+
+    // deleted from the working tree only
+    fs::remove_file(working_dir.join("c")).unwrap();
+
+    // modified without changing the size
+    fs::write(working_dir.join("dir/d"), "e").unwrap();
+
+    // staged removal
+    git().args(["rm", "--cached", "-q", "a"]).assert().success();
+
+    // new files
+    fs::write(working_dir.join("new"), "new").unwrap();
+    git().stage(["new"]);
+    fs::write(working_dir.join("dir/untracked"), "untracked").unwrap();
+    fs::create_dir_all(working_dir.join("untracked_dir/inner")).unwrap();
+    fs::write(working_dir.join("untracked_dir/inner/file"), "file").unwrap();
+
+    // empty directories are not reported
+    fs::create_dir(working_dir.join("empty")).unwrap();
+}
+
+#[test]
+fn porcelain() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    make_changes(&working_dir);
+
+    assert_eq!(
+        rustgit(&working_dir).status_porcelain(),
+        git(&working_dir).status_porcelain()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn short_in_subdirectory() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    make_changes(&working_dir);
+
+    let sub_dir = working_dir.join("dir");
+    let short_status = |mut command: crate::common::GitCommand| {
+        let assert = command.args(["status", "--short"]).assert().success();
+        from_utf8(&assert.get_output().stdout).unwrap().to_string()
+    };
+
+    assert_eq!(short_status(rustgit(&sub_dir)), short_status(git(&sub_dir)));
+
+    Ok(())
+}
+
+#[test]
+fn intent_to_add() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    make_changes(&working_dir);
+    fs::write(working_dir.join("empty/ita"), "").unwrap();
+    fs::write(working_dir.join("dir/ita"), "ita").unwrap();
+    git(&working_dir)
+        .args(["add", "-N", "empty/ita", "dir/ita"])
+        .assert()
+        .success();
+
+    assert_eq!(
+        rustgit(&working_dir).status_porcelain(),
+        git(&working_dir).status_porcelain()
+    );
+    let status = rustgit(&working_dir).status();
+    assert!(status.contains("Changes not staged for commit:\n"));
+    assert!(status.contains("\tnew file:   dir/ita\n"));
+
+    Ok(())
+}
+
+#[test]
+fn long() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    make_changes(&working_dir);
+
+    insta::assert_snapshot!("long", rustgit(&working_dir).status());
+
+    Ok(())
+}
+
+#[test]
+fn clean() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    let git = || git(&working_dir);
+
+    rustgit(&working_dir).init();
+    fs::write(working_dir.join("a"), "a").unwrap();
+    git().stage(["a"]);
+    git().commit("initial commit");
+
+    assert_eq!(
+        rustgit(&working_dir).status(),
+        "On branch main\nnothing to commit, working tree clean\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn unmerged() -> anyhow::Result<()> {
+    let working_dir = test_path!();
+    let git = || git(&working_dir);
+
+    rustgit(&working_dir).init();
+    for name in ["modified", "deleted_by_them", "deleted_by_us"] {
+        fs::write(working_dir.join(name), "base\n").unwrap();
+    }
+    git().stage(["."]);
+    git().commit("base");
+
+    git()
+        .args(["switch", "-q", "-c", "other"])
+        .assert()
+        .success();
+    fs::write(working_dir.join("modified"), "theirs\n").unwrap();
+    fs::write(working_dir.join("deleted_by_us"), "theirs\n").unwrap();
+    fs::write(working_dir.join("added"), "theirs\n").unwrap();
+    fs::remove_file(working_dir.join("deleted_by_them")).unwrap();
+    git().args(["add", "-A"]).assert().success();
+    git().commit("theirs");
+
+    git().args(["switch", "-q", "main"]).assert().success();
+    fs::write(working_dir.join("modified"), "ours\n").unwrap();
+    fs::write(working_dir.join("deleted_by_them"), "ours\n").unwrap();
+    fs::write(working_dir.join("added"), "ours\n").unwrap();
+    fs::remove_file(working_dir.join("deleted_by_us")).unwrap();
+    git().args(["add", "-A"]).assert().success();
+    git().commit("ours");
+    git().args(["merge", "other"]).assert().failure();
+    fs::write(working_dir.join("staged"), "staged\n").unwrap();
+    git().stage(["staged"]);
+
+    let output = |mut command: crate::common::GitCommand, args: &[&str]| {
+        let assert = command.arg("status").args(args).assert().success();
+        from_utf8(&assert.get_output().stdout).unwrap().to_string()
+    };
+    for args in [&["--short"][..], &["--porcelain"]] {
+        assert_eq!(output(rustgit(&working_dir), args), output(git(), args));
+    }
+    assert_eq!(
+        output(rustgit(&working_dir), &[]),
+        output(git(), &[]).replace("\"git ", "\"rustgit ")
+    );
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks() -> anyhow::Result<()> {
+    use std::os::unix::fs::symlink;
+
+    let working_dir = test_path!();
+    rustgit(&working_dir).init();
+    fs::create_dir(working_dir.join("dir")).unwrap();
+    fs::write(working_dir.join("dir/file"), "file").unwrap();
+    symlink("dir", working_dir.join("tracked")).unwrap();
+    git(&working_dir).stage(["."]);
+    git(&working_dir).commit("initial commit");
+
+    // symbolic links are never followed, even when they make a loop
+    symlink("dir", working_dir.join("untracked")).unwrap();
+    symlink(".", working_dir.join("dir/loop")).unwrap();
+    assert_eq!(
+        rustgit(&working_dir).status_porcelain(),
+        git(&working_dir).status_porcelain()
+    );
+
+    Ok(())
+}
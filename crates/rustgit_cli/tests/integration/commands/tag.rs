@@ -0,0 +1,176 @@
+use crate::common::{git, head_sha, populate_folder, rustgit, InstaSettingsExt};
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::path::Path;
+use test_utils::test_path;
+
+fn init_with_commit(working_dir: &Path) {
+    git(working_dir).init();
+    populate_folder(working_dir);
+    git(working_dir).stage(["."]);
+    git(working_dir).commit("initial commit");
+}
+
+fn object_type(working_dir: &Path, object: &str) -> String {
+    git(working_dir)
+        .cat_file(["-t", object])
+        .trim_end()
+        .to_string()
+}
+
+// rustgit tag <tagname>
+#[test]
+fn lightweight() {
+    let working_dir = test_path!();
+    init_with_commit(&working_dir);
+
+    rustgit(&working_dir)
+        .args(["tag", "v1.0"])
+        .assert()
+        .success();
+
+    assert_eq!(object_type(&working_dir, "v1.0"), "commit");
+    assert_eq!(
+        git(&working_dir).rev_parse(["v1.0"]),
+        head_sha(&working_dir)
+    );
+}
+
+// rustgit tag -a <tagname> -m <message>
+#[test]
+fn annotated() {
+    let working_dir = test_path!();
+    init_with_commit(&working_dir);
+
+    rustgit(&working_dir)
+        .args(["tag", "-a", "v1.0", "-m", "first release"])
+        .assert()
+        .success();
+
+    assert_eq!(object_type(&working_dir, "v1.0"), "tag");
+    assert_eq!(
+        git(&working_dir).rev_parse(["v1.0^{commit}"]),
+        head_sha(&working_dir)
+    );
+
+    let output = git(&working_dir).cat_file(["tag", "v1.0"]);
+    let mut settings = insta::Settings::clone_current();
+    settings.add_sha1_filter();
+    settings.add_filter(r"tagger .* <.*> \d+ [+-]\d{4}", "tagger [tagger]");
+    settings.bind(|| {
+        insta::assert_snapshot!(output);
+    });
+}
+
+// rustgit tag -a <tagname> -m <message> <tag object>, where the tagged tag is in a pack
+#[test]
+fn tag_of_packed_tag() {
+    let working_dir = test_path!();
+    init_with_commit(&working_dir);
+
+    git(&working_dir)
+        .args(["tag", "-a", "inner", "-m", "inner tag"])
+        .assert()
+        .success();
+    git(&working_dir).args(["gc", "--quiet"]).assert().success();
+    let inner = git(&working_dir).rev_parse(["inner"]);
+
+    rustgit(&working_dir)
+        .args(["tag", "-m", "outer tag", "outer", &inner])
+        .assert()
+        .success();
+
+    assert_eq!(object_type(&working_dir, "outer"), "tag");
+    assert!(git(&working_dir)
+        .cat_file(["tag", "outer"])
+        .starts_with(&format!("object {inner}\ntype tag\n")));
+}
+
+#[test]
+fn already_exists() {
+    let working_dir = test_path!();
+    init_with_commit(&working_dir);
+
+    rustgit(&working_dir)
+        .args(["tag", "v1.0"])
+        .assert()
+        .success();
+    rustgit(&working_dir)
+        .args(["tag", "v1.0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("tag 'v1.0' already exists"));
+
+    rustgit(&working_dir)
+        .args(["tag", "-f", "v1.0"])
+        .assert()
+        .success();
+}
+
+// rustgit tag, with both packed and loose tags
+#[test]
+fn list() {
+    let working_dir = test_path!();
+    init_with_commit(&working_dir);
+
+    git(&working_dir).args(["tag", "v1.0"]).assert().success();
+    git(&working_dir).args(["tag", "v0.1"]).assert().success();
+    git(&working_dir).args(["gc", "--quiet"]).assert().success();
+    git(&working_dir).args(["tag", "v2.0"]).assert().success();
+
+    rustgit(&working_dir)
+        .arg("tag")
+        .assert()
+        .success()
+        .stdout(predicate::eq("v0.1\nv1.0\nv2.0\n"));
+
+    // a packed tag already exists
+    rustgit(&working_dir)
+        .args(["tag", "v1.0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("tag 'v1.0' already exists"));
+}
+
+#[test]
+fn reflog() {
+    let working_dir = test_path!();
+    init_with_commit(&working_dir);
+    git(&working_dir)
+        .args(["config", "core.logAllRefUpdates", "always"])
+        .assert()
+        .success();
+
+    let reflog_message = |tagname: &str| {
+        let assert = git(&working_dir)
+            .args(["log", "-g", "--format=%gs"])
+            .arg(format!("refs/tags/{}", tagname))
+            .assert()
+            .success();
+        String::from_utf8(assert.get_output().stdout.clone()).unwrap()
+    };
+    for args in [&[][..], &["-m", "message"], &["HEAD^{tree}"]] {
+        rustgit(&working_dir)
+            .args(["tag", "rustgit"])
+            .args(args)
+            .assert()
+            .success();
+        git(&working_dir)
+            .args(["tag", "git"])
+            .args(args)
+            .assert()
+            .success();
+        assert_eq!(
+            reflog_message("rustgit"),
+            reflog_message("git"),
+            "{:?}",
+            args
+        );
+        for tagname in ["rustgit", "git"] {
+            git(&working_dir)
+                .args(["tag", "-d", tagname])
+                .assert()
+                .success();
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use crate::common::{git, rustgit};
+use assert_cmd::prelude::*;
+use std::{fs, path::Path};
+use test_utils::test_path;
+
+// Three commits on main
+fn make_history(working_dir: &Path) {
+    rustgit(working_dir).init();
+    for content in ["1", "2", "3"] {
+        fs::write(working_dir.join("a"), content).unwrap();
+        rustgit(working_dir).stage(["a"]);
+        rustgit(working_dir).commit(content);
+    }
+}
+
+#[test]
+fn update() {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+    let head = git(&working_dir).rev_parse(["HEAD"]);
+    let parent = git(&working_dir).rev_parse(["HEAD~"]);
+
+    rustgit(&working_dir)
+        .args(["update-ref", "refs/heads/topic", "HEAD~", ""])
+        .assert()
+        .success();
+    assert_eq!(git(&working_dir).rev_parse(["topic"]), parent);
+
+    // the expected value doesn't match
+    rustgit(&working_dir)
+        .args(["update-ref", "refs/heads/topic", "HEAD", "HEAD~2"])
+        .assert()
+        .failure()
+        .stderr(format!(
+            "fatal: update_ref failed for ref 'refs/heads/topic': cannot lock ref \
+             'refs/heads/topic': is at {} but expected {}\n",
+            parent,
+            git(&working_dir).rev_parse(["HEAD~2"])
+        ));
+    rustgit(&working_dir)
+        .args(["update-ref", "refs/heads/topic", "HEAD", "HEAD~"])
+        .assert()
+        .success();
+    assert_eq!(git(&working_dir).rev_parse(["topic"]), head);
+
+    // HEAD is followed to main, unless --no-deref is given
+    rustgit(&working_dir)
+        .args(["update-ref", "-m", "rewind", "HEAD", "HEAD~"])
+        .assert()
+        .success();
+    assert_eq!(git(&working_dir).rev_parse(["main"]), parent);
+    assert_eq!(
+        fs::read_to_string(working_dir.join(".git/HEAD")).unwrap(),
+        "ref: refs/heads/main\n"
+    );
+    let reflog = git(&working_dir).args(["reflog", "-1"]).assert().success();
+    assert_eq!(
+        String::from_utf8_lossy(&reflog.get_output().stdout),
+        format!("{} HEAD@{{0}}: rewind\n", &parent[..7])
+    );
+    rustgit(&working_dir)
+        .args(["update-ref", "--no-deref", "HEAD", &head])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(working_dir.join(".git/HEAD")).unwrap(),
+        format!("{}\n", head)
+    );
+
+    rustgit(&working_dir)
+        .args(["update-ref", "refs/heads/main/sub", "HEAD"])
+        .assert()
+        .failure()
+        .stderr(
+            "fatal: update_ref failed for ref 'refs/heads/main/sub': cannot lock ref \
+             'refs/heads/main/sub': 'refs/heads/main' exists; cannot create 'refs/heads/main/sub'\n",
+        );
+    rustgit(&working_dir)
+        .args(["update-ref", "refs/heads/x", "missing"])
+        .assert()
+        .failure()
+        .stderr("fatal: missing: not a valid SHA1\n");
+}
+
+#[test]
+fn delete() {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+    git(&working_dir)
+        .args(["branch", "topic"])
+        .assert()
+        .success();
+    git(&working_dir)
+        .args(["pack-refs", "--all"])
+        .assert()
+        .success();
+
+    rustgit(&working_dir)
+        .args(["update-ref", "-d", "refs/heads/topic", "HEAD~"])
+        .assert()
+        .failure()
+        .code(128);
+    rustgit(&working_dir)
+        .args(["update-ref", "-d", "refs/heads/topic", "HEAD"])
+        .assert()
+        .success();
+    git(&working_dir)
+        .args(["rev-parse", "--verify", "-q", "topic"])
+        .assert()
+        .failure();
+    assert!(!working_dir.join(".git/logs/refs/heads/topic").exists());
+
+    // like git, deleting a missing reference is fine
+    rustgit(&working_dir)
+        .args(["update-ref", "-d", "refs/heads/topic"])
+        .assert()
+        .success();
+    rustgit(&working_dir)
+        .args(["update-ref", "-d", "refs/heads/topic", "HEAD"])
+        .assert()
+        .code(128)
+        .stderr(
+            "fatal: cannot lock ref 'refs/heads/topic': unable to resolve reference \
+             'refs/heads/topic'\n",
+        );
+}
+
+#[test]
+fn stdin() {
+    let working_dir = test_path!();
+    make_history(&working_dir);
+    let head = git(&working_dir).rev_parse(["HEAD"]);
+    let parent = git(&working_dir).rev_parse(["HEAD~"]);
+
+    rustgit(&working_dir)
+        .args(["update-ref", "--stdin"])
+        .assert_with_stdin(
+            "create refs/heads/a HEAD~\n\
+             create refs/tags/t HEAD\n\
+             verify refs/heads/b\n\
+             update HEAD HEAD~ HEAD\n",
+        )
+        .success();
+    assert_eq!(git(&working_dir).rev_parse(["a"]), parent);
+    assert_eq!(git(&working_dir).rev_parse(["t"]), head);
+    assert_eq!(git(&working_dir).rev_parse(["main"]), parent);
+
+    // nothing is applied when a check fails
+    rustgit(&working_dir)
+        .args(["update-ref", "--stdin"])
+        .assert_with_stdin(&format!(
+            "delete refs/heads/a\n\
+             create refs/heads/c HEAD\n\
+             update refs/tags/t {0} {0}\n",
+            parent
+        ))
+        .failure()
+        .stderr(format!(
+            "fatal: cannot lock ref 'refs/tags/t': is at {} but expected {}\n",
+            head, parent
+        ));
+    assert_eq!(git(&working_dir).rev_parse(["a"]), parent);
+    git(&working_dir)
+        .args(["rev-parse", "--verify", "-q", "c"])
+        .assert()
+        .failure();
+    assert!(!working_dir.join(".git/refs/heads/a.lock").exists());
+    assert!(!working_dir.join(".git/refs/heads/c.lock").exists());
+
+    rustgit(&working_dir)
+        .args(["update-ref", "--stdin"])
+        .assert_with_stdin("delete refs/heads/a\ncreate refs/heads/a/b HEAD\n")
+        .failure()
+        .stderr("fatal: cannot process 'refs/heads/a' and 'refs/heads/a/b' at the same time\n");
+    rustgit(&working_dir)
+        .args(["update-ref", "--stdin"])
+        .assert_with_stdin("update refs/heads/a HEAD\nupdate refs/heads/a HEAD~\n")
+        .failure()
+        .stderr("fatal: multiple updates for ref 'refs/heads/a' not allowed\n");
+    rustgit(&working_dir)
+        .args(["update-ref", "--stdin"])
+        .assert_with_stdin("create refs/heads/main HEAD\n")
+        .failure()
+        .stderr("fatal: cannot lock ref 'refs/heads/main': reference already exists\n");
+    rustgit(&working_dir)
+        .args(["update-ref", "--stdin"])
+        .assert_with_stdin("update refs/heads/a\n")
+        .failure()
+        .stderr("fatal: update refs/heads/a: missing <newvalue>\n");
+    rustgit(&working_dir)
+        .args(["update-ref", "--stdin"])
+        .assert_with_stdin("move refs/heads/a HEAD\n")
+        .failure()
+        .stderr("fatal: unknown command: move refs/heads/a HEAD\n");
+}
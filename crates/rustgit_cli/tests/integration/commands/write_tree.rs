@@ -1,55 +1,103 @@
-use crate::common::{git, populate_folder, rustgit};
-use test_utils::{test_path, touch};
-
-#[test]
-fn write_tree() {
-    let working_dir = test_path!();
-    let git = || git(&working_dir);
-
-    git().init();
-
-    populate_folder(&working_dir);
-
-    git().stage(&["."]);
-
-    let tree_hash = rustgit(&working_dir).write_tree();
-
-    insta::assert_snapshot!(git().ls_tree(tree_hash));
-}
-
-// Git actually sorts the file list for the entire project before building the tree, rather than
-// sorting entries within trees themselves
-#[test]
-fn sort_in_correct_order() {
-    let working_dir = test_path!();
-
-    let git = || git(&working_dir);
-    git().init();
-
-    std::fs::create_dir(working_dir.join("foo")).unwrap();
-    touch(&working_dir.join("foo.txt")).unwrap();
-    touch(&working_dir.join("foo").join("bar.txt")).unwrap();
-
-    git().stage(&["."]);
-
-    let tree_hash = rustgit(&working_dir).write_tree();
-
-    insta::assert_snapshot!(git().ls_tree(tree_hash));
-}
-
-// When the index doesn't contain all the working tree
-// This test that write-tree actually use the index rather than the working tree
-#[test]
-fn partial_index() {
-    let working_dir = test_path!();
-    let git = || git(&working_dir);
-
-    git().init();
-
-    populate_folder(&working_dir);
-
-    git().stage(&["file1.txt", "dir1"]);
-
-    let tree_hash = rustgit(&working_dir).write_tree();
-    insta::assert_snapshot!(git().ls_tree(tree_hash));
-}
+use crate::common::{git, populate_folder, rustgit};
+use std::fs;
+use std::path::Path;
+use test_utils::{test_path, touch};
+
+#[test]
+fn write_tree() {
+    let working_dir = test_path!();
+    let git = || git(&working_dir);
+
+    git().init();
+
+    populate_folder(&working_dir);
+
+    git().stage(&["."]);
+
+    let tree_hash = rustgit(&working_dir).write_tree();
+
+    insta::assert_snapshot!(git().ls_tree(tree_hash));
+}
+
+// Git actually sorts the file list for the entire project before building the tree, rather than
+// sorting entries within trees themselves
+#[test]
+fn sort_in_correct_order() {
+    let working_dir = test_path!();
+
+    let git = || git(&working_dir);
+    git().init();
+
+    std::fs::create_dir(working_dir.join("foo")).unwrap();
+    touch(&working_dir.join("foo.txt")).unwrap();
+    touch(&working_dir.join("foo").join("bar.txt")).unwrap();
+
+    git().stage(&["."]);
+
+    let tree_hash = rustgit(&working_dir).write_tree();
+
+    insta::assert_snapshot!(git().ls_tree(tree_hash));
+}
+
+// When the index doesn't contain all the working tree
+// This test that write-tree actually use the index rather than the working tree
+#[test]
+fn partial_index() {
+    let working_dir = test_path!();
+    let git = || git(&working_dir);
+
+    git().init();
+
+    populate_folder(&working_dir);
+
+    git().stage(&["file1.txt", "dir1"]);
+
+    let tree_hash = rustgit(&working_dir).write_tree();
+    insta::assert_snapshot!(git().ls_tree(tree_hash));
+}
+
+// The TREE extension of the index, which is the last one rustgit and git write here
+fn cache_tree(working_dir: &Path) -> Vec<u8> {
+    let index = fs::read(working_dir.join(".git/index")).unwrap();
+    let start = index
+        .windows(4)
+        .rposition(|window| window == b"TREE")
+        .expect("the index should have a cache tree");
+    index[start..index.len() - 20].to_vec()
+}
+
+// write-tree records the trees in the index like git, and updates them after a change
+#[test]
+fn cache_tree_extension() {
+    let test_path = test_path!();
+    let rustgit_dir = test_path.join("rustgit");
+    let git_dir = test_path.join("git");
+
+    for dir in [&rustgit_dir, &git_dir] {
+        fs::create_dir(dir).unwrap();
+        git(dir).init();
+        populate_folder(dir);
+        fs::create_dir_all(dir.join("dir1/inner")).unwrap();
+        fs::write(dir.join("dir1/inner/file"), "inner").unwrap();
+        git(dir).stage(["."]);
+    }
+
+    let tree_hash = rustgit(&rustgit_dir).write_tree();
+    assert_eq!(tree_hash, git(&git_dir).write_tree());
+    assert_eq!(cache_tree(&rustgit_dir), cache_tree(&git_dir));
+
+    // git reads the index written by rustgit
+    assert_eq!(git(&rustgit_dir).write_tree(), tree_hash);
+
+    // adding a file invalidates the trees that contain it
+    for dir in [&rustgit_dir, &git_dir] {
+        fs::write(dir.join("dir1/inner/file"), "changed").unwrap();
+        fs::write(dir.join("dir1/new"), "new").unwrap();
+    }
+    rustgit(&rustgit_dir).stage(["dir1/inner/file", "dir1/new"]);
+    git(&git_dir).stage(["dir1/inner/file", "dir1/new"]);
+
+    let tree_hash = rustgit(&rustgit_dir).write_tree();
+    assert_eq!(tree_hash, git(&git_dir).write_tree());
+    assert_eq!(cache_tree(&rustgit_dir), cache_tree(&git_dir));
+}
@@ -1,8 +1,14 @@
 use assert_cmd::assert::Assert;
 use assert_cmd::prelude::*;
 use rustgit::oid::Sha1HashHexString;
+use std::io::Write;
 use std::str::from_utf8;
-use std::{ffi::OsStr, fs, path::Path, process::Command};
+use std::{
+    ffi::OsStr,
+    fs,
+    path::Path,
+    process::{Command, Stdio},
+};
 
 pub(crate) struct GitCommand(Command);
 
@@ -21,6 +27,11 @@ impl GitCommand {
         self
     }
 
+    pub fn env_remove<K: AsRef<OsStr>>(mut self, key: K) -> Self {
+        self.0.env_remove(key);
+        self
+    }
+
     pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut GitCommand {
         self.0.arg(arg);
         self
@@ -39,6 +50,24 @@ impl GitCommand {
         &mut self.0
     }
 
+    /// Run the command with `input` as its standard input
+    pub(crate) fn assert_with_stdin(&mut self, input: &str) -> Assert {
+        let mut child = self
+            .0
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap().assert()
+    }
+
     pub(crate) fn init(mut self) {
         self.arg("init").0.assert().success();
     }
@@ -66,6 +95,11 @@ impl GitCommand {
         from_utf8(&assert.get_output().stdout).unwrap().to_string()
     }
 
+    pub(crate) fn status_porcelain(mut self) -> String {
+        let assert = self.args(["status", "--porcelain"]).assert().success();
+        from_utf8(&assert.get_output().stdout).unwrap().to_string()
+    }
+
     pub(crate) fn write_tree(mut self) -> Sha1HashHexString {
         let assert = self.args(["write-tree"]).assert().success();
         Sha1HashHexString::from_u8_slice(&assert.get_output().stdout).unwrap()
@@ -1,2 +1,10 @@
+// Lints of newer clippy versions that the existing tests predate
+#![allow(
+    clippy::needless_borrow,
+    clippy::needless_borrows_for_generic_args,
+    clippy::needless_lifetimes,
+    clippy::unused_unit
+)]
+
 mod commands;
 mod common;
@@ -1,6 +1,7 @@
 pub fn touch(path: &std::path::Path) -> std::io::Result<()> {
     match std::fs::OpenOptions::new()
         .create(true)
+        .truncate(false)
         .write(true)
         .open(path)
     {